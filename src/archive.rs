@@ -0,0 +1,155 @@
+use std::fs;
+use std::io::{self, Cursor, Read};
+use std::path::{Component, Path, PathBuf};
+use flate2::read::GzDecoder;
+use log::{debug, info};
+use thiserror::Error;
+use crate::vfs::MemoryFs;
+
+/// Custom error type for archive sources.
+#[derive(Error, Debug)]
+pub enum ArchiveError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Zip error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("Failed to download {url}: {message}")]
+    Download { url: String, message: String },
+}
+
+/// The archive formats that can be used as a source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    TarGz,
+    Tar,
+}
+
+impl ArchiveKind {
+    /// Detects the archive format of a source from its file name or URL.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The source path or URL.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(ArchiveKind)` if the source names a supported archive, `None` otherwise.
+    pub fn detect(source: &str) -> Option<Self> {
+        let name = source.split(['?', '#']).next().unwrap_or(source).to_lowercase();
+        if name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else if name.ends_with(".tar") {
+            Some(Self::Tar)
+        } else {
+            None
+        }
+    }
+}
+
+/// Checks whether a source refers to a remote URL rather than a local path.
+pub fn is_url(source: &str) -> bool {
+    source.starts_with("http://") || source.starts_with("https://")
+}
+
+/// Loads an archive from a local path or URL and extracts its files into memory.
+///
+/// If every entry in the archive sits under a single top-level directory (as in
+/// `project-1.0/...` tarballs), that directory is stripped.
+///
+/// # Arguments
+///
+/// * `source` - The path or URL of the archive.
+/// * `kind` - The archive format.
+///
+/// # Returns
+///
+/// Returns `Result<MemoryFs, ArchiveError>` containing the extracted files,
+/// or an `ArchiveError` if the archive could not be read.
+pub fn load(source: &str, kind: ArchiveKind) -> Result<MemoryFs, ArchiveError> {
+    let bytes = if is_url(source) {
+        info!("Downloading archive from {}", source);
+        download(source)?
+    } else {
+        fs::read(source)?
+    };
+    debug!("Read {} bytes of archive data", bytes.len());
+
+    let entries = match kind {
+        ArchiveKind::Zip => read_zip(&bytes)?,
+        ArchiveKind::TarGz => read_tar(GzDecoder::new(Cursor::new(bytes)))?,
+        ArchiveKind::Tar => read_tar(Cursor::new(bytes))?,
+    };
+    let prefix = common_root(&entries);
+
+    let mut memory_fs = MemoryFs::new();
+    for (path, content) in entries {
+        let path = match prefix {
+            Some(ref prefix) => path.strip_prefix(prefix).map(Path::to_path_buf).unwrap_or(path),
+            None => path,
+        };
+        memory_fs.add_file(path, content);
+    }
+    info!("Extracted archive {}", source);
+    Ok(memory_fs)
+}
+
+fn download(url: &str) -> Result<Vec<u8>, ArchiveError> {
+    let response = ureq::get(url).call().map_err(|e| ArchiveError::Download {
+        url: url.to_string(),
+        message: e.to_string(),
+    })?;
+    let mut bytes = Vec::new();
+    response.into_reader().read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_zip(bytes: &[u8]) -> Result<Vec<(PathBuf, Vec<u8>)>, ArchiveError> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))?;
+    let mut entries = Vec::new();
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        if !file.is_file() {
+            continue;
+        }
+        // enclosed_name rejects absolute paths and `..` traversal.
+        let Some(path) = file.enclosed_name() else { continue };
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)?;
+        entries.push((path, content));
+    }
+    Ok(entries)
+}
+
+fn read_tar(reader: impl Read) -> Result<Vec<(PathBuf, Vec<u8>)>, ArchiveError> {
+    let mut archive = tar::Archive::new(reader);
+    let mut entries = Vec::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path()?.into_owned();
+        if !path.components().all(|component| matches!(component, Component::Normal(_) | Component::CurDir)) {
+            continue;
+        }
+        let mut content = Vec::new();
+        entry.read_to_end(&mut content)?;
+        entries.push((path, content));
+    }
+    Ok(entries)
+}
+
+/// Returns the single top-level directory shared by all entries, if there is one.
+fn common_root(entries: &[(PathBuf, Vec<u8>)]) -> Option<PathBuf> {
+    let mut roots = entries.iter().map(|(path, _)| {
+        let mut components = path.components().filter(|c| matches!(c, Component::Normal(_)));
+        let first = components.next();
+        // Files directly at the top level have no shared root directory.
+        components.next().and(first).map(|c| PathBuf::from(c.as_os_str()))
+    });
+    let first = roots.next()??;
+    roots.all(|root| root.as_ref() == Some(&first)).then_some(first)
+}
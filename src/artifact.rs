@@ -1,16 +1,247 @@
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use std::{fs, io};
-use std::collections::HashSet;
+use std::io::Write;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::SystemTime;
 use log::{debug, info, warn};
-use walkdir::{WalkDir, DirEntry};
+use rayon::prelude::*;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
 use thiserror::Error;
-use crate::config::Config;
+use crate::{binary, checksum, chunk, comments, dependencies, encoding, format, history, imports, locales, markdown, notebook, owner, paths, plugin, redact, rules, runs, sql, tokens, transform};
+use crate::chunk::{Limits, Part};
+use crate::owner::OwnerError;
+use crate::plugin::{Plugin, PluginError};
+use crate::transform::TransformError;
+use crate::cache::{Cache, Dependency, FileStamp};
+use crate::locales::LocaleTree;
+use crate::manifest::{Manifest, MANIFEST_FILE, MANIFEST_MARKDOWN_FILE};
+use crate::config::{BinaryMode, Config, OutputFormat, SubmoduleMode};
+use crate::git::{self, GitError, Submodule};
+use crate::vfs::{FileKind, FileSystem, Metadata};
+use crate::redact::Redaction;
+use crate::preflight::format_bytes;
+use crate::whitespace;
 
 /// Represents a file artifact to be processed and written.
+#[derive(Debug, Clone)]
 pub struct Artifact {
     pub original_path: PathBuf,
+    pub relative_path: PathBuf,
     pub new_filename: String,
     pub content: String,
+    pub checksum: String,
+    pub tokens: usize,
+    /// Other relative paths whose content is byte-identical to this artifact's.
+    pub duplicates: Vec<PathBuf>,
+    /// The size and modification time of the source file when it was read, if it came from one.
+    pub stamp: Option<FileStamp>,
+    /// The credentials replaced with `[REDACTED]` in `content`.
+    pub redactions: Vec<Redaction>,
+    /// How `content` stands in for the source file if it was binary.
+    pub binary: Option<BinaryMode>,
+    /// The encoding the source file was transcoded to UTF-8 from, if it was not UTF-8.
+    pub encoding: Option<String>,
+    /// Which part of the source file this is, if it was split with `--split-tokens` or `--split-lines`.
+    pub part: Option<Part>,
+    /// Files besides the source file that `content` was built from, such as Markdown includes.
+    pub dependencies: Vec<Dependency>,
+}
+
+/// A file selected for collection, identified without reading its content.
+#[derive(Debug, Clone)]
+pub struct PlannedFile {
+    pub original_path: PathBuf,
+    pub relative_path: PathBuf,
+    pub new_filename: String,
+    pub size: u64,
+    /// When the file was last modified at planning time, if known.
+    pub modified: Option<SystemTime>,
+}
+
+/// The outcome of the planning phase of a collection.
+#[derive(Debug, Clone, Default)]
+pub struct CollectPlan {
+    /// The files that passed the filter chain, in walk order.
+    pub files: Vec<PlannedFile>,
+    /// Stub artifacts standing in for submodules that are not descended into.
+    pub submodule_stubs: Vec<Artifact>,
+    /// Locale trees to replace with translation key listings.
+    pub locale_trees: Vec<LocaleTree>,
+    /// FIFOs, sockets, and devices that passed the filters but are never read, relative to
+    /// the source directory.
+    pub special_files: Vec<PathBuf>,
+}
+
+/// How source paths are turned into destination filenames.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NamingScheme {
+    /// Joins path components with `separator`, keeping the first `keep_dirs` directory
+    /// levels as real directories.
+    Flatten { separator: String, keep_dirs: usize },
+    /// Recreates the source directory tree unchanged.
+    Preserve,
+}
+
+impl NamingScheme {
+    /// Selects the naming scheme for a configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The configuration options.
+    ///
+    /// # Returns
+    ///
+    /// `NamingScheme::Preserve` if `--preserve-structure` is set, `NamingScheme::Flatten` otherwise.
+    pub fn from_config(config: &Config) -> Self {
+        if config.preserve_structure {
+            Self::Preserve
+        } else {
+            Self::Flatten {
+                separator: config.flatten_separator.clone(),
+                keep_dirs: config.keep_dirs,
+            }
+        }
+    }
+
+    /// Builds the destination filename for a path relative to the source directory.
+    ///
+    /// The prefix and suffix are applied to the file name itself, never to its directories.
+    ///
+    /// # Arguments
+    ///
+    /// * `relative_path` - The relative path of the file.
+    /// * `prefix` - The prefix to prepend to the file name.
+    /// * `suffix` - The suffix to insert before the file name's extension.
+    ///
+    /// # Returns
+    ///
+    /// A `String` containing the new filename, with `/` separating any directories.
+    pub fn name(&self, relative_path: &Path, prefix: &str, suffix: &str) -> String {
+        let components: Vec<String> = relative_path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        let (dirs, file_name) = match self {
+            Self::Flatten { separator, keep_dirs } => {
+                let kept = (*keep_dirs).min(components.len().saturating_sub(1));
+                let (dirs, rest) = components.split_at(kept);
+                (dirs, rest.join(separator))
+            }
+            Self::Preserve => match components.split_last() {
+                Some((file_name, dirs)) => (dirs, file_name.clone()),
+                None => (&components[..0], String::new()),
+            },
+        };
+        let file_name = Artifact::apply_name_affixes(&file_name, prefix, suffix);
+        if dirs.is_empty() {
+            file_name
+        } else {
+            format!("{}/{}", dirs.join("/"), file_name)
+        }
+    }
+}
+
+/// A destination that written artifacts are stored in.
+pub trait Sink {
+    /// Stores a file in the sink.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the file, relative to the sink's root and separated by `/`.
+    /// * `content` - The bytes to store.
+    ///
+    /// # Returns
+    ///
+    /// Returns `io::Result<()>` indicating success or failure of the write operation.
+    fn put(&mut self, name: &str, content: &[u8]) -> io::Result<()>;
+}
+
+/// Stores files as loose files beneath a directory.
+#[derive(Debug, Clone)]
+pub struct DirSink {
+    root: PathBuf,
+}
+
+impl DirSink {
+    /// Creates a sink writing beneath the given directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The directory files are written into.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl Sink for DirSink {
+    fn put(&mut self, name: &str, content: &[u8]) -> io::Result<()> {
+        let dest_path = self.root.join(name);
+        if let Some(parent) = dest_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(dest_path, content)
+    }
+}
+
+/// Stores files as deflated entries of a zip archive.
+///
+/// The archive is only complete once [`ZipSink::finish`] has been called.
+pub struct ZipSink {
+    zip: zip::ZipWriter<fs::File>,
+}
+
+impl ZipSink {
+    /// Creates the archive file, replacing any existing file at the same path.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the zip archive.
+    ///
+    /// # Returns
+    ///
+    /// Returns `io::Result<Self>` containing the sink, or an error if the file cannot be created.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        Ok(Self { zip: zip::ZipWriter::new(fs::File::create(path)?) })
+    }
+
+    /// Writes the archive's central directory and closes the file.
+    pub fn finish(self) -> io::Result<()> {
+        self.zip.finish()?;
+        Ok(())
+    }
+}
+
+impl Sink for ZipSink {
+    fn put(&mut self, name: &str, content: &[u8]) -> io::Result<()> {
+        let options = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        self.zip.start_file(name, options)?;
+        self.zip.write_all(content)
+    }
+}
+
+/// Number of times a file that keeps changing while it is read is read again before giving up.
+const MAX_READ_ATTEMPTS: usize = 3;
+
+/// The outcome of reading a planned file.
+enum Loaded {
+    Artifact(Box<Artifact>),
+    Empty,
+    Binary,
+    /// The file could not be read for lack of permission; holds its relative path.
+    Unreadable(PathBuf),
+    Skipped,
+}
+
+/// The outcome of running a single file through the filter chain.
+enum Planned {
+    File(PlannedFile),
+    /// The path is a FIFO, socket, or device, which is never read; holds its relative path.
+    Special(PathBuf),
+    Skipped,
 }
 
 /// Custom error type for artifact-related operations.
@@ -20,132 +251,1250 @@ pub enum ArtifactError {
     Io(#[from] io::Error),
     #[error("Path strip error: {0}")]
     StripPrefix(#[from] std::path::StripPrefixError),
+    #[error("Invalid pattern: {0}")]
+    Glob(#[from] globset::Error),
+    #[error("{0} kept changing while it was being collected")]
+    Unstable(PathBuf),
+    #[error("{0} is a binary file")]
+    Binary(PathBuf),
+    #[error("Git error: {0}")]
+    Git(#[from] GitError),
+    #[error("{name} would be written by more than one file: {paths}")]
+    Collision { name: String, paths: String },
+    #[error("{0}")]
+    Owner(#[from] OwnerError),
+    #[error("{0}")]
+    Transform(#[from] TransformError),
+    #[error("{0}")]
+    Plugin(#[from] PluginError),
+    #[error("Entry file {0} does not exist in the source directory")]
+    MissingEntry(PathBuf),
+}
+
+/// The compiled filter chain that decides which files become artifacts.
+pub struct CollectFilter {
+    ignored_dirs: Vec<String>,
+    excluded_extensions: Vec<String>,
+    included_extensions: Vec<String>,
+    output_paths: Vec<PathBuf>,
+    temp_globs: GlobSet,
+    test_globs: GlobSet,
+    include_globs: GlobSet,
+    exclude_globs: GlobSet,
+    since: Option<SystemTime>,
+    owners: Option<Vec<u32>>,
+    excluded_owners: Vec<u32>,
+    allowed_files: Option<HashSet<PathBuf>>,
+    plugins: Vec<Arc<Plugin>>,
+}
+
+impl CollectFilter {
+    /// Compiles the filter chain for a configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The configuration options.
+    /// * `ignored_dirs` - The directories to ignore, relative to the source directory.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<Self, ArtifactError>` containing the compiled filter,
+    /// or an `ArtifactError` if a pattern or plugin is invalid.
+    pub fn new(config: &Config, ignored_dirs: Vec<String>) -> Result<Self, ArtifactError> {
+        let temp_patterns = config.get_temp_patterns();
+        let filter = Self {
+            ignored_dirs,
+            output_paths: Self::output_paths(config),
+            excluded_extensions: config.get_excluded_extensions(),
+            included_extensions: config.get_included_extensions(),
+            temp_globs: Artifact::build_globset(&temp_patterns)?,
+            test_globs: Artifact::build_globset(&config.get_test_patterns())?,
+            include_globs: Artifact::build_globset(&config.include_globs)?,
+            exclude_globs: Artifact::build_globset(&config.exclude_globs)?,
+            since: config.since,
+            owners: config.owner.as_deref().map(owner::resolve_users).transpose()?,
+            excluded_owners: owner::resolve_users(&config.exclude_owner)?,
+            allowed_files: Self::git_allowlist(config)?,
+            plugins: config.plugins.iter().map(|path| plugin::load(path)).collect::<Result<_, _>>()?,
+        };
+
+        debug!("Ignored dirs: {:?}", filter.ignored_dirs);
+        debug!("Output paths inside the source: {:?}", filter.output_paths);
+        debug!("Excluded extensions: {:?}", filter.excluded_extensions);
+        debug!("Included extensions: {:?}", filter.included_extensions);
+        debug!("Temp patterns: {:?}", temp_patterns);
+        debug!("Include globs: {:?}", config.include_globs);
+        debug!("Exclude globs: {:?}", config.exclude_globs);
+        if let Some(since) = filter.since {
+            debug!("Modified since: {}", runs::utc_timestamp(since));
+        }
+        debug!("Owners: {:?}, excluded owners: {:?}", filter.owners, filter.excluded_owners);
+        Ok(filter)
+    }
+
+    /// Resolves the files that `--git-tracked` and `--git-diff` limit collection to.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The configuration options.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<Option<HashSet<PathBuf>>, GitError>` containing the allowed paths
+    /// relative to the source directory, `None` if neither option is set, or a `GitError`
+    /// if git could not list them.
+    fn git_allowlist(config: &Config) -> Result<Option<HashSet<PathBuf>>, GitError> {
+        let mut allowed: Option<HashSet<PathBuf>> = None;
+        if config.git_tracked {
+            let tracked = git::tracked_files(&config.source_dir)?;
+            info!("Only collecting the {} files tracked by git", tracked.len());
+            allowed = Some(tracked);
+        }
+        if let Some(ref git_ref) = config.git_diff {
+            let changed = git::changed_files(&config.source_dir, git_ref)?;
+            info!("Only collecting the {} files changed since {}", changed.len(), git_ref);
+            allowed = Some(match allowed {
+                Some(tracked) => tracked.intersection(&changed).cloned().collect(),
+                None => changed,
+            });
+        }
+        Ok(allowed)
+    }
+
+    /// Compiles the filter chain for a configuration using its default ignored directories.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The configuration options.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<Self, ArtifactError>` containing the compiled filter,
+    /// or an `ArtifactError` if a pattern or plugin is invalid.
+    pub fn from_config(config: &Config) -> Result<Self, ArtifactError> {
+        Self::new(config, config.get_ignored_dirs())
+    }
+
+    /// Locates the destination directory and single output file relative to the source directory.
+    ///
+    /// Paths are compared after canonicalization, so a destination nested anywhere inside
+    /// the source (or reached through `..` or a symlink) is still recognized. Outputs that
+    /// lie outside the source directory are omitted.
+    fn output_paths(config: &Config) -> Vec<PathBuf> {
+        let Some(source_dir) = canonicalize_lenient(&config.source_dir) else {
+            return Vec::new();
+        };
+        [Some(&config.dest_dir), config.single_file.as_ref(), config.output_archive.as_ref()]
+            .into_iter()
+            .flatten()
+            .filter_map(|output| canonicalize_lenient(output))
+            .filter_map(|output| output.strip_prefix(&source_dir).ok().map(Path::to_path_buf))
+            .filter(|relative| !relative.as_os_str().is_empty())
+            .collect()
+    }
+
+    /// Checks whether a file passes every filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file.
+    /// * `relative_path` - The path of the file relative to the source directory.
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if the file should be collected, `false` otherwise.
+    pub fn accepts(&self, path: &Path, relative_path: &Path) -> bool {
+        let is_ignored = Artifact::is_ignored(relative_path, &self.ignored_dirs)
+            || self.output_paths.iter().any(|output| relative_path.starts_with(output));
+        let is_excluded = Artifact::is_excluded(path, &self.excluded_extensions);
+        let is_included = Artifact::is_included(path, &self.included_extensions);
+        let is_temp = Artifact::is_temp_file(path, &self.temp_globs);
+        let is_glob_included = self.include_globs.is_empty() || self.include_globs.is_match(relative_path);
+        let is_glob_excluded = self.exclude_globs.is_match(relative_path);
+        let is_test = self.test_globs.is_match(relative_path);
+        let is_allowed = self.allowed_files.as_ref().is_none_or(|allowed| allowed.contains(relative_path));
+
+        debug!("File: {}, ignored: {}, excluded: {}, included: {}, temp: {}, glob included: {}, glob excluded: {}, test: {}, allowed: {}",
+               path.display(), is_ignored, is_excluded, is_included, is_temp, is_glob_included, is_glob_excluded, is_test, is_allowed);
+
+        !is_ignored && !is_excluded && is_included && !is_temp && is_glob_included && !is_glob_excluded && !is_test && is_allowed
+    }
+
+    /// Checks whether a file was modified recently enough to pass `--since`.
+    ///
+    /// Files whose filesystem does not record a modification time always pass.
+    ///
+    /// # Arguments
+    ///
+    /// * `modified` - When the file was last modified, if known.
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if the file should be collected, `false` otherwise.
+    pub fn accepts_modified(&self, modified: Option<SystemTime>) -> bool {
+        match (self.since, modified) {
+            (Some(since), Some(modified)) => modified >= since,
+            _ => true,
+        }
+    }
+
+    /// Checks whether a file's owner passes `--owner` and `--exclude-owner`.
+    ///
+    /// Files whose filesystem does not record an owner always pass.
+    ///
+    /// # Arguments
+    ///
+    /// * `owner` - The user ID of the file's owner, if known.
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if the file should be collected, `false` otherwise.
+    pub fn accepts_owner(&self, owner: Option<u32>) -> bool {
+        let Some(owner) = owner else {
+            return true;
+        };
+        self.owners.as_ref().is_none_or(|owners| owners.contains(&owner)) && !self.excluded_owners.contains(&owner)
+    }
+
+    /// Checks whether the `--plugin` plugins that export `should_include` accept a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `relative_path` - The path of the file, relative to the source directory.
+    /// * `metadata` - The metadata of the file.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<bool, PluginError>` containing `true` if every plugin accepts the
+    /// file, or the error of the first plugin that failed.
+    pub fn accepts_plugins(&self, relative_path: &Path, metadata: &Metadata) -> Result<bool, PluginError> {
+        for plugin in &self.plugins {
+            if !plugin.should_include(relative_path, metadata)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
 }
 
 impl Artifact {
-    /// Creates a new `Artifact` instance.
+    /// Creates a new `Artifact` from a planned file, reading its content through the given filesystem.
     ///
     /// # Arguments
     ///
-    /// * `original_path` - The original path of the file.
-    /// * `source_dir` - The source directory path.
+    /// * `fs` - The filesystem to read from.
+    /// * `planned` - The planned file to read.
+    /// * `config` - The configuration options.
     ///
     /// # Returns
     ///
     /// Returns `Result<Self, ArtifactError>` containing the new `Artifact` if successful,
-    /// or an `ArtifactError` if an error occurs during creation.
-    pub fn new(original_path: PathBuf, source_dir: &Path) -> Result<Self, ArtifactError> {
-        let relative_path = original_path.strip_prefix(source_dir)?;
-        let new_filename = Self::generate_new_filename(relative_path);
-        let content = fs::read_to_string(&original_path)?;
+    /// or an `ArtifactError` if the file could not be read or kept changing while it was read.
+    pub fn new(fs: &dyn FileSystem, planned: PlannedFile, config: &Config) -> Result<Self, ArtifactError> {
+        let treatment = rules::treatment(config, &planned.relative_path);
+        Self::new_cached(fs, planned, &treatment.config, treatment.truncate, &Cache::default())
+    }
 
+    /// Creates a new `Artifact` from a planned file, reusing the artifact recorded by the
+    /// previous run if neither the file nor anything its content was built from changed.
+    ///
+    /// Output of `--transform-cmd` commands and plugins may depend on more than the file,
+    /// so such files are always read and transformed again; only their token count is
+    /// reused if the resulting content is identical.
+    ///
+    /// `config` is the configuration with the file's `[[rules]]` already applied, and
+    /// `truncate` the number of lines they keep, if any.
+    fn new_cached(
+        fs: &dyn FileSystem,
+        planned: PlannedFile,
+        config: &Config,
+        truncate: Option<usize>,
+        cache: &Cache,
+    ) -> Result<Self, ArtifactError> {
+        let reusable = config.transform_cmds.is_empty() && config.plugins.is_empty();
+        let cached = FileStamp::new(planned.size, planned.modified)
+            .filter(|_| reusable)
+            .and_then(|stamp| cache.lookup(fs, &planned.relative_path, stamp).map(|entry| (stamp, entry)));
+        if let Some((stamp, entry)) = cached {
+            debug!("Reusing cached artifact for {}", planned.relative_path.display());
+            return Ok(Self {
+                original_path: planned.original_path,
+                relative_path: planned.relative_path,
+                new_filename: planned.new_filename,
+                content: entry.content.clone(),
+                checksum: entry.checksum.clone(),
+                tokens: entry.tokens,
+                duplicates: Vec::new(),
+                stamp: Some(stamp),
+                redactions: entry.redactions.clone(),
+                binary: entry.binary,
+                encoding: entry.encoding.clone(),
+                part: None,
+                dependencies: entry.dependencies.clone(),
+            });
+        }
+
+        let (bytes, stamp) = Self::read_stable(fs, &planned)?;
+        let mut redactions = Vec::new();
+        let mut source_encoding = None;
+        let mut dependencies = Vec::new();
+        let is_utf16 = !config.strict_utf8 && encoding::utf16_bom(&bytes).is_some();
+        let (content, binary) = if !is_utf16 && binary::is_binary(&bytes) {
+            match config.binary_mode {
+                BinaryMode::Skip => return Err(ArtifactError::Binary(planned.original_path)),
+                BinaryMode::Placeholder => (binary::placeholder(&planned.relative_path, &bytes), Some(BinaryMode::Placeholder)),
+                BinaryMode::Base64 => (binary::encode(&bytes), Some(BinaryMode::Base64)),
+            }
+        } else {
+            let mut content = match String::from_utf8(bytes) {
+                Ok(content) => content,
+                Err(e) if config.strict_utf8 => return Err(io::Error::new(io::ErrorKind::InvalidData, e).into()),
+                Err(e) => {
+                    let (content, name) = encoding::transcode(e.as_bytes());
+                    debug!("Transcoded {} from {} to UTF-8", planned.relative_path.display(), name);
+                    source_encoding = Some(name.to_string());
+                    content
+                }
+            };
+            let is_markdown = markdown::is_markdown(&planned.relative_path);
+            if is_markdown && config.expand_includes {
+                let (expanded, included) = markdown::expand_includes(fs, &planned.original_path, &content, &config.source_dir);
+                content = expanded;
+                dependencies = included
+                    .into_iter()
+                    .map(|(path, metadata)| Dependency {
+                        stamp: metadata.and_then(|metadata| FileStamp::new(metadata.len, metadata.modified)),
+                        path,
+                    })
+                    .collect();
+            }
+            // Redact before reshaping so that reported line numbers match the source file
+            // (and any content it includes).
+            if !config.no_redact {
+                (content, redactions) = redact::redact(&content);
+            }
+            if is_markdown {
+                content = markdown::process_front_matter(&content, config.front_matter);
+            }
+            if config.convert_notebooks && notebook::is_notebook(&planned.relative_path) {
+                match notebook::to_script(&content) {
+                    Ok(script) => content = script,
+                    Err(e) => warn!("Keeping {} as JSON, it could not be read as a notebook: {}", planned.relative_path.display(), e),
+                }
+            }
+            if config.summarize_sql_dumps && sql::is_sql(&planned.relative_path) && content.len() >= sql::DUMP_THRESHOLD {
+                debug!("Dropping the data from SQL dump {}", planned.relative_path.display());
+                content = sql::summarize_dump(&content);
+            }
+            if config.no_tests || config.skeleton || !config.transforms.is_empty() || !config.transform_cmds.is_empty() || !config.plugins.is_empty() {
+                content = transform::apply(&transform::for_config(config), &planned.relative_path, &content)?;
+            }
+            if config.squeeze_blank_lines {
+                content = whitespace::squeeze_blank_lines(&content);
+            }
+            if config.minify_indent {
+                content = whitespace::minify_indent(&content);
+            }
+            if let Some(lines) = truncate {
+                content = rules::truncate(&content, lines);
+            }
+            (content, None)
+        };
+        let checksum = checksum::short_hash(&content);
+        let tokens = cache.tokens(&planned.relative_path, &checksum).unwrap_or_else(|| tokens::count(&content));
         Ok(Self {
+            original_path: planned.original_path,
+            relative_path: planned.relative_path,
+            new_filename: planned.new_filename,
+            content,
+            checksum,
+            tokens,
+            duplicates: Vec::new(),
+            stamp,
+            redactions,
+            binary,
+            encoding: source_encoding,
+            part: None,
+            dependencies,
+        })
+    }
+
+    /// Reads a planned file, re-reading it if it changed since it was planned.
+    ///
+    /// A read is trusted only if the file's size and modification time after reading match
+    /// what was expected beforehand and the number of bytes read matches the size. Otherwise
+    /// the file is read again, expecting its latest metadata, up to `MAX_READ_ATTEMPTS` times.
+    ///
+    /// # Arguments
+    ///
+    /// * `fs` - The filesystem to read from.
+    /// * `planned` - The planned file, carrying its size and modification time at planning time.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<(Vec<u8>, Option<FileStamp>), ArtifactError>` containing the file content
+    /// and the size and modification time it was read at, or an `ArtifactError::Unstable`
+    /// if the file never held still long enough to be read.
+    fn read_stable(fs: &dyn FileSystem, planned: &PlannedFile) -> Result<(Vec<u8>, Option<FileStamp>), ArtifactError> {
+        let path = &planned.original_path;
+        let (mut expected_len, mut expected_modified) = (planned.size, planned.modified);
+        for attempt in 1..=MAX_READ_ATTEMPTS {
+            let bytes = fs.read(path)?;
+            let after = fs.metadata(path)?;
+            if bytes.len() as u64 == expected_len && after.len == expected_len && after.modified == expected_modified {
+                if attempt > 1 {
+                    warn!("{} changed during collection; collected its latest content", path.display());
+                }
+                return Ok((bytes, FileStamp::new(expected_len, expected_modified)));
+            }
+            debug!("{} changed while being read (attempt {} of {})", path.display(), attempt, MAX_READ_ATTEMPTS);
+            (expected_len, expected_modified) = (after.len, after.modified);
+        }
+        Err(ArtifactError::Unstable(path.clone()))
+    }
+
+    /// Creates an artifact for generated content that does not come from a single source file.
+    ///
+    /// # Arguments
+    ///
+    /// * `original_path` - The source path the content describes.
+    /// * `relative_path` - The path the content is listed under, relative to the source directory.
+    /// * `content` - The generated content.
+    /// * `config` - The configuration options.
+    ///
+    /// # Returns
+    ///
+    /// A `Self` named after `relative_path`.
+    pub fn generated(original_path: PathBuf, relative_path: PathBuf, content: String, config: &Config) -> Self {
+        let new_filename = Self::generate_new_filename(&relative_path, config);
+        Self::with_content(original_path, relative_path, new_filename, content)
+    }
+
+    fn with_content(original_path: PathBuf, relative_path: PathBuf, new_filename: String, content: String) -> Self {
+        let checksum = checksum::short_hash(&content);
+        let tokens = tokens::count(&content);
+        Self {
             original_path,
+            relative_path,
             new_filename,
             content,
-        })
+            checksum,
+            tokens,
+            duplicates: Vec::new(),
+            stamp: None,
+            redactions: Vec::new(),
+            binary: None,
+            encoding: None,
+            part: None,
+            dependencies: Vec::new(),
+        }
     }
 
-    /// Generates a new filename by replacing path separators with underscores.
+    /// Generates the destination filename for a file using the configured naming scheme.
     ///
     /// # Arguments
     ///
     /// * `relative_path` - The relative path of the file.
+    /// * `config` - The configuration options.
     ///
     /// # Returns
     ///
     /// A `String` containing the new filename.
-    fn generate_new_filename(relative_path: &Path) -> String {
-        relative_path.to_string_lossy().replace(std::path::MAIN_SEPARATOR, "_")
+    fn generate_new_filename(relative_path: &Path, config: &Config) -> String {
+        let name = NamingScheme::from_config(config).name(relative_path, &config.name_prefix, &config.name_suffix);
+        Self::fit_name_length(&format::file_name(config.format, &name), relative_path, config.max_filename_length)
+    }
+
+    /// Shortens the file name part of a new filename that is longer than the limit.
+    ///
+    /// The stem is truncated on a character boundary and followed by a hash of the
+    /// relative path, so the shortened name is the same on every run and distinct for
+    /// different files. The extension is kept so that file types stay recognizable.
+    ///
+    /// # Arguments
+    ///
+    /// * `new_filename` - The new filename, with `/` separating any kept directories.
+    /// * `relative_path` - The relative path of the file, used for the hash.
+    /// * `max_len` - The maximum length of the file name part, in bytes.
+    ///
+    /// # Returns
+    ///
+    /// A `String` containing the new filename, shortened if necessary.
+    fn fit_name_length(new_filename: &str, relative_path: &Path, max_len: usize) -> String {
+        let (dir, file_name) = match new_filename.rsplit_once('/') {
+            Some((dir, file_name)) => (format!("{}/", dir), file_name),
+            None => (String::new(), new_filename),
+        };
+        if file_name.len() <= max_len {
+            return new_filename.to_string();
+        }
+
+        let hash = &checksum::short_hash(&relative_path.to_string_lossy())[..8];
+        let extension = Path::new(file_name)
+            .extension()
+            .map(|ext| format!(".{}", ext.to_string_lossy()))
+            .unwrap_or_default();
+        let tail = format!("-{}{}", hash, extension);
+        let shortened = match max_len.checked_sub(tail.len()) {
+            Some(room) => {
+                let mut end = room;
+                while !file_name.is_char_boundary(end) {
+                    end -= 1;
+                }
+                format!("{}{}", &file_name[..end], tail)
+            }
+            None => hash.to_string(),
+        };
+        debug!("Shortened {} to {}", file_name, shortened);
+        format!("{}{}", dir, shortened)
     }
 
-    /// Writes the artifact content to the destination directory.
+    /// Adds a prefix and suffix to a file name.
+    ///
+    /// The suffix is inserted before the file extension so that file types stay recognizable.
     ///
     /// # Arguments
     ///
-    /// * `dest_dir` - The destination directory path.
+    /// * `file_name` - The file name to decorate.
+    /// * `prefix` - The prefix to prepend to the file name.
+    /// * `suffix` - The suffix to insert before the file name's extension.
+    ///
+    /// # Returns
+    ///
+    /// A `String` containing the decorated file name.
+    fn apply_name_affixes(file_name: &str, prefix: &str, suffix: &str) -> String {
+        if prefix.is_empty() && suffix.is_empty() {
+            return file_name.to_string();
+        }
+        let name = Path::new(file_name);
+        let stem = name.file_stem().unwrap_or_default().to_string_lossy();
+        match name.extension() {
+            Some(extension) => format!("{}{}{}.{}", prefix, stem, suffix, extension.to_string_lossy()),
+            None => format!("{}{}{}", prefix, stem, suffix),
+        }
+    }
+
+    /// Writes the artifact, rendered in the given format, into a sink under its new filename.
+    ///
+    /// # Arguments
+    ///
+    /// * `sink` - The sink to write into.
+    /// * `checksum_header` - Whether to prepend a checksum header line to the rendered content,
+    ///   which is left out for formats without comments.
+    /// * `format` - The format to render the artifact in.
     ///
     /// # Returns
     ///
     /// Returns `io::Result<()>` indicating success or failure of the write operation.
-    pub fn write(&self, dest_dir: &Path) -> io::Result<()> {
-        let dest_path = dest_dir.join(&self.new_filename);
-        fs::write(dest_path, &self.content)
+    pub fn write(&self, sink: &mut dyn Sink, checksum_header: bool, format: OutputFormat) -> io::Result<()> {
+        let body = format::render(format, self);
+        let header = checksum_header
+            .then(|| checksum::header_line(Path::new(&self.new_filename), &checksum::short_hash(&body)))
+            .flatten();
+        match header {
+            Some(header) => sink.put(&self.new_filename, format!("{}\n{}", header, body).as_bytes()),
+            None => sink.put(&self.new_filename, body.as_bytes()),
+        }
     }
 
     /// Collects artifacts from the source directory based on the provided configuration.
     ///
+    /// This plans the collection with [`Artifact::plan`] and then reads every planned
+    /// file with [`Artifact::execute`].
+    ///
+    /// # Arguments
+    ///
+    /// * `fs` - The filesystem to collect from.
+    /// * `config` - The configuration options.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<Vec<Self>, ArtifactError>` containing a vector of collected artifacts
+    /// if successful, or an `ArtifactError` if an error occurs during collection.
+    pub fn collect(fs: &dyn FileSystem, config: &Config) -> Result<Vec<Self>, ArtifactError> {
+        let plan = Self::plan(fs, config)?;
+        Self::execute(fs, plan, config)
+    }
+
+    /// Plans a collection by running every file through the filter chain without reading it.
+    ///
     /// If target directories are specified in the configuration, only files within those
     /// directories (and their subdirectories) will be processed. Otherwise, all files in
     /// the source directory will be processed, except those in ignored directories.
+    /// All directory listing and metadata lookups go through `fs`, which allows planning
+    /// against in-memory or otherwise virtual sources.
     ///
     /// # Arguments
     ///
+    /// * `fs` - The filesystem to plan against.
     /// * `config` - The configuration options.
     ///
     /// # Returns
     ///
-    /// Returns `Result<Vec<Self>, ArtifactError>` containing a vector of collected artifacts
-    /// if successful, or an `ArtifactError` if an error occurs during collection.
-    pub fn collect(config: &Config) -> Result<Vec<Self>, ArtifactError> {
-        debug!("Entering Artifact::collect");
-        info!("Starting artifact collection from {}", config.source_dir.display());
-        let mut artifacts = Vec::new();
-        let ignored_dirs = config.get_ignored_dirs();
+    /// Returns `Result<CollectPlan, ArtifactError>` containing the files that would be
+    /// collected, or an `ArtifactError` if an error occurs during planning.
+    pub fn plan(fs: &dyn FileSystem, config: &Config) -> Result<CollectPlan, ArtifactError> {
+        debug!("Entering Artifact::plan");
+        info!("Planning artifact collection from {}", config.source_dir.display());
+        let mut ignored_dirs = config.get_ignored_dirs();
+        let submodule_stubs = Self::handle_submodules(config, &mut ignored_dirs);
+        let filter = CollectFilter::new(config, ignored_dirs)?;
         let target_dirs = config.get_target_dirs();
-        let excluded_extensions = config.get_excluded_extensions();
-        let included_extensions = config.get_included_extensions();
         let mut processed_files = HashSet::new();
 
-        debug!("Ignored dirs: {:?}", ignored_dirs);
         debug!("Target dirs: {:?}", target_dirs);
-        debug!("Excluded extensions: {:?}", excluded_extensions);
-        debug!("Included extensions: {:?}", included_extensions);
 
         let mut dirs_to_walk = vec![config.source_dir.clone()];
         if !target_dirs.is_empty() {
             dirs_to_walk.extend(target_dirs.iter().map(|dir| config.source_dir.join(dir)));
         }
 
+        let mut paths = Vec::new();
         for dir in dirs_to_walk {
             debug!("Walking directory: {}", dir.display());
-            for entry in WalkDir::new(&dir).follow_links(true).into_iter().filter_map(Result::ok) {
-                let path = entry.path().to_path_buf();
+            paths.extend(fs.walk(&dir).into_iter().filter(|path| processed_files.insert(path.clone())));
+        }
+        // Walk order depends on the filesystem, so sort to keep runs reproducible.
+        paths.sort_by(|a, b| config.walk_order.compare(a, b));
+
+        let mut bundle_dirs = Vec::new();
+        if !config.include_bundles {
+            for path in paths.iter().filter(|path| Manifest::is_manifest(fs, path)) {
+                if let Some(bundle_dir) = path.parent().and_then(|parent| parent.strip_prefix(&config.source_dir).ok()) {
+                    warn!("Skipping previously generated artifacts in {}", path.parent().unwrap_or(path).display());
+                    bundle_dirs.push(bundle_dir.to_path_buf());
+                }
+            }
+        }
+
+        // Filtering needs a metadata lookup per file, so spread it across threads;
+        // collecting an indexed parallel iterator keeps the sorted order.
+        let planned: Vec<Planned> = paths
+            .par_iter()
+            .map(|path| {
                 debug!("Processing entry: {}", path.display());
+                Self::plan_entry(fs, path, config, &filter)
+            })
+            .collect::<Result<_, _>>()?;
+        let mut files = Vec::new();
+        let mut special_files = Vec::new();
+        for planned in planned {
+            match planned {
+                Planned::File(file) if !bundle_dirs.iter().any(|dir| file.relative_path.starts_with(dir)) => files.push(file),
+                Planned::Special(path) => special_files.push(path),
+                Planned::File(_) | Planned::Skipped => {}
+            }
+        }
+
+        if !config.entries.is_empty() {
+            let selected = Self::entry_files(fs, config)?;
+            files.retain(|file| selected.contains(&file.relative_path));
+        }
 
-                if path.is_file() && processed_files.insert(path.clone()) {
-                    let relative_path = path.strip_prefix(&config.source_dir).map_err(ArtifactError::StripPrefix)?;
-                    let is_ignored = Self::is_ignored(relative_path, &ignored_dirs);
-                    let is_excluded = Self::is_excluded(&path, &excluded_extensions);
-                    let is_included = Self::is_included(&path, &included_extensions);
-
-                    debug!("File: {}, ignored: {}, excluded: {}, included: {}",
-                           path.display(), is_ignored, is_excluded, is_included);
-
-                    if !is_ignored && !is_excluded && is_included {
-                        debug!("Creating artifact for file: {}", path.display());
-
-                        match Self::new(path.clone(), &config.source_dir) {
-                            Ok(artifact) => {
-                                info!("Created artifact: {}", artifact.new_filename);
-                                artifacts.push(artifact);
-                            },
-                            Err(e) => {
-                                warn!("Failed to process file {}: {}", path.display(), e);
-                            }
-                        }
-                    } else {
-                        debug!("Skipping file: {}", path.display());
-                    }
+        let (mut files, locale_trees) = locales::consolidate(files, config);
+        Self::disambiguate(&mut files, config)?;
+
+        debug!("Exiting Artifact::plan");
+        Ok(CollectPlan { files, submodule_stubs, locale_trees, special_files })
+    }
+
+    /// Resolves the files that `--entry` limits collection to: the entries themselves, and
+    /// with `--follow-imports` the local files they import, transitively.
+    ///
+    /// # Arguments
+    ///
+    /// * `fs` - The filesystem to read from.
+    /// * `config` - The configuration options.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<HashSet<PathBuf>, ArtifactError>` containing the paths relative to the
+    /// source directory, or `ArtifactError::MissingEntry` if an entry is not a file beneath it.
+    fn entry_files(fs: &dyn FileSystem, config: &Config) -> Result<HashSet<PathBuf>, ArtifactError> {
+        let mut entries = HashSet::new();
+        for entry in &config.entries {
+            let relative: PathBuf = entry
+                .strip_prefix(&config.source_dir)
+                .unwrap_or(entry)
+                .components()
+                .filter(|component| !matches!(component, Component::CurDir))
+                .collect();
+            let beneath = relative.components().all(|component| matches!(component, Component::Normal(_)));
+            let is_file = fs.metadata(&config.source_dir.join(&relative)).is_ok_and(|metadata| metadata.kind == FileKind::File);
+            if !beneath || !is_file {
+                return Err(ArtifactError::MissingEntry(entry.clone()));
+            }
+            entries.insert(relative);
+        }
+        if !config.follow_imports {
+            return Ok(entries);
+        }
+        let paths: Vec<PathBuf> = entries.iter().map(|entry| config.source_dir.join(entry)).collect();
+        let selected = imports::closure(fs, &config.source_dir, &paths);
+        info!("Following imports from {} entries selected {} files", entries.len(), selected.len());
+        Ok(selected)
+    }
+
+    /// Checks whether a name is taken by a file rustifacts writes next to the artifacts.
+    fn is_reserved_name(name: &str, config: &Config) -> bool {
+        name == MANIFEST_FILE || (config.manifest_markdown && name == MANIFEST_MARKDOWN_FILE)
+    }
+
+    /// Renames planned files whose new filenames collide, so that none overwrites another.
+    ///
+    /// Within each group of colliding files, the one with the lowest relative path keeps
+    /// the name and the others get a suffix derived from a hash of their relative path,
+    /// which keeps the names stable from run to run. A file that would be written as
+    /// `MANIFEST.json` (or `MANIFEST.md` with `--manifest-markdown`) is always renamed.
+    ///
+    /// # Arguments
+    ///
+    /// * `files` - The planned files.
+    /// * `config` - The configuration options.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<(), ArtifactError>`, or `ArtifactError::Collision` if two files
+    /// collide and `--fail-on-collision` is set.
+    fn disambiguate(files: &mut [PlannedFile], config: &Config) -> Result<(), ArtifactError> {
+        let mut by_name: HashMap<String, Vec<usize>> = HashMap::new();
+        for (index, planned) in files.iter().enumerate() {
+            by_name.entry(planned.new_filename.clone()).or_default().push(index);
+        }
+        let mut collisions: Vec<(String, Vec<usize>)> = by_name
+            .into_iter()
+            .filter(|(name, indices)| indices.len() > 1 || Self::is_reserved_name(name, config))
+            .collect();
+        collisions.sort();
+
+        for (name, mut indices) in collisions {
+            indices.sort_by(|&a, &b| files[a].relative_path.cmp(&files[b].relative_path));
+            if indices.len() > 1 && config.fail_on_collision {
+                let paths: Vec<String> = indices.iter().map(|&index| files[index].relative_path.display().to_string()).collect();
+                return Err(ArtifactError::Collision { name, paths: paths.join(", ") });
+            }
+            let (renamed, overwritten) = if Self::is_reserved_name(&name, config) {
+                (&indices[..], "the manifest")
+            } else {
+                (&indices[1..], "another artifact")
+            };
+            for &index in renamed {
+                let new_filename = Self::distinct_name(&name, &files[index].relative_path, config);
+                warn!(
+                    "{} would overwrite {} named {}; writing it as {}",
+                    files[index].relative_path.display(),
+                    overwritten,
+                    name,
+                    new_filename
+                );
+                files[index].new_filename = new_filename;
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolves a collision between an artifact added after the initial collection, such as
+    /// a file created while watching, and the names already in use.
+    ///
+    /// The names in use keep their files, so an artifact whose name is taken (or would be
+    /// written as the manifest) is renamed the way [`Artifact::plan`] renames colliding files.
+    ///
+    /// # Arguments
+    ///
+    /// * `taken` - The names already in use, each with the relative path of the file using it.
+    /// * `config` - The configuration options.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<(), ArtifactError>`, or `ArtifactError::Collision` if the name is taken
+    /// and `--fail-on-collision` is set.
+    pub fn disambiguate_against(&mut self, taken: &HashMap<&str, &Path>, config: &Config) -> Result<(), ArtifactError> {
+        let owner = taken.get(self.new_filename.as_str()).filter(|owner| **owner != self.relative_path);
+        let overwritten = match owner {
+            Some(owner) if config.fail_on_collision => {
+                let paths = format!("{}, {}", owner.display(), self.relative_path.display());
+                return Err(ArtifactError::Collision { name: self.new_filename.clone(), paths });
+            }
+            Some(_) => "another artifact",
+            None if Self::is_reserved_name(&self.new_filename, config) => "the manifest",
+            None => return Ok(()),
+        };
+        let new_filename = Self::distinct_name(&self.new_filename, &self.relative_path, config);
+        warn!(
+            "{} would overwrite {} named {}; writing it as {}",
+            self.relative_path.display(),
+            overwritten,
+            self.new_filename,
+            new_filename
+        );
+        self.new_filename = new_filename;
+        Ok(())
+    }
+
+    /// Derives a name for a file whose name collides, from a hash of its relative path.
+    fn distinct_name(name: &str, relative_path: &Path, config: &Config) -> String {
+        let hash = checksum::short_hash(&relative_path.to_string_lossy());
+        let (dir, file_name) = match name.rsplit_once('/') {
+            Some((dir, file_name)) => (format!("{}/", dir), file_name),
+            None => (String::new(), name),
+        };
+        Self::fit_name_length(
+            &format!("{}{}", dir, Self::apply_name_affixes(file_name, "", &format!("-{}", &hash[..8]))),
+            relative_path,
+            config.max_filename_length,
+        )
+    }
+
+    /// Reads every file in a plan and creates its artifact.
+    ///
+    /// Files that fail to read or exceed the line limit are logged and skipped, as are files
+    /// that keep changing while they are read or that a `--transform-cmd` command fails on,
+    /// unless `--strict` is set.
+    ///
+    /// # Arguments
+    ///
+    /// * `fs` - The filesystem to read from.
+    /// * `plan` - The plan produced by [`Artifact::plan`].
+    /// * `config` - The configuration options.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<Vec<Self>, ArtifactError>` containing the collected artifacts, followed
+    /// by any submodule stubs, translation key listings, dependency summary, and git log, or an `ArtifactError` if, under
+    /// `--strict`, a file kept changing while it was read or could not be transformed.
+    pub fn execute(fs: &dyn FileSystem, plan: CollectPlan, config: &Config) -> Result<Vec<Self>, ArtifactError> {
+        let cache = Cache::for_config(config);
+        // Files are read in parallel; collecting an indexed parallel iterator keeps plan order.
+        let loaded: Vec<Result<Loaded, ArtifactError>> =
+            plan.files.into_par_iter().map(|planned| Self::load(fs, planned, config, &cache)).collect();
+        let mut artifacts = Vec::new();
+        let (mut empty, mut binaries) = (0, 0);
+        let mut unreadable = Vec::new();
+        for loaded in loaded {
+            match loaded? {
+                Loaded::Artifact(artifact) => {
+                    info!("Created artifact: {}", artifact.new_filename);
+                    artifacts.push(*artifact);
                 }
+                Loaded::Empty => empty += 1,
+                Loaded::Binary => binaries += 1,
+                Loaded::Unreadable(path) => unreadable.push(path),
+                Loaded::Skipped => {}
             }
         }
+        Self::report_redactions(&artifacts);
+        if !config.keep_duplicates {
+            artifacts = Self::dedup(artifacts);
+        }
+        let limits = Limits { tokens: config.split_tokens, lines: config.split_lines };
+        if limits.is_set() {
+            artifacts = artifacts.into_iter().flat_map(|artifact| artifact.split(limits, config)).collect();
+        }
+        let git_log = config.include_git_log.and_then(|count| history::git_log(&artifacts, count, config));
+        artifacts.extend(plan.submodule_stubs);
+        artifacts.extend(plan.locale_trees.iter().map(|tree| locales::key_listing(fs, tree, config)));
+        if config.dependency_summary || config.dependency_licenses {
+            artifacts.extend(dependencies::summary(fs, &artifacts, config));
+        }
+        artifacts.extend(git_log);
 
         info!("Artifact collection completed. Total artifacts: {}", artifacts.len());
-        debug!("Exiting Artifact::collect");
+        if empty > 0 {
+            info!("Skipped {} empty or comment-only files", empty);
+        }
+        if !unreadable.is_empty() {
+            warn!("Skipped {} files not readable by the current user: {}", unreadable.len(), Self::list_paths(&unreadable));
+        }
+        if !plan.special_files.is_empty() {
+            info!("Skipped {} special files (FIFOs, sockets, or devices), which are never read: {}",
+                  plan.special_files.len(), Self::list_paths(&plan.special_files));
+        }
+        let transcoded = artifacts.iter().filter(|artifact| artifact.encoding.is_some()).count();
+        if transcoded > 0 {
+            info!("Transcoded {} files from legacy encodings to UTF-8", transcoded);
+        }
+        if binaries > 0 {
+            info!("Skipped {} binary files; pass --binary-mode placeholder or base64 to include them", binaries);
+        }
         Ok(artifacts)
     }
 
+    /// Logs each credential that was redacted and how many there were in total.
+    fn report_redactions(artifacts: &[Self]) {
+        let mut total = 0;
+        for artifact in artifacts {
+            for redaction in &artifact.redactions {
+                warn!("Redacted {} in {} (line {})", redaction.kind, artifact.relative_path.display(), redaction.line);
+            }
+            total += artifact.redactions.len();
+        }
+        if total > 0 {
+            warn!("Redacted {} secrets; pass --no-redact to keep them", total);
+        }
+    }
+
+    /// Splits an artifact that exceeds the limits into parts, with [`chunk::split`].
+    ///
+    /// Each part is named after the artifact with `_part01`, `_part02`, and so on inserted
+    /// before its extensions, and lists the lines of the source file it holds. Binary files
+    /// are never split.
+    ///
+    /// # Arguments
+    ///
+    /// * `limits` - The most tokens and lines a part may hold.
+    /// * `config` - The configuration options.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec<Self>` containing the parts in order, or the artifact alone if it is within
+    /// the limits.
+    fn split(self, limits: Limits, config: &Config) -> Vec<Self> {
+        if self.binary.is_some() {
+            return vec![self];
+        }
+        let ranges = chunk::split(&self.relative_path, &self.content, limits);
+        if ranges.len() < 2 {
+            return vec![self];
+        }
+        debug!("Splitting {} into {} parts", self.relative_path.display(), ranges.len());
+        let lines: Vec<&str> = self.content.split_inclusive('\n').collect();
+        let width = ranges.len().to_string().len().max(2);
+        ranges
+            .iter()
+            .enumerate()
+            .map(|(index, range)| {
+                let new_filename = Self::fit_name_length(
+                    &Self::part_filename(&self.new_filename, index + 1, width),
+                    &self.relative_path.join(format!("part{}", index + 1)),
+                    config.max_filename_length,
+                );
+                let content = lines[range.clone()].concat();
+                let mut part = Self::with_content(self.original_path.clone(), self.relative_path.clone(), new_filename, content);
+                part.duplicates = self.duplicates.clone();
+                part.stamp = self.stamp;
+                part.dependencies = self.dependencies.clone();
+                part.redactions = self
+                    .redactions
+                    .iter()
+                    .filter(|redaction| range.contains(&(redaction.line - 1)))
+                    .cloned()
+                    .collect();
+                part.encoding = self.encoding.clone();
+                part.part = Some(Part { index: index + 1, count: ranges.len(), start_line: range.start + 1, end_line: range.end });
+                part
+            })
+            .collect()
+    }
+
+    /// Inserts `_partNN` into a new filename before the extensions of its file name part.
+    fn part_filename(new_filename: &str, index: usize, width: usize) -> String {
+        let (dir, file_name) = new_filename.rsplit_once('/').map_or(("", new_filename), |(dir, name)| (dir, name));
+        // A leading dot belongs to the name of a dotfile, not to an extension.
+        let stem_len = file_name.char_indices().skip(1).find(|&(_, c)| c == '.').map_or(file_name.len(), |(i, _)| i);
+        let name = format!("{}_part{:0width$}{}", &file_name[..stem_len], index, &file_name[stem_len..], width = width);
+        if dir.is_empty() { name } else { format!("{}/{}", dir, name) }
+    }
+
+    /// Merges artifacts with byte-identical content into one.
+    ///
+    /// The artifact with the lowest relative path is kept and lists the paths of the others
+    /// in `duplicates`. Empty files are never merged.
+    ///
+    /// # Arguments
+    ///
+    /// * `artifacts` - The artifacts to deduplicate.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec<Self>` containing the remaining artifacts, in their original order.
+    fn dedup(mut artifacts: Vec<Self>) -> Vec<Self> {
+        let mut order: Vec<usize> = (0..artifacts.len()).collect();
+        order.sort_by(|&a, &b| artifacts[a].relative_path.cmp(&artifacts[b].relative_path));
+
+        let mut kept_by_content: HashMap<&str, usize> = HashMap::new();
+        let mut merged_into = vec![None; artifacts.len()];
+        for index in order {
+            let content = artifacts[index].content.as_str();
+            if content.is_empty() {
+                continue;
+            }
+            match kept_by_content.get(content) {
+                Some(&kept) => merged_into[index] = Some(kept),
+                None => {
+                    kept_by_content.insert(content, index);
+                }
+            }
+        }
+
+        let mut merged = 0;
+        for (index, kept) in merged_into.iter().enumerate() {
+            if let Some(kept) = *kept {
+                let duplicate = artifacts[index].relative_path.clone();
+                debug!("{} duplicates {}", duplicate.display(), artifacts[kept].relative_path.display());
+                artifacts[kept].duplicates.push(duplicate);
+                merged += 1;
+            }
+        }
+        if merged > 0 {
+            info!("Merged {} duplicate files into the artifacts they duplicate", merged);
+        }
+        artifacts
+            .into_iter()
+            .zip(merged_into)
+            .filter_map(|(artifact, kept)| kept.is_none().then_some(artifact))
+            .collect()
+    }
+
+    /// Lists the first few of a set of skipped paths for a summary log line.
+    fn list_paths(paths: &[PathBuf]) -> String {
+        let shown: Vec<String> = paths.iter().take(3).map(|path| path.display().to_string()).collect();
+        let more = paths.len().saturating_sub(shown.len());
+        if more > 0 {
+            format!("{} and {} more (listed with RUST_LOG=debug)", shown.join(", "), more)
+        } else {
+            shown.join(", ")
+        }
+    }
+
+    /// Runs a single file through the filter chain and plans it if it passes.
+    ///
+    /// # Arguments
+    ///
+    /// * `fs` - The filesystem to look the file up in.
+    /// * `path` - The path of the file, beneath the source directory.
+    /// * `config` - The configuration options.
+    /// * `filter` - The compiled filter chain.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<Option<PlannedFile>, ArtifactError>` containing the planned file if it
+    /// passed the filters, `None` if it was filtered out, is excluded by a `[[rules]]`
+    /// section, is a FIFO, socket, or device, was not modified since `--since`, has an owner
+    /// excluded by `--owner` or `--exclude-owner`, exceeds its size limit, is left out by a
+    /// `--plugin`, or its metadata is unreadable, or an `ArtifactError` if the path is not
+    /// beneath the source directory or, under `--strict`, a plugin failed on it.
+    pub fn plan_file(
+        fs: &dyn FileSystem,
+        path: &Path,
+        config: &Config,
+        filter: &CollectFilter,
+    ) -> Result<Option<PlannedFile>, ArtifactError> {
+        match Self::plan_entry(fs, path, config, filter)? {
+            Planned::File(planned) => Ok(Some(planned)),
+            Planned::Special(_) | Planned::Skipped => Ok(None),
+        }
+    }
+
+    /// Runs a single file through the filter chain, telling special files apart from
+    /// files that were skipped for other reasons.
+    fn plan_entry(fs: &dyn FileSystem, path: &Path, config: &Config, filter: &CollectFilter) -> Result<Planned, ArtifactError> {
+        let relative_path = path.strip_prefix(&config.source_dir)?;
+        if !filter.accepts(path, relative_path) {
+            debug!("Skipping file: {}", path.display());
+            return Ok(Planned::Skipped);
+        }
+        let treatment = rules::treatment(config, relative_path);
+        if treatment.exclude {
+            debug!("Skipping file {}: excluded by a rule", path.display());
+            return Ok(Planned::Skipped);
+        }
+
+        let metadata = match fs.metadata(path) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                warn!("Failed to process file {}: {}", path.display(), e);
+                return Ok(Planned::Skipped);
+            }
+        };
+        if let FileKind::Special(kind) = metadata.kind {
+            debug!("Skipping file {}: it is a {}", path.display(), kind.describe());
+            return Ok(Planned::Special(relative_path.to_path_buf()));
+        }
+        if !filter.accepts_modified(metadata.modified) {
+            debug!("Skipping file {}: not modified since {}",
+                   path.display(), config.since.map(runs::utc_timestamp).unwrap_or_default());
+            return Ok(Planned::Skipped);
+        }
+        if !filter.accepts_owner(metadata.owner) {
+            debug!("Skipping file {}: owned by user {}", path.display(), metadata.owner.unwrap_or_default());
+            return Ok(Planned::Skipped);
+        }
+        if let Some(limit) = treatment.config.max_file_size_for(relative_path).filter(|limit| metadata.len > *limit) {
+            info!("Skipping file {}: {} exceeds the {} size limit",
+                  path.display(), format_bytes(metadata.len), format_bytes(limit));
+            return Ok(Planned::Skipped);
+        }
+        match filter.accepts_plugins(relative_path, &metadata) {
+            Ok(true) => {}
+            Ok(false) => {
+                debug!("Skipping file {}: left out by a plugin", path.display());
+                return Ok(Planned::Skipped);
+            }
+            Err(e) if config.strict => return Err(e.into()),
+            Err(e) => {
+                warn!("Skipping file {}: {}", path.display(), e);
+                return Ok(Planned::Skipped);
+            }
+        }
+        Ok(Planned::File(PlannedFile {
+            original_path: path.to_path_buf(),
+            relative_path: relative_path.to_path_buf(),
+            new_filename: Self::generate_new_filename(relative_path, config),
+            size: metadata.len,
+            modified: metadata.modified,
+        }))
+    }
+
+    /// Runs a single file through the filter chain and creates its artifact if it passes.
+    ///
+    /// Files that fail to read are logged and skipped rather than aborting collection.
+    ///
+    /// # Arguments
+    ///
+    /// * `fs` - The filesystem to read from.
+    /// * `path` - The path of the file, beneath the source directory.
+    /// * `config` - The configuration options.
+    /// * `filter` - The compiled filter chain.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<Option<Self>, ArtifactError>` containing the artifact if the file was
+    /// collected, `None` if it was filtered out or unreadable, or an `ArtifactError` if the
+    /// path is not beneath the source directory or, under `--strict`, the file kept changing
+    /// or could not be transformed.
+    pub fn collect_file(
+        fs: &dyn FileSystem,
+        path: &Path,
+        config: &Config,
+        filter: &CollectFilter,
+    ) -> Result<Option<Self>, ArtifactError> {
+        let Some(planned) = Self::plan_file(fs, path, config, filter)? else {
+            return Ok(None);
+        };
+        match Self::load(fs, planned, config, &Cache::default())? {
+            Loaded::Artifact(artifact) => {
+                info!("Created artifact: {}", artifact.new_filename);
+                Ok(Some(*artifact))
+            }
+            Loaded::Empty | Loaded::Binary | Loaded::Unreadable(_) | Loaded::Skipped => Ok(None),
+        }
+    }
+
+    /// Reads a planned file, skipping it if it is unreadable, binary under `--binary-mode skip`,
+    /// exceeds the line limit, or is empty apart from whitespace and comments.
+    ///
+    /// A file that keeps changing while it is read, or that a `--transform-cmd` command
+    /// fails on, is skipped with a warning, or returned as an error under `--strict`.
+    fn load(fs: &dyn FileSystem, planned: PlannedFile, config: &Config, cache: &Cache) -> Result<Loaded, ArtifactError> {
+        let path = planned.original_path.clone();
+        let relative_path = planned.relative_path.clone();
+        debug!("Creating artifact for file: {}", path.display());
+        let treatment = rules::treatment(config, &relative_path);
+        let config = treatment.config.as_ref();
+        let loaded = match Self::new_cached(fs, planned, config, treatment.truncate, cache) {
+            Ok(artifact) if Self::exceeds_max_lines(&artifact, config.max_lines) => {
+                info!("Skipping file {}: more than {} lines",
+                      path.display(), config.max_lines.unwrap_or_default());
+                Loaded::Skipped
+            },
+            Ok(artifact) if !config.include_bundles && Self::is_generated(&artifact.content) => {
+                warn!("Skipping previously generated artifact: {}", path.display());
+                Loaded::Skipped
+            },
+            Ok(artifact) if !config.keep_empty && comments::is_effectively_empty(&path, &artifact.content) => {
+                debug!("Skipping empty or comment-only file: {}", path.display());
+                Loaded::Empty
+            },
+            Ok(artifact) => Loaded::Artifact(Box::new(artifact)),
+            Err(ArtifactError::Binary(_)) => {
+                debug!("Skipping binary file: {}", path.display());
+                Loaded::Binary
+            },
+            Err(e @ (ArtifactError::Unstable(_) | ArtifactError::Transform(_))) if config.strict => return Err(e),
+            Err(ArtifactError::Io(e)) if e.kind() == io::ErrorKind::PermissionDenied => {
+                debug!("Skipping file {}: not readable by the current user", path.display());
+                Loaded::Unreadable(relative_path)
+            },
+            Err(e) => {
+                warn!("Failed to process file {}: {}", path.display(), e);
+                Loaded::Skipped
+            }
+        };
+        Ok(loaded)
+    }
+
+    /// Applies the configured submodule mode.
+    ///
+    /// Submodules that should not be descended into are added to the ignored directories,
+    /// and a stub artifact is produced for each one that should be represented.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The configuration options.
+    /// * `ignored_dirs` - The ignored directories list to extend.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec<Self>` containing the submodule stub artifacts.
+    fn handle_submodules(config: &Config, ignored_dirs: &mut Vec<String>) -> Vec<Self> {
+        let Some(mode) = config.include_submodules else {
+            return Vec::new();
+        };
+        let max_depth = match mode {
+            SubmoduleMode::Descend => config.submodule_depth + 1,
+            SubmoduleMode::Skip | SubmoduleMode::Stub => 1,
+        };
+        let submodules = match git::submodules(&config.source_dir, max_depth) {
+            Ok(submodules) => submodules,
+            Err(e) => {
+                warn!("Failed to list git submodules: {}", e);
+                return Vec::new();
+            }
+        };
+
+        let mut stubs = Vec::new();
+        for submodule in submodules {
+            if mode == SubmoduleMode::Descend && submodule.depth <= config.submodule_depth {
+                debug!("Descending into submodule: {}", submodule.path.display());
+                ignored_dirs.push(submodule.path.join(".git").to_string_lossy().into_owned());
+                continue;
+            }
+            ignored_dirs.push(submodule.path.to_string_lossy().into_owned());
+            if mode == SubmoduleMode::Skip {
+                debug!("Skipping submodule: {}", submodule.path.display());
+            } else {
+                info!("Created stub for submodule: {}", submodule.path.display());
+                stubs.push(Self::submodule_stub(&submodule, config));
+            }
+        }
+        stubs
+    }
+
+    /// Creates a stub artifact describing a submodule instead of its contents.
+    ///
+    /// # Arguments
+    ///
+    /// * `submodule` - The submodule to describe.
+    /// * `config` - The configuration options.
+    ///
+    /// # Returns
+    ///
+    /// A `Self` whose content notes the submodule path and pinned commit.
+    fn submodule_stub(submodule: &Submodule, config: &Config) -> Self {
+        let content = format!(
+            "Git submodule: {}\nPinned commit: {}\nSubmodule contents are not included.\n",
+            submodule.path.display(),
+            submodule.commit
+        );
+        let name = NamingScheme::from_config(config).name(&submodule.path, &config.name_prefix, &config.name_suffix);
+        let new_filename = Self::fit_name_length(
+            &format::file_name(config.format, &format!("{}.submodule.txt", name)),
+            &submodule.path,
+            config.max_filename_length,
+        );
+        Self::with_content(config.source_dir.join(&submodule.path), submodule.path.clone(), new_filename, content)
+    }
+
     /// Checks if a given path should be ignored based on the ignored directories list.
     ///
     /// # Arguments
@@ -160,22 +1509,68 @@ impl Artifact {
         ignored_dirs.iter().any(|dir| path.starts_with(dir))
     }
 
-    /// Writes all artifacts to the destination directory.
+    /// Checks whether content looks like rustifacts output: a `--single-file` bundle
+    /// (with or without a project header) or an artifact written with a checksum header.
     ///
     /// # Arguments
     ///
-    /// * `artifacts` - A slice of `Artifact` instances to write.
-    /// * `dest_dir` - The destination directory path.
+    /// * `content` - The content to check.
     ///
     /// # Returns
     ///
-    /// Returns `io::Result<()>` indicating success or failure of the write operations.
-    pub fn write_all(artifacts: &[Self], dest_dir: &Path) -> io::Result<()> {
-        fs::create_dir_all(dest_dir)?;
-        for artifact in artifacts {
-            artifact.write(dest_dir)?;
+    /// Returns `true` if the content was generated by rustifacts, `false` otherwise.
+    fn is_generated(content: &str) -> bool {
+        let first_line = content.lines().next().unwrap_or_default();
+        let is_bundle = first_line.starts_with("--- BEGIN ")
+            && first_line.ends_with(" ---")
+            && content.contains("\n--- END ");
+        is_bundle || first_line == format::PROJECT_HEADER || first_line.contains(checksum::HEADER_MARKER)
+    }
+
+    /// Checks if an artifact has more lines than the configured limit.
+    ///
+    /// # Arguments
+    ///
+    /// * `artifact` - The artifact to check.
+    /// * `max_lines` - The maximum number of lines, if any.
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if a limit is set and the artifact exceeds it, `false` otherwise.
+    fn exceeds_max_lines(artifact: &Self, max_lines: Option<usize>) -> bool {
+        max_lines.is_some_and(|max| artifact.content.lines().count() > max)
+    }
+
+    /// Checks if a given file is an editor dropping or temp file based on its name.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to check.
+    /// * `temp_globs` - The compiled temp file name patterns.
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if the file name matches a temp pattern, `false` otherwise.
+    fn is_temp_file(path: &Path, temp_globs: &GlobSet) -> bool {
+        path.file_name().is_some_and(|name| temp_globs.is_match(name))
+    }
+
+    /// Compiles a list of glob patterns into a `GlobSet`.
+    ///
+    /// # Arguments
+    ///
+    /// * `patterns` - The glob patterns to compile.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<GlobSet, ArtifactError>` containing the compiled patterns,
+    /// or an `ArtifactError` if a pattern or plugin is invalid.
+    fn build_globset(patterns: &[String]) -> Result<GlobSet, ArtifactError> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            builder.add(GlobBuilder::new(&paths::normalize_glob(pattern)).literal_separator(true).build()?);
         }
-        Ok(())
+        Ok(builder.build()?)
     }
 
     /// Checks if a given file should be excluded based on its extension.
@@ -194,7 +1589,7 @@ impl Artifact {
         }
         if let Some(extension) = path.extension() {
             let ext = extension.to_string_lossy().to_lowercase();
-            excluded_extensions.iter().any(|excluded| *excluded == ext)
+            excluded_extensions.contains(&ext)
         } else {
             false
         }
@@ -216,9 +1611,249 @@ impl Artifact {
         }
         if let Some(extension) = path.extension() {
             let ext = extension.to_string_lossy().to_lowercase();
-            included_extensions.iter().any(|included| *included == ext)
+            included_extensions.contains(&ext)
         } else {
             false
         }
     }
-}
\ No newline at end of file
+}
+
+/// Canonicalizes a path that may not exist yet by canonicalizing its nearest existing
+/// ancestor and appending the remaining components.
+fn canonicalize_lenient(path: &Path) -> Option<PathBuf> {
+    let absolute = if path.is_absolute() { path.to_path_buf() } else { std::env::current_dir().ok()?.join(path) };
+    let existing = absolute.ancestors().find(|ancestor| ancestor.exists())?;
+    let rest = absolute.strip_prefix(existing).ok()?;
+    Some(paths::normalize_drive(&existing.canonicalize().ok()?.join(rest)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+    use crate::rules::Rule;
+    use crate::vfs::{MemoryFs, RealFs};
+    use crate::writer::{DirectoryWriter, Writer};
+
+    /// A configuration collecting from `project/` in a `MemoryFs`, with a destination
+    /// that does not exist so that no cache is read from disk.
+    fn config() -> Config {
+        Config {
+            source_dir: PathBuf::from("project"),
+            dest_dir: PathBuf::from("/nonexistent/rustifacts-tests"),
+            ..Config::default()
+        }
+    }
+
+    fn memory_fs(files: &[(&str, &[u8])]) -> MemoryFs {
+        let mut fs = MemoryFs::new();
+        for (path, content) in files {
+            fs.add_file(Path::new("project").join(path), *content);
+        }
+        fs
+    }
+
+    fn planned_paths(plan: &CollectPlan) -> Vec<String> {
+        plan.files.iter().map(|planned| planned.relative_path.to_string_lossy().replace('\\', "/")).collect()
+    }
+
+    fn rules(toml: &str) -> Vec<Rule> {
+        #[derive(serde::Deserialize)]
+        struct Rules {
+            rules: Vec<Rule>,
+        }
+        toml::from_str::<Rules>(toml).expect("rules parse").rules
+    }
+
+    #[test]
+    fn plan_skips_ignored_dirs_and_excluded_extensions() {
+        let fs = memory_fs(&[
+            ("src/main.rs", b"fn main() {}\n"),
+            ("target/debug/build.rs", b"fn build() {}\n"),
+            ("node_modules/left-pad/index.js", b"module.exports = 1;\n"),
+            ("logs/today.log", b"started\n"),
+            ("assets/logo.png", b"png"),
+        ]);
+        let config = Config { additional_ignored_dirs: "logs".to_string(), excluded_extensions: "png".to_string(), ..config() };
+        let plan = Artifact::plan(&fs, &config).unwrap();
+        assert_eq!(planned_paths(&plan), ["src/main.rs"]);
+    }
+
+    #[test]
+    fn plan_keeps_only_included_extensions() {
+        let fs = memory_fs(&[("src/lib.rs", b"pub fn f() {}\n"), ("README.md", b"# Title\n"), ("Makefile", b"all:\n")]);
+        let config = Config { included_extensions: "rs".to_string(), ..config() };
+        let plan = Artifact::plan(&fs, &config).unwrap();
+        assert_eq!(planned_paths(&plan), ["src/lib.rs"]);
+    }
+
+    #[test]
+    fn plan_applies_include_and_exclude_globs() {
+        let fs = memory_fs(&[
+            ("src/lib.rs", b"pub fn f() {}\n"),
+            ("src/lib_test.rs", b"fn t() {}\n"),
+            ("src/nested/mod.rs", b"pub mod a;\n"),
+            ("docs/guide.md", b"# Guide\n"),
+        ]);
+        let config = Config {
+            include_globs: vec!["src/**/*.rs".to_string()],
+            exclude_globs: vec!["**/*_test.rs".to_string()],
+            ..config()
+        };
+        let plan = Artifact::plan(&fs, &config).unwrap();
+        assert_eq!(planned_paths(&plan), ["src/lib.rs", "src/nested/mod.rs"]);
+    }
+
+    #[test]
+    fn plan_skips_temp_files_by_default() {
+        let fs = memory_fs(&[("src/main.rs", b"fn main() {}\n"), ("src/main.rs~", b"fn main() {}\n"), (".DS_Store", b"x")]);
+        let plan = Artifact::plan(&fs, &config()).unwrap();
+        assert_eq!(planned_paths(&plan), ["src/main.rs"]);
+    }
+
+    #[test]
+    fn plan_applies_size_limits() {
+        let fs = memory_fs(&[
+            ("small.rs", b"fn a() {}\n"),
+            ("large.rs", &[b'a'; 2048]),
+            ("data.json", &[b'1'; 600]),
+        ]);
+        let mut config = Config { max_file_size: Some(1024), ..config() };
+        config.max_file_size_per_extension.insert("json".to_string(), 512);
+        let plan = Artifact::plan(&fs, &config).unwrap();
+        assert_eq!(planned_paths(&plan), ["small.rs"]);
+    }
+
+    #[test]
+    fn plan_excludes_files_matched_by_an_exclude_rule() {
+        let fs = memory_fs(&[("src/main.rs", b"fn main() {}\n"), ("src/generated/api.rs", b"pub fn api() {}\n")]);
+        let config = Config { rules: rules("[[rules]]\npattern = \"src/generated/**\"\nexclude = true\n"), ..config() };
+        let plan = Artifact::plan(&fs, &config).unwrap();
+        assert_eq!(planned_paths(&plan), ["src/main.rs"]);
+    }
+
+    #[test]
+    fn plan_renames_colliding_names() {
+        let fs = memory_fs(&[("src/a/b.rs", b"fn one() {}\n"), ("src/a_b.rs", b"fn two() {}\n")]);
+        let plan = Artifact::plan(&fs, &config()).unwrap();
+        let names: Vec<&str> = plan.files.iter().map(|planned| planned.new_filename.as_str()).collect();
+        assert_eq!(names[0], "src_a_b.rs");
+        assert_ne!(names[1], "src_a_b.rs");
+        assert!(names[1].starts_with("src_a_b-") && names[1].ends_with(".rs"), "{}", names[1]);
+    }
+
+    #[test]
+    fn plan_fails_on_collision_when_asked_to() {
+        let fs = memory_fs(&[("src/a/b.rs", b"fn one() {}\n"), ("src/a_b.rs", b"fn two() {}\n")]);
+        let config = Config { fail_on_collision: true, ..config() };
+        assert!(matches!(Artifact::plan(&fs, &config), Err(ArtifactError::Collision { .. })));
+    }
+
+    #[test]
+    fn execute_truncates_files_matched_by_a_rule() {
+        let fs = memory_fs(&[("CHANGELOG.md", b"# Changes\n\n- one\n- two\n- three\n")]);
+        let config = Config { rules: rules("[[rules]]\npattern = \"CHANGELOG.md\"\ntruncate = 2\n"), ..config() };
+        let artifacts = Artifact::collect(&fs, &config).unwrap();
+        assert_eq!(artifacts.len(), 1);
+        assert!(artifacts[0].content.starts_with("# Changes\n"));
+        assert!(!artifacts[0].content.contains("- two"));
+    }
+
+    #[test]
+    fn execute_skips_binary_files_by_default() {
+        let fs = memory_fs(&[("main.rs", b"fn main() {}\n"), ("image.bin", b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR")]);
+        let artifacts = Artifact::collect(&fs, &config()).unwrap();
+        let names: Vec<&str> = artifacts.iter().map(|artifact| artifact.new_filename.as_str()).collect();
+        assert_eq!(names, ["main.rs"]);
+    }
+
+    #[test]
+    fn execute_writes_binary_placeholders_and_base64() {
+        let bytes: &[u8] = b"\x00\x01\x02\x03binary";
+        let fs = memory_fs(&[("blob.bin", bytes)]);
+
+        let config = Config { binary_mode: BinaryMode::Placeholder, ..config() };
+        let artifacts = Artifact::collect(&fs, &config).unwrap();
+        assert_eq!(artifacts[0].binary, Some(BinaryMode::Placeholder));
+        assert!(artifacts[0].content.starts_with("[binary file omitted]\npath: blob.bin\n"));
+
+        let config = Config { binary_mode: BinaryMode::Base64, ..config };
+        let artifacts = Artifact::collect(&fs, &config).unwrap();
+        assert_eq!(artifacts[0].binary, Some(BinaryMode::Base64));
+        assert_eq!(binary::decode(&artifacts[0].content).unwrap(), bytes);
+    }
+
+    #[test]
+    fn execute_skips_empty_files_and_merges_duplicates() {
+        let fs = memory_fs(&[
+            ("a.rs", b"fn same() {}\n"),
+            ("b.rs", b"fn same() {}\n"),
+            ("empty.rs", b"\n\n"),
+            ("comments.rs", b"// only a comment\n"),
+        ]);
+        let artifacts = Artifact::collect(&fs, &config()).unwrap();
+        assert_eq!(artifacts.len(), 1);
+        assert_eq!(artifacts[0].relative_path, Path::new("a.rs"));
+        assert_eq!(artifacts[0].duplicates, [PathBuf::from("b.rs")]);
+    }
+
+    /// Collects from `dir/project` into `dir/out` and writes the artifacts, leaving the
+    /// cache behind for the next run.
+    fn cached_run(dir: &Path) -> Vec<Artifact> {
+        let config = Config {
+            source_dir: dir.join("project"),
+            dest_dir: dir.join("out"),
+            expand_includes: true,
+            ..Config::default()
+        };
+        let artifacts = Artifact::collect(&RealFs, &config).unwrap();
+        DirectoryWriter::from_config(&config).write(&artifacts).unwrap();
+        artifacts
+    }
+
+    fn write_with_mtime(path: &Path, content: &str, modified: SystemTime) {
+        fs::write(path, content).unwrap();
+        fs::File::options().write(true).open(path).unwrap().set_modified(modified).unwrap();
+    }
+
+    fn content_of<'a>(artifacts: &'a [Artifact], relative_path: &str) -> &'a str {
+        &artifacts.iter().find(|artifact| artifact.relative_path == Path::new(relative_path)).unwrap().content
+    }
+
+    #[test]
+    fn execute_reuses_cached_artifacts_of_unchanged_files_without_reading_them() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("project/a.rs");
+        fs::create_dir_all(source.parent().unwrap()).unwrap();
+        let modified = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        write_with_mtime(&source, "fn a() {}\n", modified);
+        cached_run(dir.path());
+
+        // Same size and modification time: the cached artifact is used and the file is not read.
+        write_with_mtime(&source, "fn b() {}\n", modified);
+        assert_eq!(content_of(&cached_run(dir.path()), "a.rs"), "fn a() {}\n");
+
+        write_with_mtime(&source, "fn b() {}\n", modified + Duration::from_secs(1));
+        let artifacts = cached_run(dir.path());
+        assert_eq!(content_of(&artifacts, "a.rs"), "fn b() {}\n");
+        assert_eq!(artifacts[0].checksum, checksum::short_hash("fn b() {}\n"));
+    }
+
+    #[test]
+    fn execute_rebuilds_cached_artifacts_whose_includes_changed() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("project");
+        fs::create_dir_all(&project).unwrap();
+        let modified = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        write_with_mtime(&project.join("README.md"), "# Guide\n\n{{#include part.md}}\n", modified);
+        write_with_mtime(&project.join("part.md"), "first\n", modified);
+        assert_eq!(content_of(&cached_run(dir.path()), "README.md"), "# Guide\n\nfirst\n");
+
+        write_with_mtime(&project.join("part.md"), "second\n", modified + Duration::from_secs(1));
+        let artifacts = cached_run(dir.path());
+        let readme = artifacts.iter().find(|artifact| artifact.relative_path == Path::new("README.md")).unwrap();
+        assert_eq!(readme.content, "# Guide\n\nsecond\n");
+        assert_eq!(readme.checksum, checksum::short_hash(&readme.content));
+        assert_eq!(fs::read_to_string(dir.path().join("out").join(&readme.new_filename)).unwrap(), readme.content);
+    }
+}
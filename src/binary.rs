@@ -0,0 +1,135 @@
+use std::path::Path;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use crate::preflight::format_bytes;
+
+/// How many leading bytes are inspected when classifying a file.
+const SNIFF_LEN: usize = 8_000;
+
+/// Width of the lines base64 content is wrapped at.
+const BASE64_LINE_LEN: usize = 76;
+
+/// File signatures: the offset of the magic bytes, the magic bytes, and a description.
+const SIGNATURES: &[(usize, &[u8], &str)] = &[
+    (0, b"\x89PNG\r\n\x1a\n", "PNG image"),
+    (0, b"\xff\xd8\xff", "JPEG image"),
+    (0, b"GIF87a", "GIF image"),
+    (0, b"GIF89a", "GIF image"),
+    (8, b"WEBP", "WebP image"),
+    (0, b"\x00\x00\x01\x00", "ICO image"),
+    (0, b"%PDF-", "PDF document"),
+    (0, b"PK\x03\x04", "ZIP archive"),
+    (0, b"\x1f\x8b", "gzip archive"),
+    (0, b"BZh", "bzip2 archive"),
+    (0, b"\xfd7zXZ\x00", "xz archive"),
+    (0, b"7z\xbc\xaf\x27\x1c", "7-Zip archive"),
+    (0, b"\x7fELF", "ELF executable"),
+    (0, b"\xcf\xfa\xed\xfe", "Mach-O executable"),
+    (0, b"\xce\xfa\xed\xfe", "Mach-O executable"),
+    (0, b"\xca\xfe\xba\xbe", "Mach-O universal binary or Java class"),
+    (0, b"MZ", "Windows executable"),
+    (0, b"\x00asm", "WebAssembly module"),
+    (0, b"SQLite format 3\x00", "SQLite database"),
+    (0, b"wOFF", "WOFF font"),
+    (0, b"wOF2", "WOFF2 font"),
+    (0, b"\x00\x01\x00\x00", "TrueType font"),
+    (0, b"OTTO", "OpenType font"),
+    (0, b"ID3", "MP3 audio"),
+    (0, b"OggS", "Ogg media"),
+    (0, b"fLaC", "FLAC audio"),
+    (8, b"WAVE", "WAV audio"),
+    (4, b"ftyp", "MP4 media"),
+];
+
+/// Checks whether file content is binary rather than text.
+///
+/// Content is binary if its first 8000 bytes contain a NUL byte (the heuristic git uses),
+/// or if it is not valid UTF-8 and starts with the signature of a known binary format.
+///
+/// # Arguments
+///
+/// * `bytes` - The content of the file.
+///
+/// # Returns
+///
+/// Returns `true` if the content is binary, `false` otherwise.
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(SNIFF_LEN)].contains(&0)
+        || (std::str::from_utf8(bytes).is_err() && signature(bytes).is_some())
+}
+
+/// Describes the type of binary content from its leading bytes.
+///
+/// # Arguments
+///
+/// * `bytes` - The content of the file.
+///
+/// # Returns
+///
+/// A description such as `"PNG image"`, or `"binary data"` if the format is not recognized.
+pub fn describe(bytes: &[u8]) -> &'static str {
+    signature(bytes).unwrap_or("binary data")
+}
+
+/// Finds the known format whose signature the content starts with.
+fn signature(bytes: &[u8]) -> Option<&'static str> {
+    SIGNATURES
+        .iter()
+        .find(|(offset, magic, _)| bytes.get(*offset..*offset + magic.len()) == Some(*magic))
+        .map(|(_, _, description)| *description)
+}
+
+/// Renders the stub that stands in for a binary file with `--binary-mode placeholder`.
+///
+/// # Arguments
+///
+/// * `relative_path` - The path of the file relative to the source directory.
+/// * `bytes` - The content of the file.
+///
+/// # Returns
+///
+/// A `String` noting the file's path, size, and type.
+pub fn placeholder(relative_path: &Path, bytes: &[u8]) -> String {
+    format!(
+        "[binary file omitted]\npath: {}\nsize: {} ({} bytes)\ntype: {}\n",
+        relative_path.to_string_lossy().replace('\\', "/"),
+        format_bytes(bytes.len() as u64),
+        bytes.len(),
+        describe(bytes)
+    )
+}
+
+/// Encodes binary content as base64 for `--binary-mode base64`, wrapped at 76 columns.
+///
+/// # Arguments
+///
+/// * `bytes` - The content of the file.
+///
+/// # Returns
+///
+/// A `String` containing the encoded content, ending in a newline unless it is empty.
+pub fn encode(bytes: &[u8]) -> String {
+    let encoded = STANDARD.encode(bytes);
+    let mut wrapped = String::with_capacity(encoded.len() + encoded.len() / BASE64_LINE_LEN + 1);
+    for line in encoded.as_bytes().chunks(BASE64_LINE_LEN) {
+        // Base64 output is ASCII, so every chunk is valid UTF-8.
+        wrapped.push_str(std::str::from_utf8(line).expect("base64 is ASCII"));
+        wrapped.push('\n');
+    }
+    wrapped
+}
+
+/// Decodes content written with `--binary-mode base64`.
+///
+/// # Arguments
+///
+/// * `text` - The wrapped base64 text.
+///
+/// # Returns
+///
+/// Returns `Result<Vec<u8>, base64::DecodeError>` containing the original bytes,
+/// or an error if the text is not valid base64.
+pub fn decode(text: &str) -> Result<Vec<u8>, base64::DecodeError> {
+    let joined: String = text.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+    STANDARD.decode(joined)
+}
@@ -0,0 +1,281 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use crate::artifact::Artifact;
+use crate::checksum;
+use crate::config::{BinaryMode, Config};
+use crate::plugin;
+use crate::redact::Redaction;
+use crate::vfs::FileSystem;
+
+/// Name of the cache file kept in the destination directory between runs.
+pub const CACHE_FILE: &str = ".rustifacts-cache.json";
+
+/// The size and modification time of a source file when it was read.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStamp {
+    pub len: u64,
+    /// Nanoseconds since the Unix epoch.
+    pub modified: u128,
+}
+
+impl FileStamp {
+    /// Creates a stamp from a file's size and modification time.
+    ///
+    /// # Arguments
+    ///
+    /// * `len` - The size of the file in bytes.
+    /// * `modified` - When the file was last modified, if known.
+    ///
+    /// # Returns
+    ///
+    /// `Some(FileStamp)` if the modification time is known, `None` otherwise.
+    pub fn new(len: u64, modified: Option<SystemTime>) -> Option<Self> {
+        let modified = modified?.duration_since(UNIX_EPOCH).ok()?.as_nanos();
+        Some(Self { len, modified })
+    }
+}
+
+/// A file besides the source file that an artifact's content was built from, such as
+/// a Markdown include.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    pub path: PathBuf,
+    /// The file's stamp just before it was read, or `None` if it could not be read or its
+    /// modification time is unknown.
+    pub stamp: Option<FileStamp>,
+}
+
+impl Dependency {
+    /// Checks whether the file still has the stamp it was read at.
+    ///
+    /// # Arguments
+    ///
+    /// * `fs` - The filesystem the file was read from.
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if the stamp is known and the file's current size and modification
+    /// time match it.
+    pub fn is_unchanged(&self, fs: &dyn FileSystem) -> bool {
+        let current = fs.metadata(&self.path).ok().and_then(|metadata| FileStamp::new(metadata.len, metadata.modified));
+        self.stamp.is_some() && current == self.stamp
+    }
+}
+
+/// What the previous run recorded about one source file.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CacheEntry {
+    pub stamp: FileStamp,
+    pub checksum: String,
+    pub tokens: usize,
+    pub new_filename: String,
+    /// The artifact's content, which is reused as long as the source file and its
+    /// dependencies are unchanged.
+    pub content: String,
+    pub redactions: Vec<Redaction>,
+    pub binary: Option<BinaryMode>,
+    pub encoding: Option<String>,
+    pub dependencies: Vec<Dependency>,
+}
+
+/// Per-file artifacts from the previous run, used to skip work for unchanged files.
+///
+/// Entries are only trusted when the settings that shape an artifact's content and
+/// name are the same as when they were recorded.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Cache {
+    settings: String,
+    entries: BTreeMap<String, CacheEntry>,
+}
+
+impl Cache {
+    /// Creates an empty cache for the given settings fingerprint.
+    pub fn new(settings: &str) -> Self {
+        Self {
+            settings: settings.to_string(),
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Loads the cache a `DirectoryWriter` left in the destination for this configuration.
+    ///
+    /// The cache is only used when writing loose files into dest_dir without `--run-dirs`,
+    /// and never with `--force`.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The configuration options.
+    ///
+    /// # Returns
+    ///
+    /// The cache from the previous run, or an empty cache if it is missing, unreadable,
+    /// disabled, or was recorded with different settings.
+    pub fn for_config(config: &Config) -> Self {
+        let settings = settings_fingerprint(config);
+        let enabled = !config.force && !config.run_dirs && config.single_file.is_none() && config.output_archive.is_none();
+        if enabled {
+            Self::load(&config.dest_dir, &settings)
+        } else {
+            Self::new(&settings)
+        }
+    }
+
+    /// Loads the cache file from a directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The directory holding the cache file.
+    /// * `settings` - The settings fingerprint of the current run.
+    ///
+    /// # Returns
+    ///
+    /// The cache, or an empty one if it is missing, unreadable, or recorded with other settings.
+    pub fn load(dir: &Path, settings: &str) -> Self {
+        let cache = fs::read_to_string(dir.join(CACHE_FILE))
+            .ok()
+            .and_then(|json| serde_json::from_str::<Self>(&json).ok());
+        match cache {
+            Some(cache) if cache.settings == settings => cache,
+            Some(_) => {
+                debug!("Ignoring cache in {}: settings changed", dir.display());
+                Self::new(settings)
+            }
+            None => Self::new(settings),
+        }
+    }
+
+    /// Looks up the entry for a source file if neither it nor any file its content was
+    /// built from changed since it was recorded.
+    ///
+    /// # Arguments
+    ///
+    /// * `fs` - The filesystem the source files are read from.
+    /// * `relative_path` - The path of the file relative to the source directory.
+    /// * `stamp` - The file's current size and modification time.
+    ///
+    /// # Returns
+    ///
+    /// The recorded entry if the file and its dependencies are unchanged, `None` otherwise.
+    pub fn lookup(&self, fs: &dyn FileSystem, relative_path: &Path, stamp: FileStamp) -> Option<&CacheEntry> {
+        self.entries
+            .get(relative_path.to_string_lossy().as_ref())
+            .filter(|entry| entry.stamp == stamp)
+            .filter(|entry| entry.dependencies.iter().all(|dependency| dependency.is_unchanged(fs)))
+    }
+
+    /// Looks up the token count recorded for a source file's artifact, if its content was
+    /// the same as now.
+    ///
+    /// # Arguments
+    ///
+    /// * `relative_path` - The path of the file relative to the source directory.
+    /// * `checksum` - The checksum of the artifact's current content.
+    ///
+    /// # Returns
+    ///
+    /// The recorded token count if the checksums match, `None` otherwise.
+    pub fn tokens(&self, relative_path: &Path, checksum: &str) -> Option<usize> {
+        self.entries
+            .get(relative_path.to_string_lossy().as_ref())
+            .filter(|entry| entry.checksum == checksum)
+            .map(|entry| entry.tokens)
+    }
+
+    /// Checks whether an artifact was already written with the same content and name.
+    ///
+    /// # Arguments
+    ///
+    /// * `artifact` - The artifact about to be written.
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if the previous run wrote identical content under the same name.
+    pub fn is_written(&self, artifact: &Artifact) -> bool {
+        self.entries
+            .get(artifact.relative_path.to_string_lossy().as_ref())
+            .is_some_and(|entry| entry.checksum == artifact.checksum && entry.new_filename == artifact.new_filename)
+    }
+
+    /// Records an artifact read from a source file.
+    ///
+    /// Generated artifacts, which have no stamp, and the parts of split files, which do
+    /// not hold the whole file's content, are not recorded.
+    pub fn record(&mut self, artifact: &Artifact) {
+        let Some(stamp) = artifact.stamp.filter(|_| artifact.part.is_none()) else {
+            return;
+        };
+        self.entries.insert(
+            artifact.relative_path.to_string_lossy().into_owned(),
+            CacheEntry {
+                stamp,
+                checksum: artifact.checksum.clone(),
+                tokens: artifact.tokens,
+                new_filename: artifact.new_filename.clone(),
+                content: artifact.content.clone(),
+                redactions: artifact.redactions.clone(),
+                binary: artifact.binary,
+                encoding: artifact.encoding.clone(),
+                dependencies: artifact.dependencies.clone(),
+            },
+        );
+    }
+
+    /// Writes the cache file into a directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The directory to write the cache file into.
+    ///
+    /// # Returns
+    ///
+    /// Returns `io::Result<()>` indicating success or failure of the write operation.
+    pub fn save(&self, dir: &Path) -> io::Result<()> {
+        let json = serde_json::to_string(self)?;
+        fs::write(dir.join(CACHE_FILE), json)
+    }
+}
+
+/// Fingerprints the settings that decide an artifact's content and written name.
+///
+/// # Arguments
+///
+/// * `config` - The configuration options.
+///
+/// # Returns
+///
+/// A `String` that changes whenever a setting affecting the output changes.
+pub fn settings_fingerprint(config: &Config) -> String {
+    let settings = format!(
+        "{}|{:?}|{:?}|{}|{:?}|{}|{}|{}|{}|{}|{}|{:?}|{:?}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{:?}|{}",
+        env!("CARGO_PKG_VERSION"),
+        config.format,
+        config.binary_mode,
+        config.strict_utf8,
+        config.front_matter,
+        config.expand_includes,
+        config.checksum_header,
+        config.squeeze_blank_lines,
+        config.minify_indent,
+        config.no_tests,
+        config.skeleton,
+        config.transforms,
+        config.transform_cmds,
+        config.convert_notebooks,
+        config.summarize_sql_dumps,
+        config.no_redact,
+        config.flatten_separator,
+        config.preserve_structure,
+        config.keep_dirs,
+        config.name_prefix,
+        config.name_suffix,
+        config.max_filename_length,
+        config.rules,
+        plugin::fingerprint(&config.plugins),
+    );
+    checksum::short_hash(&settings)
+}
@@ -0,0 +1,317 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use walkdir::WalkDir;
+use crate::cache::CACHE_FILE;
+use crate::config::OutputFormat;
+use crate::format;
+use crate::manifest::{Manifest, ManifestEntry, MANIFEST_FILE, MANIFEST_MARKDOWN_FILE};
+
+/// Marker that identifies a rustifacts checksum header line.
+pub const HEADER_MARKER: &str = "rustifacts-checksum: sha256:";
+
+/// Number of hex characters kept from the SHA-256 digest.
+const SHORT_HASH_LEN: usize = 16;
+
+/// Custom error type for checksum verification.
+#[derive(Error, Debug)]
+pub enum ChecksumError {
+    #[error("missing checksum header")]
+    MissingHeader,
+    #[error("checksum mismatch: recorded as {expected}, content hashes to {actual}")]
+    Mismatch { expected: String, actual: String },
+    #[error("the markup around the content was edited")]
+    Markup,
+}
+
+/// Computes a short, stable content hash.
+///
+/// # Arguments
+///
+/// * `content` - The content to hash.
+///
+/// # Returns
+///
+/// A `String` containing the first 16 hex characters of the SHA-256 digest.
+pub fn short_hash(content: &str) -> String {
+    let digest = Sha256::digest(content.as_bytes());
+    let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+    hex[..SHORT_HASH_LEN].to_string()
+}
+
+/// Builds the checksum header line for a file, using the comment syntax of its extension.
+///
+/// Formats without comments, such as JSON and CSV, get no header, since any line added
+/// to them would break consumers that parse them. Their checksum is only recorded in the
+/// manifest.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file, used to pick the comment syntax.
+/// * `hash` - The short content hash to embed.
+///
+/// # Returns
+///
+/// `Some(String)` containing the header line, without a trailing newline, or `None` if the
+/// file's format has no comments.
+pub fn header_line(path: &Path, hash: &str) -> Option<String> {
+    let (open, close) = comment_delimiters(&extension(path))?;
+    if close.is_empty() {
+        Some(format!("{} {}{}", open, HEADER_MARKER, hash))
+    } else {
+        Some(format!("{} {}{} {}", open, HEADER_MARKER, hash, close))
+    }
+}
+
+/// Checks whether a file's format can hold a checksum header line.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file.
+///
+/// # Returns
+///
+/// Returns `true` unless the file's extension names a format without comments.
+pub fn supports_header(path: &Path) -> bool {
+    comment_delimiters(&extension(path)).is_some()
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+/// Returns the line comment delimiters for a file extension.
+///
+/// Extensions without a known comment syntax fall back to `#`, and formats that cannot
+/// hold comments at all return `None`.
+fn comment_delimiters(extension: &str) -> Option<(&'static str, &'static str)> {
+    match extension {
+        "json" | "jsonl" | "ndjson" | "geojson" | "ipynb" | "webmanifest" | "csv" | "tsv" => None,
+        "rs" | "js" | "jsx" | "ts" | "tsx" | "mjs" | "cjs" | "jsonc" | "c" | "h" | "cc" | "cpp"
+        | "hpp" | "java" | "kt" | "kts" | "go" | "swift" | "cs" | "scala" | "php" | "dart"
+        | "proto" | "gradle" => Some(("//", "")),
+        "css" | "scss" | "less" => Some(("/*", "*/")),
+        "md" | "mdx" | "html" | "htm" | "xml" | "svg" | "vue" | "svelte" => Some(("<!--", "-->")),
+        "sql" | "lua" | "hs" => Some(("--", "")),
+        _ => Some(("#", "")),
+    }
+}
+
+/// Verifies the checksum header of an artifact's text.
+///
+/// # Arguments
+///
+/// * `text` - The full artifact text, including the header line.
+///
+/// # Returns
+///
+/// Returns `Result<&str, ChecksumError>` containing the content without its header
+/// if the checksum matches, or a `ChecksumError` otherwise.
+pub fn verify(text: &str) -> Result<&str, ChecksumError> {
+    let (first_line, body) = text.split_once('\n').ok_or(ChecksumError::MissingHeader)?;
+    let start = first_line.find(HEADER_MARKER).ok_or(ChecksumError::MissingHeader)?;
+    let expected: String = first_line[start + HEADER_MARKER.len()..]
+        .chars()
+        .take_while(|c| c.is_ascii_hexdigit())
+        .collect();
+    let actual = short_hash(body);
+    if expected == actual {
+        Ok(body)
+    } else {
+        Err(ChecksumError::Mismatch { expected, actual })
+    }
+}
+
+/// Verifies an artifact without a header line against the checksum its manifest records.
+///
+/// # Arguments
+///
+/// * `text` - The full artifact text.
+/// * `expected` - The checksum recorded in the manifest.
+///
+/// # Returns
+///
+/// Returns `Result<&str, ChecksumError>` containing the text if it hashes to `expected`,
+/// or a `ChecksumError` otherwise.
+pub fn verify_recorded<'a>(text: &'a str, expected: &str) -> Result<&'a str, ChecksumError> {
+    if expected.is_empty() {
+        return Err(ChecksumError::MissingHeader);
+    }
+    let actual = short_hash(text);
+    if actual == expected {
+        Ok(text)
+    } else {
+        Err(ChecksumError::Mismatch { expected: expected.to_string(), actual })
+    }
+}
+
+/// Removes the checksum header line from an artifact's text, if it has one.
+///
+/// # Arguments
+///
+/// * `text` - The full artifact text.
+///
+/// # Returns
+///
+/// A `&str` containing the content without its header line.
+pub fn strip_header(text: &str) -> &str {
+    match text.split_once('\n') {
+        Some((first_line, body)) if first_line.contains(HEADER_MARKER) => body,
+        _ => text,
+    }
+}
+
+/// Verifies an artifact listed in a manifest and recovers the content it was rendered from.
+///
+/// A checksum header covers the artifact as it was rendered in `format`. An artifact
+/// without one is verified against the checksum the manifest records for its content.
+///
+/// # Arguments
+///
+/// * `text` - The full artifact text.
+/// * `format` - The format the artifact was rendered in.
+/// * `entry` - The artifact's manifest entry.
+///
+/// # Returns
+///
+/// A tuple of the artifact's content, as far as it could be recovered, and the outcome of
+/// verifying it: `ChecksumError::MissingHeader` if there was nothing to verify it against.
+pub fn verify_artifact(text: &str, format: OutputFormat, entry: &ManifestEntry) -> (String, Result<(), ChecksumError>) {
+    let (body, header) = match verify(text) {
+        Ok(body) => (body, Some(Ok(()))),
+        Err(ChecksumError::MissingHeader) => (text, None),
+        Err(e) => (strip_header(text), Some(Err(e))),
+    };
+    let Some(content) = format::unrender(format, body, entry.size) else {
+        return (body.to_string(), Err(header.and_then(Result::err).unwrap_or(ChecksumError::Markup)));
+    };
+    let result = header.unwrap_or_else(|| verify_recorded(&content, &entry.checksum).map(|_| ()));
+    (content, result)
+}
+
+/// Verifies every artifact file in a directory and its subdirectories.
+///
+/// Artifacts listed in the manifest of the directory they were written into are verified
+/// with [`verify_artifact`], so those written without a checksum header are checked against
+/// the manifest. Any other file must carry a header.
+///
+/// # Arguments
+///
+/// * `dir` - The directory containing written artifacts.
+///
+/// # Returns
+///
+/// Returns `io::Result<Vec<(PathBuf, ChecksumError)>>` listing each file that failed verification.
+pub fn verify_dir(dir: &Path) -> io::Result<Vec<(PathBuf, ChecksumError)>> {
+    let mut failures = Vec::new();
+    if !dir.is_dir() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("{} is not a directory", dir.display())));
+    }
+    let mut entries: Vec<PathBuf> = WalkDir::new(dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .map(|entry| entry.into_path())
+        .filter(|path| path.is_file() && !path.ends_with(MANIFEST_FILE) && !path.ends_with(MANIFEST_MARKDOWN_FILE) && !path.ends_with(CACHE_FILE))
+        .collect();
+    entries.sort();
+
+    let mut manifests = HashMap::new();
+    for path in entries {
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(_) => continue,
+        };
+        let result = match recorded_entry(dir, &path, &mut manifests) {
+            Some((format, entry)) => verify_artifact(&text, format, entry).1,
+            None => verify(&text).map(|_| ()),
+        };
+        if let Err(e) = result {
+            failures.push((path, e));
+        }
+    }
+    Ok(failures)
+}
+
+/// Finds the entry for an artifact in the manifest of the nearest directory between it and
+/// `root` that has one, along with the format the manifest records.
+fn recorded_entry<'a>(
+    root: &Path,
+    path: &Path,
+    manifests: &'a mut HashMap<PathBuf, Option<Manifest>>,
+) -> Option<(OutputFormat, &'a ManifestEntry)> {
+    let ancestors: Vec<&Path> = path.ancestors().skip(1).take_while(|ancestor| ancestor.starts_with(root)).collect();
+    for ancestor in &ancestors {
+        manifests.entry(ancestor.to_path_buf()).or_insert_with(|| Manifest::read_from(ancestor));
+    }
+    let (ancestor, manifest) = ancestors
+        .into_iter()
+        .find_map(|ancestor| manifests.get(ancestor)?.as_ref().map(|manifest| (ancestor, manifest)))?;
+    let name = path.strip_prefix(ancestor).ok()?.to_string_lossy().replace('\\', "/");
+    let entry = manifest.artifacts.iter().find(|entry| entry.new_filename == name)?;
+    Some((manifest.format, entry))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_uses_the_comment_syntax_of_the_extension() {
+        assert_eq!(header_line(Path::new("main.rs"), "abc").unwrap(), "// rustifacts-checksum: sha256:abc");
+        assert_eq!(header_line(Path::new("style.css"), "abc").unwrap(), "/* rustifacts-checksum: sha256:abc */");
+        assert_eq!(header_line(Path::new("Makefile"), "abc").unwrap(), "# rustifacts-checksum: sha256:abc");
+    }
+
+    #[test]
+    fn formats_without_comments_get_no_header() {
+        for name in ["package.json", "data.csv", "notebook.ipynb"] {
+            assert!(header_line(Path::new(name), "abc").is_none(), "{}", name);
+            assert!(!supports_header(Path::new(name)), "{}", name);
+        }
+        assert!(supports_header(Path::new("tsconfig.jsonc")));
+    }
+
+    #[test]
+    fn verify_detects_edits() {
+        let body = "fn main() {}\n";
+        let text = format!("{}\n{}", header_line(Path::new("main.rs"), &short_hash(body)).unwrap(), body);
+        assert_eq!(verify(&text).unwrap(), body);
+        assert!(matches!(verify(&text.replace("main", "other")), Err(ChecksumError::Mismatch { .. })));
+        assert!(matches!(verify(body), Err(ChecksumError::MissingHeader)));
+    }
+
+    #[test]
+    fn verify_recorded_compares_with_the_manifest_checksum() {
+        let text = "{\"a\": 1}\n";
+        assert_eq!(verify_recorded(text, &short_hash(text)).unwrap(), text);
+        assert!(matches!(verify_recorded("{\"a\": 2}\n", &short_hash(text)), Err(ChecksumError::Mismatch { .. })));
+        assert!(matches!(verify_recorded(text, ""), Err(ChecksumError::MissingHeader)));
+    }
+
+    #[test]
+    fn verify_dir_checks_header_less_artifacts_against_the_manifest() {
+        use crate::artifact::Artifact;
+        use crate::config::Config;
+        use crate::writer::{DirectoryWriter, Writer};
+
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::default();
+        let artifacts: Vec<Artifact> = [("src/main.rs", "fn main() {}\n"), ("package.json", "{}\n")]
+            .iter()
+            .map(|(path, content)| Artifact::generated(PathBuf::from(path), PathBuf::from(path), content.to_string(), &config))
+            .collect();
+        DirectoryWriter::new(dir.path()).write(&artifacts).unwrap();
+        assert!(verify_dir(dir.path()).unwrap().is_empty());
+
+        fs::write(dir.path().join("src_main.rs"), "fn main() { edited() }\n").unwrap();
+        let failures = verify_dir(dir.path()).unwrap();
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].0.ends_with("src_main.rs"));
+        assert!(matches!(failures[0].1, ChecksumError::Mismatch { .. }));
+    }
+}
@@ -0,0 +1,168 @@
+use std::ops::Range;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use tree_sitter::Parser;
+use crate::skeleton::Grammar;
+use crate::tokens;
+
+/// The size above which a file is split into parts, set with `--split-tokens` and
+/// `--split-lines`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// The most tokens a part may hold.
+    pub tokens: Option<usize>,
+    /// The most lines a part may hold.
+    pub lines: Option<usize>,
+}
+
+impl Limits {
+    /// Returns `true` if either limit is set.
+    pub fn is_set(&self) -> bool {
+        self.tokens.is_some() || self.lines.is_some()
+    }
+}
+
+/// Where an artifact sits among the parts of a file that was split.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Part {
+    /// The 1-based number of the part.
+    pub index: usize,
+    /// How many parts the file was split into.
+    pub count: usize,
+    /// The 1-based line of the file the part starts on.
+    pub start_line: usize,
+    /// The 1-based line of the file the part ends on, inclusive.
+    pub end_line: usize,
+}
+
+/// Splits content that exceeds the limits into parts at sensible boundaries.
+///
+/// Parts start at top-level items, such as functions, classes, and impl blocks, together
+/// with the comments and attributes directly above them, in the languages `--skeleton`
+/// supports. Items too large for one part, and files in other languages or that cannot
+/// be parsed, are split where a blank line ends a paragraph, and failing that between
+/// lines. Consecutive items are packed into a part for as long as it stays within the
+/// limits. Token counts are the sum of each line's, so they are approximate.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file, used to choose its language.
+/// * `content` - The content to split.
+/// * `limits` - The most tokens and lines a part may hold.
+///
+/// # Returns
+///
+/// A `Vec` of the 0-based line ranges of the parts, or an empty `Vec` if the content is
+/// within the limits.
+///
+/// # Example
+///
+/// ```
+/// use std::path::Path;
+/// use rustifacts::chunk::{split, Limits};
+///
+/// let source = "fn one() {\n    1\n}\n\nfn two() {\n    2\n}\n";
+/// let limits = Limits { tokens: None, lines: Some(4) };
+/// assert_eq!(split(Path::new("lib.rs"), source, limits), vec![0..4, 4..7]);
+/// assert!(split(Path::new("lib.rs"), source, Limits { tokens: None, lines: Some(10) }).is_empty());
+/// ```
+pub fn split(path: &Path, content: &str, limits: Limits) -> Vec<Range<usize>> {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let mut token_sums = vec![0];
+    if limits.tokens.is_some() {
+        for line in &lines {
+            token_sums.push(token_sums[token_sums.len() - 1] + tokens::count(line));
+        }
+    }
+    let fits = |range: &Range<usize>| {
+        limits.lines.is_none_or(|max| range.len() <= max)
+            && limits.tokens.is_none_or(|max| token_sums[range.end] - token_sums[range.start] <= max)
+    };
+    if fits(&(0..lines.len())) {
+        return Vec::new();
+    }
+
+    let levels = [item_starts(path, content), paragraph_starts(&lines), (1..lines.len()).collect()];
+    let mut parts = Vec::new();
+    pack(0..lines.len(), &levels, &fits, &mut parts);
+    parts
+}
+
+/// Packs a range of lines into parts, cutting only at the starts in the first level and
+/// splitting any part that is still too large with the next level.
+fn pack(range: Range<usize>, levels: &[Vec<usize>], fits: &dyn Fn(&Range<usize>) -> bool, parts: &mut Vec<Range<usize>>) {
+    let Some((starts, finer)) = levels.split_first() else {
+        // A single line that exceeds the limits is kept whole.
+        parts.push(range);
+        return;
+    };
+    let cuts = starts.iter().copied().filter(|&start| start > range.start && start < range.end).chain([range.end]);
+    let mut start = range.start;
+    let mut end = range.start;
+    for cut in cuts {
+        if fits(&(start..cut)) {
+            end = cut;
+            continue;
+        }
+        if end > start {
+            parts.push(start..end);
+            start = end;
+        }
+        if fits(&(start..cut)) {
+            end = cut;
+        } else {
+            pack(start..cut, finer, fits, parts);
+            start = cut;
+            end = cut;
+        }
+    }
+    if end > start {
+        parts.push(start..end);
+    }
+}
+
+/// Finds the lines that top-level items start on, including the comments and attributes
+/// directly above them.
+fn item_starts(path: &Path, content: &str) -> Vec<usize> {
+    let Some(grammar) = Grammar::for_path(path) else {
+        return Vec::new();
+    };
+    let mut parser = Parser::new();
+    if parser.set_language(&grammar.language()).is_err() {
+        return Vec::new();
+    }
+    let Some(tree) = parser.parse(content, None) else {
+        return Vec::new();
+    };
+    if tree.root_node().has_error() {
+        return Vec::new();
+    }
+
+    let mut starts = Vec::new();
+    // The first line of the comments and attributes above the next item, and where they end.
+    let mut leading: Option<(usize, usize)> = None;
+    let mut cursor = tree.walk();
+    for child in tree.root_node().children(&mut cursor) {
+        let (row, end_row) = (child.start_position().row, child.end_position().row);
+        if child.kind().contains("comment") || child.kind() == "attribute_item" {
+            leading = match leading {
+                Some((first, last)) if row <= last + 1 => Some((first, end_row)),
+                _ => Some((row, end_row)),
+            };
+            continue;
+        }
+        let start = match leading.take() {
+            Some((first, last)) if row <= last + 1 => first,
+            _ => row,
+        };
+        starts.push(start);
+    }
+    starts
+}
+
+/// Finds the lines that follow a blank line and start a paragraph.
+fn paragraph_starts(lines: &[&str]) -> Vec<usize> {
+    (1..lines.len())
+        .filter(|&index| lines[index - 1].trim().is_empty() && !lines[index].trim().is_empty())
+        .collect()
+}
@@ -0,0 +1,213 @@
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use log::{debug, info, warn};
+use thiserror::Error;
+use walkdir::WalkDir;
+use crate::cache::CACHE_FILE;
+use crate::delta::DELTA_DIR;
+use crate::manifest::{Manifest, MANIFEST_FILE, MANIFEST_MARKDOWN_FILE};
+use crate::runs::{LATEST, RUNS_DIR};
+
+/// Custom error type for cleaning a destination directory.
+#[derive(Error, Debug)]
+pub enum CleanError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Invalid manifest: {0}")]
+    Manifest(#[from] serde_json::Error),
+    #[error("No {MANIFEST_FILE} in {0}; refusing to clean a directory Rustifacts did not write")]
+    MissingManifest(PathBuf),
+    #[error("Refusing to remove {0}: it contains the source directory")]
+    ContainsSource(PathBuf),
+}
+
+/// Counts of what a clean did.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CleanSummary {
+    /// Artifacts deleted from the destination.
+    pub removed: usize,
+    /// Manifest entries whose artifact was already gone.
+    pub missing: usize,
+}
+
+/// Removes the artifacts a previous run wrote into a destination directory.
+///
+/// Only the files listed in each `MANIFEST.json` (in the destination itself, in every
+/// `runs/<run-id>/` directory, and in the `delta/` directory of `re-export`), the manifests, and the incremental cache are deleted.
+/// Directories left empty afterwards are removed too, while any other files are left
+/// alone. With `all`, the whole destination directory is removed instead, provided it
+/// holds a manifest and does not contain the source directory.
+///
+/// # Arguments
+///
+/// * `dest_dir` - The destination directory to clean.
+/// * `source_dir` - The source directory, which `all` must never remove.
+/// * `all` - Whether to remove the whole destination directory.
+///
+/// # Returns
+///
+/// Returns `Result<CleanSummary, CleanError>` describing the removed artifacts,
+/// or a `CleanError` if the destination was not written by rustifacts or cannot be cleaned.
+pub fn clean(dest_dir: &Path, source_dir: &Path, all: bool) -> Result<CleanSummary, CleanError> {
+    info!("Cleaning {}", dest_dir.display());
+    let output_dirs = output_dirs(dest_dir)?;
+    if output_dirs.is_empty() {
+        return Err(CleanError::MissingManifest(dest_dir.to_path_buf()));
+    }
+
+    let mut summary = CleanSummary::default();
+    if all {
+        let dest = dest_dir.canonicalize()?;
+        if source_dir.canonicalize().is_ok_and(|source| source.starts_with(&dest)) {
+            return Err(CleanError::ContainsSource(dest_dir.to_path_buf()));
+        }
+        for dir in &output_dirs {
+            let manifest: Manifest = serde_json::from_str(&fs::read_to_string(dir.join(MANIFEST_FILE))?)?;
+            summary.removed += manifest.artifacts.len();
+        }
+        fs::remove_dir_all(dest_dir)?;
+        return Ok(summary);
+    }
+
+    for dir in &output_dirs {
+        clean_output_dir(dir, &mut summary)?;
+    }
+    remove_if_empty(&dest_dir.join(RUNS_DIR));
+    // `latest` is a symlink on Unix and a file naming the run elsewhere; drop it once its run is gone.
+    let latest = dest_dir.join(LATEST);
+    let dangling = latest.is_symlink() && !latest.exists();
+    if dangling || (latest.is_file() && !dest_dir.join(RUNS_DIR).is_dir()) {
+        fs::remove_file(&latest)?;
+    }
+    remove_if_empty(dest_dir);
+    Ok(summary)
+}
+
+/// Removes files from an output directory that the artifacts about to be written do not account for.
+///
+/// The files listed in the directory's existing manifest are removed unless their names are
+/// in `keep`. With `foreign`, every other file in the directory is removed as well, except
+/// the manifest and cache; otherwise files rustifacts did not write are never touched.
+/// Directories left empty are removed.
+///
+/// # Arguments
+///
+/// * `dir` - The output directory.
+/// * `keep` - The names (relative to `dir`, separated by `/`) of the files to keep.
+/// * `foreign` - Whether to also remove files that are not listed in the manifest.
+///
+/// # Returns
+///
+/// Returns `Result<usize, CleanError>` containing the number of removed files,
+/// or a `CleanError` if the manifest cannot be read or a file cannot be removed.
+pub fn prune(dir: &Path, keep: &HashSet<String>, foreign: bool) -> Result<usize, CleanError> {
+    let mut stale = BTreeSet::new();
+    if Manifest::exists_in(dir) {
+        let manifest: Manifest = serde_json::from_str(&fs::read_to_string(dir.join(MANIFEST_FILE))?)?;
+        stale.extend(
+            manifest
+                .written_files()
+                .filter(|name| Path::new(name).components().all(|component| matches!(component, Component::Normal(_))))
+                .map(String::from),
+        );
+    }
+    if foreign {
+        for path in WalkDir::new(dir).into_iter().filter_map(Result::ok).filter(|entry| entry.file_type().is_file()) {
+            if let Ok(relative) = path.path().strip_prefix(dir) {
+                stale.insert(relative.to_string_lossy().replace('\\', "/"));
+            }
+        }
+        stale.remove(MANIFEST_FILE);
+        stale.remove(MANIFEST_MARKDOWN_FILE);
+        stale.remove(CACHE_FILE);
+    }
+
+    let mut removed = 0;
+    for name in stale.iter().filter(|name| !keep.contains(*name)) {
+        let path = dir.join(name);
+        match fs::remove_file(&path) {
+            Ok(()) => {
+                debug!("Pruned {}", path.display());
+                removed += 1;
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        for parent in path.ancestors().skip(1).take_while(|parent| *parent != dir) {
+            remove_if_empty(parent);
+        }
+    }
+    Ok(removed)
+}
+
+/// Finds the directories beneath the destination that hold a rustifacts manifest.
+fn output_dirs(dest_dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut dirs = Vec::new();
+    if Manifest::exists_in(dest_dir) {
+        dirs.push(dest_dir.to_path_buf());
+    }
+    let delta_dir = dest_dir.join(DELTA_DIR);
+    if Manifest::exists_in(&delta_dir) {
+        dirs.push(delta_dir);
+    }
+    let runs_dir = dest_dir.join(RUNS_DIR);
+    if runs_dir.is_dir() {
+        let mut runs: Vec<PathBuf> = fs::read_dir(&runs_dir)?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| Manifest::exists_in(path))
+            .collect();
+        runs.sort();
+        dirs.extend(runs);
+    }
+    Ok(dirs)
+}
+
+/// Removes the artifacts, manifest, and cache of one output directory.
+fn clean_output_dir(dir: &Path, summary: &mut CleanSummary) -> Result<(), CleanError> {
+    let manifest: Manifest = serde_json::from_str(&fs::read_to_string(dir.join(MANIFEST_FILE))?)?;
+    // With --markdown-per-target several entries share one document.
+    let mut seen = HashSet::new();
+    for new_filename in manifest.written_files().filter(|name| seen.insert(*name)) {
+        let name = Path::new(new_filename);
+        if !name.components().all(|component| matches!(component, Component::Normal(_))) {
+            warn!("Skipping {}: path escapes the destination directory", new_filename);
+            continue;
+        }
+        let path = dir.join(name);
+        match fs::remove_file(&path) {
+            Ok(()) => {
+                debug!("Removed {}", path.display());
+                summary.removed += 1;
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => summary.missing += 1,
+            Err(e) => return Err(e.into()),
+        }
+        // Artifacts written beneath kept directories leave those directories behind.
+        for parent in path.ancestors().skip(1).take_while(|parent| *parent != dir) {
+            remove_if_empty(parent);
+        }
+    }
+    if Manifest::is_markdown_manifest(&dir.join(MANIFEST_MARKDOWN_FILE)) {
+        fs::remove_file(dir.join(MANIFEST_MARKDOWN_FILE))?;
+    }
+    for file in [CACHE_FILE, MANIFEST_FILE] {
+        match fs::remove_file(dir.join(file)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+    }
+    remove_if_empty(dir);
+    Ok(())
+}
+
+/// Removes a directory if it exists and is empty.
+fn remove_if_empty(dir: &Path) {
+    if fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_none()) {
+        if let Err(e) = fs::remove_dir(dir) {
+            warn!("Failed to remove {}: {}", dir.display(), e);
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::BTreeSet;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand};
+use crate::config::Config;
+
+/// Prepares project files for upload as artifacts.
+///
+/// Collection options are global, so they may be given before or after the subcommand
+/// (`rustifacts -s ./app stats` and `rustifacts stats -s ./app` are equivalent).
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None, args_override_self = true)]
+pub struct Cli {
+    /// The subcommand to run; without one, `collect` is run
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub config: Config,
+}
+
+impl Cli {
+    /// Returns the subcommand to run, defaulting to `collect`.
+    pub fn subcommand(&self) -> Command {
+        self.command.clone().unwrap_or(Command::Collect)
+    }
+
+    /// Parses the command line like `Cli::parse_from`, also recording which options were given.
+    ///
+    /// The names of the options given on the command line are stored in
+    /// `Config::explicit_args`, so that config files and presets applied later do not
+    /// override them. Like `Cli::parse_from`, this exits with a usage message on bad input.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - The full command line, including the program name.
+    ///
+    /// # Returns
+    ///
+    /// The parsed `Cli`.
+    pub fn parse_recording_explicit<I, T>(args: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let command = Self::command();
+        let matches = command.clone().get_matches_from(args);
+        let mut cli = Self::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+        cli.config.explicit_args = explicit_args(&command, &matches);
+        cli
+    }
+}
+
+/// Returns the names of the options whose values came from the command line rather than a default.
+fn explicit_args(command: &clap::Command, matches: &ArgMatches) -> BTreeSet<String> {
+    command
+        .get_arguments()
+        .map(|arg| arg.get_id().as_str())
+        .filter(|id| matches.value_source(id).is_some_and(|source| source != ValueSource::DefaultValue))
+        .map(String::from)
+        .collect()
+}
+
+/// The subcommands of `rustifacts presets`.
+#[derive(Subcommand, Debug, Clone)]
+pub enum PresetsCommand {
+    /// List the built-in and user-defined presets (the default)
+    List,
+    /// Print a preset's full configuration as TOML, ready to copy into a configuration file
+    Show {
+        /// The preset to show; several separated by `,` or `+` show their combination
+        name: String,
+    },
+}
+
+/// Parses a command-line argument as JSON.
+fn parse_json(text: &str) -> Result<serde_json::Value, serde_json::Error> {
+    serde_json::from_str(text)
+}
+
+/// The subcommands of `rustifacts daemon`.
+#[derive(Subcommand, Debug, Clone)]
+pub enum DaemonCommand {
+    /// Run the daemon in the foreground (the default)
+    Start,
+    /// Call a JSON-RPC method on the running daemon and print its result as JSON
+    Send {
+        /// The method to call, such as list, query, estimate, stats, collect, write, or upload
+        method: String,
+        /// The method's parameters as a JSON object, e.g. '{"paths": ["src/main.rs"]}'
+        #[arg(value_parser = parse_json)]
+        params: Option<serde_json::Value>,
+    },
+    /// Stop the running daemon
+    Stop,
+}
+
+/// The subcommands of the command-line interface.
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Collect artifacts from the source and write them to the destination (the default)
+    Collect,
+    /// Write a starter rustifacts.toml into the current directory, seeded from --preset if one is given
+    Init,
+    /// List the available presets, or show what one contains
+    Presets {
+        #[command(subcommand)]
+        action: Option<PresetsCommand>,
+    },
+    /// Un-flatten previously written artifacts back into their original directory tree
+    Restore {
+        /// The artifact directory (with its MANIFEST.json) or --single-file output to restore from
+        #[arg(long, value_name = "PATH")]
+        from: PathBuf,
+        /// The directory to rebuild the original tree in
+        #[arg(long, value_name = "DIR")]
+        to: PathBuf,
+        /// Also restore artifacts whose checksum shows they were edited or corrupted after being written
+        #[arg(long)]
+        allow_modified: bool,
+    },
+    /// Remove the artifacts listed in the destination's manifest, leaving other files alone
+    Clean {
+        /// Remove the whole destination directory instead of only the listed artifacts
+        #[arg(long)]
+        all: bool,
+    },
+    /// Collect artifacts, then upload the written files to a target declared in the config file
+    Upload {
+        /// The name of the target, as declared under [targets.<name>]
+        #[arg(long, value_name = "NAME")]
+        target: String,
+    },
+    /// Collect artifacts like collect, then copy them into the destination's delta/ directory for re-uploading
+    ReExport {
+        /// Copy only the artifacts whose content changed since the destination's previous manifest
+        #[arg(long)]
+        only_changed: bool,
+    },
+    /// Write artifacts, then keep the destination in sync as source files change
+    Watch {
+        /// Milliseconds the source must stay quiet before changes are synced
+        #[arg(long, default_value_t = 500)]
+        debounce_ms: u64,
+        /// Upload the initial output and every file rewritten afterwards to the target declared under [targets.<NAME>]
+        #[arg(long, value_name = "NAME")]
+        upload: Option<String>,
+    },
+    /// Report file counts, byte totals, and token totals for the current selection without writing anything
+    Stats {
+        /// Break the totals down per file extension
+        #[arg(long)]
+        by_extension: bool,
+    },
+    /// Collect a small generated example project with --preset (rust if none is given) and print what was written
+    Demo {
+        /// Keep the example project and its output instead of removing them afterwards
+        #[arg(long)]
+        keep: bool,
+    },
+    /// Serve collection requests over a local socket, keeping the walk cache warm between them
+    Daemon {
+        /// The socket to listen on or connect to; defaults to one per source directory under the user cache directory
+        #[arg(long, value_name = "PATH")]
+        socket: Option<PathBuf>,
+        #[command(subcommand)]
+        action: Option<DaemonCommand>,
+    },
+}
@@ -0,0 +1,159 @@
+use std::path::PathBuf;
+use crate::artifact::{Artifact, ArtifactError, CollectPlan};
+use crate::config::Config;
+use crate::vfs::{FileSystem, RealFs};
+
+/// Builder for collecting artifacts from a source directory.
+///
+/// # Example
+///
+/// ```no_run
+/// use rustifacts::ArtifactCollector;
+///
+/// let artifacts = ArtifactCollector::new("./my_project")
+///     .ignore_dirs(["fixtures"])
+///     .include_ext(["rs", "toml"])
+///     .collect()?;
+/// # Ok::<(), rustifacts::artifact::ArtifactError>(())
+/// ```
+pub struct ArtifactCollector {
+    config: Config,
+    fs: Box<dyn FileSystem>,
+}
+
+impl ArtifactCollector {
+    /// Creates a collector for the given source directory with default settings.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The source directory to collect from.
+    pub fn new(source: impl Into<PathBuf>) -> Self {
+        let config = Config {
+            source_dir: source.into(),
+            ..Config::default()
+        };
+        Self::from_config(config)
+    }
+
+    /// Creates a collector from a fully populated `Config`.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The configuration options.
+    pub fn from_config(config: Config) -> Self {
+        Self {
+            config,
+            fs: Box::new(RealFs),
+        }
+    }
+
+    /// Collects from the given filesystem instead of the local disk.
+    pub fn filesystem(mut self, fs: impl FileSystem + 'static) -> Self {
+        self.fs = Box::new(fs);
+        self
+    }
+
+    /// Collects from the given boxed filesystem instead of the local disk.
+    pub fn boxed_filesystem(mut self, fs: Box<dyn FileSystem>) -> Self {
+        self.fs = fs;
+        self
+    }
+
+    /// Adds directories to ignore, in addition to the defaults.
+    pub fn ignore_dirs<I, S>(mut self, dirs: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        append(&mut self.config.additional_ignored_dirs, dirs);
+        self
+    }
+
+    /// Restricts collection to the given directories, relative to the source directory.
+    pub fn target_dirs<I, S>(mut self, dirs: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let target_dirs = self.config.target_dirs.get_or_insert_with(String::new);
+        append(target_dirs, dirs);
+        self
+    }
+
+    /// Only collects files with the given extensions.
+    pub fn include_ext<I, S>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        append(&mut self.config.included_extensions, extensions);
+        self
+    }
+
+    /// Skips files with the given extensions.
+    pub fn exclude_ext<I, S>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        append(&mut self.config.excluded_extensions, extensions);
+        self
+    }
+
+    /// Only collects files whose path relative to the source directory matches one of the
+    /// include globs. May be called repeatedly.
+    pub fn include_glob(mut self, pattern: impl Into<String>) -> Self {
+        self.config.include_globs.push(pattern.into());
+        self
+    }
+
+    /// Skips files whose path relative to the source directory matches the glob.
+    pub fn exclude_glob(mut self, pattern: impl Into<String>) -> Self {
+        self.config.exclude_globs.push(pattern.into());
+        self
+    }
+
+    /// Returns the configuration the collector will use.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Returns the filesystem the collector reads from.
+    pub fn fs(&self) -> &dyn FileSystem {
+        self.fs.as_ref()
+    }
+
+    /// Walks the source and collects every matching artifact.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<Vec<Artifact>, ArtifactError>` containing the collected artifacts
+    /// if successful, or an `ArtifactError` if an error occurs during collection.
+    pub fn collect(&self) -> Result<Vec<Artifact>, ArtifactError> {
+        Artifact::collect(self.fs.as_ref(), &self.config)
+    }
+
+    /// Runs the filter pipeline without reading any file contents.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<CollectPlan, ArtifactError>` containing the files that would be
+    /// collected, or an `ArtifactError` if an error occurs during planning.
+    pub fn plan(&self) -> Result<CollectPlan, ArtifactError> {
+        Artifact::plan(self.fs.as_ref(), &self.config)
+    }
+}
+
+/// Appends values to a comma-separated list.
+fn append<I, S>(list: &mut String, values: I)
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    for value in values {
+        if !list.is_empty() {
+            list.push(',');
+        }
+        list.push_str(&value.into());
+    }
+}
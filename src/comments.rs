@@ -0,0 +1,94 @@
+use std::path::Path;
+
+/// The comment syntax of a language.
+#[derive(Debug, Clone, Copy)]
+pub struct CommentSyntax {
+    /// Prefixes that start a comment running to the end of the line.
+    pub line: &'static [&'static str],
+    /// Opening and closing delimiters of block comments.
+    pub block: &'static [(&'static str, &'static str)],
+}
+
+const C_LIKE: CommentSyntax = CommentSyntax { line: &["//"], block: &[("/*", "*/")] };
+const HASH: CommentSyntax = CommentSyntax { line: &["#"], block: &[] };
+const PYTHON: CommentSyntax = CommentSyntax { line: &["#"], block: &[("\"\"\"", "\"\"\""), ("'''", "'''")] };
+const CSS: CommentSyntax = CommentSyntax { line: &[], block: &[("/*", "*/")] };
+const MARKUP: CommentSyntax = CommentSyntax { line: &[], block: &[("<!--", "-->")] };
+const DASH: CommentSyntax = CommentSyntax { line: &["--"], block: &[] };
+
+impl CommentSyntax {
+    /// Looks up the comment syntax for a file based on its extension.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(CommentSyntax)` for known languages, `None` otherwise.
+    pub fn for_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        match extension.as_str() {
+            "rs" | "js" | "jsx" | "ts" | "tsx" | "mjs" | "cjs" | "c" | "h" | "cc" | "cpp" | "hpp"
+            | "java" | "kt" | "kts" | "go" | "swift" | "cs" | "scala" | "php" | "dart" | "proto"
+            | "gradle" => Some(C_LIKE),
+            "py" | "pyi" => Some(PYTHON),
+            "sh" | "bash" | "zsh" | "rb" | "pl" | "r" | "toml" | "yaml" | "yml" | "cfg" | "ini"
+            | "dockerfile" | "mk" => Some(HASH),
+            "css" | "scss" | "less" => Some(CSS),
+            "html" | "htm" | "xml" | "svg" | "vue" | "svelte" => Some(MARKUP),
+            "sql" | "lua" | "hs" => Some(DASH),
+            _ => None,
+        }
+    }
+}
+
+/// Checks whether a file contains nothing but whitespace and comments.
+///
+/// Files with an unknown comment syntax only count as empty if they are whitespace-only.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file, used to pick the comment syntax.
+/// * `content` - The content of the file.
+///
+/// # Returns
+///
+/// Returns `true` if removing every comment would leave only whitespace, `false` otherwise.
+pub fn is_effectively_empty(path: &Path, content: &str) -> bool {
+    if content.trim().is_empty() {
+        return true;
+    }
+    let Some(syntax) = CommentSyntax::for_path(path) else {
+        return false;
+    };
+    strip_block_comments(content, syntax.block)
+        .lines()
+        .map(str::trim)
+        .all(|line| line.is_empty() || syntax.line.iter().any(|prefix| line.starts_with(prefix)))
+}
+
+/// Removes block comments from content, including an unterminated trailing one.
+fn strip_block_comments(content: &str, blocks: &[(&str, &str)]) -> String {
+    let mut stripped = String::with_capacity(content.len());
+    let mut rest = content;
+    loop {
+        let next = blocks
+            .iter()
+            .filter_map(|&(open, close)| rest.find(open).map(|start| (start, open, close)))
+            .min_by_key(|&(start, _, _)| start);
+        let Some((start, open, close)) = next else {
+            stripped.push_str(rest);
+            return stripped;
+        };
+        stripped.push_str(&rest[..start]);
+        let after_open = &rest[start + open.len()..];
+        match after_open.find(close) {
+            Some(end) => {
+                stripped.push('\n');
+                rest = &after_open[end + close.len()..];
+            }
+            None => return stripped,
+        }
+    }
+}
@@ -1,45 +1,554 @@
-use clap::Parser;
-use std::path::PathBuf;
+use clap::{Args, FromArgMatches, ValueEnum};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use log::{debug, info};
+use crate::checksum;
+use crate::git::{self, GitError};
+use crate::paths;
 use crate::config_file::ConfigFile;
+use crate::global_config::GlobalConfig;
+use crate::preflight;
+use crate::rules::Rule;
+use crate::runs;
+use crate::transform::TransformKind;
+use crate::upload::TargetConfig;
+
+/// The destination directory used when none is given.
+pub const DEFAULT_DEST_DIR: &str = "./claude_files";
 
 /// Configuration options for the Rustifacts file preparation tool.
 ///
-/// This struct is derived from `clap::Parser` to automatically generate
-/// a command-line interface for setting these options.
-#[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+/// This struct is derived from `clap::Args` and flattened into the command-line
+/// interface in `cli`, where its options are accepted before or after any subcommand.
+#[derive(Args, Debug, Clone)]
 pub struct Config {
     /// Source directory to process files from
-    #[arg(short, long, default_value = ".")]
+    #[arg(short, long, default_value = ".", global = true)]
     pub source_dir: PathBuf,
 
-    /// Destination directory to copy processed files to
-    #[arg(short, long, default_value = "./claude_files")]
+    /// Where to collect from: the source directory as given, or the top level of the git repository containing it
+    #[arg(long, value_enum, default_value = "source", value_name = "MODE", global = true)]
+    pub root: RootMode,
+
+    /// Destination directory to copy processed files to; may contain {preset} and {date} placeholders. Defaults to a per-project directory under the user cache directory
+    #[arg(short, long, default_value = DEFAULT_DEST_DIR, hide_default_value = true, global = true)]
     pub dest_dir: PathBuf,
 
+    /// Default the destination to ./claude_files (or a preset's ./{preset}_context) instead of a directory under the user cache directory
+    #[arg(long, global = true)]
+    pub dest_in_project: bool,
+
     /// Comma-separated list of additional directories to ignore
-    #[arg(short, long, default_value = "")]
+    #[arg(short, long, default_value = "", global = true)]
     pub additional_ignored_dirs: String,
 
     /// Comma-separated list of target directories to include (relative to source_dir)
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     pub target_dirs: Option<String>,
 
     /// Comma-separated list of file extensions to exclude (e.g., "jpg,png,pdf")
-    #[arg(short = 'x', long, default_value = "")]
+    #[arg(short = 'x', long, default_value = "", global = true)]
     pub excluded_extensions: String,
 
     /// Comma-separated list of file extensions to include (e.g., "rs,toml,md")
-    #[arg(short = 'i', long, default_value = "")]
+    #[arg(short = 'i', long, default_value = "", global = true)]
     pub included_extensions: String,
 
-    /// Preset configuration to use (e.g., "nextjs")
-    #[arg(long)]
+    /// Glob pattern matched against paths relative to source_dir; when given, only matching files are collected (repeatable, e.g., "src/**/*.rs")
+    #[arg(long = "include-glob", value_name = "GLOB", global = true)]
+    pub include_globs: Vec<String>,
+
+    /// Glob pattern matched against paths relative to source_dir to exclude (repeatable, e.g., "**/*_test.go")
+    #[arg(long = "exclude-glob", value_name = "GLOB", global = true)]
+    pub exclude_globs: Vec<String>,
+
+    /// Only collect this file, relative to source_dir, and with --follow-imports the local files it imports (repeatable, e.g., "src/routes/checkout.ts")
+    #[arg(long = "entry", value_name = "PATH", global = true)]
+    pub entries: Vec<PathBuf>,
+
+    /// Also collect the files the --entry files import, transitively (TypeScript, JavaScript, Rust, and Python)
+    #[arg(long, requires = "entries", global = true)]
+    pub follow_imports: bool,
+
+    /// Order in which files are collected, logged, and bundled: by path name, with directories before or after the files beside them, or level by level
+    #[arg(long, value_enum, default_value = "name", value_name = "ORDER", global = true)]
+    pub walk_order: WalkOrder,
+
+    /// How to collect binary files: skip them, write a placeholder noting their size and type, or encode them as base64
+    #[arg(long, value_enum, default_value = "skip", value_name = "MODE", global = true)]
+    pub binary_mode: BinaryMode,
+
+    /// Skip files larger than this size (e.g., 500KB or 2MB)
+    #[arg(long, value_name = "SIZE", value_parser = preflight::parse_bytes, global = true)]
+    pub max_file_size: Option<u64>,
+
+    /// Size limits for particular file extensions, overriding --max-file-size (set in the config file)
+    #[arg(skip)]
+    pub max_file_size_per_extension: BTreeMap<String, u64>,
+
+    /// Only collect files tracked by git, leaving out untracked scratch files, local env files, and build leftovers
+    #[arg(long, global = true)]
+    pub git_tracked: bool,
+
+    /// Only collect files changed since this git ref (branch, tag, or commit such as "main" or "HEAD~5"), including untracked files
+    #[arg(long, value_name = "REF", global = true)]
+    pub git_diff: Option<String>,
+
+    /// Only collect files modified after this UTC date or timestamp (e.g., "2024-01-01") or within this age (e.g., "7d", "12h")
+    #[arg(long, value_name = "WHEN", value_parser = runs::parse_since, global = true)]
+    pub since: Option<SystemTime>,
+
+    /// Skip files with more than this many lines (e.g., generated snapshots)
+    #[arg(long, value_name = "LINES", global = true)]
+    pub max_lines: Option<usize>,
+
+    /// Comma-separated list of users (names or numeric IDs); only files owned by one of them are collected (Unix only)
+    #[arg(long, value_name = "USERS", global = true)]
+    pub owner: Option<String>,
+
+    /// Comma-separated list of users (names or numeric IDs) whose files are not collected, such as "root" (Unix only)
+    #[arg(long, default_value = "", value_name = "USERS", global = true)]
+    pub exclude_owner: String,
+
+    /// What to do with YAML/TOML front matter at the top of Markdown and MDX files: keep it, strip it, or summarize it to its title and description
+    #[arg(long, value_enum, default_value = "keep", value_name = "MODE", global = true)]
+    pub front_matter: FrontMatterMode,
+
+    /// Replace local include directives in Markdown and MDX files ({{#include path}}, {% include_relative path %}) with the included content
+    #[arg(long, global = true)]
+    pub expand_includes: bool,
+
+    /// Collapse runs of blank lines in each artifact into a single blank line
+    #[arg(long, global = true)]
+    pub squeeze_blank_lines: bool,
+
+    /// Shrink space indentation in each artifact to the narrowest width that preserves nesting
+    #[arg(long, global = true)]
+    pub minify_indent: bool,
+
+    /// Keep only signatures and structure, replacing function bodies in Rust, Python, JavaScript, TypeScript, and Go files with { … }
+    #[arg(long, global = true)]
+    pub skeleton: bool,
+
+    /// Skip test files and directories (e.g., tests/, __tests__/, *_test.go, *.spec.ts, test_*.py) and remove #[cfg(test)] items from Rust files
+    #[arg(long, global = true)]
+    pub no_tests: bool,
+
+    /// Run each artifact through a content transformer, such as strip-comments (repeatable, applied in order)
+    #[arg(long = "transform", value_enum, value_name = "NAME", global = true)]
+    pub transforms: Vec<TransformKind>,
+
+    /// Pipe each artifact's content through a shell command, after any --transform ones; its path is in RUSTIFACTS_PATH (repeatable, e.g., "sqlfluff fix -")
+    #[arg(long = "transform-cmd", value_name = "COMMAND", global = true)]
+    pub transform_cmds: Vec<String>,
+
+    /// Filter and transform files with a sandboxed WebAssembly plugin exporting should_include and/or transform (repeatable; needs the wasm-plugins feature)
+    #[arg(long = "plugin", value_name = "PATH", global = true)]
+    pub plugins: Vec<PathBuf>,
+
+    /// Convert Jupyter notebooks to percent-format scripts (# %% cells), dropping outputs and metadata
+    #[arg(long, global = true)]
+    pub convert_notebooks: bool,
+
+    /// Drop the data (INSERT and COPY statements) from SQL files of 64 KB or more, keeping the schema
+    #[arg(long, global = true)]
+    pub summarize_sql_dumps: bool,
+
+    /// Collect previously generated rustifacts output (directories with a manifest, bundles, checksummed artifacts) instead of skipping it
+    #[arg(long, global = true)]
+    pub include_bundles: bool,
+
+    /// Write byte-identical files separately instead of merging them into one artifact
+    #[arg(long, global = true)]
+    pub keep_duplicates: bool,
+
+    /// Fail instead of skipping files that keep changing while they are being collected or whose --transform-cmd or --plugin fails
+    #[arg(long, global = true)]
+    pub strict: bool,
+
+    /// Walk the source tree from scratch instead of reusing the directory listings saved by earlier runs
+    #[arg(long, global = true)]
+    pub no_walk_cache: bool,
+
+    /// Skip files that are not valid UTF-8 instead of transcoding them from a detected encoding
+    #[arg(long, global = true)]
+    pub strict_utf8: bool,
+
+    /// Do not replace detected credentials (AWS keys, private keys, bearer tokens) with [REDACTED]
+    #[arg(long, global = true)]
+    pub no_redact: bool,
+
+    /// Keep files that are empty apart from whitespace and comments (e.g., license-only headers, `__init__.py` stubs)
+    #[arg(long, global = true)]
+    pub keep_empty: bool,
+
+    /// Split files with more tokens than this into parts at top-level functions and classes, or blank lines, named like src_main_part01.rs
+    #[arg(long, value_name = "TOKENS", global = true)]
+    pub split_tokens: Option<usize>,
+
+    /// Split files with more lines than this into parts, the way --split-tokens does
+    #[arg(long, value_name = "LINES", global = true)]
+    pub split_lines: Option<usize>,
+
+    /// Maximum total number of tokens across all artifacts
+    #[arg(long, value_name = "N", global = true)]
+    pub max_tokens: Option<usize>,
+
+    /// What to do when the artifacts exceed --max-tokens: fail, or drop the largest files until they fit
+    #[arg(long, value_enum, default_value = "fail", value_name = "POLICY", global = true)]
+    pub budget_policy: BudgetPolicy,
+
+    /// Print the token count of every artifact and the total after collection
+    #[arg(long, global = true)]
+    pub token_report: bool,
+
+    /// Report each language's share of the selection by bytes and tokens, and record it in the manifest and bundle header
+    #[arg(long, global = true)]
+    pub lang_stats: bool,
+
+    /// How to collect translation files under locales/, i18n/, and similar directories
+    #[arg(long, value_enum, default_value = "default", value_name = "MODE", global = true)]
+    pub locales: LocaleMode,
+
+    /// The locale kept when --locales is "default"
+    #[arg(long, default_value = "en", value_name = "LOCALE", global = true)]
+    pub default_locale: String,
+
+    /// Add a _dependencies.md artifact summarizing the direct dependencies of collected Cargo.toml, package.json, and pyproject.toml files
+    #[arg(long, global = true)]
+    pub dependency_summary: bool,
+
+    /// Include the licenses of installed dependencies in the dependency summary (implies --dependency-summary)
+    #[arg(long, global = true)]
+    pub dependency_licenses: bool,
+
+    /// Add a _gitlog.md artifact with the subjects and bodies of the last N commits
+    #[arg(long, value_name = "N", global = true)]
+    pub include_git_log: Option<usize>,
+
+    /// Only list commits touching the collected files in the --include-git-log artifact
+    #[arg(long, global = true)]
+    pub git_log_selected: bool,
+
+    /// Comma-separated list of additional file name patterns to exclude (e.g., "*.bak,*.tmp")
+    #[arg(long, default_value = "", global = true)]
+    pub temp_patterns: String,
+
+    /// Do not exclude editor droppings and temp files (e.g., "*~", "*.swp", ".DS_Store") by default
+    #[arg(long, global = true)]
+    pub no_default_temp_patterns: bool,
+
+    /// How artifacts are rendered: plain copies, Markdown with each file in a fenced code block, or claude-xml <document> blocks
+    #[arg(long, value_enum, default_value = "plain", value_name = "FORMAT", global = true)]
+    pub format: OutputFormat,
+
+    /// Write all artifacts into this single file, delimited by their original paths, instead of dest_dir
+    #[arg(long, value_name = "FILE", global = true)]
+    pub single_file: Option<PathBuf>,
+
+    /// Start the --single-file bundle with a project header (name, description, languages, commit, generation time)
+    #[arg(long, requires = "single_file", global = true)]
+    pub bundle_header: bool,
+
+    /// Fail instead of renaming files whose flattened names collide (e.g., "foo/bar.rs" and "foo_bar.rs")
+    #[arg(long, global = true)]
+    pub fail_on_collision: bool,
+
+    /// Longest file name to write, in bytes; longer flattened names are truncated and given a hash suffix
+    #[arg(long, default_value_t = 255, value_name = "BYTES", global = true)]
+    pub max_filename_length: usize,
+
+    /// Remove previously written artifacts that no longer correspond to a source file (with --force, any other file in dest_dir too)
+    #[arg(long, global = true)]
+    pub prune: bool,
+
+    /// Remove every previously written artifact from dest_dir before writing (with --force, any other file too)
+    #[arg(long, global = true)]
+    pub clean: bool,
+
+    /// Write one Markdown document with a table of contents per target directory (e.g., src.md, tests.md) instead of one file per artifact
+    #[arg(long, conflicts_with_all = ["single_file", "output_archive"], global = true)]
+    pub markdown_per_target: bool,
+
+    /// Also write MANIFEST.md, a Markdown table of each artifact's original path, name, size, line count, and checksum
+    #[arg(long, global = true)]
+    pub manifest_markdown: bool,
+
+    /// Write the artifacts and manifest into this zip archive instead of loose files in dest_dir
+    #[arg(long, value_name = "FILE", conflicts_with = "single_file", global = true)]
+    pub output_archive: Option<PathBuf>,
+
+    /// Number of leading directory levels to keep as real directories instead of flattening (e.g., 1 keeps "src/")
+    #[arg(long, default_value_t = 0, value_name = "K", global = true)]
+    pub keep_dirs: usize,
+
+    /// Separator that replaces path separators when flattening (e.g., "__" or "--")
+    #[arg(long, default_value = "_", value_name = "SEP", global = true)]
+    pub flatten_separator: String,
+
+    /// Recreate the source directory tree under dest_dir instead of flattening
+    #[arg(long, global = true)]
+    pub preserve_structure: bool,
+
+    /// Prefix added to every generated filename (e.g., the repository name)
+    #[arg(long, default_value = "", global = true)]
+    pub name_prefix: String,
+
+    /// Suffix added to every generated filename, before its extension
+    #[arg(long, default_value = "", global = true)]
+    pub name_suffix: String,
+
+    /// Preset configuration to use (e.g., "nextjs"), or "auto" to detect it from the project's marker files
+    #[arg(long, global = true)]
     pub preset: Option<String>,
 
-    /// Path to the configuration file
-    #[arg(long, short = 'c')]
+    /// Comma-separated list of presets to collect in one run, sharing one walk of the source; each is written to its own destination
+    #[arg(long, value_name = "PRESETS", conflicts_with_all = ["preset", "single_file", "output_archive"], global = true)]
+    pub matrix: Option<String>,
+
+    /// Path to the configuration file (TOML, or YAML or JSON by extension); without one, rustifacts.toml, .yaml, .yml, or .json (optionally dot-prefixed) is looked for in the source directory and its ancestors up to the repository root
+    #[arg(long, short = 'c', global = true)]
     pub config_file: Option<PathBuf>,
+
+    /// Do not look for a configuration file when --config-file is not given
+    #[arg(long, conflicts_with = "config_file", global = true)]
+    pub no_config: bool,
+
+    /// Warn about unknown keys in configuration files and ignore them, instead of failing
+    #[arg(long, global = true)]
+    pub lax_config: bool,
+
+    /// Repeat the last collect, upload, or re-export run started from this directory; any other arguments are added after its own
+    #[arg(long, global = true)]
+    pub last: bool,
+
+    /// Prepend a checksum header line to each written artifact (formats without comments, such as JSON, are only checksummed in MANIFEST.json)
+    #[arg(long, global = true)]
+    pub checksum_header: bool,
+
+    /// Collect from a temporary checkout of this git ref (commit SHA, tag, or branch)
+    #[arg(long, value_name = "REF", global = true)]
+    pub at_ref: Option<String>,
+
+    /// How to handle git submodules: skip them, replace them with a stub noting their pinned SHA, or descend into them
+    #[arg(long, value_enum, value_name = "MODE", global = true)]
+    pub include_submodules: Option<SubmoduleMode>,
+
+    /// Maximum submodule nesting depth to descend into; deeper submodules are stubbed
+    #[arg(long, default_value_t = 1, global = true)]
+    pub submodule_depth: usize,
+
+    /// Report the N largest artifacts by tokens, with ready-to-copy exclusion suggestions
+    #[arg(long, value_name = "N", global = true)]
+    pub top: Option<usize>,
+
+    /// Write each run into its own dest_dir/runs/<run-id>/ directory and point dest_dir/latest at it
+    #[arg(long, global = true)]
+    pub run_dirs: bool,
+
+    /// Write a JSON summary of the run (run ID, destination, written and skipped counts, tokens) to this file
+    #[arg(long, value_name = "FILE", global = true)]
+    pub summary_json: Option<PathBuf>,
+
+    /// Open the destination directory, or the --single-file or --output-archive file, in the file manager or default app after a successful run
+    #[arg(long, global = true)]
+    pub open: bool,
+
+    /// Open the output with this command instead, given the path as its last argument (implies --open, e.g., "code")
+    #[arg(long, value_name = "COMMAND", global = true)]
+    pub open_with: Option<String>,
+
+    /// Unique ID of the current run, assigned at startup
+    #[arg(skip)]
+    pub run_id: String,
+
+    /// Write into a non-empty destination directory even if it has no rustifacts manifest; with init, overwrite an existing rustifacts.toml
+    #[arg(long, global = true)]
+    pub force: bool,
+
+    /// Verify the checksum headers of artifacts in the destination directory instead of collecting
+    #[arg(long, global = true)]
+    pub verify: bool,
+
+    /// List the files that would be collected, with their new names and sizes, without reading or writing anything
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    /// Upload targets declared under `[targets.<name>]` in the config file
+    #[arg(skip)]
+    pub targets: BTreeMap<String, TargetConfig>,
+
+    /// Per-pattern rules declared under `[[rules]]` in the config file, in declaration order
+    #[arg(skip)]
+    pub rules: Vec<Rule>,
+
+    /// Names of the options given on the command line, which the config file and presets do not override
+    #[arg(skip)]
+    pub explicit_args: BTreeSet<String>,
+
+    /// Whether a config file set `dest_dir`
+    #[arg(skip)]
+    pub dest_dir_from_file: bool,
+}
+
+impl Default for Config {
+    /// Returns the configuration used when no command-line arguments are given.
+    fn default() -> Self {
+        let command = Self::augment_args(clap::Command::new(env!("CARGO_PKG_NAME")));
+        let matches = command.get_matches_from([env!("CARGO_PKG_NAME")]);
+        Self::from_arg_matches(&matches).expect("default arguments are valid")
+    }
+}
+
+/// How files with binary content are collected.
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BinaryMode {
+    /// Leave binary files out.
+    Skip,
+    /// Write a stub noting the file's path, size, and type.
+    Placeholder,
+    /// Write the file's content encoded as base64.
+    Base64,
+}
+
+/// What to do when the collected artifacts exceed the `--max-tokens` budget.
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetPolicy {
+    /// Abort without writing anything.
+    Fail,
+    /// Drop the largest artifacts until the rest fit.
+    Trim,
+}
+
+/// How translation files beneath locale directories are collected.
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LocaleMode {
+    /// Collect every translation file.
+    All,
+    /// Collect only the default locale's files.
+    Default,
+    /// Replace translation files with a listing of their keys across all locales.
+    Keys,
+}
+
+/// Which directory collection starts from.
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RootMode {
+    /// The source directory as given.
+    Source,
+    /// The top level of the git repository containing the source directory.
+    Git,
+}
+
+/// What to do with the front-matter block at the top of Markdown and MDX files.
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FrontMatterMode {
+    /// Leave the front matter as it is.
+    Keep,
+    /// Remove the front matter.
+    Strip,
+    /// Keep only the title, description, and summary keys.
+    Summarize,
+}
+
+/// How artifacts are rendered when they are written.
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Copy each file's content unchanged.
+    #[default]
+    Plain,
+    /// Put each file under a heading naming its path, in a language-tagged fenced code block.
+    Markdown,
+    /// Wrap each file in the `<document>` markup recommended for long-context prompts.
+    #[value(name = "claude-xml")]
+    #[serde(rename = "claude-xml")]
+    ClaudeXml,
+}
+
+/// How git submodules beneath the source directory are handled during collection.
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SubmoduleMode {
+    /// Leave submodule contents out entirely.
+    Skip,
+    /// Replace each submodule with a stub artifact noting its pinned SHA.
+    Stub,
+    /// Collect submodule contents up to `--submodule-depth` levels deep.
+    Descend,
+}
+
+/// The order in which files are walked, and so collected, logged, and bundled.
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum WalkOrder {
+    /// Depth-first, with the entries of each directory sorted by name.
+    Name,
+    /// Depth-first, with the subdirectories of each directory before its files.
+    DirsFirst,
+    /// Depth-first, with the files of each directory before its subdirectories.
+    FilesFirst,
+    /// Breadth-first: every file of one level before any file of the next, sorted by path within a level.
+    BreadthFirst,
+}
+
+impl WalkOrder {
+    /// Compares two file paths in this order.
+    ///
+    /// # Arguments
+    ///
+    /// * `a` - The first path.
+    /// * `b` - The second path.
+    ///
+    /// # Returns
+    ///
+    /// An `Ordering` telling whether `a` comes before or after `b`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::cmp::Ordering;
+    /// use std::path::Path;
+    /// use rustifacts::config::WalkOrder;
+    ///
+    /// let (nested, top) = (Path::new("a/z.rs"), Path::new("b.rs"));
+    /// assert_eq!(WalkOrder::Name.compare(nested, top), Ordering::Less);
+    /// assert_eq!(WalkOrder::FilesFirst.compare(nested, top), Ordering::Greater);
+    /// assert_eq!(WalkOrder::DirsFirst.compare(Path::new("z/a.rs"), top), Ordering::Less);
+    /// assert_eq!(WalkOrder::BreadthFirst.compare(nested, Path::new("c.rs")), Ordering::Greater);
+    /// ```
+    pub fn compare(self, a: &Path, b: &Path) -> Ordering {
+        match self {
+            Self::Name => a.cmp(b),
+            Self::BreadthFirst => a.components().count().cmp(&b.components().count()).then_with(|| a.cmp(b)),
+            Self::DirsFirst | Self::FilesFirst => {
+                let (mut left, mut right) = (a.components(), b.components());
+                loop {
+                    match (left.next(), right.next()) {
+                        (Some(x), Some(y)) if x == y => continue,
+                        (Some(x), Some(y)) => {
+                            // An entry is a directory if the path continues beneath it.
+                            let (x_is_dir, y_is_dir) = (left.next().is_some(), right.next().is_some());
+                            let kinds = match self {
+                                Self::DirsFirst => y_is_dir.cmp(&x_is_dir),
+                                _ => x_is_dir.cmp(&y_is_dir),
+                            };
+                            return kinds.then_with(|| x.cmp(&y));
+                        }
+                        _ => return a.cmp(b),
+                    }
+                }
+            }
+        }
+    }
 }
 
 impl Config {
@@ -63,15 +572,18 @@ impl Config {
             "__pycache__".to_string(),
         ];
 
-        // Add the destination directory to the ignored list
-        if let Some(dest_dir_name) = self.dest_dir.file_name() {
+        // Add the destination directory to the ignored list, unless it is in the cache directory
+        let in_cache = GlobalConfig::cache_dir().is_some_and(|cache_dir| {
+            paths::normalize_drive(&self.dest_dir).starts_with(paths::normalize_drive(&cache_dir))
+        });
+        if let Some(dest_dir_name) = self.dest_dir.file_name().filter(|_| !in_cache) {
             ignored_dirs.push(dest_dir_name.to_string_lossy().into_owned());
         }
 
         ignored_dirs.extend(self.additional_ignored_dirs
             .split(',')
-            .filter(|s| !s.is_empty())
-            .map(String::from));
+            .filter(|s| !s.trim().is_empty())
+            .map(paths::normalize_separators));
 
         ignored_dirs
     }
@@ -88,8 +600,13 @@ impl Config {
     pub fn get_target_dirs(&self) -> Vec<PathBuf> {
         self.target_dirs
             .as_ref()
-            .map(|dirs| dirs.split(',').filter(|s| !s.is_empty()).map(PathBuf::from).collect())
-            .unwrap_or_else(Vec::new)
+            .map(|dirs| {
+                dirs.split(',')
+                    .filter(|s| !s.trim().is_empty())
+                    .map(|dir| PathBuf::from(paths::normalize_separators(dir)))
+                    .collect()
+            })
+            .unwrap_or_default()
     }
 
     /// Returns a vector of file extensions to exclude during processing.
@@ -118,8 +635,70 @@ impl Config {
             .collect()
     }
 
+    /// Returns a vector of file name patterns that mark temporary or editor files.
+    ///
+    /// This method combines a default list of editor droppings and temp patterns,
+    /// unless disabled, with any additional patterns specified by the user.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec<String>` containing all file name patterns to be excluded.
+    pub fn get_temp_patterns(&self) -> Vec<String> {
+        let mut patterns = Vec::new();
+        if !self.no_default_temp_patterns {
+            patterns.extend([
+                "*~", "*.swp", ".#*", "#*#", "*.orig", "*.rej", ".DS_Store", "Thumbs.db",
+            ].map(String::from));
+        }
+
+        patterns.extend(self.temp_patterns
+            .split(',')
+            .filter(|s| !s.is_empty())
+            .map(|s| s.trim().to_string()));
+
+        patterns
+    }
+
+    /// Returns the path patterns that mark test files and directories, which `--no-tests`
+    /// skips.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec<String>` containing the glob patterns, which is empty without `--no-tests`.
+    pub fn get_test_patterns(&self) -> Vec<String> {
+        if !self.no_tests {
+            return Vec::new();
+        }
+        [
+            "**/tests/**", "**/test/**", "**/__tests__/**", "**/__mocks__/**", "**/__snapshots__/**", "**/spec/**",
+            "**/testdata/**", "**/tests.rs", "**/*_test.go", "**/*.test.*", "**/*.spec.*", "**/*_spec.rb",
+            "**/test_*.py", "**/*_test.py", "**/conftest.py", "**/*Test.java", "**/*Tests.java", "**/*Test.kt",
+            "**/*Tests.cs",
+        ]
+        .map(String::from)
+        .to_vec()
+    }
+
+    /// Returns the size limit that applies to a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file.
+    ///
+    /// # Returns
+    ///
+    /// The limit in bytes for the file's extension if one is set, otherwise `--max-file-size`.
+    pub fn max_file_size_for(&self, path: &Path) -> Option<u64> {
+        path.extension()
+            .and_then(|ext| self.max_file_size_per_extension.get(&ext.to_string_lossy().to_lowercase()))
+            .copied()
+            .or(self.max_file_size)
+    }
+
     /// Applies a preset configuration to the current Config instance.
     ///
+    /// Options given explicitly on the command line keep their values.
+    ///
     /// # Arguments
     ///
     /// * `preset_name` - The name of the preset to apply.
@@ -128,19 +707,184 @@ impl Config {
     ///
     /// Returns `Result<(), String>` indicating success or failure of applying the preset.
     pub fn apply_preset(&mut self, preset_name: &str) -> Result<(), String> {
-        crate::presets::apply_preset(self, preset_name)
+        self.apply_layer(|config| crate::presets::apply_preset(config, preset_name))
+    }
+
+    /// Returns the presets listed with `--matrix`, without duplicates.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec<String>` containing the preset names, in the order given.
+    pub fn get_matrix_presets(&self) -> Vec<String> {
+        let mut presets: Vec<String> = Vec::new();
+        for preset in self.matrix.iter().flat_map(|matrix| matrix.split(',')).map(str::trim) {
+            if !preset.is_empty() && !presets.iter().any(|seen| seen == preset) {
+                presets.push(preset.to_string());
+            }
+        }
+        presets
+    }
+
+    /// Builds the configuration for one preset of a `--matrix` run.
+    ///
+    /// The preset is applied to a copy of this configuration. If the destination does not
+    /// contain a `{preset}` placeholder, the preset writes into a subdirectory named after
+    /// it, so that no two presets share a destination.
+    ///
+    /// # Arguments
+    ///
+    /// * `preset_name` - The name of the preset to apply, or several joined with `+`.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<Config, String>` containing the preset's configuration, or an error
+    /// if the preset does not exist.
+    pub fn matrix_entry(&self, preset_name: &str) -> Result<Config, String> {
+        let mut config = self.clone();
+        config.matrix = None;
+        config.apply_preset(preset_name)?;
+        if !config.dest_dir.to_string_lossy().contains("{preset}") {
+            config.dest_dir = config.dest_dir.join(preset_name);
+        }
+        config.expand_dest_dir(Some(preset_name));
+        Ok(config)
+    }
+
+    /// Replaces the source directory with the top level of its git repository if `--root git` is set.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<(), GitError>` indicating success, or a `GitError` if the source
+    /// directory is not inside a git repository.
+    pub fn resolve_root(&mut self) -> Result<(), GitError> {
+        if self.root == RootMode::Git {
+            self.source_dir = PathBuf::from(git::run(&self.source_dir, &["rev-parse", "--show-toplevel"])?);
+            info!("Collecting from the repository root {}", self.source_dir.display());
+        }
+        Ok(())
+    }
+
+    /// Checks whether the destination is left at its default, rather than chosen with `-d`
+    /// or with `dest_dir` in a config file (even if it was chosen to be `./claude_files`).
+    pub fn is_default_dest(&self) -> bool {
+        !self.explicit_args.contains("dest_dir") && !self.dest_dir_from_file
+    }
+
+    /// Moves a destination left at its default into the user cache directory.
+    ///
+    /// Unless `--dest-in-project` is set, the default destination becomes
+    /// `<cache dir>/<source name>-<hash>/{preset}`, where the hash is of the canonical
+    /// source path, so output never lands in the source tree and each project keeps its
+    /// own directory. The default is kept if no cache directory is known.
+    pub fn apply_default_dest(&mut self) {
+        if self.dest_in_project || !self.is_default_dest() {
+            return;
+        }
+        let Some(cache_dir) = GlobalConfig::cache_dir() else {
+            debug!("No user cache directory is known; writing to {}", DEFAULT_DEST_DIR);
+            return;
+        };
+        let source_dir = self.source_dir.canonicalize().unwrap_or_else(|_| self.source_dir.clone());
+        let name = source_dir.file_name().map_or("project".into(), |name| name.to_string_lossy());
+        let project = format!("{}-{}", name, checksum::short_hash(&source_dir.to_string_lossy()));
+        self.dest_dir = cache_dir.join(project).join("{preset}");
+    }
+
+    /// Expands the `{preset}` and `{date}` placeholders in the destination directory.
+    ///
+    /// `{preset}` becomes the name of the applied preset, or `default` if none was applied,
+    /// and `{date}` becomes the current UTC date as `YYYY-MM-DD`.
+    ///
+    /// # Arguments
+    ///
+    /// * `preset_name` - The name of the applied preset, if any. Combined presets are
+    ///   joined with `+` (e.g., `rust+docs`).
+    pub fn expand_dest_dir(&mut self, preset_name: Option<&str>) {
+        let template = self.dest_dir.to_string_lossy();
+        if !template.contains('{') {
+            return;
+        }
+        let expanded = template
+            .replace("{preset}", &preset_name.map_or("default".to_string(), |name| crate::presets::split_presets(name).join("+")))
+            .replace("{date}", &today());
+        self.dest_dir = PathBuf::from(expanded);
     }
 
     /// Applies configuration from a file to the current Config instance.
     ///
+    /// Options given explicitly on the command line keep their values.
+    ///
     /// # Returns
     ///
     /// Returns `anyhow::Result<()>` indicating success or failure of applying the configuration file.
     pub fn apply_config_file(&mut self) -> anyhow::Result<()> {
         if let Some(ref config_path) = self.config_file {
-            let file_config = ConfigFile::read_from_file(config_path)?;
-            file_config.apply_to_config(self);
+            let file_config = ConfigFile::read_from_file(config_path, self.lax_config)?;
+            self.apply_layer(|config| {
+                file_config.apply_to_config(config);
+                anyhow::Ok(())
+            })?;
         }
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Applies settings that rank below the command line, such as a config file or a preset.
+    ///
+    /// Settings are layered as defaults, then the config file, then presets, then the
+    /// options given explicitly on the command line. Every layer is applied in turn, after
+    /// which the options listed in `explicit_args` get their command-line values back.
+    ///
+    /// # Arguments
+    ///
+    /// * `apply` - Applies the layer's settings to the configuration.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<(), E>` with the error of `apply`, if any.
+    fn apply_layer<E>(&mut self, apply: impl FnOnce(&mut Self) -> Result<(), E>) -> Result<(), E> {
+        let before = self.clone();
+        apply(self)?;
+        self.restore_explicit_args(before);
+        Ok(())
+    }
+
+    /// Copies the values of the explicitly given options from an earlier copy of the configuration.
+    fn restore_explicit_args(&mut self, before: Config) {
+        // The fields are listed once, in an exhaustive pattern, so that a new option cannot
+        // be added without deciding whether the command line takes precedence for it.
+        macro_rules! restore {
+            ([$($field:ident),* $(,)?], [$($skipped:ident),* $(,)?]) => {
+                let Config { $($field,)* $($skipped: _,)* } = before;
+                $(
+                    if self.explicit_args.contains(stringify!($field)) {
+                        self.$field = $field;
+                    }
+                )*
+            };
+        }
+        restore!(
+            [
+                source_dir, root, dest_dir, dest_in_project, additional_ignored_dirs, target_dirs,
+                excluded_extensions, included_extensions, include_globs, exclude_globs, entries, follow_imports, walk_order, binary_mode,
+                max_file_size, max_file_size_per_extension, git_tracked, git_diff, since, max_lines,
+                owner, exclude_owner,
+                front_matter, expand_includes, squeeze_blank_lines, minify_indent, skeleton, no_tests, transforms, transform_cmds, plugins, convert_notebooks,
+                summarize_sql_dumps, include_bundles, keep_duplicates, strict, no_walk_cache, strict_utf8,
+                no_redact, keep_empty, split_tokens, split_lines, max_tokens, budget_policy, token_report, lang_stats, locales, default_locale,
+                dependency_summary, dependency_licenses, include_git_log, git_log_selected,
+                temp_patterns, no_default_temp_patterns, format, single_file, bundle_header,
+                fail_on_collision, max_filename_length, prune, clean, markdown_per_target,
+                manifest_markdown, output_archive, keep_dirs, flatten_separator, preserve_structure,
+                name_prefix, name_suffix, preset, matrix, config_file, no_config, lax_config, last,
+                checksum_header, at_ref, include_submodules, submodule_depth, top, run_dirs, summary_json, open, open_with, force,
+                verify, dry_run,
+            ],
+            [run_id, targets, rules, explicit_args, dest_dir_from_file]
+        );
+    }
+}
+
+/// Returns the current UTC date formatted as `YYYY-MM-DD`.
+fn today() -> String {
+    runs::utc_timestamp(SystemTime::now())[..10].to_string()
+}
@@ -1,50 +1,329 @@
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 use serde::Deserialize;
-use toml;
-use anyhow::{Result, Context};
-use crate::config::Config;
+use anyhow::{bail, Context, Result};
+use log::{debug, warn};
+use crate::config::{BinaryMode, BudgetPolicy, Config, FrontMatterMode, LocaleMode, OutputFormat, RootMode, SubmoduleMode, WalkOrder};
+use crate::presets::PresetConfig;
+use crate::paths::{self, ExpandError};
+use crate::{preflight, runs};
+use crate::rules::Rule;
+use crate::transform::TransformKind;
+use crate::upload::TargetConfig;
+
+/// Name of the configuration file written by `rustifacts init`.
+pub const CONFIG_FILE_NAME: &str = "rustifacts.toml";
+
+/// Names, without extension, under which a configuration file is discovered.
+pub const CONFIG_FILE_STEMS: &[&str] = &["rustifacts", ".rustifacts"];
+
+/// Extensions under which a configuration file is discovered, in order of preference.
+pub const CONFIG_FILE_EXTENSIONS: &[&str] = &["toml", "yaml", "yml", "json"];
+
+/// Opening comment of the configuration written by `rustifacts init`.
+const STARTER_HEADER: &str = r#"# Rustifacts configuration, loaded automatically when collecting from this directory or below it.
+# Every setting is optional; options given explicitly on the command line take precedence over the values here.
+"#;
+
+/// Commented-out example settings written by `rustifacts init`.
+const STARTER_OPTIONS: &str = r#"# extends = "../rustifacts.toml"
+# source_dir = "."
+# dest_dir = "./claude_files"
+# dest_in_project = true
+# additional_ignored_dirs = ["temp", "logs"]
+# target_dirs = ["src", "tests"]
+# included_extensions = ["rs", "toml", "md"]
+# excluded_extensions = ["lock"]
+# exclude_globs = ["**/*_test.go"]
+"#;
 
 #[derive(Deserialize, Debug)]
 pub struct ConfigFile {
+    /// Path of a base configuration file whose settings this one overrides, relative to this file.
+    pub extends: Option<String>,
+    /// The loaded base configuration named by `extends`.
+    #[serde(skip)]
+    pub base: Option<Box<ConfigFile>>,
     pub source_dir: Option<String>,
+    pub root: Option<RootMode>,
     pub dest_dir: Option<String>,
+    pub dest_in_project: Option<bool>,
     pub additional_ignored_dirs: Option<Vec<String>>,
     pub target_dirs: Option<Vec<String>>,
     pub excluded_extensions: Option<Vec<String>>,
     pub included_extensions: Option<Vec<String>>,
+    pub include_globs: Option<Vec<String>>,
+    pub exclude_globs: Option<Vec<String>>,
+    pub entries: Option<Vec<String>>,
+    pub follow_imports: Option<bool>,
+    pub walk_order: Option<WalkOrder>,
+    pub format: Option<OutputFormat>,
+    pub single_file: Option<String>,
+    pub output_archive: Option<String>,
+    pub markdown_per_target: Option<bool>,
+    pub manifest_markdown: Option<bool>,
+    pub prune: Option<bool>,
+    pub clean: Option<bool>,
+    pub fail_on_collision: Option<bool>,
+    pub max_filename_length: Option<usize>,
+    pub keep_dirs: Option<usize>,
+    pub flatten_separator: Option<String>,
+    pub preserve_structure: Option<bool>,
+    pub binary_mode: Option<BinaryMode>,
+    pub max_file_size: Option<ByteSize>,
+    pub max_file_size_per_extension: Option<BTreeMap<String, ByteSize>>,
+    pub git_tracked: Option<bool>,
+    pub git_diff: Option<String>,
+    pub since: Option<Since>,
+    pub max_lines: Option<usize>,
+    pub owner: Option<Vec<String>>,
+    pub exclude_owner: Option<Vec<String>>,
+    pub front_matter: Option<FrontMatterMode>,
+    pub expand_includes: Option<bool>,
+    pub squeeze_blank_lines: Option<bool>,
+    pub minify_indent: Option<bool>,
+    pub skeleton: Option<bool>,
+    pub no_tests: Option<bool>,
+    pub transforms: Option<Vec<TransformKind>>,
+    pub transform_cmds: Option<Vec<String>>,
+    pub plugins: Option<Vec<String>>,
+    pub convert_notebooks: Option<bool>,
+    pub summarize_sql_dumps: Option<bool>,
+    pub keep_empty: Option<bool>,
+    pub split_tokens: Option<usize>,
+    pub split_lines: Option<usize>,
+    pub include_bundles: Option<bool>,
+    pub keep_duplicates: Option<bool>,
+    pub strict: Option<bool>,
+    pub strict_utf8: Option<bool>,
+    pub walk_cache: Option<bool>,
+    pub bundle_header: Option<bool>,
+    pub redact: Option<bool>,
+    pub max_tokens: Option<usize>,
+    pub budget_policy: Option<BudgetPolicy>,
+    pub token_report: Option<bool>,
+    pub lang_stats: Option<bool>,
+    pub locales: Option<LocaleMode>,
+    pub default_locale: Option<String>,
+    pub dependency_summary: Option<bool>,
+    pub dependency_licenses: Option<bool>,
+    pub include_git_log: Option<usize>,
+    pub git_log_selected: Option<bool>,
+    pub name_prefix: Option<String>,
+    pub name_suffix: Option<String>,
+    pub temp_patterns: Option<Vec<String>>,
+    pub default_temp_patterns: Option<bool>,
+    pub preset: Option<String>,
+    pub matrix: Option<Vec<String>>,
+    pub checksum_header: Option<bool>,
+    pub at_ref: Option<String>,
+    pub include_submodules: Option<SubmoduleMode>,
+    pub submodule_depth: Option<usize>,
+    pub top: Option<usize>,
+    pub run_dirs: Option<bool>,
+    pub summary_json: Option<String>,
+    pub open: Option<bool>,
+    pub open_with: Option<String>,
+    pub force: Option<bool>,
+    pub targets: Option<BTreeMap<String, TargetConfig>>,
+    pub rules: Option<Vec<Rule>>,
+}
+
+/// A size in bytes, written as a number of bytes or a string such as `"500KB"`.
+#[derive(Debug, Clone, Copy)]
+pub struct ByteSize(pub u64);
+
+impl<'de> Deserialize<'de> for ByteSize {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Size {
+            Bytes(u64),
+            Text(String),
+        }
+        match Size::deserialize(deserializer)? {
+            Size::Bytes(bytes) => Ok(Self(bytes)),
+            Size::Text(text) => preflight::parse_bytes(&text).map(Self).map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+/// A point in time, written as a UTC date or timestamp such as `"2024-01-01"` or an age such as `"7d"`.
+#[derive(Debug, Clone, Copy)]
+pub struct Since(pub SystemTime);
+
+impl<'de> Deserialize<'de> for Since {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        runs::parse_since(&text).map(Self).map_err(serde::de::Error::custom)
+    }
 }
 
 impl ConfigFile {
-    /// Reads and parses a configuration file.
+    /// Reads and parses a configuration file, along with the chain of files it extends.
+    ///
+    /// The format is chosen by the file's extension: `.yaml` and `.yml` files are read as
+    /// YAML, `.json` files as JSON, and anything else as TOML. All three use the same keys,
+    /// and a file may extend one in another format.
+    ///
+    /// Unknown keys, which usually are typos, are errors that suggest the closest known key.
+    /// With `lax`, they are logged as warnings and ignored instead.
+    ///
+    /// In `source_dir`, `dest_dir`, `single_file`, `output_archive`, `summary_json`, `plugins`,
+    /// and `extends`, a leading `~` and `$VAR` or `${VAR}` references are expanded, and
+    /// relative paths are resolved against the directory containing the file that sets them.
     ///
     /// # Arguments
     ///
     /// * `path` - The path to the configuration file.
+    /// * `lax` - Whether to ignore unknown keys instead of failing.
     ///
     /// # Returns
     ///
     /// Returns `Result<Self, anyhow::Error>` containing the parsed ConfigFile if successful,
-    /// or an error if reading or parsing fails.
-    pub fn read_from_file(path: &Path) -> Result<Self> {
+    /// or an error if reading or parsing fails, if a file has unknown keys, or if the files
+    /// extend each other in a cycle, or if a path names an unset environment variable.
+    pub fn read_from_file(path: &Path, lax: bool) -> Result<Self> {
+        Self::read_extending(path, lax, &mut Vec::new())
+    }
+
+    /// Reads a configuration file and, recursively, the base it extends.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the configuration file.
+    /// * `lax` - Whether to ignore unknown keys instead of failing.
+    /// * `chain` - The canonical paths of the files that extend this one, outermost first.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<Self, anyhow::Error>` containing the parsed ConfigFile and its bases.
+    fn read_extending(path: &Path, lax: bool, chain: &mut Vec<PathBuf>) -> Result<Self> {
+        let canonical = path
+            .canonicalize()
+            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+        if chain.contains(&canonical) {
+            let cycle: Vec<String> =
+                chain.iter().chain([&canonical]).map(|file| file.display().to_string()).collect();
+            bail!("Config files extend each other in a cycle: {}", cycle.join(" -> "));
+        }
+        chain.push(canonical);
+
+        let mut config = Self::parse(path, lax)?;
+        config
+            .resolve_paths(path.parent().unwrap_or(Path::new("")))
+            .with_context(|| format!("Invalid path in config file: {}", path.display()))?;
+        if let Some(ref extends) = config.extends {
+            let base_path = PathBuf::from(extends);
+            debug!("Config file {} extends {}", path.display(), base_path.display());
+            config.base = Some(Box::new(Self::read_extending(&base_path, lax, chain)?));
+        }
+        Ok(config)
+    }
+
+    /// Expands and resolves the path settings of this file against its directory.
+    fn resolve_paths(&mut self, dir: &Path) -> std::result::Result<(), ExpandError> {
+        let Self { extends, source_dir, dest_dir, single_file, output_archive, summary_json, plugins, .. } = self;
+        let plugins = plugins.iter_mut().flatten();
+        for path in [extends, source_dir, dest_dir, single_file, output_archive, summary_json].into_iter().flatten().chain(plugins) {
+            *path = paths::resolve_config_path(path, dir)?;
+        }
+        Ok(())
+    }
+
+    /// Parses a single configuration file in the format given by its extension.
+    fn parse(path: &Path, lax: bool) -> Result<Self> {
         let contents = fs::read_to_string(path)
             .with_context(|| format!("Failed to read config file: {}", path.display()))?;
-        let config: ConfigFile = toml::from_str(&contents)
+        let extension = path.extension().and_then(|ext| ext.to_str()).map(str::to_ascii_lowercase);
+        let mut unknown = Vec::new();
+        let record = |key: serde_ignored::Path| unknown.push(key.to_string());
+        let config: Result<ConfigFile> = match extension.as_deref() {
+            Some("yaml" | "yml") => {
+                serde_ignored::deserialize(serde_yaml::Deserializer::from_str(&contents), record).map_err(Into::into)
+            }
+            Some("json") => {
+                serde_ignored::deserialize(&mut serde_json::Deserializer::from_str(&contents), record).map_err(Into::into)
+            }
+            _ => serde_ignored::deserialize(toml::Deserializer::new(&contents), record).map_err(Into::into),
+        };
+        let config = config
             .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
+        if !unknown.is_empty() {
+            let known = field_names::<ConfigFile>();
+            let problems: Vec<String> = unknown
+                .iter()
+                .map(|key| match key.strip_prefix("rules.") {
+                    // A key inside a `[[rules]]` section is compared with the keys a rule takes.
+                    Some(rule_key) => (key, suggest(rule_key.rsplit('.').next().unwrap_or(rule_key), field_names::<Rule>())),
+                    None => (key, suggest(key, known)),
+                })
+                .map(|(key, suggestion)| match suggestion {
+                    Some(suggestion) => format!("unknown key `{}`; did you mean `{}`?", key, suggestion),
+                    None => format!("unknown key `{}`", key),
+                })
+                .collect();
+            if !lax {
+                bail!(
+                    "Invalid config file {}:\n  {}\nFix the keys, or pass --lax-config to ignore unknown keys",
+                    path.display(),
+                    problems.join("\n  ")
+                );
+            }
+            for problem in problems {
+                warn!("{}: ignoring {}", path.display(), problem);
+            }
+        }
         Ok(config)
     }
 
     /// Applies the configuration from the file to the given Config instance.
     ///
+    /// The base configuration, if the file extends one, is applied first, so that this
+    /// file's settings override it. Settings this file leaves out keep the base's value.
+    ///
     /// # Arguments
     ///
     /// * `config` - The Config instance to update.
     pub fn apply_to_config(&self, config: &mut Config) {
+        // Every Config field is named here so that adding a command-line option without
+        // deciding on its config-file counterpart fails to compile. `targets` and `rules`
+        // can only be declared in the file, and the fields below them select what a single
+        // invocation does and are command-line only. The tests at the end of this file check
+        // that every option has a key.
+        let Config {
+            source_dir: _, root: _, dest_dir: _, dest_in_project: _, additional_ignored_dirs: _, target_dirs: _,
+            excluded_extensions: _, included_extensions: _, include_globs: _, exclude_globs: _, entries: _, follow_imports: _, walk_order: _,
+            binary_mode: _, max_file_size: _, max_file_size_per_extension: _, git_tracked: _, git_diff: _, since: _, max_lines: _, owner: _, exclude_owner: _, front_matter: _, expand_includes: _, squeeze_blank_lines: _, minify_indent: _, skeleton: _, no_tests: _, transforms: _, transform_cmds: _, plugins: _, convert_notebooks: _, summarize_sql_dumps: _, include_bundles: _,
+            keep_duplicates: _, strict: _, strict_utf8: _, no_walk_cache: _, no_redact: _, keep_empty: _, split_tokens: _, split_lines: _, max_tokens: _, budget_policy: _,
+            token_report: _, lang_stats: _, locales: _, default_locale: _,
+            dependency_summary: _, dependency_licenses: _, include_git_log: _, git_log_selected: _, temp_patterns: _,
+            no_default_temp_patterns: _, format: _, single_file: _, bundle_header: _, fail_on_collision: _,
+            max_filename_length: _, output_archive: _, prune: _, clean: _, markdown_per_target: _, manifest_markdown: _, keep_dirs: _, flatten_separator: _,
+            preserve_structure: _, name_prefix: _, name_suffix: _, preset: _, matrix: _,
+            checksum_header: _, at_ref: _, include_submodules: _, submodule_depth: _, top: _,
+            run_dirs: _, summary_json: _, open: _, open_with: _, force: _, targets: _, rules: _,
+            config_file: _, no_config: _, lax_config: _, last: _, run_id: _, verify: _, dry_run: _, explicit_args: _,
+            dest_dir_from_file: _,
+        } = config;
+
+        if let Some(ref base) = self.base {
+            base.apply_to_config(config);
+        }
         if let Some(ref source_dir) = self.source_dir {
             config.source_dir = source_dir.into();
         }
+        if let Some(root) = self.root {
+            config.root = root;
+        }
         if let Some(ref dest_dir) = self.dest_dir {
             config.dest_dir = dest_dir.into();
+            config.dest_dir_from_file = true;
+        }
+        if let Some(dest_in_project) = self.dest_in_project {
+            config.dest_in_project = dest_in_project;
         }
         if let Some(ref ignored_dirs) = self.additional_ignored_dirs {
             config.additional_ignored_dirs = ignored_dirs.join(",");
@@ -58,5 +337,461 @@ impl ConfigFile {
         if let Some(ref included_exts) = self.included_extensions {
             config.included_extensions = included_exts.join(",");
         }
+        if let Some(ref include_globs) = self.include_globs {
+            config.include_globs = include_globs.clone();
+        }
+        if let Some(ref exclude_globs) = self.exclude_globs {
+            config.exclude_globs = exclude_globs.clone();
+        }
+        if let Some(ref entries) = self.entries {
+            config.entries = entries.iter().map(PathBuf::from).collect();
+        }
+        if let Some(follow_imports) = self.follow_imports {
+            config.follow_imports = follow_imports;
+        }
+        if let Some(walk_order) = self.walk_order {
+            config.walk_order = walk_order;
+        }
+        if let Some(format) = self.format {
+            config.format = format;
+        }
+        if let Some(ref single_file) = self.single_file {
+            config.single_file = Some(single_file.into());
+        }
+        if let Some(ref output_archive) = self.output_archive {
+            config.output_archive = Some(output_archive.into());
+        }
+        if let Some(prune) = self.prune {
+            config.prune = prune;
+        }
+        if let Some(clean) = self.clean {
+            config.clean = clean;
+        }
+        if let Some(markdown_per_target) = self.markdown_per_target {
+            config.markdown_per_target = markdown_per_target;
+        }
+        if let Some(manifest_markdown) = self.manifest_markdown {
+            config.manifest_markdown = manifest_markdown;
+        }
+        if let Some(fail_on_collision) = self.fail_on_collision {
+            config.fail_on_collision = fail_on_collision;
+        }
+        if let Some(max_filename_length) = self.max_filename_length {
+            config.max_filename_length = max_filename_length;
+        }
+        if let Some(keep_dirs) = self.keep_dirs {
+            config.keep_dirs = keep_dirs;
+        }
+        if let Some(ref flatten_separator) = self.flatten_separator {
+            config.flatten_separator = flatten_separator.clone();
+        }
+        if let Some(preserve_structure) = self.preserve_structure {
+            config.preserve_structure = preserve_structure;
+        }
+        if let Some(binary_mode) = self.binary_mode {
+            config.binary_mode = binary_mode;
+        }
+        if let Some(max_file_size) = self.max_file_size {
+            config.max_file_size = Some(max_file_size.0);
+        }
+        if let Some(ref limits) = self.max_file_size_per_extension {
+            config.max_file_size_per_extension.extend(
+                limits.iter().map(|(ext, size)| (ext.trim_start_matches('.').to_lowercase(), size.0)),
+            );
+        }
+        if let Some(git_tracked) = self.git_tracked {
+            config.git_tracked = git_tracked;
+        }
+        if let Some(ref git_diff) = self.git_diff {
+            config.git_diff = Some(git_diff.clone());
+        }
+        if let Some(since) = self.since {
+            config.since = Some(since.0);
+        }
+        if let Some(max_lines) = self.max_lines {
+            config.max_lines = Some(max_lines);
+        }
+        if let Some(ref owner) = self.owner {
+            config.owner = Some(owner.join(","));
+        }
+        if let Some(ref exclude_owner) = self.exclude_owner {
+            config.exclude_owner = exclude_owner.join(",");
+        }
+        if let Some(front_matter) = self.front_matter {
+            config.front_matter = front_matter;
+        }
+        if let Some(expand_includes) = self.expand_includes {
+            config.expand_includes = expand_includes;
+        }
+        if let Some(squeeze_blank_lines) = self.squeeze_blank_lines {
+            config.squeeze_blank_lines = squeeze_blank_lines;
+        }
+        if let Some(minify_indent) = self.minify_indent {
+            config.minify_indent = minify_indent;
+        }
+        if let Some(skeleton) = self.skeleton {
+            config.skeleton = skeleton;
+        }
+        if let Some(no_tests) = self.no_tests {
+            config.no_tests = no_tests;
+        }
+        if let Some(ref transforms) = self.transforms {
+            config.transforms = transforms.clone();
+        }
+        if let Some(ref transform_cmds) = self.transform_cmds {
+            config.transform_cmds = transform_cmds.clone();
+        }
+        if let Some(ref plugins) = self.plugins {
+            config.plugins = plugins.iter().map(PathBuf::from).collect();
+        }
+        if let Some(convert_notebooks) = self.convert_notebooks {
+            config.convert_notebooks = convert_notebooks;
+        }
+        if let Some(summarize_sql_dumps) = self.summarize_sql_dumps {
+            config.summarize_sql_dumps = summarize_sql_dumps;
+        }
+        if let Some(keep_empty) = self.keep_empty {
+            config.keep_empty = keep_empty;
+        }
+        if let Some(split_tokens) = self.split_tokens {
+            config.split_tokens = Some(split_tokens);
+        }
+        if let Some(split_lines) = self.split_lines {
+            config.split_lines = Some(split_lines);
+        }
+        if let Some(include_bundles) = self.include_bundles {
+            config.include_bundles = include_bundles;
+        }
+        if let Some(keep_duplicates) = self.keep_duplicates {
+            config.keep_duplicates = keep_duplicates;
+        }
+        if let Some(strict) = self.strict {
+            config.strict = strict;
+        }
+        if let Some(strict_utf8) = self.strict_utf8 {
+            config.strict_utf8 = strict_utf8;
+        }
+        if let Some(walk_cache) = self.walk_cache {
+            config.no_walk_cache = !walk_cache;
+        }
+        if let Some(bundle_header) = self.bundle_header {
+            config.bundle_header = bundle_header;
+        }
+        if let Some(redact) = self.redact {
+            config.no_redact = !redact;
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            config.max_tokens = Some(max_tokens);
+        }
+        if let Some(budget_policy) = self.budget_policy {
+            config.budget_policy = budget_policy;
+        }
+        if let Some(token_report) = self.token_report {
+            config.token_report = token_report;
+        }
+        if let Some(lang_stats) = self.lang_stats {
+            config.lang_stats = lang_stats;
+        }
+        if let Some(locales) = self.locales {
+            config.locales = locales;
+        }
+        if let Some(ref default_locale) = self.default_locale {
+            config.default_locale = default_locale.clone();
+        }
+        if let Some(dependency_summary) = self.dependency_summary {
+            config.dependency_summary = dependency_summary;
+        }
+        if let Some(dependency_licenses) = self.dependency_licenses {
+            config.dependency_licenses = dependency_licenses;
+        }
+        if let Some(include_git_log) = self.include_git_log {
+            config.include_git_log = Some(include_git_log);
+        }
+        if let Some(git_log_selected) = self.git_log_selected {
+            config.git_log_selected = git_log_selected;
+        }
+        if let Some(ref name_prefix) = self.name_prefix {
+            config.name_prefix = name_prefix.clone();
+        }
+        if let Some(ref name_suffix) = self.name_suffix {
+            config.name_suffix = name_suffix.clone();
+        }
+        if let Some(ref temp_patterns) = self.temp_patterns {
+            config.temp_patterns = temp_patterns.join(",");
+        }
+        if let Some(default_temp_patterns) = self.default_temp_patterns {
+            config.no_default_temp_patterns = !default_temp_patterns;
+        }
+        if let Some(ref preset) = self.preset {
+            config.preset = Some(preset.clone());
+        }
+        if let Some(ref matrix) = self.matrix {
+            config.matrix = Some(matrix.join(","));
+        }
+        if let Some(checksum_header) = self.checksum_header {
+            config.checksum_header = checksum_header;
+        }
+        if let Some(ref at_ref) = self.at_ref {
+            config.at_ref = Some(at_ref.clone());
+        }
+        if let Some(include_submodules) = self.include_submodules {
+            config.include_submodules = Some(include_submodules);
+        }
+        if let Some(submodule_depth) = self.submodule_depth {
+            config.submodule_depth = submodule_depth;
+        }
+        if let Some(top) = self.top {
+            config.top = Some(top);
+        }
+        if let Some(run_dirs) = self.run_dirs {
+            config.run_dirs = run_dirs;
+        }
+        if let Some(ref summary_json) = self.summary_json {
+            config.summary_json = Some(summary_json.into());
+        }
+        if let Some(open) = self.open {
+            config.open = open;
+        }
+        if let Some(ref open_with) = self.open_with {
+            config.open_with = Some(open_with.clone());
+        }
+        if let Some(force) = self.force {
+            config.force = force;
+        }
+        if let Some(ref targets) = self.targets {
+            config.targets.extend(targets.clone());
+        }
+        if let Some(ref rules) = self.rules {
+            config.rules.extend(rules.iter().cloned());
+        }
+    }
+}
+
+/// Suggests the known key closest to an unknown one, for a "did you mean" hint.
+///
+/// Only the top-level part of a dotted key is compared. A suggestion is made when the
+/// keys differ by at most a third of the unknown key's length, and by no more than 3 edits.
+///
+/// # Arguments
+///
+/// * `key` - The unknown key.
+/// * `known` - The keys that are accepted.
+///
+/// # Returns
+///
+/// Returns `Some(&str)` with the closest known key, or `None` if none is close enough.
+///
+/// # Example
+///
+/// ```
+/// use rustifacts::config_file::suggest;
+///
+/// let known = ["target_dirs", "include_globs", "exclude_globs"];
+/// assert_eq!(suggest("target_dir", &known), Some("target_dirs"));
+/// assert_eq!(suggest("exlcude_globs", &known), Some("exclude_globs"));
+/// assert_eq!(suggest("colour", &known), None);
+/// ```
+pub fn suggest<'a>(key: &str, known: &[&'a str]) -> Option<&'a str> {
+    let key = key.split('.').next().unwrap_or(key);
+    let limit = (key.chars().count() / 3).clamp(1, 3);
+    known
+        .iter()
+        .map(|candidate| (edit_distance(key, candidate), *candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Counts the insertions, deletions, substitutions, and swaps of adjacent characters
+/// that turn one string into the other.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    rows[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1).min(rows[i][j - 1] + 1).min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+/// Returns the field names a struct accepts when deserialized.
+///
+/// The names are recorded by a deserializer that serde asks for the struct, so they
+/// always match the struct's definition, renames included.
+fn field_names<'de, T: Deserialize<'de>>() -> &'static [&'static str] {
+    struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de> serde::Deserializer<'de> for FieldNames<'_> {
+        type Error = serde::de::value::Error;
+
+        fn deserialize_any<V: serde::de::Visitor<'de>>(self, _: V) -> std::result::Result<V::Value, Self::Error> {
+            Err(serde::de::Error::custom("only structs record their field names"))
+        }
+
+        fn deserialize_struct<V: serde::de::Visitor<'de>>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> std::result::Result<V::Value, Self::Error> {
+            *self.0 = fields;
+            Err(serde::de::Error::custom("field names recorded"))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf option
+            unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier ignored_any
+        }
     }
-}
\ No newline at end of file
+
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(FieldNames(&mut fields));
+    fields
+}
+
+/// Looks for a project-local configuration file.
+///
+/// `rustifacts.toml`, `.yaml`, `.yml`, or `.json`, then the same names with a leading dot,
+/// are looked for in the source directory and each of its ancestors up to the root of the git repository containing it. Outside a
+/// repository, only the source directory itself is searched.
+///
+/// # Arguments
+///
+/// * `source_dir` - The source directory, or an archive whose directory is searched.
+///
+/// # Returns
+///
+/// Returns `Some(PathBuf)` with the path of the nearest configuration file, or `None` if
+/// there is none.
+pub fn discover(source_dir: &Path) -> Option<PathBuf> {
+    let start = source_dir.canonicalize().ok()?;
+    let start = if start.is_file() { start.parent()?.to_path_buf() } else { start };
+    let repo_root = start.ancestors().find(|dir| dir.join(".git").exists());
+    let search: Vec<&Path> = match repo_root {
+        Some(root) => start.ancestors().take_while(|dir| dir.starts_with(root)).collect(),
+        None => vec![start.as_path()],
+    };
+    search
+        .into_iter()
+        .flat_map(|dir| {
+            CONFIG_FILE_STEMS.iter().flat_map(move |stem| {
+                CONFIG_FILE_EXTENSIONS.iter().map(move |ext| dir.join(format!("{}.{}", stem, ext)))
+            })
+        })
+        .find(|path| path.is_file())
+}
+
+/// Writes a starter configuration file into a directory.
+///
+/// When seeded from a preset, the file spells out the preset's settings, followed by
+/// commented-out examples of the settings the preset leaves alone.
+///
+/// # Arguments
+///
+/// * `dir` - The directory to write `rustifacts.toml` into.
+/// * `preset` - The name and configuration of the preset to seed the file from, if any.
+/// * `force` - Whether to overwrite an existing file.
+///
+/// # Returns
+///
+/// Returns `Result<PathBuf, anyhow::Error>` containing the path of the written file,
+/// or an error if the file already exists (without `force`) or cannot be written.
+pub fn init(dir: &Path, preset: Option<(&str, &PresetConfig)>, force: bool) -> Result<PathBuf> {
+    let path = dir.join(CONFIG_FILE_NAME);
+    if path.exists() && !force {
+        bail!("{} already exists; pass --force to overwrite it", path.display());
+    }
+    let mut contents = format!("{}\n", STARTER_HEADER);
+    let mut options: Vec<&str> = STARTER_OPTIONS.lines().collect();
+    if let Some((name, preset)) = preset {
+        let settings = preset.to_toml(name)?;
+        options.retain(|line| {
+            let key = line.trim_start_matches("# ").split(" =").next().unwrap_or_default();
+            !settings.lines().any(|setting| setting.starts_with(&format!("{} =", key)))
+        });
+        contents.push_str(&settings);
+        contents.push('\n');
+    }
+    for line in options {
+        contents.push_str(line);
+        contents.push('\n');
+    }
+    fs::write(&path, contents)
+        .with_context(|| format!("Failed to write config file: {}", path.display()))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+    use crate::cli::Cli;
+
+    /// Command-line options that select what a single invocation does, and so have no
+    /// config-file key.
+    const CLI_ONLY: &[&str] = &["config_file", "no_config", "lax_config", "last", "verify", "dry_run", "help", "version"];
+
+    /// Options whose config-file key is named differently, as `(option, key)`.
+    const RENAMED: &[(&str, &str)] = &[
+        ("no_walk_cache", "walk_cache"),
+        ("no_redact", "redact"),
+        ("no_default_temp_patterns", "default_temp_patterns"),
+    ];
+
+    #[test]
+    fn every_option_has_a_config_file_key() {
+        let keys = field_names::<ConfigFile>();
+        let missing: Vec<String> = Cli::command()
+            .get_arguments()
+            .map(|arg| arg.get_id().as_str())
+            .filter(|id| !CLI_ONLY.contains(id))
+            .map(|id| RENAMED.iter().find(|(option, _)| *option == id).map_or(id, |(_, key)| *key))
+            .filter(|key| !keys.contains(key))
+            .map(String::from)
+            .collect();
+        assert!(missing.is_empty(), "options without a config-file key: {}", missing.join(", "));
+    }
+
+    #[test]
+    fn every_config_file_key_sets_an_option() {
+        // Keys for settings that only the config file can declare.
+        const FILE_ONLY: &[&str] = &["extends", "max_file_size_per_extension", "targets", "rules"];
+        let options: Vec<String> = Cli::command().get_arguments().map(|arg| arg.get_id().to_string()).collect();
+        let orphaned: Vec<&str> = field_names::<ConfigFile>()
+            .iter()
+            .copied()
+            .filter(|key| !FILE_ONLY.contains(key))
+            .filter(|key| {
+                let option = RENAMED.iter().find(|(_, renamed)| renamed == key).map_or(*key, |(option, _)| *option);
+                !options.iter().any(|id| id == option)
+            })
+            .collect();
+        assert!(orphaned.is_empty(), "config-file keys without an option: {}", orphaned.join(", "));
+    }
+
+    #[test]
+    fn a_chosen_dest_dir_is_kept_even_if_it_matches_the_default() {
+        let mut config = Config::default();
+        assert!(config.is_default_dest());
+
+        let file: ConfigFile = toml::from_str(r#"dest_dir = "./claude_files""#).unwrap();
+        file.apply_to_config(&mut config);
+        config.apply_default_dest();
+        assert_eq!(config.dest_dir, Path::new(crate::config::DEFAULT_DEST_DIR));
+
+        let mut config = Cli::parse_recording_explicit(["rustifacts", "-d", "./claude_files"]).config;
+        config.apply_default_dest();
+        assert_eq!(config.dest_dir, Path::new(crate::config::DEFAULT_DEST_DIR));
+    }
+}
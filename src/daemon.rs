@@ -0,0 +1,485 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use thiserror::Error;
+use crate::artifact::{Artifact, ArtifactError, CollectFilter};
+use crate::checksum;
+use crate::config::Config;
+use crate::global_config::GlobalConfig;
+use crate::open;
+use crate::paths;
+use crate::preflight::{self, PreflightError};
+use crate::rpc::{self, RpcError};
+use crate::stats;
+use crate::tokens::{self, TokenError};
+use crate::upload::{self, UploadError};
+use crate::vfs::{FileSystem, RealFs};
+use crate::walk_cache::WalkCache;
+use crate::writer::{self, DirectoryWriter};
+
+/// Directory under the user cache directory that holds the daemons' sockets.
+const SOCKETS_DIR: &str = "daemon";
+
+/// Custom error type for daemon mode.
+#[derive(Error, Debug)]
+pub enum DaemonError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Artifact error: {0}")]
+    Artifact(#[from] ArtifactError),
+    #[error("Preflight error: {0}")]
+    Preflight(#[from] PreflightError),
+    #[error("Token budget error: {0}")]
+    Tokens(#[from] TokenError),
+    #[error("Upload error: {0}")]
+    Upload(#[from] UploadError),
+    #[error("Watch error: {0}")]
+    Notify(#[from] notify::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("{} (code {})", .0.message, .0.code)]
+    Rpc(RpcError),
+    #[error("{0} is outside the source directory")]
+    OutsideSource(PathBuf),
+    #[error("A daemon is already listening on {0}")]
+    AlreadyRunning(PathBuf),
+    #[error("No daemon is listening on {0}; start one with `rustifacts daemon`")]
+    NotRunning(PathBuf),
+    #[error("Daemon mode requires Unix domain sockets, which this platform does not provide")]
+    Unsupported,
+}
+
+impl From<DaemonError> for RpcError {
+    fn from(error: DaemonError) -> Self {
+        let code = match error {
+            DaemonError::OutsideSource(_) | DaemonError::Upload(UploadError::UnknownTarget(_)) => rpc::INVALID_PARAMS,
+            _ => rpc::SERVER_ERROR,
+        };
+        RpcError::new(code, error.to_string())
+    }
+}
+
+/// Parameters naming files, relative to the source directory or absolute.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct PathsParams {
+    paths: Option<Vec<String>>,
+}
+
+/// Parameters of `estimate`: files to count, or text to count instead of any files.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct EstimateParams {
+    paths: Option<Vec<String>>,
+    text: Option<String>,
+}
+
+/// Parameters of `upload`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct UploadParams {
+    target: String,
+}
+
+/// Returns the default socket of the daemon serving a source directory.
+///
+/// Each source directory gets its own socket under the user cache directory, named
+/// after a hash of its canonical path, so clients find the daemon from the source alone.
+///
+/// # Arguments
+///
+/// * `source_dir` - The source directory the daemon serves.
+///
+/// # Returns
+///
+/// Returns `Some(PathBuf)` with the socket path, or `None` if the source directory does
+/// not exist or there is no user cache directory.
+pub fn socket_path(source_dir: &Path) -> Option<PathBuf> {
+    let canonical = source_dir.canonicalize().ok()?;
+    let name = format!("{}.sock", checksum::short_hash(&canonical.to_string_lossy()));
+    GlobalConfig::cache_dir().map(|dir| dir.join(SOCKETS_DIR).join(name))
+}
+
+/// The state shared by the connections to a running daemon.
+struct Daemon {
+    config: Config,
+    socket: PathBuf,
+    /// The walk cache kept warm between requests, unless `--no-walk-cache` is set.
+    walk_cache: Option<WalkCache>,
+    /// Set by the watcher when the source changes, and cleared when the walk cache is invalidated.
+    changed: Arc<AtomicBool>,
+    /// Requests share the walk cache and the destination, so they are handled one at a time.
+    busy: Mutex<()>,
+    stopping: AtomicBool,
+}
+
+impl Daemon {
+    fn fs(&self) -> &dyn FileSystem {
+        match self.walk_cache {
+            Some(ref walk_cache) => walk_cache,
+            None => &RealFs,
+        }
+    }
+
+    /// Runs one JSON-RPC method.
+    fn call(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+        let _busy = self.busy.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if self.changed.swap(false, Ordering::SeqCst) {
+            if let Some(ref walk_cache) = self.walk_cache {
+                walk_cache.invalidate();
+            }
+        }
+        let result = match method {
+            "ping" => self.ping(),
+            "list" => self.list(),
+            "query" => self.query(rpc::params::<PathsParams>(params)?.paths.unwrap_or_default()),
+            "estimate" => self.estimate(rpc::params(params)?),
+            "stats" => self.stats(),
+            "collect" => self.collect(rpc::params::<PathsParams>(params)?.paths),
+            "write" => self.write(),
+            "upload" => self.upload(rpc::params(params)?),
+            "shutdown" => {
+                self.stopping.store(true, Ordering::SeqCst);
+                Ok(Value::Null)
+            }
+            _ => return Err(RpcError::new(rpc::METHOD_NOT_FOUND, format!("Unknown method {:?}", method))),
+        };
+        Ok(result?)
+    }
+
+    fn ping(&self) -> Result<Value, DaemonError> {
+        Ok(json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "source": self.config.source_dir,
+            "destination": DirectoryWriter::from_config(&self.config).output_dir(),
+        }))
+    }
+
+    /// Lists the files that would be collected, without reading them.
+    fn list(&self) -> Result<Value, DaemonError> {
+        let plan = Artifact::plan(self.fs(), &self.config)?;
+        let files: Vec<Value> = plan
+            .files
+            .iter()
+            .map(|planned| json!({"path": planned.relative_path, "name": planned.new_filename, "size": planned.size}))
+            .chain(plan.submodule_stubs.iter().map(|stub| {
+                json!({"path": stub.relative_path, "name": stub.new_filename, "size": stub.content.len()})
+            }))
+            .collect();
+        Ok(json!({ "files": files }))
+    }
+
+    /// Reports whether each of the given files is part of the selection, and under which name.
+    fn query(&self, requested: Vec<String>) -> Result<Value, DaemonError> {
+        let plan = Artifact::plan(self.fs(), &self.config)?;
+        let selected: BTreeMap<&Path, &str> = plan
+            .files
+            .iter()
+            .map(|planned| (planned.relative_path.as_path(), planned.new_filename.as_str()))
+            .collect();
+        let files = requested
+            .into_iter()
+            .map(|path| {
+                let relative = self.relative_path(&path)?;
+                let name = selected.get(relative.as_path());
+                Ok(json!({"path": path, "selected": name.is_some(), "name": name}))
+            })
+            .collect::<Result<Vec<Value>, DaemonError>>()?;
+        Ok(json!({ "files": files }))
+    }
+
+    /// Counts the tokens of some text, of the given files, or of the whole selection.
+    fn estimate(&self, params: EstimateParams) -> Result<Value, DaemonError> {
+        if let Some(text) = params.text {
+            return Ok(json!({"tokens": tokens::count(&text)}));
+        }
+        let Some(requested) = params.paths else {
+            let artifacts = Artifact::collect(self.fs(), &self.config)?;
+            return Ok(json!({"tokens": tokens::total(&artifacts), "files": artifacts.len()}));
+        };
+        let filter = CollectFilter::from_config(&self.config)?;
+        let mut total = 0;
+        let mut files = Vec::new();
+        for path in requested {
+            let absolute = self.config.source_dir.join(self.relative_path(&path)?);
+            let tokens = Artifact::collect_file(self.fs(), &absolute, &self.config, &filter)?.map(|artifact| artifact.tokens);
+            total += tokens.unwrap_or_default();
+            files.push(json!({"path": path, "tokens": tokens}));
+        }
+        Ok(json!({"tokens": total, "files": files}))
+    }
+
+    fn stats(&self) -> Result<Value, DaemonError> {
+        let artifacts = Artifact::collect(self.fs(), &self.config)?;
+        Ok(json!({"total": stats::total(&artifacts), "by_extension": stats::by_extension(&artifacts)}))
+    }
+
+    /// Collects the given files, or the whole selection, returning their content.
+    fn collect(&self, requested: Option<Vec<String>>) -> Result<Value, DaemonError> {
+        let artifacts = match requested {
+            Some(requested) => {
+                let filter = CollectFilter::from_config(&self.config)?;
+                let mut artifacts = Vec::new();
+                for path in requested {
+                    let absolute = self.config.source_dir.join(self.relative_path(&path)?);
+                    artifacts.extend(Artifact::collect_file(self.fs(), &absolute, &self.config, &filter)?);
+                }
+                artifacts
+            }
+            None => Artifact::collect(self.fs(), &self.config)?,
+        };
+        let artifacts: Vec<Value> = artifacts
+            .iter()
+            .map(|artifact| {
+                json!({
+                    "path": artifact.relative_path,
+                    "name": artifact.new_filename,
+                    "tokens": artifact.tokens,
+                    "content": artifact.content,
+                })
+            })
+            .collect();
+        Ok(json!({ "artifacts": artifacts }))
+    }
+
+    /// Collects the artifacts and writes them like a regular run.
+    fn write(&self) -> Result<Value, DaemonError> {
+        let config = &self.config;
+        let plan = Artifact::plan(self.fs(), config)?;
+        preflight::run(config, &plan)?;
+        let mut artifacts = Artifact::execute(self.fs(), plan, config)?;
+        if let Some(max_tokens) = config.max_tokens {
+            tokens::enforce_budget(&mut artifacts, max_tokens, config.budget_policy)?;
+        }
+        writer::from_config(config).write(&artifacts)?;
+        Ok(json!({
+            "written": artifacts.len(),
+            "destination": open::output_path(config),
+        }))
+    }
+
+    /// Writes the artifacts, then uploads the written files to a target from the config file.
+    fn upload(&self, params: UploadParams) -> Result<Value, DaemonError> {
+        let target = upload::find_target(&self.config, &params.target)?.build();
+        let mut result = self.write()?;
+        let files = upload::output_files(&self.config)?;
+        result["uploaded"] = json!(target.upload_all(&files)?);
+        result["target"] = json!(params.target);
+        Ok(result)
+    }
+
+    /// Turns a path from a request into a path relative to the source directory.
+    ///
+    /// Paths that leave the source directory, absolute or relative, are rejected, including
+    /// absolute paths inside it that climb out again with `..`.
+    fn relative_path(&self, path: &str) -> Result<PathBuf, DaemonError> {
+        let relative = if Path::new(path).is_absolute() {
+            let absolute = paths::normalize_drive(Path::new(path));
+            absolute
+                .strip_prefix(paths::normalize_drive(&self.config.source_dir))
+                .map(Path::to_path_buf)
+                .map_err(|_| DaemonError::OutsideSource(absolute.clone()))?
+        } else {
+            PathBuf::from(paths::normalize_separators(path))
+        };
+        if !relative.components().all(|component| matches!(component, Component::Normal(_) | Component::CurDir)) {
+            return Err(DaemonError::OutsideSource(PathBuf::from(path)));
+        }
+        Ok(relative)
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::fs;
+    use std::io::{self, BufRead, BufReader, Write};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::Path;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use log::{debug, info, warn};
+    use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+    use serde_json::Value;
+    use crate::config::Config;
+    use crate::rpc;
+    use crate::walk_cache::WalkCache;
+    use super::{Daemon, DaemonError};
+
+    pub fn serve(mut config: Config, socket: &Path) -> Result<(), DaemonError> {
+        config.source_dir = config.source_dir.canonicalize()?;
+        if socket.exists() {
+            if UnixStream::connect(socket).is_ok() {
+                return Err(DaemonError::AlreadyRunning(socket.to_path_buf()));
+            }
+            debug!("Removing stale socket {}", socket.display());
+            fs::remove_file(socket)?;
+        }
+        if let Some(parent) = socket.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let listener = UnixListener::bind(socket)?;
+
+        let changed = Arc::new(AtomicBool::new(false));
+        let watched = Arc::clone(&changed);
+        let mut watcher = RecommendedWatcher::new(
+            move |event: notify::Result<Event>| {
+                if event.is_ok() {
+                    watched.store(true, Ordering::SeqCst);
+                }
+            },
+            notify::Config::default(),
+        )?;
+        watcher.watch(&config.source_dir, RecursiveMode::Recursive)?;
+
+        let daemon = Arc::new(Daemon {
+            walk_cache: (!config.no_walk_cache).then(|| WalkCache::new(&config.source_dir)),
+            config,
+            socket: socket.to_path_buf(),
+            changed,
+            busy: Mutex::new(()),
+            stopping: AtomicBool::new(false),
+        });
+        info!("Daemon for {} listening on {}", daemon.config.source_dir.display(), socket.display());
+
+        for stream in listener.incoming() {
+            if daemon.stopping.load(Ordering::SeqCst) {
+                break;
+            }
+            match stream {
+                Ok(stream) => {
+                    let daemon = Arc::clone(&daemon);
+                    thread::spawn(move || {
+                        if let Err(e) = serve_connection(&daemon, stream) {
+                            debug!("Connection closed: {}", e);
+                        }
+                    });
+                }
+                Err(e) => warn!("Failed to accept a connection: {}", e),
+            }
+        }
+
+        fs::remove_file(socket)?;
+        info!("Daemon stopped");
+        Ok(())
+    }
+
+    /// Answers the requests of one client, one line each, until it disconnects.
+    fn serve_connection(daemon: &Daemon, stream: UnixStream) -> io::Result<()> {
+        let mut writer = stream.try_clone()?;
+        for line in BufReader::new(stream).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = rpc::handle(&line, |method, params| {
+                debug!("Handling {}", method);
+                daemon.call(method, params)
+            });
+            if let Some(response) = response {
+                writeln!(writer, "{}", response)?;
+            }
+            if daemon.stopping.load(Ordering::SeqCst) {
+                // Wake the accept loop so that it sees the daemon is stopping.
+                let _ = UnixStream::connect(&daemon.socket);
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn send(socket: &Path, method: &str, params: Value) -> Result<Value, DaemonError> {
+        let mut stream = UnixStream::connect(socket).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused => DaemonError::NotRunning(socket.to_path_buf()),
+            _ => e.into(),
+        })?;
+        writeln!(stream, "{}", rpc::request(method, params, 1))?;
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line)?;
+        let mut response: Value = serde_json::from_str(&line)?;
+        match response.get("error") {
+            Some(error) => Err(DaemonError::Rpc(serde_json::from_value(error.clone())?)),
+            None => Ok(response["result"].take()),
+        }
+    }
+}
+
+/// Runs the daemon in the foreground until a client calls `shutdown`.
+///
+/// The daemon listens on a Unix domain socket for JSON-RPC 2.0 requests, one per line
+/// (or one batch per line), and answers each on one line. The walk cache stays in
+/// memory between requests, and a watcher on the source tells it when to check the
+/// tree again, so requests skip the start-up and traversal cost of a new process.
+///
+/// # Arguments
+///
+/// * `config` - The configuration options every request is served with.
+/// * `socket` - The path of the socket to listen on.
+///
+/// # Returns
+///
+/// Returns `Result<(), DaemonError>` once the daemon has stopped, or an error if it could
+/// not start, for instance because another daemon already listens on the socket.
+pub fn serve(config: Config, socket: &Path) -> Result<(), DaemonError> {
+    #[cfg(unix)]
+    return unix::serve(config, socket);
+    #[cfg(not(unix))]
+    {
+        let _ = (config, socket);
+        Err(DaemonError::Unsupported)
+    }
+}
+
+/// Calls a method on a running daemon and waits for its result.
+///
+/// # Arguments
+///
+/// * `socket` - The path of the daemon's socket.
+/// * `method` - The name of the method to call.
+/// * `params` - The method's parameters, or `Value::Null` for none.
+///
+/// # Returns
+///
+/// Returns `Result<Value, DaemonError>` containing the method's result, `DaemonError::Rpc`
+/// if the daemon answered with an error, or `DaemonError::NotRunning` if nothing listens
+/// on the socket.
+pub fn send(socket: &Path, method: &str, params: Value) -> Result<Value, DaemonError> {
+    #[cfg(unix)]
+    return unix::send(socket, method, params);
+    #[cfg(not(unix))]
+    {
+        let _ = (socket, method, params);
+        Err(DaemonError::Unsupported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn daemon(source_dir: &str) -> Daemon {
+        Daemon {
+            config: Config { source_dir: PathBuf::from(source_dir), ..Config::default() },
+            socket: PathBuf::new(),
+            walk_cache: None,
+            changed: Arc::default(),
+            busy: Mutex::new(()),
+            stopping: AtomicBool::new(false),
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn relative_path_rejects_paths_outside_the_source() {
+        let daemon = daemon("/tmp/d/src");
+        assert_eq!(daemon.relative_path("/tmp/d/src/lib/mod.rs").unwrap(), Path::new("lib/mod.rs"));
+        assert_eq!(daemon.relative_path("lib/mod.rs").unwrap(), Path::new("lib/mod.rs"));
+        for path in ["/tmp/d/src/../secret/key.rs", "../secret/key.rs", "lib/../../secret/key.rs", "/tmp/d/secret/key.rs"] {
+            assert!(matches!(daemon.relative_path(path), Err(DaemonError::OutsideSource(_))), "{}", path);
+        }
+    }
+}
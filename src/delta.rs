@@ -0,0 +1,125 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use crate::artifact::Artifact;
+use crate::config::Config;
+use crate::manifest::Manifest;
+use crate::writer::{DirectoryWriter, Writer};
+
+/// Name of the directory inside the destination that `re-export` writes into.
+pub const DELTA_DIR: &str = "delta";
+
+/// Custom error type for writing a delta.
+#[derive(Error, Debug)]
+pub enum DeltaError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("{0} exists and was not written by rustifacts; move it away or remove it")]
+    Foreign(PathBuf),
+}
+
+/// What a re-export wrote into the delta directory.
+#[derive(Debug, Default)]
+pub struct DeltaSummary {
+    /// The number of artifacts written into the delta directory.
+    pub written: usize,
+    /// The number of artifacts left out because they are unchanged.
+    pub unchanged: usize,
+    /// The source paths of the previous manifest that are no longer collected.
+    pub removed: Vec<String>,
+}
+
+/// Selects the artifacts whose content or name differs from their entry in a previous manifest.
+///
+/// Artifacts are matched to manifest entries by their source path, so an artifact is
+/// changed if its source is new, its checksum differs, or it is written under a new name.
+///
+/// # Arguments
+///
+/// * `artifacts` - The artifacts of the current run.
+/// * `previous` - The manifest of the run to compare against.
+///
+/// # Returns
+///
+/// A `Vec<&Artifact>` containing the changed artifacts, in their original order.
+pub fn changed<'a>(artifacts: &'a [Artifact], previous: &Manifest) -> Vec<&'a Artifact> {
+    let entries: HashMap<&str, (&str, &str)> = previous
+        .artifacts
+        .iter()
+        .map(|entry| (entry.original_path.as_str(), (entry.checksum.as_str(), entry.new_filename.as_str())))
+        .collect();
+    artifacts
+        .iter()
+        .filter(|artifact| {
+            let path = artifact.relative_path.to_string_lossy();
+            entries.get(path.as_ref()) != Some(&(artifact.checksum.as_str(), artifact.new_filename.as_str()))
+        })
+        .collect()
+}
+
+/// Lists the source paths of a previous manifest that the current run no longer collects.
+fn removed(artifacts: &[Artifact], previous: &Manifest) -> Vec<String> {
+    let current: HashSet<String> = artifacts
+        .iter()
+        .flat_map(|artifact| std::iter::once(&artifact.relative_path).chain(&artifact.duplicates))
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect();
+    previous
+        .artifacts
+        .iter()
+        .flat_map(|entry| std::iter::once(&entry.original_path).chain(&entry.duplicates))
+        .filter(|path| !current.contains(*path))
+        .cloned()
+        .collect()
+}
+
+/// Writes artifacts into the `delta/` directory of a destination, replacing any earlier delta.
+///
+/// With `only_changed`, only the artifacts that differ from the previous manifest are
+/// written; without a previous manifest, or without `only_changed`, every artifact is.
+/// The delta directory gets a manifest of its own listing just the artifacts it holds.
+///
+/// # Arguments
+///
+/// * `dest_dir` - The destination directory the full set of artifacts was written to.
+/// * `artifacts` - The artifacts of the current run.
+/// * `previous` - The destination's manifest from before the current run, if it had one.
+/// * `only_changed` - Whether to leave out artifacts that are unchanged.
+/// * `config` - The configuration, whose format and header settings the delta uses.
+///
+/// # Returns
+///
+/// Returns `Result<DeltaSummary, DeltaError>` describing what was written, or an error if
+/// the delta directory holds files rustifacts did not write or cannot be written.
+pub fn write(
+    dest_dir: &Path,
+    artifacts: &[Artifact],
+    previous: Option<&Manifest>,
+    only_changed: bool,
+    config: &Config,
+) -> Result<DeltaSummary, DeltaError> {
+    let delta_dir = dest_dir.join(DELTA_DIR);
+    if delta_dir.exists() {
+        if !Manifest::exists_in(&delta_dir) && fs::read_dir(&delta_dir)?.next().is_some() {
+            return Err(DeltaError::Foreign(delta_dir));
+        }
+        fs::remove_dir_all(&delta_dir)?;
+    }
+
+    let selected: Vec<Artifact> = match previous {
+        Some(previous) if only_changed => changed(artifacts, previous).into_iter().cloned().collect(),
+        _ => artifacts.to_vec(),
+    };
+    DirectoryWriter::new(&delta_dir)
+        .checksum_header(config.checksum_header)
+        .format(config.format)
+        .manifest_markdown(config.manifest_markdown)
+        .write(&selected)?;
+    Ok(DeltaSummary {
+        written: selected.len(),
+        unchanged: artifacts.len() - selected.len(),
+        removed: previous.map(|previous| removed(artifacts, previous)).unwrap_or_default(),
+    })
+}
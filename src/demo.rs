@@ -0,0 +1,178 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+use thiserror::Error;
+use walkdir::WalkDir;
+use crate::artifact::{Artifact, ArtifactError};
+use crate::config::Config;
+use crate::vfs::RealFs;
+use crate::writer::{self, SingleFileWriter, Writer};
+
+/// The preset `rustifacts demo` collects with when none is given.
+pub const DEFAULT_PRESET: &str = "rust";
+
+/// The files of the example project, relative to its root.
+///
+/// Besides the sources a preset keeps, the project holds a script, a build output,
+/// and a lock file, so that the demo also shows what gets left out.
+const PROJECT_FILES: &[(&str, &str)] = &[
+    (
+        "Cargo.toml",
+        "[package]\nname = \"greeter\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[dependencies]\n",
+    ),
+    ("Cargo.lock", "version = 3\n\n[[package]]\nname = \"greeter\"\nversion = \"0.1.0\"\n"),
+    (
+        "README.md",
+        "# greeter\n\nA tiny command-line tool that greets whoever you name.\n\n```sh\ncargo run -- Ferris\n```\n",
+    ),
+    (
+        "src/main.rs",
+        "use std::env;\n\nfn main() {\n    let name = env::args().nth(1).unwrap_or_else(|| \"world\".to_string());\n    println!(\"{}\", greeter::greet(&name));\n}\n",
+    ),
+    (
+        "src/lib.rs",
+        "//! Builds greetings.\n\n/// Returns a greeting for `name`.\npub fn greet(name: &str) -> String {\n    format!(\"Hello, {}!\", name)\n}\n",
+    ),
+    (
+        "tests/greet.rs",
+        "#[test]\nfn greets_by_name() {\n    assert_eq!(greeter::greet(\"Ferris\"), \"Hello, Ferris!\");\n}\n",
+    ),
+    (
+        "docs/usage.md",
+        "# Usage\n\nPass a name as the first argument. Without one, `greeter` greets the world.\n",
+    ),
+    (
+        "scripts/release.py",
+        "import subprocess\n\nsubprocess.run([\"cargo\", \"build\", \"--release\"], check=True)\n",
+    ),
+    ("target/debug/greeter.d", "/tmp/greeter/target/debug/greeter: src/main.rs src/lib.rs\n"),
+];
+
+/// Custom error type for running the demo.
+#[derive(Error, Debug)]
+pub enum DemoError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Collection failed: {0}")]
+    Artifact(#[from] ArtifactError),
+}
+
+/// The outcome of a demo run, held in a temporary directory that is removed when the
+/// `Demo` is dropped unless it is kept.
+#[derive(Debug)]
+pub struct Demo {
+    dir: TempDir,
+    /// The root of the example project.
+    pub source_dir: PathBuf,
+    /// The directory the artifacts were written into.
+    pub dest_dir: PathBuf,
+    /// The file the artifacts were bundled into, as `--single-file` writes it.
+    pub bundle_file: PathBuf,
+    /// The collected artifacts.
+    pub artifacts: Vec<Artifact>,
+}
+
+impl Demo {
+    /// Returns the temporary directory holding the project and its output.
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Keeps the temporary directory instead of removing it, returning its path.
+    pub fn keep(self) -> PathBuf {
+        self.dir.into_path()
+    }
+}
+
+/// Writes the example project into a directory.
+///
+/// # Arguments
+///
+/// * `dir` - The directory to write the project into, created if it does not exist.
+///
+/// # Returns
+///
+/// Returns `io::Result<()>` indicating whether every file was written.
+pub fn write_project(dir: &Path) -> io::Result<()> {
+    for (path, content) in PROJECT_FILES {
+        let path = dir.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, content)?;
+    }
+    Ok(())
+}
+
+/// Generates the example project in a temporary directory and collects it.
+///
+/// The artifacts are written the way the configuration would write them into a
+/// directory, and bundled into a single file as well. Options that choose an output
+/// location, such as `--single-file` and `--output-archive`, are ignored.
+///
+/// # Arguments
+///
+/// * `config` - The configuration to collect with, with its preset already applied.
+///
+/// # Returns
+///
+/// Returns `Result<Demo, DemoError>` holding the project and what was written from it,
+/// or a `DemoError` if the project could not be written or collected.
+pub fn run(config: &Config) -> Result<Demo, DemoError> {
+    let dir = tempfile::Builder::new().prefix("rustifacts-demo-").tempdir()?;
+    let source_dir = dir.path().join("greeter");
+    let dest_dir = dir.path().join("artifacts");
+    let bundle_file = dir.path().join("bundle.txt");
+    write_project(&source_dir)?;
+
+    let mut config = config.clone();
+    config.source_dir = source_dir.clone();
+    config.dest_dir = dest_dir.clone();
+    config.single_file = None;
+    config.output_archive = None;
+
+    let artifacts = Artifact::collect(&RealFs, &config)?;
+    writer::from_config(&config).write(&artifacts)?;
+    SingleFileWriter::new(&bundle_file)
+        .format(config.format)
+        .order(config.walk_order)
+        .project_header(&source_dir)
+        .lang_stats(config.lang_stats)
+        .write(&artifacts)?;
+
+    Ok(Demo { dir, source_dir, dest_dir, bundle_file, artifacts })
+}
+
+/// Renders a directory as an indented tree, one entry per line.
+///
+/// Directories end with a `/`, and files that are not in `collected` are marked as left
+/// out when a set is given.
+///
+/// # Arguments
+///
+/// * `root` - The directory to render.
+/// * `collected` - The paths, relative to `root`, of the files that were collected.
+///
+/// # Returns
+///
+/// A `String` containing the tree, which is empty if `root` cannot be read.
+pub fn tree(root: &Path, collected: Option<&HashSet<PathBuf>>) -> String {
+    let mut output = String::new();
+    let entries = WalkDir::new(root).min_depth(1).sort_by_file_name().into_iter().filter_map(Result::ok);
+    for entry in entries {
+        let indent = "  ".repeat(entry.depth());
+        let name = entry.file_name().to_string_lossy();
+        if entry.file_type().is_dir() {
+            output.push_str(&format!("{}{}/\n", indent, name));
+            continue;
+        }
+        let relative = entry.path().strip_prefix(root).unwrap_or(entry.path());
+        match collected {
+            Some(collected) if !collected.contains(relative) => output.push_str(&format!("{}{}  (left out)\n", indent, name)),
+            _ => output.push_str(&format!("{}{}\n", indent, name)),
+        }
+    }
+    output
+}
@@ -0,0 +1,455 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::path::{Path, PathBuf};
+use log::debug;
+use serde_json::Value;
+use crate::artifact::Artifact;
+use crate::config::Config;
+use crate::vfs::FileSystem;
+
+/// The name of the generated dependency summary, at the root of the source directory.
+pub const SUMMARY_FILE: &str = "_dependencies.md";
+
+/// The package manifests whose dependencies are summarized.
+pub const MANIFESTS: &[&str] = &["Cargo.toml", "package.json", "pyproject.toml"];
+
+/// The dependency tables of a `Cargo.toml`, in the order they are listed.
+const CARGO_TABLES: &[&str] = &["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// The dependency objects of a `package.json`, in the order they are listed.
+const NPM_OBJECTS: &[&str] = &["dependencies", "devDependencies", "peerDependencies", "optionalDependencies"];
+
+/// A direct dependency declared in a manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    /// The name of the package.
+    pub name: String,
+    /// The version requirement, or where the package comes from when it is not a registry version.
+    pub requirement: String,
+    /// The version pinned by the lock file next to the manifest, if there is one.
+    pub locked: Option<String>,
+    /// The license declared by the installed package, if it is found locally.
+    pub license: Option<String>,
+}
+
+/// The dependencies declared in one manifest, grouped by kind (e.g. `dev-dependencies`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestDependencies {
+    /// The manifest, relative to the source directory.
+    pub path: PathBuf,
+    /// The dependencies of each group, in the order the groups appear in the manifest.
+    pub groups: Vec<(String, Vec<Dependency>)>,
+}
+
+/// Lists the direct dependencies declared in a package manifest.
+///
+/// `Cargo.toml` dependency tables (including per-target ones), `package.json` dependency
+/// objects, and `pyproject.toml` PEP 621, PEP 735, and Poetry dependencies are read.
+/// Locked versions are not filled in.
+///
+/// # Arguments
+///
+/// * `path` - The path of the manifest, used to recognize its kind.
+/// * `content` - The content of the manifest.
+///
+/// # Returns
+///
+/// A `Vec` of `(group, dependencies)` pairs, which is empty if the manifest is not
+/// recognized, cannot be parsed, or declares no dependencies.
+///
+/// # Example
+///
+/// ```
+/// use std::path::Path;
+/// use rustifacts::dependencies::direct_dependencies;
+///
+/// let manifest = "[dependencies]\nserde = { version = \"1.0\", features = [\"derive\"] }\nlog = \"0.4\"\n";
+/// let groups = direct_dependencies(Path::new("Cargo.toml"), manifest);
+/// assert_eq!(groups[0].0, "dependencies");
+/// assert_eq!(groups[0].1[0].name, "log");
+/// assert_eq!(groups[0].1[1].requirement, "1.0 (features: derive)");
+/// ```
+pub fn direct_dependencies(path: &Path, content: &str) -> Vec<(String, Vec<Dependency>)> {
+    let groups = match path.file_name().and_then(|name| name.to_str()) {
+        Some("Cargo.toml") => content.parse().map(|manifest| cargo_dependencies(&manifest)).unwrap_or_default(),
+        Some("package.json") => serde_json::from_str(content).map(|manifest| npm_dependencies(&manifest)).unwrap_or_default(),
+        Some("pyproject.toml") => content.parse().map(|manifest| python_dependencies(&manifest)).unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    groups.into_iter().filter(|(_, dependencies)| !dependencies.is_empty()).collect()
+}
+
+/// Builds the `_dependencies.md` artifact summarizing the direct dependencies of the
+/// collected package manifests.
+///
+/// Versions pinned by a lock file next to each manifest (`Cargo.lock`,
+/// `package-lock.json`, `poetry.lock`, or `uv.lock`) are listed alongside the declared
+/// requirements. With `--dependency-licenses`, licenses are read from packages found
+/// locally: the Cargo registry, `node_modules/`, or a `.venv/` beside the manifest.
+///
+/// # Arguments
+///
+/// * `fs` - The filesystem to read from.
+/// * `artifacts` - The collected artifacts, among which manifests are looked for.
+/// * `config` - The configuration options.
+///
+/// # Returns
+///
+/// `Some(Artifact)` containing the summary, or `None` if no collected manifest declares
+/// any dependencies.
+pub fn summary(fs: &dyn FileSystem, artifacts: &[Artifact], config: &Config) -> Option<Artifact> {
+    let mut manifests: Vec<ManifestDependencies> = artifacts
+        .iter()
+        .filter(|artifact| is_manifest(&artifact.relative_path))
+        .filter_map(|artifact| read_manifest(fs, &artifact.original_path, &artifact.relative_path, config.dependency_licenses))
+        .collect();
+    if manifests.is_empty() {
+        return None;
+    }
+    manifests.sort_by(|a, b| a.path.cmp(&b.path));
+    let content = render(&manifests, config.dependency_licenses);
+    Some(Artifact::generated(config.source_dir.clone(), PathBuf::from(SUMMARY_FILE), content, config))
+}
+
+/// Renders dependency listings as Markdown, one section per manifest and a table per group.
+///
+/// # Arguments
+///
+/// * `manifests` - The manifests to render.
+/// * `licenses` - Whether to include a license column.
+///
+/// # Returns
+///
+/// A `String` containing the Markdown.
+pub fn render(manifests: &[ManifestDependencies], licenses: bool) -> String {
+    let mut output = String::from("# Dependencies\n\nDirect dependencies declared in the project's package manifests.\n");
+    for manifest in manifests {
+        output.push_str(&format!("\n## {}\n", manifest.path.display()));
+        for (group, dependencies) in &manifest.groups {
+            output.push_str(&format!("\n### {}\n\n", group));
+            if licenses {
+                output.push_str("| Package | Requirement | Locked | License |\n|---|---|---|---|\n");
+            } else {
+                output.push_str("| Package | Requirement | Locked |\n|---|---|---|\n");
+            }
+            for dependency in dependencies {
+                let locked = dependency.locked.as_deref().unwrap_or("-");
+                output.push_str(&format!("| {} | {} | {} |", escape(&dependency.name), escape(&dependency.requirement), locked));
+                if licenses {
+                    output.push_str(&format!(" {} |", escape(dependency.license.as_deref().unwrap_or("unknown"))));
+                }
+                output.push('\n');
+            }
+        }
+    }
+    output
+}
+
+/// Checks whether a path names a package manifest whose dependencies are summarized.
+fn is_manifest(path: &Path) -> bool {
+    path.file_name().and_then(|name| name.to_str()).is_some_and(|name| MANIFESTS.contains(&name))
+}
+
+/// Reads a manifest and fills in locked versions, and licenses if asked for.
+fn read_manifest(fs: &dyn FileSystem, original_path: &Path, relative_path: &Path, licenses: bool) -> Option<ManifestDependencies> {
+    let content = fs.read_to_string(original_path).ok()?;
+    let mut groups = direct_dependencies(relative_path, &content);
+    if groups.is_empty() {
+        debug!("No dependencies to summarize in {}", relative_path.display());
+        return None;
+    }
+    let dir = original_path.parent().unwrap_or(Path::new(""));
+    let locked = locked_versions(fs, relative_path, dir);
+    for dependency in groups.iter_mut().flat_map(|(_, dependencies)| dependencies.iter_mut()) {
+        let versions = locked.get(&normalize(relative_path, &dependency.name));
+        dependency.locked = versions.and_then(|versions| locked_version(versions, &dependency.requirement));
+        if licenses {
+            dependency.license = license(fs, relative_path, dir, dependency);
+        }
+    }
+    Some(ManifestDependencies { path: relative_path.to_path_buf(), groups })
+}
+
+fn cargo_dependencies(manifest: &toml::Table) -> Vec<(String, Vec<Dependency>)> {
+    let mut groups: Vec<(String, Vec<Dependency>)> = CARGO_TABLES
+        .iter()
+        .map(|table| (table.to_string(), cargo_table(manifest.get(*table))))
+        .collect();
+    if let Some(targets) = manifest.get("target").and_then(|targets| targets.as_table()) {
+        for (target, tables) in targets {
+            for table in CARGO_TABLES {
+                groups.push((format!("{} ({})", table, target), cargo_table(tables.get(*table))));
+            }
+        }
+    }
+    let workspace = manifest.get("workspace").and_then(|workspace| workspace.get("dependencies"));
+    groups.push(("workspace.dependencies".to_string(), cargo_table(workspace)));
+    groups
+}
+
+/// Lists a Cargo dependency table, whose entries are a version string or a table.
+fn cargo_table(table: Option<&toml::Value>) -> Vec<Dependency> {
+    let Some(table) = table.and_then(|table| table.as_table()) else {
+        return Vec::new();
+    };
+    table
+        .iter()
+        .map(|(name, spec)| {
+            // A renamed dependency (`alias = { package = "real" }`) is locked under its real name.
+            let package = spec.get("package").and_then(|package| package.as_str()).unwrap_or(name);
+            let requirement = match spec {
+                toml::Value::String(version) => version.clone(),
+                spec => cargo_requirement(spec),
+            };
+            let name = if package == name { name.clone() } else { format!("{} ({})", name, package) };
+            Dependency { name, requirement, locked: None, license: None }
+        })
+        .collect()
+}
+
+fn cargo_requirement(spec: &toml::Value) -> String {
+    let text = |key: &str| spec.get(key).and_then(|value| value.as_str());
+    let mut requirement = if spec.get("workspace").and_then(|workspace| workspace.as_bool()) == Some(true) {
+        "workspace".to_string()
+    } else if let Some(path) = text("path") {
+        format!("path {}", path)
+    } else if let Some(git) = text("git") {
+        let reference = text("tag").or(text("rev")).or(text("branch"));
+        match reference {
+            Some(reference) => format!("git {} @ {}", git, reference),
+            None => format!("git {}", git),
+        }
+    } else {
+        text("version").unwrap_or("*").to_string()
+    };
+    let features: Vec<&str> = spec
+        .get("features")
+        .and_then(|features| features.as_array())
+        .map(|features| features.iter().filter_map(|feature| feature.as_str()).collect())
+        .unwrap_or_default();
+    if !features.is_empty() {
+        requirement.push_str(&format!(" (features: {})", features.join(", ")));
+    }
+    if spec.get("optional").and_then(|optional| optional.as_bool()) == Some(true) {
+        requirement.push_str(" (optional)");
+    }
+    requirement
+}
+
+fn npm_dependencies(manifest: &Value) -> Vec<(String, Vec<Dependency>)> {
+    NPM_OBJECTS
+        .iter()
+        .map(|object| {
+            let dependencies = manifest
+                .get(*object)
+                .and_then(|dependencies| dependencies.as_object())
+                .map(|dependencies| {
+                    dependencies
+                        .iter()
+                        .map(|(name, version)| Dependency {
+                            name: name.clone(),
+                            requirement: version.as_str().unwrap_or("*").to_string(),
+                            locked: None,
+                            license: None,
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            (object.to_string(), dependencies)
+        })
+        .collect()
+}
+
+fn python_dependencies(manifest: &toml::Table) -> Vec<(String, Vec<Dependency>)> {
+    let mut groups = Vec::new();
+    let project = manifest.get("project");
+    groups.push(("dependencies".to_string(), pep508_list(project.and_then(|project| project.get("dependencies")))));
+    let optional = project.and_then(|project| project.get("optional-dependencies")).and_then(|optional| optional.as_table());
+    for (extra, list) in optional.into_iter().flatten() {
+        groups.push((format!("optional-dependencies ({})", extra), pep508_list(Some(list))));
+    }
+    let dependency_groups = manifest.get("dependency-groups").and_then(|groups| groups.as_table());
+    for (group, list) in dependency_groups.into_iter().flatten() {
+        groups.push((format!("dependency-groups ({})", group), pep508_list(Some(list))));
+    }
+
+    let poetry = manifest.get("tool").and_then(|tool| tool.get("poetry"));
+    groups.push(("tool.poetry.dependencies".to_string(), poetry_table(poetry.and_then(|poetry| poetry.get("dependencies")))));
+    let poetry_groups = poetry.and_then(|poetry| poetry.get("group")).and_then(|groups| groups.as_table());
+    for (group, table) in poetry_groups.into_iter().flatten() {
+        groups.push((format!("tool.poetry.group ({})", group), poetry_table(table.get("dependencies"))));
+    }
+    groups
+}
+
+/// Lists PEP 508 requirement strings such as `requests[socks]>=2.31; python_version < "4"`.
+fn pep508_list(list: Option<&toml::Value>) -> Vec<Dependency> {
+    list.and_then(|list| list.as_array())
+        .map(|list| list.iter().filter_map(|entry| entry.as_str()).filter_map(pep508).collect())
+        .unwrap_or_default()
+}
+
+fn pep508(requirement: &str) -> Option<Dependency> {
+    let requirement = requirement.trim();
+    let end = requirement
+        .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-')))
+        .unwrap_or(requirement.len());
+    let name = &requirement[..end];
+    if name.is_empty() {
+        return None;
+    }
+    let rest = requirement[end..].trim();
+    let rest = match rest.strip_prefix('[') {
+        Some(extras) => extras.split_once(']').map_or("", |(_, rest)| rest).trim(),
+        None => rest,
+    };
+    let version = rest.split(';').next().unwrap_or_default().trim();
+    let requirement = if version.is_empty() { "*" } else { version };
+    Some(Dependency { name: name.to_string(), requirement: requirement.to_string(), locked: None, license: None })
+}
+
+fn poetry_table(table: Option<&toml::Value>) -> Vec<Dependency> {
+    let Some(table) = table.and_then(|table| table.as_table()) else {
+        return Vec::new();
+    };
+    table
+        .iter()
+        // Poetry lists the supported Python versions among the dependencies.
+        .filter(|(name, _)| name.as_str() != "python")
+        .map(|(name, spec)| {
+            let text = |key: &str| spec.get(key).and_then(|value| value.as_str());
+            let requirement = match spec {
+                toml::Value::String(version) => version.clone(),
+                _ => match (text("version"), text("path"), text("git")) {
+                    (Some(version), _, _) => version.to_string(),
+                    (None, Some(path), _) => format!("path {}", path),
+                    (None, None, Some(git)) => format!("git {}", git),
+                    _ => "*".to_string(),
+                },
+            };
+            Dependency { name: name.clone(), requirement, locked: None, license: None }
+        })
+        .collect()
+}
+
+/// Chooses which of the versions a lock file pins for a package the requirement resolved to.
+///
+/// When several are pinned, the one the requirement's version is a prefix of is chosen
+/// (`0.22` picks `0.22.1` over `0.21.7`), falling back to the last one listed.
+fn locked_version(versions: &[String], requirement: &str) -> Option<String> {
+    let wanted = requirement.trim_start_matches(['^', '~', '=', ' ']).split([' ', ',']).next().unwrap_or_default();
+    let matching = versions.iter().find(|version| !wanted.is_empty() && version.starts_with(wanted));
+    matching.or(versions.last()).cloned()
+}
+
+/// Reads the versions pinned by the lock file next to a manifest, keyed by normalized name.
+fn locked_versions(fs: &dyn FileSystem, manifest: &Path, dir: &Path) -> BTreeMap<String, Vec<String>> {
+    let mut locked = BTreeMap::new();
+    match manifest.file_name().and_then(|name| name.to_str()) {
+        Some("package.json") => {
+            let lock: Option<Value> = fs
+                .read_to_string(&dir.join("package-lock.json"))
+                .ok()
+                .and_then(|lock| serde_json::from_str(&lock).ok());
+            let packages = lock.as_ref().and_then(|lock| lock.get("packages")).and_then(|packages| packages.as_object());
+            for (path, package) in packages.into_iter().flatten() {
+                // Only top-level installs are direct dependencies; nested ones are pinned for other packages.
+                let Some(name) = path.strip_prefix("node_modules/").filter(|name| !name.contains("/node_modules/")) else {
+                    continue;
+                };
+                if let Some(version) = package.get("version").and_then(|version| version.as_str()) {
+                    locked.insert(name.to_string(), vec![version.to_string()]);
+                }
+            }
+        }
+        Some(manifest_name) => {
+            let lock_files: &[&str] = if manifest_name == "Cargo.toml" { &["Cargo.lock"] } else { &["poetry.lock", "uv.lock"] };
+            for lock_file in lock_files {
+                let lock: Option<toml::Table> =
+                    fs.read_to_string(&dir.join(lock_file)).ok().and_then(|lock| lock.parse().ok());
+                let packages = lock.as_ref().and_then(|lock| lock.get("package")).and_then(|packages| packages.as_array());
+                for package in packages.into_iter().flatten() {
+                    let name = package.get("name").and_then(|name| name.as_str());
+                    let version = package.get("version").and_then(|version| version.as_str());
+                    if let (Some(name), Some(version)) = (name, version) {
+                        locked.entry(normalize(manifest, name)).or_default().push(version.to_string());
+                    }
+                }
+            }
+        }
+        None => {}
+    }
+    locked
+}
+
+/// Normalizes a package name the way its ecosystem compares names.
+fn normalize(manifest: &Path, name: &str) -> String {
+    // Renamed Cargo dependencies are listed as `alias (package)`.
+    let name = name.rsplit_once(" (").map_or(name, |(_, package)| package.trim_end_matches(')'));
+    if manifest.file_name().is_some_and(|file_name| file_name == "pyproject.toml") {
+        name.to_lowercase().replace(['_', '.'], "-")
+    } else {
+        name.to_string()
+    }
+}
+
+/// Finds the license of an installed dependency.
+fn license(fs: &dyn FileSystem, manifest: &Path, dir: &Path, dependency: &Dependency) -> Option<String> {
+    let name = normalize(manifest, &dependency.name);
+    match manifest.file_name()?.to_str()? {
+        "Cargo.toml" => cargo_license(fs, &name, dependency.locked.as_deref()?),
+        "package.json" => {
+            let package: Value =
+                serde_json::from_str(&fs.read_to_string(&dir.join("node_modules").join(&name).join("package.json")).ok()?).ok()?;
+            package.get("license").and_then(|license| license.as_str()).map(String::from)
+        }
+        _ => python_license(fs, dir, &name),
+    }
+}
+
+/// Reads a crate's license from its unpacked source in the Cargo registry.
+fn cargo_license(fs: &dyn FileSystem, name: &str, version: &str) -> Option<String> {
+    let cargo_home = env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cargo")))?;
+    let registries = fs.read_dir(&cargo_home.join("registry").join("src")).ok()?;
+    registries.iter().find_map(|registry| {
+        let manifest: toml::Table =
+            fs.read_to_string(&registry.join(format!("{}-{}", name, version)).join("Cargo.toml")).ok()?.parse().ok()?;
+        manifest.get("package")?.get("license")?.as_str().map(String::from)
+    })
+}
+
+/// Reads a package's license from its metadata in a `.venv/` beside the manifest.
+fn python_license(fs: &dyn FileSystem, dir: &Path, name: &str) -> Option<String> {
+    let lib = dir.join(".venv").join("lib");
+    let site_packages: Vec<PathBuf> = fs
+        .read_dir(&lib)
+        .ok()?
+        .into_iter()
+        .map(|python| python.join("site-packages"))
+        .collect();
+    site_packages.iter().flat_map(|dir| fs.read_dir(dir).unwrap_or_default()).find_map(|entry| {
+        let file_name = entry.file_name()?.to_str()?;
+        let (package, _) = file_name.strip_suffix(".dist-info")?.split_once('-')?;
+        if package.to_lowercase().replace(['_', '.'], "-") != name {
+            return None;
+        }
+        let metadata = fs.read_to_string(&entry.join("METADATA")).ok()?;
+        let header = |field: &str| {
+            metadata
+                .lines()
+                .take_while(|line| !line.is_empty())
+                .find_map(|line| line.strip_prefix(field))
+                .map(str::trim)
+                .filter(|value| !value.is_empty() && *value != "UNKNOWN")
+                .map(String::from)
+        };
+        header("License-Expression:").or_else(|| header("License:"))
+    })
+}
+
+/// Escapes the characters that would break a Markdown table cell.
+fn escape(text: &str) -> String {
+    text.replace('|', "\\|")
+}
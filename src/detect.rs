@@ -0,0 +1,134 @@
+use std::fs;
+use std::path::Path;
+use log::debug;
+
+/// The preset name that asks for the project type to be detected.
+pub const AUTO_PRESET: &str = "auto";
+
+/// A preset and the check that recognizes projects it fits.
+struct Rule {
+    preset: &'static str,
+    /// Returns the marker that matched, if any.
+    matches: fn(&Path) -> Option<String>,
+}
+
+/// Detection rules in order of precedence.
+///
+/// Frameworks come before the languages they are built on (`django` before `python`),
+/// and platforms before the build tools they share with other projects, so that the
+/// most specific preset wins when several match.
+const RULES: &[Rule] = &[
+    Rule { preset: "nextjs", matches: nextjs },
+    Rule { preset: "django", matches: django },
+    Rule { preset: "laravel", matches: |dir| marker(dir, &["artisan"]) },
+    Rule { preset: "rails", matches: |dir| marker(dir, &["bin/rails", "config/application.rb"]) },
+    Rule { preset: "android", matches: android },
+    Rule { preset: "ios", matches: ios },
+    Rule { preset: "gamedev", matches: gamedev },
+    Rule { preset: "rust", matches: |dir| marker(dir, &["Cargo.toml"]) },
+    Rule { preset: "cpp", matches: |dir| marker(dir, &["CMakeLists.txt", "meson.build"]) },
+    Rule { preset: "datascience", matches: datascience },
+    Rule { preset: "python", matches: |dir| marker(dir, &["pyproject.toml", "setup.py", "setup.cfg", "requirements.txt"]) },
+    Rule { preset: "database", matches: |dir| marker(dir, &["schema.prisma", "prisma/schema.prisma", "schema.sql", "db/schema.sql"]) },
+];
+
+/// The preset chosen for a source directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Detection {
+    pub preset: &'static str,
+    /// The marker file or directory that identified the project type.
+    pub marker: String,
+    /// Other presets whose rules also matched, in order of precedence.
+    pub also_matched: Vec<&'static str>,
+}
+
+/// Detects the project type of a source directory from the marker files at its root.
+///
+/// Every rule is checked, and the first match in order of precedence wins. Polyglot
+/// repositories therefore get the most specific preset, and the other matches are
+/// reported so that one of them can be chosen explicitly instead.
+///
+/// # Arguments
+///
+/// * `source_dir` - The source directory to inspect.
+///
+/// # Returns
+///
+/// Returns `Some(Detection)` describing the chosen preset, or `None` if no rule matched.
+pub fn detect_preset(source_dir: &Path) -> Option<Detection> {
+    let mut matched = RULES.iter().filter_map(|rule| {
+        let marker = (rule.matches)(source_dir)?;
+        debug!("Preset {} matches {} because of {}", rule.preset, source_dir.display(), marker);
+        Some((rule.preset, marker))
+    });
+    let (preset, marker) = matched.next()?;
+    Some(Detection {
+        preset,
+        marker,
+        also_matched: matched.map(|(preset, _)| preset).collect(),
+    })
+}
+
+/// Returns the first of the given paths that exists in the directory.
+fn marker(dir: &Path, paths: &[&str]) -> Option<String> {
+    paths.iter().find(|path| dir.join(path).exists()).map(|path| path.to_string())
+}
+
+/// Returns the alphabetically first entry in the directory with one of the given extensions.
+fn entry_with_extension(dir: &Path, extensions: &[&str]) -> Option<String> {
+    fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let extension = path.extension()?.to_str()?;
+            extensions.contains(&extension).then(|| entry.file_name().to_string_lossy().into_owned())
+        })
+        .min()
+}
+
+/// A `package.json` depending on `next`, or a Next.js configuration file.
+fn nextjs(dir: &Path) -> Option<String> {
+    let depends_on_next = fs::read_to_string(dir.join("package.json"))
+        .ok()
+        .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok())
+        .is_some_and(|package| {
+            ["dependencies", "devDependencies"].iter().any(|key| package[key].get("next").is_some())
+        });
+    if depends_on_next {
+        return Some("package.json (next dependency)".to_string());
+    }
+    marker(dir, &["next.config.js", "next.config.mjs", "next.config.ts"])
+}
+
+/// A Django `manage.py`, or Python requirements naming Django.
+fn django(dir: &Path) -> Option<String> {
+    if let Some(found) = marker(dir, &["manage.py"]) {
+        return Some(found);
+    }
+    ["requirements.txt", "pyproject.toml"].iter().find_map(|name| {
+        let text = fs::read_to_string(dir.join(name)).ok()?;
+        text.to_lowercase().contains("django").then(|| format!("{} (django dependency)", name))
+    })
+}
+
+/// An Android manifest in the app module or at the root.
+fn android(dir: &Path) -> Option<String> {
+    marker(dir, &["app/src/main/AndroidManifest.xml", "src/main/AndroidManifest.xml", "AndroidManifest.xml"])
+}
+
+/// An Xcode project or workspace, or a CocoaPods `Podfile`.
+fn ios(dir: &Path) -> Option<String> {
+    entry_with_extension(dir, &["xcodeproj", "xcworkspace"]).or_else(|| marker(dir, &["Podfile"]))
+}
+
+/// An Unreal `.uproject` file or a Unity project's version file.
+fn gamedev(dir: &Path) -> Option<String> {
+    entry_with_extension(dir, &["uproject"]).or_else(|| marker(dir, &["ProjectSettings/ProjectVersion.txt"]))
+}
+
+/// Jupyter notebooks at the root or in a `notebooks` directory.
+fn datascience(dir: &Path) -> Option<String> {
+    entry_with_extension(dir, &["ipynb"])
+        .or_else(|| entry_with_extension(&dir.join("notebooks"), &["ipynb"]).map(|name| format!("notebooks/{}", name)))
+}
@@ -0,0 +1,41 @@
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE};
+
+/// Finds a UTF-16 byte order mark at the start of file content.
+///
+/// UTF-16 text is full of NUL bytes, so it has to be recognized before binary detection.
+///
+/// # Arguments
+///
+/// * `bytes` - The content of the file.
+///
+/// # Returns
+///
+/// The UTF-16 encoding the mark announces, or `None` if there is no UTF-16 mark.
+pub fn utf16_bom(bytes: &[u8]) -> Option<&'static Encoding> {
+    Encoding::for_bom(bytes)
+        .map(|(encoding, _)| encoding)
+        .filter(|encoding| *encoding == UTF_16LE || *encoding == UTF_16BE)
+}
+
+/// Transcodes text in a legacy encoding (such as Latin-1 or Shift-JIS) to UTF-8.
+///
+/// The encoding is taken from a byte order mark if there is one and detected from the
+/// content otherwise. Bytes that are invalid in the detected encoding become U+FFFD.
+///
+/// # Arguments
+///
+/// * `bytes` - The content of the file, which is not valid UTF-8.
+///
+/// # Returns
+///
+/// A tuple of the transcoded content and the name of the encoding it was decoded from.
+pub fn transcode(bytes: &[u8]) -> (String, &'static str) {
+    let encoding = Encoding::for_bom(bytes).map(|(encoding, _)| encoding).unwrap_or_else(|| {
+        let mut detector = EncodingDetector::new();
+        detector.feed(bytes, true);
+        detector.guess(None, false)
+    });
+    let (content, encoding, _) = encoding.decode(bytes);
+    (content.into_owned(), encoding.name())
+}
@@ -0,0 +1,383 @@
+use std::path::Path;
+use crate::artifact::Artifact;
+use crate::config::OutputFormat;
+use crate::project::ProjectInfo;
+
+/// First line of the project header at the top of plain bundles.
+pub const PROJECT_HEADER: &str = "--- PROJECT ---";
+
+/// Note added to a plain bundle's `--- END` line when the content had no trailing newline.
+pub const NO_NEWLINE_NOTE: &str = " (no newline at end of file)";
+
+/// Returns the name an artifact is written under in the given format.
+///
+/// Formats that wrap content in markup get their own extension appended, so
+/// `src_main.rs` becomes `src_main.rs.md` for Markdown and `src_main.rs.xml` for claude-xml.
+///
+/// # Arguments
+///
+/// * `format` - The output format.
+/// * `new_filename` - The flattened (or preserved) name of the artifact.
+///
+/// # Returns
+///
+/// A `String` containing the name to write the artifact under.
+pub fn file_name(format: OutputFormat, new_filename: &str) -> String {
+    match format {
+        OutputFormat::Plain => new_filename.to_string(),
+        OutputFormat::Markdown => format!("{}.md", new_filename),
+        OutputFormat::ClaudeXml => format!("{}.xml", new_filename),
+    }
+}
+
+/// Renders one artifact as the body of its own output file.
+///
+/// # Arguments
+///
+/// * `format` - The output format.
+/// * `artifact` - The artifact to render.
+///
+/// # Returns
+///
+/// A `String` containing the rendered artifact.
+pub fn render(format: OutputFormat, artifact: &Artifact) -> String {
+    match format {
+        OutputFormat::Plain => artifact.content.clone(),
+        OutputFormat::Markdown => markdown_section(artifact),
+        OutputFormat::ClaudeXml => xml_document(artifact, None),
+    }
+}
+
+/// Recovers an artifact's content from the body of its own output file, undoing [`render`].
+///
+/// Rendering adds a newline before the closing markup to content that does not end in
+/// one, so `size`, the length of the content in bytes as recorded in the manifest, tells
+/// whether the last newline belongs to the content.
+///
+/// # Arguments
+///
+/// * `format` - The output format the artifact was rendered in.
+/// * `body` - The artifact's output file, without its checksum header.
+/// * `size` - The length of the artifact's content in bytes.
+///
+/// # Returns
+///
+/// `Some(String)` containing the content, or `None` if the body is not laid out the way
+/// `render` writes it.
+pub fn unrender(format: OutputFormat, body: &str, size: u64) -> Option<String> {
+    let mut content = match format {
+        OutputFormat::Plain => return Some(body.to_string()),
+        OutputFormat::Markdown => {
+            let (heading, rest) = body.split_once('\n')?;
+            let (opening, inner) = rest.strip_prefix('\n')?.split_once('\n')?;
+            let fence = &opening[..opening.len() - opening.trim_start_matches('`').len()];
+            if !heading.starts_with("## ") || fence.len() < 3 {
+                return None;
+            }
+            inner.strip_suffix(&format!("{}\n", fence))?.to_string()
+        }
+        OutputFormat::ClaudeXml => {
+            let (source, rest) = body.strip_prefix("<document>\n")?.split_once('\n')?;
+            if !source.starts_with("<source>") || !source.ends_with("</source>") {
+                return None;
+            }
+            let escaped = rest.strip_prefix("<document_contents>\n")?.strip_suffix("</document_contents>\n</document>\n")?;
+            unescape_xml(escaped)?
+        }
+    };
+    if content.len() as u64 == size + 1 && content.ends_with('\n') {
+        content.pop();
+    }
+    Some(content)
+}
+
+/// Renders every artifact into the body of a single output file.
+///
+/// Artifacts are rendered in the order given; callers sort them first when the
+/// output must be stable across runs. claude-xml bundles wrap the documents in a
+/// `<documents>` element and number them from 1.
+///
+/// # Arguments
+///
+/// * `format` - The output format.
+/// * `artifacts` - The artifacts to render.
+///
+/// # Returns
+///
+/// A `String` containing the rendered bundle.
+pub fn render_bundle(format: OutputFormat, artifacts: &[&Artifact]) -> String {
+    let mut output = String::new();
+    if format == OutputFormat::ClaudeXml {
+        output.push_str("<documents>\n");
+    }
+    for (index, artifact) in artifacts.iter().enumerate() {
+        match format {
+            OutputFormat::Plain => {
+                let relative_path = display_path(artifact);
+                output.push_str(&format!("--- BEGIN {} ---\n", relative_path));
+                output.push_str(&artifact.content);
+                let note = if artifact.content.ends_with('\n') {
+                    ""
+                } else {
+                    output.push('\n');
+                    NO_NEWLINE_NOTE
+                };
+                output.push_str(&format!("--- END {}{} ---\n\n", relative_path, note));
+            }
+            OutputFormat::Markdown => {
+                output.push_str(&markdown_section(artifact));
+                output.push('\n');
+            }
+            OutputFormat::ClaudeXml => output.push_str(&xml_document(artifact, Some(index + 1))),
+        }
+    }
+    if format == OutputFormat::ClaudeXml {
+        output.push_str("</documents>\n");
+    }
+    output
+}
+
+/// Renders the project header placed at the top of a bundle.
+///
+/// Plain bundles get a `--- PROJECT ---` block of `Key: value` lines, Markdown bundles a
+/// title and a list, and claude-xml bundles a `<project>` element ahead of `<documents>`.
+///
+/// # Arguments
+///
+/// * `format` - The output format.
+/// * `project` - The project metadata.
+///
+/// # Returns
+///
+/// A `String` containing the rendered header, ending in a blank line.
+pub fn render_header(format: OutputFormat, project: &ProjectInfo) -> String {
+    let mut details = Vec::new();
+    if !project.languages.is_empty() {
+        details.push(("Languages", project.languages_summary()));
+        if project.token_shares {
+            details.push(("Tokens", project.tokens_summary()));
+        }
+    }
+    if let Some(ref commit) = project.commit {
+        details.push(("Commit", commit.clone()));
+    }
+    details.push(("Generated", project.generated_at.clone()));
+    details.push(("Tool", project.tool_version.clone()));
+
+    let mut output = String::new();
+    if format == OutputFormat::Markdown {
+        output.push_str(&format!("# {}\n\n", project.name));
+        if let Some(ref description) = project.description {
+            output.push_str(&format!("{}\n\n", description));
+        }
+        for (key, value) in details {
+            output.push_str(&format!("- **{}:** {}\n", key, value));
+        }
+        output.push('\n');
+        return output;
+    }
+
+    let mut fields = vec![("Name", project.name.clone())];
+    if let Some(ref description) = project.description {
+        fields.push(("Description", description.clone()));
+    }
+    fields.extend(details);
+    if format == OutputFormat::ClaudeXml {
+        output.push_str("<project>\n");
+        for (key, value) in fields {
+            let tag = key.to_lowercase();
+            output.push_str(&format!("<{}>{}</{}>\n", tag, escape_xml(&value), tag));
+        }
+        output.push_str("</project>\n\n");
+    } else {
+        output.push_str(PROJECT_HEADER);
+        output.push('\n');
+        for (key, value) in fields {
+            output.push_str(&format!("{}: {}\n", key, value));
+        }
+        output.push_str("--- END PROJECT ---\n\n");
+    }
+    output
+}
+
+/// Renders a Markdown document with a title, a table of contents, and one section per artifact.
+///
+/// # Arguments
+///
+/// * `title` - The document title.
+/// * `artifacts` - The artifacts to include, in the order they should appear.
+///
+/// # Returns
+///
+/// A `String` containing the rendered document.
+pub fn markdown_document(title: &str, artifacts: &[&Artifact]) -> String {
+    let mut output = format!("# {}\n\n## Contents\n\n", title);
+    for artifact in artifacts {
+        let path = display_path(artifact);
+        output.push_str(&format!("- [{}](#{})\n", path, anchor(&path)));
+    }
+    for artifact in artifacts {
+        output.push('\n');
+        output.push_str(&markdown_section(artifact));
+    }
+    output
+}
+
+/// Returns the anchor GitHub-flavored Markdown generates for a heading.
+fn anchor(heading: &str) -> String {
+    heading
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Renders an artifact as a heading naming its path, followed by a fenced code block.
+///
+/// The fence is made longer than any backtick run in the content so that embedded
+/// code blocks (e.g., in Markdown sources) cannot close it early.
+fn markdown_section(artifact: &Artifact) -> String {
+    let longest_run = artifact
+        .content
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    let mut section = format!(
+        "## {}\n\n{}{}\n{}",
+        display_path(artifact),
+        fence,
+        language(&artifact.relative_path),
+        artifact.content
+    );
+    if !artifact.content.is_empty() && !artifact.content.ends_with('\n') {
+        section.push('\n');
+    }
+    section.push_str(&fence);
+    section.push('\n');
+    section
+}
+
+/// Renders an artifact as a `<document>` element with its path as the source.
+///
+/// `index` numbers the document within a `<documents>` bundle.
+fn xml_document(artifact: &Artifact, index: Option<usize>) -> String {
+    let open = match index {
+        Some(index) => format!("<document index=\"{}\">", index),
+        None => "<document>".to_string(),
+    };
+    let mut document = format!(
+        "{}\n<source>{}</source>\n<document_contents>\n{}",
+        open,
+        escape_xml(&display_path(artifact)),
+        escape_xml(&artifact.content)
+    );
+    if !artifact.content.is_empty() && !artifact.content.ends_with('\n') {
+        document.push('\n');
+    }
+    document.push_str("</document_contents>\n</document>\n");
+    document
+}
+
+/// Escapes the characters that would otherwise be read as XML markup.
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Reverses [`escape_xml`].
+///
+/// Returns `None` if the text holds a `&` that does not start one of the escapes it writes,
+/// or markup characters that should have been escaped.
+fn unescape_xml(text: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(['&', '<', '>']) {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let (escape, c) = [("&amp;", '&'), ("&lt;", '<'), ("&gt;", '>'), ("&quot;", '"')]
+            .into_iter()
+            .find(|(escape, _)| rest.starts_with(escape))?;
+        unescaped.push(c);
+        rest = &rest[escape.len()..];
+    }
+    unescaped.push_str(rest);
+    Some(unescaped)
+}
+
+/// Returns an artifact's relative path with `/` separators.
+fn display_path(artifact: &Artifact) -> String {
+    artifact.relative_path.to_string_lossy().replace('\\', "/")
+}
+
+/// Infers the fenced code block language for a file from its extension or name.
+///
+/// Unknown files get an empty tag, which renders as plain text.
+pub(crate) fn language(path: &Path) -> &'static str {
+    let file_name = path.file_name().map(|name| name.to_string_lossy().to_lowercase()).unwrap_or_default();
+    match file_name.as_str() {
+        "dockerfile" => return "dockerfile",
+        "makefile" | "gnumakefile" => return "makefile",
+        "cmakelists.txt" => return "cmake",
+        _ => {}
+    }
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "rs" => "rust",
+        "py" | "pyi" => "python",
+        "js" | "mjs" | "cjs" => "javascript",
+        "jsx" => "jsx",
+        "ts" | "mts" | "cts" => "typescript",
+        "tsx" => "tsx",
+        "go" => "go",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "swift" => "swift",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hh" | "hpp" | "hxx" => "cpp",
+        "cs" => "csharp",
+        "rb" => "ruby",
+        "php" => "php",
+        "dart" => "dart",
+        "scala" => "scala",
+        "lua" => "lua",
+        "hs" => "haskell",
+        "ex" | "exs" => "elixir",
+        "sh" | "bash" | "zsh" => "bash",
+        "ps1" => "powershell",
+        "sql" => "sql",
+        "html" | "htm" => "html",
+        "css" => "css",
+        "scss" => "scss",
+        "less" => "less",
+        "vue" => "vue",
+        "svelte" => "svelte",
+        "json" | "arb" => "json",
+        "yaml" | "yml" => "yaml",
+        "toml" => "toml",
+        "xml" | "svg" => "xml",
+        "md" | "mdx" => "markdown",
+        "proto" => "protobuf",
+        "gradle" => "groovy",
+        "graphql" | "gql" => "graphql",
+        "tf" => "hcl",
+        _ => "",
+    }
+}
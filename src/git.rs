@@ -0,0 +1,252 @@
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use log::{debug, warn};
+use tempfile::TempDir;
+use thiserror::Error;
+
+/// Custom error type for git-related operations.
+#[derive(Error, Debug)]
+pub enum GitError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("`git {command}` failed: {stderr}")]
+    CommandFailed { command: String, stderr: String },
+}
+
+/// Runs a git command in the given directory and returns its trimmed standard output.
+///
+/// # Arguments
+///
+/// * `dir` - The directory to run git in.
+/// * `args` - The arguments to pass to git.
+///
+/// # Returns
+///
+/// Returns `Result<String, GitError>` containing the command output if it succeeded,
+/// or a `GitError` if git could not be run or exited unsuccessfully.
+pub fn run(dir: &Path, args: &[&str]) -> Result<String, GitError> {
+    debug!("Running git {} in {}", args.join(" "), dir.display());
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output()?;
+    if !output.status.success() {
+        return Err(GitError::CommandFailed {
+            command: args.join(" "),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Lists the files tracked by git beneath a directory, including those of initialized submodules.
+///
+/// # Arguments
+///
+/// * `dir` - The directory to list tracked files for.
+///
+/// # Returns
+///
+/// Returns `Result<HashSet<PathBuf>, GitError>` containing the tracked paths relative to
+/// `dir`, or a `GitError` if `dir` is not inside a git repository.
+pub fn tracked_files(dir: &Path) -> Result<HashSet<PathBuf>, GitError> {
+    let listing = run(dir, &["ls-files", "-z", "--recurse-submodules"])?;
+    Ok(listing.split('\0').filter(|path| !path.is_empty()).map(PathBuf::from).collect())
+}
+
+/// Lists the files beneath a directory that differ from a git ref.
+///
+/// Changes are taken relative to the merge base of `git_ref` and `HEAD`, so that
+/// `main` yields what the current branch changed rather than everything `main` gained
+/// since. Committed, staged, and unstaged changes are included, as are untracked files
+/// that are not ignored. Deleted files are not listed.
+///
+/// # Arguments
+///
+/// * `dir` - The directory to list changed files for.
+/// * `git_ref` - The commit SHA, tag, or branch to compare against.
+///
+/// # Returns
+///
+/// Returns `Result<HashSet<PathBuf>, GitError>` containing the changed paths relative to
+/// `dir`, or a `GitError` if `dir` is not inside a git repository or the ref is unknown.
+pub fn changed_files(dir: &Path, git_ref: &str) -> Result<HashSet<PathBuf>, GitError> {
+    let changed = run(dir, &["diff", "--merge-base", "--name-only", "--diff-filter=d", "-z", "--relative", git_ref, "--"])?;
+    let untracked = run(dir, &["ls-files", "-z", "--others", "--exclude-standard"])?;
+    Ok(changed
+        .split('\0')
+        .chain(untracked.split('\0'))
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// A temporary, detached git worktree checked out at a specific ref.
+///
+/// The worktree is removed from the repository when this value is dropped,
+/// leaving the user's own checkout untouched.
+pub struct Worktree {
+    repo_dir: PathBuf,
+    checkout_dir: PathBuf,
+    prefix: PathBuf,
+    _temp_dir: TempDir,
+}
+
+impl Worktree {
+    /// Checks out `git_ref` into a new temporary worktree.
+    ///
+    /// # Arguments
+    ///
+    /// * `source_dir` - A directory inside the repository to check out from.
+    /// * `git_ref` - The commit SHA, tag, or branch to check out.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<Self, GitError>` containing the new `Worktree` if successful,
+    /// or a `GitError` if the ref could not be resolved or checked out.
+    pub fn checkout(source_dir: &Path, git_ref: &str) -> Result<Self, GitError> {
+        let repo_dir = PathBuf::from(run(source_dir, &["rev-parse", "--show-toplevel"])?);
+        let prefix = PathBuf::from(run(source_dir, &["rev-parse", "--show-prefix"])?);
+        let commit = run(&repo_dir, &["rev-parse", "--verify", &format!("{}^{{commit}}", git_ref)])?;
+
+        let temp_dir = tempfile::Builder::new().prefix("rustifacts-").tempdir()?;
+        let checkout_dir = temp_dir.path().join("checkout");
+        let checkout_arg = checkout_dir.to_string_lossy().into_owned();
+        run(&repo_dir, &["worktree", "add", "--detach", &checkout_arg, &commit])?;
+
+        Ok(Self {
+            repo_dir,
+            checkout_dir,
+            prefix,
+            _temp_dir: temp_dir,
+        })
+    }
+
+    /// Returns the path inside the worktree that corresponds to the original source directory.
+    pub fn source_dir(&self) -> PathBuf {
+        self.checkout_dir.join(&self.prefix)
+    }
+}
+
+impl Drop for Worktree {
+    fn drop(&mut self) {
+        let checkout_arg = self.checkout_dir.to_string_lossy().into_owned();
+        if let Err(e) = run(&self.repo_dir, &["worktree", "remove", "--force", &checkout_arg]) {
+            warn!("Failed to remove temporary worktree {}: {}", self.checkout_dir.display(), e);
+        }
+    }
+}
+
+/// A git submodule discovered beneath a source directory.
+#[derive(Debug, Clone)]
+pub struct Submodule {
+    /// Path of the submodule relative to the source directory.
+    pub path: PathBuf,
+    /// Commit SHA the submodule is pinned to.
+    pub commit: String,
+    /// Nesting level, where 1 is a submodule of the source repository itself.
+    pub depth: usize,
+}
+
+/// Lists the submodules beneath a directory, descending into nested submodules.
+///
+/// Submodules are discovered from gitlink entries in the index, so pinned SHAs are
+/// reported even when a submodule has not been initialized.
+///
+/// # Arguments
+///
+/// * `dir` - The directory to list submodules for.
+/// * `max_depth` - The deepest nesting level to report.
+///
+/// # Returns
+///
+/// Returns `Result<Vec<Submodule>, GitError>` containing the discovered submodules,
+/// or a `GitError` if `dir` is not inside a git repository.
+pub fn submodules(dir: &Path, max_depth: usize) -> Result<Vec<Submodule>, GitError> {
+    let mut found = Vec::new();
+    collect_submodules(dir, Path::new(""), 1, max_depth, &mut found)?;
+    Ok(found)
+}
+
+fn collect_submodules(
+    dir: &Path,
+    prefix: &Path,
+    depth: usize,
+    max_depth: usize,
+    found: &mut Vec<Submodule>,
+) -> Result<(), GitError> {
+    if depth > max_depth {
+        return Ok(());
+    }
+    let listing = run(dir, &["ls-files", "--stage"])?;
+    for line in listing.lines() {
+        // Format: "<mode> <sha> <stage>\t<path>", where mode 160000 marks a gitlink.
+        let Some((meta, path)) = line.split_once('\t') else { continue };
+        let mut fields = meta.split_whitespace();
+        if fields.next() != Some("160000") {
+            continue;
+        }
+        let commit = fields.next().unwrap_or_default().to_string();
+        let submodule_dir = dir.join(path);
+        found.push(Submodule {
+            path: prefix.join(path),
+            commit,
+            depth,
+        });
+        if submodule_dir.join(".git").exists() {
+            collect_submodules(&submodule_dir, &prefix.join(path), depth + 1, max_depth, found)?;
+        }
+    }
+    Ok(())
+}
+
+/// A commit listed by [`log`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Commit {
+    /// The abbreviated commit SHA.
+    pub hash: String,
+    /// The name of the author.
+    pub author: String,
+    /// The author date, in ISO 8601 format.
+    pub date: String,
+    /// The first line of the commit message.
+    pub subject: String,
+    /// The rest of the commit message, which may be empty.
+    pub body: String,
+}
+
+/// Lists the most recent commits reachable from `HEAD`, newest first.
+///
+/// # Arguments
+///
+/// * `dir` - The directory to run git in.
+/// * `count` - The most commits to list.
+/// * `paths` - If given, only commits touching one of these paths, relative to `dir`, are listed.
+///
+/// # Returns
+///
+/// Returns `Result<Vec<Commit>, GitError>` containing the commits, or a `GitError` if
+/// `dir` is not inside a git repository or it has no commits.
+pub fn log(dir: &Path, count: usize, paths: Option<&[PathBuf]>) -> Result<Vec<Commit>, GitError> {
+    let count = format!("--max-count={}", count);
+    // Fields are separated by the unit separator and commits by the record separator.
+    let mut args = vec!["log".to_string(), count, "--format=%h%x1f%an%x1f%aI%x1f%s%x1f%b%x1e".to_string()];
+    if let Some(paths) = paths {
+        args.push("--".to_string());
+        args.extend(paths.iter().map(|path| format!(":(literal){}", path.to_string_lossy())));
+    }
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let output = run(dir, &args)?;
+    Ok(output
+        .split('\x1e')
+        .filter_map(|record| {
+            let mut fields = record.trim_start_matches('\n').splitn(5, '\x1f');
+            Some(Commit {
+                hash: fields.next().filter(|hash| !hash.is_empty())?.to_string(),
+                author: fields.next()?.to_string(),
+                date: fields.next()?.to_string(),
+                subject: fields.next()?.to_string(),
+                body: fields.next().unwrap_or_default().trim().to_string(),
+            })
+        })
+        .collect())
+}
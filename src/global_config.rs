@@ -0,0 +1,110 @@
+use std::collections::{BTreeMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use anyhow::{bail, Context, Result};
+use clap::CommandFactory;
+use serde::Deserialize;
+use crate::cli::Cli;
+use crate::presets::PresetConfig;
+
+/// The user-wide configuration, shared by every project.
+///
+/// It lives at `$XDG_CONFIG_HOME/rustifacts/config.toml`, falling back to
+/// `~/.config/rustifacts/config.toml` (`%APPDATA%\rustifacts\config.toml` on Windows).
+#[derive(Deserialize, Debug, Default)]
+pub struct GlobalConfig {
+    #[serde(default)]
+    pub aliases: BTreeMap<String, AliasArgs>,
+    /// User-defined presets, available alongside the built-in ones.
+    #[serde(default)]
+    pub presets: BTreeMap<String, PresetConfig>,
+}
+
+/// The arguments an alias expands to, written either as one string or as a list.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum AliasArgs {
+    Line(String),
+    List(Vec<String>),
+}
+
+impl AliasArgs {
+    fn to_args(&self) -> Vec<String> {
+        match self {
+            Self::Line(line) => line.split_whitespace().map(String::from).collect(),
+            Self::List(args) => args.clone(),
+        }
+    }
+}
+
+impl GlobalConfig {
+    /// Returns the path of the global configuration file, if a home directory is known.
+    pub fn path() -> Option<PathBuf> {
+        let base = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(base.join(env!("CARGO_PKG_NAME")).join("config.toml"))
+    }
+
+    /// Returns the user-wide cache directory, if a home directory is known.
+    ///
+    /// It is `$XDG_CACHE_HOME/rustifacts`, falling back to `~/.cache/rustifacts`
+    /// (`%LOCALAPPDATA%\rustifacts` on Windows).
+    pub fn cache_dir() -> Option<PathBuf> {
+        let base = env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("LOCALAPPDATA").map(PathBuf::from))
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+        Some(base.join(env!("CARGO_PKG_NAME")))
+    }
+
+    /// Loads the global configuration, returning an empty one if the file does not exist.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<Self, anyhow::Error>` containing the parsed configuration,
+    /// or an error if the file exists but cannot be read or parsed.
+    pub fn load() -> Result<Self> {
+        let Some(path) = Self::path().filter(|path| path.is_file()) else {
+            return Ok(Self::default());
+        };
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read global config file: {}", path.display()))?;
+        toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse global config file: {}", path.display()))
+    }
+
+    /// Expands an alias given as the first command-line argument.
+    ///
+    /// `rustifacts fe -d ./out` with `fe = "--preset nextjs"` becomes
+    /// `rustifacts --preset nextjs -d ./out`. Aliases may refer to other aliases, but never
+    /// shadow the built-in subcommands.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - The full command line, including the program name.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<Vec<String>, anyhow::Error>` containing the expanded command line,
+    /// or an error if the aliases refer to each other in a cycle.
+    pub fn expand_aliases(&self, mut args: Vec<String>) -> Result<Vec<String>> {
+        let command = Cli::command();
+        let mut expanded = HashSet::new();
+        while let Some(name) = args.get(1).cloned() {
+            if command.find_subcommand(&name).is_some() {
+                break;
+            }
+            let Some(alias) = self.aliases.get(&name) else {
+                break;
+            };
+            if !expanded.insert(name.clone()) {
+                bail!("Alias '{}' refers to itself", name);
+            }
+            args.splice(1..2, alias.to_args());
+        }
+        Ok(args)
+    }
+}
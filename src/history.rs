@@ -0,0 +1,90 @@
+use std::path::PathBuf;
+use log::warn;
+use crate::artifact::Artifact;
+use crate::config::Config;
+use crate::git::{self, Commit};
+
+/// The name of the generated git log, at the root of the source directory.
+pub const GIT_LOG_FILE: &str = "_gitlog.md";
+
+/// Builds the `_gitlog.md` artifact listing the most recent commits, with
+/// `--include-git-log`.
+///
+/// With `--git-log-selected`, only commits touching one of the collected files are
+/// listed. If the source directory is not in a git repository, a warning is logged and no
+/// artifact is built.
+///
+/// # Arguments
+///
+/// * `artifacts` - The collected artifacts, whose paths scope the log with `--git-log-selected`.
+/// * `count` - The most commits to list.
+/// * `config` - The configuration options.
+///
+/// # Returns
+///
+/// `Some(Artifact)` containing the log, or `None` if it could not be read or lists no commits.
+pub fn git_log(artifacts: &[Artifact], count: usize, config: &Config) -> Option<Artifact> {
+    let paths: Option<Vec<PathBuf>> = config.git_log_selected.then(|| {
+        let mut paths: Vec<PathBuf> = artifacts
+            .iter()
+            .flat_map(|artifact| std::iter::once(&artifact.relative_path).chain(&artifact.duplicates))
+            .cloned()
+            .collect();
+        paths.sort();
+        paths.dedup();
+        paths
+    });
+    let commits = match git::log(&config.source_dir, count, paths.as_deref()) {
+        Ok(commits) => commits,
+        Err(e) => {
+            warn!("Not including the git log: {}", e);
+            return None;
+        }
+    };
+    if commits.is_empty() {
+        return None;
+    }
+    let content = render(&commits, config.git_log_selected);
+    Some(Artifact::generated(config.source_dir.clone(), PathBuf::from(GIT_LOG_FILE), content, config))
+}
+
+/// Renders commits as Markdown, one section per commit.
+///
+/// # Arguments
+///
+/// * `commits` - The commits to render, newest first.
+/// * `selected` - Whether the commits were limited to those touching the collected files.
+///
+/// # Returns
+///
+/// A `String` containing the Markdown.
+///
+/// # Example
+///
+/// ```
+/// use rustifacts::git::Commit;
+/// use rustifacts::history::render;
+///
+/// let commit = Commit {
+///     hash: "1a2b3c4".to_string(),
+///     author: "Ada".to_string(),
+///     date: "2024-05-01T12:00:00+00:00".to_string(),
+///     subject: "Fix parser".to_string(),
+///     body: String::new(),
+/// };
+/// assert!(render(&[commit], false).contains("## 1a2b3c4 Fix parser\n\nAda, 2024-05-01T12:00:00+00:00\n"));
+/// ```
+pub fn render(commits: &[Commit], selected: bool) -> String {
+    let scope = if selected { " touching the collected files" } else { "" };
+    let mut output = match commits.len() {
+        1 => format!("# Git log\n\nThe last commit{}.\n", scope),
+        count => format!("# Git log\n\nThe last {} commits{}, newest first.\n", count, scope),
+    };
+    for commit in commits {
+        output.push_str(&format!("\n## {} {}\n\n{}, {}\n", commit.hash, commit.subject, commit.author, commit.date));
+        if !commit.body.is_empty() {
+            output.push_str(&format!("\n{}\n", commit.body));
+        }
+    }
+    output
+}
@@ -0,0 +1,470 @@
+use std::collections::{HashSet, VecDeque};
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+use log::debug;
+use regex::Regex;
+use serde_json::Value;
+use crate::vfs::{FileKind, FileSystem};
+
+/// The extensions tried, in order, for a JavaScript or TypeScript import without one.
+const SCRIPT_EXTENSIONS: &[&str] = &["ts", "tsx", "d.ts", "js", "jsx", "mjs", "cjs", "mts", "cts", "json"];
+
+/// The extensions of files whose imports are read as JavaScript or TypeScript imports.
+const SCRIPT_SOURCES: &[&str] = &["ts", "tsx", "js", "jsx", "mjs", "cjs", "mts", "cts", "vue", "svelte", "astro"];
+
+/// Rust files whose submodules sit beside them rather than in a directory named after them.
+const RUST_ROOT_FILES: &[&str] = &["lib.rs", "main.rs", "mod.rs", "build.rs"];
+
+/// Directories whose Rust files are each the root of a crate.
+const RUST_ROOT_DIRS: &[&str] = &["bin", "tests", "examples", "benches"];
+
+/// Finds the local files an entry file depends on, directly or through other local files.
+///
+/// Imports are followed in JavaScript and TypeScript (`import`, `export … from`,
+/// `require()`, and dynamic `import()` of relative paths, or of `paths` and `baseUrl`
+/// aliases from the `tsconfig.json` or `jsconfig.json` at the root), Rust (`mod`
+/// declarations, and `crate::`, `self::`, `super::`, and own-crate paths), and Python
+/// (relative and absolute imports of modules beneath the root or its `src/`). Packages
+/// from registries are not followed, and neither is anything outside the root. Imports
+/// are found by pattern rather than parsed, so an import in a comment is followed too.
+///
+/// # Arguments
+///
+/// * `fs` - The filesystem to read from.
+/// * `root` - The directory the closure is limited to, usually the source directory.
+/// * `entries` - The files to start from, beneath `root`.
+///
+/// # Returns
+///
+/// A `HashSet<PathBuf>` containing the paths, relative to `root`, of the entries and
+/// every local file they import.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashSet;
+/// use std::path::{Path, PathBuf};
+/// use rustifacts::imports::closure;
+/// use rustifacts::vfs::MemoryFs;
+///
+/// let mut fs = MemoryFs::new();
+/// fs.add_file("app/src/main.ts", "import { cart } from './cart';\n");
+/// fs.add_file("app/src/cart/index.ts", "import React from 'react';\nexport const cart = [];\n");
+/// fs.add_file("app/src/unused.ts", "export {};\n");
+///
+/// let files = closure(&fs, Path::new("app"), &[PathBuf::from("app/src/main.ts")]);
+/// let expected: HashSet<PathBuf> = ["src/main.ts", "src/cart/index.ts"].into_iter().map(PathBuf::from).collect();
+/// assert_eq!(files, expected);
+/// ```
+pub fn closure(fs: &dyn FileSystem, root: &Path, entries: &[PathBuf]) -> HashSet<PathBuf> {
+    let resolver = Resolver::new(fs, root);
+    let mut seen = HashSet::new();
+    let mut queue: VecDeque<PathBuf> = entries.iter().map(|entry| clean(entry)).collect();
+    while let Some(path) = queue.pop_front() {
+        if !seen.insert(path.clone()) {
+            continue;
+        }
+        let Ok(content) = fs.read_to_string(&path) else {
+            continue;
+        };
+        for import in resolver.imports(&path, &content) {
+            if import.starts_with(&resolver.root) && !seen.contains(&import) {
+                debug!("{} imports {}", path.display(), import.display());
+                queue.push_back(import);
+            }
+        }
+    }
+    seen.iter().filter_map(|path| path.strip_prefix(&resolver.root).ok()).map(Path::to_path_buf).collect()
+}
+
+/// Resolves the imports of files beneath a root.
+struct Resolver<'a> {
+    fs: &'a dyn FileSystem,
+    root: PathBuf,
+    /// The directory non-relative script imports are resolved against, from `baseUrl`.
+    base_url: Option<PathBuf>,
+    /// The `paths` aliases, as patterns (with at most one `*`) and the paths they map to.
+    aliases: Vec<(String, Vec<PathBuf>)>,
+}
+
+impl<'a> Resolver<'a> {
+    fn new(fs: &'a dyn FileSystem, root: &Path) -> Self {
+        let root = clean(root);
+        let mut resolver = Self { fs, root, base_url: None, aliases: Vec::new() };
+        let tsconfig = ["tsconfig.json", "jsconfig.json"]
+            .iter()
+            .find_map(|name| fs.read_to_string(&resolver.root.join(name)).ok())
+            .and_then(|json| serde_json::from_str::<Value>(&strip_json_comments(&json)).ok());
+        let options = tsconfig.as_ref().and_then(|tsconfig| tsconfig.get("compilerOptions"));
+        let base_url = options.and_then(|options| options.get("baseUrl")).and_then(|base_url| base_url.as_str());
+        let alias_dir = clean(&resolver.root.join(base_url.unwrap_or(".")));
+        resolver.base_url = base_url.map(|_| alias_dir.clone());
+        if let Some(paths) = options.and_then(|options| options.get("paths")).and_then(|paths| paths.as_object()) {
+            for (pattern, targets) in paths {
+                let targets = targets
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|target| target.as_str())
+                    .map(|target| alias_dir.join(target))
+                    .collect();
+                resolver.aliases.push((pattern.clone(), targets));
+            }
+        }
+        resolver
+    }
+
+    /// Lists the local files a file imports that exist.
+    fn imports(&self, path: &Path, content: &str) -> Vec<PathBuf> {
+        let extension = path.extension().map(|extension| extension.to_string_lossy().to_lowercase()).unwrap_or_default();
+        match extension.as_str() {
+            "rs" => self.rust_imports(path, content),
+            "py" | "pyi" => self.python_imports(path, content),
+            extension if SCRIPT_SOURCES.contains(&extension) => self.script_imports(path, content),
+            _ => Vec::new(),
+        }
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        self.fs.metadata(path).is_ok_and(|metadata| metadata.kind == FileKind::File)
+    }
+
+    fn script_imports(&self, path: &Path, content: &str) -> Vec<PathBuf> {
+        let dir = path.parent().unwrap_or(Path::new(""));
+        script_pattern()
+            .captures_iter(content)
+            .filter_map(|captures| captures.iter().skip(1).flatten().next())
+            .filter_map(|specifier| self.resolve_script(dir, specifier.as_str()))
+            .collect()
+    }
+
+    /// Resolves an import specifier to a file, trying extensions and `index` files.
+    fn resolve_script(&self, dir: &Path, specifier: &str) -> Option<PathBuf> {
+        let specifier = specifier.split(['?', '#']).next().unwrap_or_default();
+        if specifier.starts_with("./") || specifier.starts_with("../") {
+            return self.script_file(&clean(&dir.join(specifier)));
+        }
+        for (pattern, targets) in &self.aliases {
+            let wildcard = match pattern.split_once('*') {
+                Some((prefix, suffix)) => specifier
+                    .strip_prefix(prefix)
+                    .and_then(|rest| rest.strip_suffix(suffix)),
+                None => (pattern == specifier).then_some(""),
+            };
+            let Some(wildcard) = wildcard else {
+                continue;
+            };
+            let found = targets
+                .iter()
+                .find_map(|target| self.script_file(&clean(Path::new(&target.to_string_lossy().replacen('*', wildcard, 1)))));
+            if found.is_some() {
+                return found;
+            }
+        }
+        self.base_url.as_ref().and_then(|base_url| self.script_file(&clean(&base_url.join(specifier))))
+    }
+
+    fn script_file(&self, base: &Path) -> Option<PathBuf> {
+        if self.is_file(base) {
+            return Some(base.to_path_buf());
+        }
+        let name = base.file_name()?.to_string_lossy();
+        // TypeScript sources are imported by the name they are compiled to (`./cart.js`).
+        let stem = ["js", "jsx", "mjs", "cjs"].iter().find_map(|extension| name.strip_suffix(&format!(".{}", extension)));
+        let candidates = stem
+            .into_iter()
+            .flat_map(|stem| ["ts", "tsx", "mts", "cts"].map(|extension| base.with_file_name(format!("{}.{}", stem, extension))))
+            .chain(SCRIPT_EXTENSIONS.iter().map(|extension| base.with_file_name(format!("{}.{}", name, extension))))
+            .chain(SCRIPT_EXTENSIONS.iter().map(|extension| base.join(format!("index.{}", extension))));
+        candidates.into_iter().find(|candidate| self.is_file(candidate))
+    }
+
+    fn rust_imports(&self, path: &Path, content: &str) -> Vec<PathBuf> {
+        let mut imports = Vec::new();
+        let module_dir = rust_module_dir(path);
+        for captures in rust_mod_pattern().captures_iter(content) {
+            let name = &captures["name"];
+            let candidates = [module_dir.join(format!("{}.rs", name)), module_dir.join(name).join("mod.rs")];
+            imports.extend(candidates.into_iter().find(|candidate| self.is_file(candidate)));
+        }
+
+        let Some(krate) = self.rust_crate(path) else {
+            return imports;
+        };
+        let mut module_paths = Vec::new();
+        for captures in rust_use_pattern().captures_iter(content) {
+            expand_use_tree("", &captures["tree"], &mut module_paths);
+        }
+        module_paths.extend(rust_path_pattern().find_iter(content).map(|path| path.as_str().to_string()));
+        for module_path in module_paths {
+            let segments: Vec<&str> = module_path.split("::").map(str::trim).collect();
+            imports.extend(self.resolve_rust(path, &krate, &segments));
+        }
+        imports
+    }
+
+    /// Finds the crate a Rust file belongs to from the nearest `Cargo.toml` above it.
+    fn rust_crate(&self, path: &Path) -> Option<RustCrate> {
+        let dir = path.ancestors().skip(1).find(|dir| self.is_file(&dir.join("Cargo.toml")))?;
+        let manifest: toml::Table = self.fs.read_to_string(&dir.join("Cargo.toml")).ok()?.parse().ok()?;
+        let lib_name = manifest
+            .get("lib")
+            .and_then(|lib| lib.get("name"))
+            .or_else(|| manifest.get("package").and_then(|package| package.get("name")))
+            .and_then(|name| name.as_str())
+            .map(|name| name.replace('-', "_"));
+        Some(RustCrate { src: dir.join("src"), lib_name })
+    }
+
+    /// Resolves a path such as `crate::config::Config` to the file of its deepest module.
+    fn resolve_rust(&self, path: &Path, krate: &RustCrate, segments: &[&str]) -> Option<PathBuf> {
+        let (&first, rest) = segments.split_first()?;
+        let mut module: Vec<String> = match first {
+            "crate" => Vec::new(),
+            "self" | "super" => rust_module_path(path, &krate.src),
+            name if krate.lib_name.as_deref() == Some(name) => Vec::new(),
+            _ => return None,
+        };
+        let mut rest = rest;
+        if first == "super" {
+            module.pop();
+        }
+        while let Some((&"super", after)) = rest.split_first() {
+            module.pop();
+            rest = after;
+        }
+        module.extend(rest.iter().take_while(|segment| is_identifier(segment)).map(|segment| segment.to_string()));
+        (1..=module.len()).rev().find_map(|len| {
+            let dir: PathBuf = module[..len - 1].iter().fold(krate.src.clone(), |dir, segment| dir.join(segment));
+            let name = &module[len - 1];
+            [dir.join(format!("{}.rs", name)), dir.join(name).join("mod.rs")]
+                .into_iter()
+                .find(|candidate| self.is_file(candidate))
+        })
+    }
+
+    fn python_imports(&self, path: &Path, content: &str) -> Vec<PathBuf> {
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let roots = [self.root.clone(), self.root.join("src")];
+        let mut imports = Vec::new();
+        for captures in python_pattern().captures_iter(content) {
+            if let Some(modules) = captures.name("modules") {
+                for module in modules.as_str().split(',') {
+                    let module = module.split_whitespace().next().unwrap_or_default();
+                    imports.extend(roots.iter().find_map(|root| self.python_module(root, module)));
+                }
+                continue;
+            }
+            let dots = captures.name("dots").map_or(0, |dots| dots.as_str().len());
+            let module = captures.name("module").map_or("", |module| module.as_str());
+            let bases: Vec<PathBuf> = if dots > 0 {
+                dir.ancestors().nth(dots - 1).map(Path::to_path_buf).into_iter().collect()
+            } else {
+                roots.to_vec()
+            };
+            for base in bases {
+                let package = self.python_module(&base, module);
+                // Imported names may be submodules of the package (`from . import views`).
+                let names = captures["names"].trim_matches(['(', ')', ' ', '\n', '\r', '\t']);
+                let submodules: Vec<PathBuf> = names
+                    .split(',')
+                    .filter_map(|name| name.split_whitespace().next())
+                    .filter_map(|name| self.python_module(&base, &[module, name].join(".")))
+                    .collect();
+                if package.is_some() || !submodules.is_empty() {
+                    imports.extend(package);
+                    imports.extend(submodules);
+                    break;
+                }
+            }
+        }
+        imports
+    }
+
+    /// Resolves a dotted module name beneath a directory to its `.py` or `__init__.py` file.
+    fn python_module(&self, base: &Path, module: &str) -> Option<PathBuf> {
+        let segments: Vec<&str> = module.split('.').filter(|segment| !segment.is_empty()).collect();
+        if !segments.iter().all(|segment| is_identifier(segment)) {
+            return None;
+        }
+        let path = segments.iter().fold(base.to_path_buf(), |path, segment| path.join(segment));
+        if segments.is_empty() {
+            return Some(path.join("__init__.py")).filter(|init| self.is_file(init));
+        }
+        [path.with_extension("py"), path.join("__init__.py")]
+            .into_iter()
+            .find(|candidate| self.is_file(candidate))
+    }
+}
+
+/// The crate a Rust file belongs to.
+struct RustCrate {
+    /// The `src/` directory of the crate.
+    src: PathBuf,
+    /// The name its library is referred to by, with `-` replaced by `_`.
+    lib_name: Option<String>,
+}
+
+/// Returns the directory the submodules a Rust file declares with `mod` sit in.
+fn rust_module_dir(path: &Path) -> PathBuf {
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let in_root_dir = dir.file_name().is_some_and(|parent| RUST_ROOT_DIRS.contains(&parent.to_string_lossy().as_ref()));
+    if RUST_ROOT_FILES.contains(&name.as_str()) || in_root_dir {
+        dir.to_path_buf()
+    } else {
+        dir.join(path.file_stem().unwrap_or_default())
+    }
+}
+
+/// Returns the module path of a Rust file within its crate (`src/a/b.rs` is `a::b`).
+fn rust_module_path(path: &Path, src: &Path) -> Vec<String> {
+    let Ok(relative) = path.strip_prefix(src) else {
+        return Vec::new();
+    };
+    let mut module: Vec<String> = relative.components().map(|component| component.as_os_str().to_string_lossy().into_owned()).collect();
+    let file = module.pop().unwrap_or_default();
+    match file.as_str() {
+        "mod.rs" => {}
+        "lib.rs" | "main.rs" if module.is_empty() => {}
+        file => module.push(file.trim_end_matches(".rs").to_string()),
+    }
+    module
+}
+
+/// Expands a `use` tree such as `crate::{a::B, c::{self, D}}` into the paths it imports.
+fn expand_use_tree(prefix: &str, tree: &str, paths: &mut Vec<String>) {
+    let tree = tree.trim();
+    let join = |prefix: &str, path: &str| match (prefix.is_empty(), path.is_empty()) {
+        (true, _) => path.to_string(),
+        (false, true) => prefix.to_string(),
+        (false, false) => format!("{}::{}", prefix, path),
+    };
+    let Some(open) = tree.find('{') else {
+        let path = tree.split(" as ").next().unwrap_or_default().trim().trim_start_matches("::");
+        paths.push(join(prefix, path.strip_suffix("::self").unwrap_or(path)));
+        return;
+    };
+    let head = join(prefix, tree[..open].trim().trim_end_matches("::"));
+    let inner = &tree[open + 1..tree.rfind('}').unwrap_or(tree.len())];
+    let mut depth = 0;
+    let mut start = 0;
+    for (index, c) in inner.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                expand_use_tree(&head, &inner[start..index], paths);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    match inner[start..].trim() {
+        "" => {}
+        "self" => paths.push(head),
+        last => expand_use_tree(&head, last, paths),
+    }
+}
+
+fn is_identifier(segment: &str) -> bool {
+    let segment = segment.strip_prefix("r#").unwrap_or(segment);
+    segment.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && segment.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Resolves `.` and `..` components without touching the filesystem.
+fn clean(path: &Path) -> PathBuf {
+    let mut cleaned = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if cleaned.file_name().is_some() => {
+                cleaned.pop();
+            }
+            component => cleaned.push(component),
+        }
+    }
+    cleaned
+}
+
+/// Removes `//` and `/* */` comments and trailing commas, which `tsconfig.json` allows.
+fn strip_json_comments(json: &str) -> String {
+    let mut stripped = String::with_capacity(json.len());
+    let mut chars = json.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            stripped.push(c);
+            match c {
+                '\\' => stripped.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                stripped.push(c);
+            }
+            ('/', Some('/')) => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        stripped.push(c);
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            _ => stripped.push(c),
+        }
+    }
+    static TRAILING_COMMA: OnceLock<Regex> = OnceLock::new();
+    let trailing_comma = TRAILING_COMMA.get_or_init(|| Regex::new(r",(\s*[}\]])").expect("trailing comma pattern is valid"));
+    trailing_comma.replace_all(&stripped, "$1").into_owned()
+}
+
+fn script_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r#"(?:\b(?:import|export)\b[^'";]*?\bfrom\s*['"]([^'"\n]+)['"]|\bimport\s*['"]([^'"\n]+)['"]|\b(?:require|import)\s*\(\s*['"]([^'"\n]+)['"]\s*\))"#)
+            .expect("script import pattern is valid")
+    })
+}
+
+fn rust_mod_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"(?m)^\s*(?:pub(?:\([^)]*\))?\s+)?mod\s+(?:r#)?(?P<name>\w+)\s*;").expect("mod pattern is valid")
+    })
+}
+
+fn rust_use_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"(?m)^\s*(?:pub(?:\([^)]*\))?\s+)?use\s+(?P<tree>[^;]+);").expect("use pattern is valid")
+    })
+}
+
+fn rust_path_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"\b[a-z_][a-z0-9_]*(?:::\w+)+").expect("path pattern is valid"))
+}
+
+fn python_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"(?m)^\s*(?:from\s+(?P<dots>\.*)(?P<module>[\w.]*)\s+import\s+(?P<names>\([^)]*\)|[^\n#]+)|import\s+(?P<modules>[\w., ]+))")
+            .expect("python import pattern is valid")
+    })
+}
@@ -0,0 +1,62 @@
+//! Prepares files for upload as artifacts to Claude AI projects.
+//!
+//! The [`ArtifactCollector`] builder walks a source directory and returns the
+//! matching files as [`Artifact`]s, which a [`Writer`] then writes out.
+
+pub mod archive;
+pub mod artifact;
+pub mod binary;
+pub mod cache;
+pub mod checksum;
+pub mod chunk;
+pub mod clean;
+pub mod cli;
+pub mod collector;
+pub mod comments;
+pub mod config;
+pub mod config_file;
+pub mod daemon;
+pub mod delta;
+pub mod dependencies;
+pub mod demo;
+pub mod detect;
+pub mod encoding;
+pub mod format;
+pub mod git;
+pub mod global_config;
+pub mod history;
+pub mod imports;
+pub mod locales;
+pub mod manifest;
+pub mod markdown;
+pub mod notebook;
+pub mod open;
+pub mod owner;
+pub mod paths;
+pub mod plugin;
+pub mod preflight;
+pub mod presets;
+pub mod project;
+pub mod redact;
+pub mod restore;
+pub mod rpc;
+pub mod rules;
+pub mod runs;
+pub mod skeleton;
+pub mod sql;
+pub mod state;
+pub mod stats;
+pub mod test_code;
+pub mod tokens;
+pub mod transform;
+pub mod upload;
+pub mod vfs;
+pub mod walk_cache;
+pub mod watch;
+pub mod whitespace;
+pub mod writer;
+
+pub use artifact::{Artifact, ArtifactError, Sink};
+pub use collector::ArtifactCollector;
+pub use config::Config;
+pub use writer::{ArchiveWriter, DirectoryWriter, MarkdownPerTargetWriter, SingleFileWriter, Writer};
@@ -0,0 +1,231 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Component, Path, PathBuf};
+use log::{debug, info, warn};
+use serde_json::Value;
+use crate::artifact::{Artifact, PlannedFile};
+use crate::config::{Config, LocaleMode};
+use crate::vfs::FileSystem;
+
+/// Directory names that hold translation resources.
+const LOCALE_DIRS: &[&str] = &["locales", "locale", "i18n", "l10n", "lang", "translations"];
+
+/// File extensions used for translation resources.
+const LOCALE_EXTENSIONS: &[&str] = &["json", "yaml", "yml", "po", "properties", "ftl", "arb", "xlf", "xliff", "strings", "toml"];
+
+/// The name of the generated key listing file inside each locale tree.
+const KEY_LISTING_FILE: &str = "translation_keys.txt";
+
+/// The translation files found beneath one locale directory, grouped by locale.
+#[derive(Debug, Clone)]
+pub struct LocaleTree {
+    /// The locale directory, relative to the source directory (e.g. `public/locales`).
+    pub root: PathBuf,
+    /// The files of each locale, keyed by locale code.
+    pub locales: BTreeMap<String, Vec<PlannedFile>>,
+}
+
+/// A translation file's place within its locale tree.
+struct LocaleFile {
+    root: PathBuf,
+    locale: String,
+}
+
+/// Consolidates translation files according to the configured locale mode.
+///
+/// Files are recognized as translations when they sit beneath a directory such as
+/// `locales/` or `i18n/`, either in a per-locale subdirectory (`locales/fr/common.json`)
+/// or named after their locale (`locales/fr.json`). In `default` mode only the default
+/// locale's files are kept; if a tree has no default locale, its alphabetically first
+/// locale is kept instead. In `keys` mode every translation file is dropped and the
+/// returned trees are turned into key listings by [`key_listing`].
+///
+/// # Arguments
+///
+/// * `files` - The planned files.
+/// * `config` - The configuration options.
+///
+/// # Returns
+///
+/// A tuple of the files to keep and, in `keys` mode, the locale trees that were dropped.
+pub fn consolidate(files: Vec<PlannedFile>, config: &Config) -> (Vec<PlannedFile>, Vec<LocaleTree>) {
+    if config.locales == LocaleMode::All {
+        return (files, Vec::new());
+    }
+
+    let mut kept = Vec::new();
+    let mut trees: BTreeMap<PathBuf, LocaleTree> = BTreeMap::new();
+    for planned in files {
+        match locale_file(&planned.relative_path) {
+            Some(LocaleFile { root, locale }) => {
+                trees
+                    .entry(root.clone())
+                    .or_insert_with(|| LocaleTree { root, locales: BTreeMap::new() })
+                    .locales
+                    .entry(locale)
+                    .or_default()
+                    .push(planned);
+            }
+            None => kept.push(planned),
+        }
+    }
+
+    let trees: Vec<LocaleTree> = trees.into_values().collect();
+    if config.locales == LocaleMode::Keys {
+        for tree in &trees {
+            info!("Replacing {} locales in {} with a key listing", tree.locales.len(), tree.root.display());
+        }
+        return (kept, trees);
+    }
+
+    for mut tree in trees {
+        let locale = if tree.locales.contains_key(&config.default_locale) {
+            config.default_locale.clone()
+        } else {
+            let first = tree.locales.keys().next().cloned().unwrap_or_default();
+            warn!("No '{}' locale in {}, keeping '{}' instead", config.default_locale, tree.root.display(), first);
+            first
+        };
+        let dropped: usize = tree.locales.iter().filter(|(code, _)| **code != locale).map(|(_, files)| files.len()).sum();
+        if dropped > 0 {
+            info!("Skipping {} translation files in {} outside the '{}' locale", dropped, tree.root.display(), locale);
+        }
+        kept.extend(tree.locales.remove(&locale).unwrap_or_default());
+    }
+    (kept, Vec::new())
+}
+
+/// Builds an artifact listing the union of translation keys across every locale in a tree.
+///
+/// Keys are read from JSON files, with nested objects joined by `.`. In per-locale
+/// directories, keys are prefixed with their file's namespace (`common:title`). Keys that
+/// are absent from some locales are annotated with the locales that lack them.
+///
+/// # Arguments
+///
+/// * `fs` - The filesystem to read from.
+/// * `tree` - The locale tree to list.
+/// * `config` - The configuration options.
+///
+/// # Returns
+///
+/// An `Artifact` containing the key listing.
+pub fn key_listing(fs: &dyn FileSystem, tree: &LocaleTree, config: &Config) -> Artifact {
+    let mut keys: BTreeMap<String, BTreeSet<&str>> = BTreeMap::new();
+    let mut unparsed = BTreeSet::new();
+    for (locale, files) in &tree.locales {
+        for planned in files {
+            let namespace = namespace(&planned.relative_path, &tree.root);
+            match read_json_keys(fs, &planned.original_path) {
+                Some(file_keys) => {
+                    for key in file_keys {
+                        let key = match namespace {
+                            Some(ref namespace) => format!("{}:{}", namespace, key),
+                            None => key,
+                        };
+                        keys.entry(key).or_default().insert(locale);
+                    }
+                }
+                None => {
+                    debug!("Not listing keys of {}", planned.relative_path.display());
+                    unparsed.insert(planned.relative_path.file_name().unwrap_or_default().to_string_lossy().into_owned());
+                }
+            }
+        }
+    }
+
+    let locales: Vec<&str> = tree.locales.keys().map(String::as_str).collect();
+    let mut content = format!(
+        "Translation keys in {} (locales: {})\n\n",
+        tree.root.display(),
+        locales.join(", ")
+    );
+    for (key, present) in &keys {
+        let missing: Vec<&str> = locales.iter().copied().filter(|locale| !present.contains(locale)).collect();
+        if missing.is_empty() {
+            content.push_str(&format!("{}\n", key));
+        } else {
+            content.push_str(&format!("{}  [missing: {}]\n", key, missing.join(", ")));
+        }
+    }
+    if !unparsed.is_empty() {
+        let unparsed: Vec<String> = unparsed.into_iter().collect();
+        content.push_str(&format!("\nFiles whose keys are not listed: {}\n", unparsed.join(", ")));
+    }
+
+    let relative_path = tree.root.join(KEY_LISTING_FILE);
+    Artifact::generated(config.source_dir.join(&tree.root), relative_path, content, config)
+}
+
+/// Works out whether a path is a translation file, and if so which tree and locale it belongs to.
+fn locale_file(relative_path: &Path) -> Option<LocaleFile> {
+    let extension = relative_path.extension()?.to_string_lossy().to_lowercase();
+    if !LOCALE_EXTENSIONS.contains(&extension.as_str()) {
+        return None;
+    }
+    let components: Vec<&str> = relative_path
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => name.to_str(),
+            _ => None,
+        })
+        .collect();
+    let position = components
+        .iter()
+        .rposition(|name| LOCALE_DIRS.contains(&name.to_lowercase().as_str()))?;
+    let root: PathBuf = components[..=position].iter().collect();
+    let rest = &components[position + 1..];
+    let locale = match rest {
+        [file] => Path::new(file).file_stem()?.to_str()?,
+        [dir, ..] => dir,
+        [] => return None,
+    };
+    is_locale_code(locale).then(|| LocaleFile { root, locale: locale.to_string() })
+}
+
+/// Checks whether a name looks like a locale code such as `en`, `pt-BR`, or `zh_Hant`.
+fn is_locale_code(name: &str) -> bool {
+    let (language, region) = match name.split_once(['-', '_']) {
+        Some((language, region)) => (language, Some(region)),
+        None => (name, None),
+    };
+    let language_ok = (2..=3).contains(&language.len()) && language.chars().all(|c| c.is_ascii_lowercase());
+    let region_ok = region.is_none_or(|region| {
+        (2..=4).contains(&region.len()) && region.chars().all(|c| c.is_ascii_alphanumeric())
+    });
+    language_ok && region_ok
+}
+
+/// Returns the namespace of a file in a per-locale directory (`locales/en/common.json` is `common`).
+fn namespace(relative_path: &Path, root: &Path) -> Option<String> {
+    let rest = relative_path.strip_prefix(root).ok()?;
+    if rest.components().count() < 2 {
+        return None;
+    }
+    let mut namespace: PathBuf = rest.components().skip(1).collect();
+    namespace.set_extension("");
+    Some(namespace.to_string_lossy().replace('\\', "/"))
+}
+
+/// Reads the flattened keys of a JSON translation file.
+fn read_json_keys(fs: &dyn FileSystem, path: &Path) -> Option<Vec<String>> {
+    if !path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json") || ext.eq_ignore_ascii_case("arb")) {
+        return None;
+    }
+    let value: Value = serde_json::from_str(&fs.read_to_string(path).ok()?).ok()?;
+    let mut keys = Vec::new();
+    flatten_keys(&value, "", &mut keys);
+    Some(keys)
+}
+
+fn flatten_keys(value: &Value, prefix: &str, keys: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                let path = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                flatten_keys(child, &path, keys);
+            }
+        }
+        _ if !prefix.is_empty() => keys.push(prefix.to_string()),
+        _ => {}
+    }
+}
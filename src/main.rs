@@ -1,14 +1,28 @@
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process;
-use log::{error, info, debug};
+use std::time::Duration;
+use log::{error, info, debug, warn};
 use env_logger::Env;
-use clap::Parser;
-use artifact::Artifact;
-use config::Config;
-
-mod config;
-mod artifact;
-mod presets;
-mod config_file;
+use rustifacts::archive::{self, ArchiveKind};
+use rayon::prelude::*;
+use rustifacts::artifact::{Artifact, CollectPlan};
+use rustifacts::checksum;
+use rustifacts::cli::{Cli, Command, DaemonCommand, PresetsCommand};
+use rustifacts::config::Config;
+use rustifacts::config_file;
+use rustifacts::git::Worktree;
+use rustifacts::global_config::GlobalConfig;
+use rustifacts::state::State;
+use rustifacts::stats::TokenRecord;
+use rustifacts::vfs::{FileSystem, RealFs, SharedWalk};
+use rustifacts::walk_cache::WalkCache;
+use rustifacts::writer::{self, DirectoryWriter};
+use rustifacts::daemon::{self, DaemonError};
+use rustifacts::manifest::Manifest;
+use rustifacts::{clean, delta, demo, detect, open, presets, preflight, restore, runs, stats, tokens, upload, watch, ArtifactCollector};
 
 /// The main entry point for the Rustifacts application.
 ///
@@ -20,36 +34,381 @@ fn main() {
 
     debug!("Starting Rustifacts");
 
-    let mut config = Config::parse();
+    // Expand a user-defined alias from the global config, if one was invoked
+    let args = match GlobalConfig::load().and_then(|global| global.expand_aliases(env::args().collect())) {
+        Ok(args) => args,
+        Err(e) => {
+            error!("Failed to expand aliases: {}", e);
+            process::exit(1);
+        }
+    };
+    // Repeat the last run started from this directory if asked to, or if no arguments were given
+    let current_dir = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let args = repeat_last_run(args, &current_dir);
+    let cli = Cli::parse_recording_explicit(&args);
+    let command = cli.subcommand();
+    let mut config = cli.config;
+    config.run_id = runs::new_run_id();
+    info!("Run ID: {}", config.run_id);
 
     debug!("Parsed initial config: {:?}", config);
 
-    // Apply configuration file if specified
+    // Apply the configuration file if specified, or discover one next to the source
+    if config.config_file.is_none() && !config.no_config && !matches!(command, Command::Init | Command::Demo { .. }) {
+        config.config_file = config_file::discover(&config.source_dir);
+        if let Some(ref config_path) = config.config_file {
+            info!("Using configuration file {}", config_path.display());
+        }
+    }
     if let Some(ref config_path) = config.config_file {
         debug!("Applying configuration from file: {}", config_path.display());
         if let Err(e) = config.apply_config_file() {
-            error!("Failed to apply configuration file: {}", e);
+            error!("Failed to apply configuration file: {:#}", e);
             process::exit(1);
         }
     }
 
     debug!("Config after applying config file: {:?}", config);
 
-    // Apply preset if specified
-    if let Some(preset_name) = config.preset.take() {
+    if let Err(e) = config.resolve_root() {
+        error!("Failed to find the git repository root of {}: {}", config.source_dir.display(), e);
+        process::exit(1);
+    }
+
+    // Apply preset if specified, showing the demo with the Rust preset unless another is chosen
+    if matches!(command, Command::Demo { .. }) && config.preset.is_none() {
+        config.preset = Some(demo::DEFAULT_PRESET.to_string());
+    }
+    let preset_name = config.preset.take().and_then(|spec| resolve_presets(&spec, &config.source_dir));
+    let matrix = config.get_matrix_presets();
+    if preset_name.is_some() && !matrix.is_empty() {
+        error!("--matrix cannot be combined with a preset; list the preset in the matrix instead");
+        process::exit(1);
+    }
+    if let Some(ref preset_name) = preset_name {
         debug!("Applying preset: {}", preset_name);
-        if let Err(e) = config.apply_preset(&preset_name) {
+        if let Err(e) = config.apply_preset(preset_name) {
             error!("Failed to apply preset: {}", e);
             process::exit(1);
         }
     }
+    config.apply_default_dest();
+    if matrix.is_empty() {
+        config.expand_dest_dir(preset_name.as_deref());
+    }
 
     debug!("Final config: {:?}", config);
 
+    // Verify existing artifacts instead of collecting if requested
+    if config.verify {
+        match verify_artifacts(&config) {
+            Ok(true) => info!("All artifact checksums verified"),
+            Ok(false) => process::exit(1),
+            Err(e) => {
+                error!("Error during checksum verification: {}", e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // Subcommands that do not read the source
+    match command {
+        Command::Init => {
+            let preset = match preset_name.as_deref().map(|name| presets::resolve_preset(name).map(|preset| (name, preset))).transpose() {
+                Ok(preset) => preset,
+                Err(e) => {
+                    error!("Failed to apply preset: {}", e);
+                    process::exit(1);
+                }
+            };
+            let dir = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+            match config_file::init(&dir, preset.as_ref().map(|(name, preset)| (*name, preset)), config.force) {
+                Ok(path) => info!("Wrote {}", path.display()),
+                Err(e) => {
+                    error!("Failed to write configuration file: {}", e);
+                    process::exit(1);
+                }
+            }
+            return;
+        }
+        Command::Presets { action } => {
+            match action.unwrap_or(PresetsCommand::List) {
+                PresetsCommand::List => {
+                    let user_presets = presets::user_presets();
+                    for name in presets::preset_names() {
+                        if user_presets.contains_key(&name) {
+                            println!("{} (user-defined)", name);
+                        } else {
+                            println!("{}", name);
+                        }
+                    }
+                }
+                PresetsCommand::Show { name } => {
+                    match presets::resolve_preset(&name).and_then(|preset| preset.to_toml(&name).map_err(|e| e.to_string())) {
+                        Ok(toml) => print!("{}", toml),
+                        Err(e) => {
+                            error!("Failed to show preset: {}", e);
+                            process::exit(1);
+                        }
+                    }
+                }
+            }
+            return;
+        }
+        Command::Demo { keep } => {
+            let demo = match demo::run(&config) {
+                Ok(demo) => demo,
+                Err(e) => {
+                    error!("Error during demo: {}", e);
+                    process::exit(1);
+                }
+            };
+            let collected: HashSet<PathBuf> = demo.artifacts.iter().map(|artifact| artifact.relative_path.clone()).collect();
+            println!("Example project ({}):", demo.source_dir.display());
+            print!("{}", demo::tree(&demo.source_dir, Some(&collected)));
+            println!();
+            println!(
+                "Collected {} files with the {} preset into {}:",
+                demo.artifacts.len(),
+                preset_name.as_deref().unwrap_or(demo::DEFAULT_PRESET),
+                demo.dest_dir.display()
+            );
+            print!("{}", demo::tree(&demo.dest_dir, None));
+            println!();
+            println!("The same files bundled with --single-file:");
+            println!();
+            print!("{}", fs::read_to_string(&demo.bundle_file).unwrap_or_default());
+            if keep {
+                info!("Kept the demo files in {}", demo.keep().display());
+            }
+            return;
+        }
+        Command::Daemon { ref socket, action: Some(ref action @ (DaemonCommand::Send { .. } | DaemonCommand::Stop)) } => {
+            let (method, params) = match action {
+                DaemonCommand::Send { method, params } => (method.as_str(), params.clone().unwrap_or_default()),
+                _ => ("shutdown", serde_json::Value::Null),
+            };
+            let socket = daemon_socket(socket.as_deref(), &config.source_dir);
+            match daemon::send(&socket, method, params) {
+                Ok(result) if result.is_null() => {}
+                Ok(result) => println!("{}", serde_json::to_string_pretty(&result).unwrap_or_default()),
+                Err(DaemonError::Rpc(e)) => {
+                    error!("The daemon could not run {}: {} (code {})", method, e.message, e.code);
+                    process::exit(1);
+                }
+                Err(e) => {
+                    error!("Failed to reach the daemon: {}", e);
+                    process::exit(1);
+                }
+            }
+            return;
+        }
+        _ => {}
+    }
+
+    // Un-flatten previously written artifacts instead of collecting if requested
+    if let Command::Restore { ref from, ref to, allow_modified } = command {
+        match restore::restore(from, to, allow_modified) {
+            Ok(summary) => {
+                info!(
+                    "Restored {} files ({} modified since written, {} missing, {} binary placeholders skipped)",
+                    summary.restored, summary.modified, summary.missing, summary.placeholders
+                );
+                if !allow_modified && summary.modified > 0 {
+                    error!("{} artifacts failed checksum verification and were not restored", summary.modified);
+                    process::exit(1);
+                }
+            }
+            Err(e) => {
+                error!("Error during restore: {}", e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Command::Clean { all } = command {
+        match clean::clean(&config.dest_dir, &config.source_dir, all) {
+            Ok(summary) => info!("Removed {} artifacts ({} already missing)", summary.removed, summary.missing),
+            Err(e) => {
+                error!("Error during clean: {}", e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // Resolve the upload target before collecting so that an unknown name fails fast
+    let upload_target = match command {
+        Command::Upload { ref target } | Command::Watch { upload: Some(ref target), .. } => match upload::find_target(&config, target) {
+            Ok(target_config) => Some((target.clone(), target_config.build())),
+            Err(e) => {
+                error!("{}", e);
+                process::exit(1);
+            }
+        },
+        _ => None,
+    };
+
+    // Check out the requested ref into a temporary worktree if specified
+    let worktree = match config.at_ref {
+        Some(ref git_ref) => {
+            debug!("Checking out {} into a temporary worktree", git_ref);
+            match Worktree::checkout(&config.source_dir, git_ref) {
+                Ok(worktree) => {
+                    info!("Collecting from {} at {}", config.source_dir.display(), git_ref);
+                    config.source_dir = worktree.source_dir();
+                    Some(worktree)
+                }
+                Err(e) => {
+                    error!("Failed to check out {}: {}", git_ref, e);
+                    process::exit(1);
+                }
+            }
+        }
+        None => None,
+    };
+
+    // Extract the source into memory if it is a zip or tarball
+    let source = config.source_dir.to_string_lossy().into_owned();
+    let source_fs: Box<dyn FileSystem> = match ArchiveKind::detect(&source) {
+        Some(_) if config.git_tracked || config.git_diff.is_some() => {
+            error!("--git-tracked and --git-diff require a local source directory");
+            process::exit(1);
+        }
+        Some(kind) => {
+            debug!("Loading {:?} archive source: {}", kind, source);
+            match archive::load(&source, kind) {
+                Ok(memory_fs) => {
+                    config.source_dir = PathBuf::from(".");
+                    Box::new(memory_fs)
+                }
+                Err(e) => {
+                    error!("Failed to load archive {}: {}", source, e);
+                    process::exit(1);
+                }
+            }
+        }
+        // A temporary worktree gets a new path every run, so there is nothing to reuse
+        None if config.no_walk_cache || worktree.is_some() => Box::new(RealFs),
+        None => Box::new(WalkCache::new(&config.source_dir)),
+    };
+
+    // Collect every preset of the matrix over a single walk of the source
+    if !matrix.is_empty() {
+        if !matches!(command, Command::Collect) || config.dry_run {
+            error!("--matrix can only be used to collect");
+            process::exit(1);
+        }
+        if config.single_file.is_some() || config.output_archive.is_some() || config.summary_json.is_some() {
+            error!("--matrix writes each preset to its own destination directory and cannot be combined with --single-file, --output-archive, or --summary-json");
+            process::exit(1);
+        }
+        let failed = collect_matrix(source_fs.as_ref(), &config, &matrix);
+        drop(worktree);
+        if failed {
+            process::exit(1);
+        }
+        // Each preset has its own directory beneath the destination, so open the destination itself
+        if config.open || config.open_with.is_some() {
+            if let Err(e) = open::open(&config.dest_dir, config.open_with.as_deref()) {
+                warn!("Failed to open the output: {}", e);
+            }
+        }
+        remember_run(&args, &current_dir);
+        return;
+    }
+
+    let collector = ArtifactCollector::from_config(config).boxed_filesystem(source_fs);
+    let config = collector.config();
+
+    // Report statistics instead of writing if requested
+    if let Command::Stats { by_extension } = command {
+        match collector.collect() {
+            Ok(artifacts) => {
+                stats::print_report(&artifacts, by_extension);
+                if config.lang_stats {
+                    println!();
+                    stats::print_language_report(&artifacts);
+                }
+                if let Some(count) = config.top {
+                    println!();
+                    stats::print_largest(&artifacts, count);
+                }
+            }
+            Err(e) => {
+                error!("Error during artifact collection: {}", e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // List what would be collected instead of writing if requested
+    if config.dry_run {
+        match collector.plan() {
+            Ok(plan) => print_plan(&plan),
+            Err(e) => {
+                error!("Error during collection planning: {}", e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // Keep the destination in sync with the source if requested
+    if let Command::Watch { debounce_ms, .. } = command {
+        if ArchiveKind::detect(&source).is_some() || worktree.is_some() {
+            error!("Watch mode requires a local source directory");
+            process::exit(1);
+        }
+        if config.output_archive.is_some() || config.markdown_per_target {
+            error!("Watch mode cannot write to --output-archive or --markdown-per-target");
+            process::exit(1);
+        }
+        let upload = upload_target.as_ref().map(|(name, target)| (name.as_str(), target.as_ref()));
+        if let Err(e) = watch::run(config.clone(), Duration::from_millis(debounce_ms), upload) {
+            error!("Error while watching {}: {}", source, e);
+            process::exit(1);
+        }
+        return;
+    }
+
+    // Serve requests over a socket if requested
+    if let Command::Daemon { ref socket, .. } = command {
+        if ArchiveKind::detect(&source).is_some() || worktree.is_some() {
+            error!("Daemon mode requires a local source directory");
+            process::exit(1);
+        }
+        let socket = daemon_socket(socket.as_deref(), &config.source_dir);
+        if let Err(e) = daemon::serve(config.clone(), &socket) {
+            error!("Error while serving {}: {}", socket.display(), e);
+            process::exit(1);
+        }
+        return;
+    }
+
+    // Write a delta of the artifacts next to the destination's previous manifest if requested
+    let previous_manifest = match command {
+        Command::ReExport { .. } => {
+            if config.single_file.is_some() || config.output_archive.is_some() || config.markdown_per_target || config.run_dirs {
+                error!("re-export writes into a destination directory and cannot be combined with --single-file, --output-archive, --markdown-per-target, or --run-dirs");
+                process::exit(1);
+            }
+            Manifest::read_from(&config.dest_dir)
+        }
+        _ => None,
+    };
+
     // Log configuration details
     info!("Starting file preparation process");
-    info!("Source directory: {}", config.source_dir.display());
-    info!("Destination directory: {}", config.dest_dir.display());
+    info!("Source: {}", source);
+    match (&config.single_file, &config.output_archive) {
+        (Some(single_file), _) => info!("Destination file: {}", single_file.display()),
+        (None, Some(output_archive)) => info!("Destination archive: {}", output_archive.display()),
+        (None, None) => info!("Destination directory: {}", DirectoryWriter::from_config(config).output_dir().display()),
+    }
     info!("Ignored directories: {:?}", config.get_ignored_dirs());
     info!("Excluded file types: {:?}", config.get_excluded_extensions());
     if let Some(ref target_dirs) = config.target_dirs {
@@ -60,31 +419,382 @@ fn main() {
 
     // Collect and process artifacts
     debug!("Starting artifact collection and processing");
-    match collect_and_process_artifacts(&config) {
-        Ok(_) => info!("File preparation completed successfully"),
+    let result = collect_and_process_artifacts(collector.fs(), config);
+    drop(worktree);
+    match result {
+        Ok((artifacts, skipped)) => {
+            info!("File preparation completed successfully");
+            if let Command::ReExport { only_changed } = command {
+                write_delta(config, &artifacts, previous_manifest.as_ref(), only_changed);
+            }
+            report_token_delta(config, TokenRecord::from_artifacts(&artifacts));
+            if let Some(ref path) = config.summary_json {
+                write_summary(config, path, &artifacts, skipped);
+            }
+        }
         Err(e) => {
             error!("Error during file preparation: {}", e);
             process::exit(1);
         }
     }
 
+    // Upload the written files if requested
+    if let Some((name, target)) = upload_target {
+        match upload::output_files(config).and_then(|files| target.upload_all(&files)) {
+            Ok(count) => info!("Uploaded {} files to {}", count, name),
+            Err(e) => {
+                error!("Error during upload to {}: {}", name, e);
+                process::exit(1);
+            }
+        }
+    }
+
+    // Open the output if requested
+    if config.open || config.open_with.is_some() {
+        if let Err(e) = open::open(&open::output_path(config), config.open_with.as_deref()) {
+            warn!("Failed to open the output: {}", e);
+        }
+    }
+
+    remember_run(&args, &current_dir);
     debug!("Rustifacts completed");
 }
 
+/// Replaces a bare invocation, or one with `--last`, with the last run remembered for a directory.
+///
+/// Arguments given alongside `--last` are added after the remembered ones. A bare
+/// invocation with nothing remembered is left alone, so it collects with the defaults.
+///
+/// # Arguments
+///
+/// * `args` - The full command line, including the program name.
+/// * `dir` - The directory Rustifacts was started from.
+///
+/// # Returns
+///
+/// A `Vec<String>` containing the command line to run.
+fn repeat_last_run(args: Vec<String>, dir: &Path) -> Vec<String> {
+    let explicit = args.iter().skip(1).any(|arg| arg == "--last");
+    if args.len() > 1 && !explicit {
+        return args;
+    }
+    let state = match State::load() {
+        Ok(state) => state,
+        Err(e) if explicit => {
+            error!("Failed to load the last run: {:#}", e);
+            process::exit(1);
+        }
+        Err(e) => {
+            warn!("Failed to load the last run: {:#}", e);
+            return args;
+        }
+    };
+    let Some(last) = state.last_run(dir) else {
+        if explicit {
+            error!("No previous collect, upload, or re-export run is remembered for {}", dir.display());
+            process::exit(1);
+        }
+        return args;
+    };
+    info!("Repeating the last run in this directory: {} {}", env!("CARGO_PKG_NAME"), last.join(" "));
+    let mut repeated = args[..1].to_vec();
+    repeated.extend(last.iter().cloned());
+    repeated.extend(args.into_iter().skip(1).filter(|arg| arg != "--last"));
+    repeated
+}
+
+/// Remembers a successful collect, upload, or re-export run as the last one for a directory.
+///
+/// # Arguments
+///
+/// * `args` - The full command line that was run, including the program name.
+/// * `dir` - The directory Rustifacts was started from.
+fn remember_run(args: &[String], dir: &Path) {
+    let result = State::load().and_then(|mut state| {
+        state.remember_run(dir, args[1..].to_vec());
+        state.save()
+    });
+    if let Err(e) = result {
+        warn!("Failed to remember this run: {:#}", e);
+    }
+}
+
 /// Collects and processes artifacts based on the provided configuration.
 ///
 /// # Arguments
 ///
-/// * `config` - The configuration options for artifact processing.
+/// * `fs` - The filesystem to collect from.
+/// * `config` - The configuration options.
 ///
 /// # Returns
 ///
-/// Returns `Result<(), Box<dyn std::error::Error>>` indicating success or failure
-/// of the artifact collection and processing.
-fn collect_and_process_artifacts(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+/// Returns `Result<(Vec<Artifact>, usize), Box<dyn std::error::Error>>` containing the written
+/// artifacts and the number of planned files that were skipped, or an error if collection or
+/// processing failed.
+fn collect_and_process_artifacts(fs: &dyn FileSystem, config: &Config) -> Result<(Vec<Artifact>, usize), Box<dyn std::error::Error>> {
+    debug!("Planning artifact collection");
+    let plan = Artifact::plan(fs, config)?;
+    preflight::run(config, &plan)?;
+    let planned: HashSet<PathBuf> = plan.files.iter().map(|planned| planned.relative_path.clone()).collect();
+    let special_files = plan.special_files.len();
     debug!("Collecting artifacts");
-    let artifacts = Artifact::collect(config)?;
+    let mut artifacts = Artifact::execute(fs, plan, config)?;
+    let collected: HashSet<&PathBuf> = artifacts
+        .iter()
+        .flat_map(|artifact| std::iter::once(&artifact.relative_path).chain(&artifact.duplicates))
+        .filter(|path| planned.contains(*path))
+        .collect();
+    let skipped = planned.len() - collected.len() + special_files;
+    let budget = match config.max_tokens {
+        Some(max_tokens) => tokens::enforce_budget(&mut artifacts, max_tokens, config.budget_policy),
+        None => Ok(()),
+    };
+    if config.token_report {
+        stats::print_token_report(&artifacts, config.max_tokens);
+    }
+    if config.lang_stats {
+        stats::print_language_report(&artifacts);
+    }
+    budget?;
     debug!("Writing artifacts");
-    Artifact::write_all(&artifacts, &config.dest_dir)?;
-    Ok(())
+    writer::from_config(config).write(&artifacts)?;
+    if let Some(count) = config.top {
+        stats::print_largest(&artifacts, count);
+    }
+    Ok((artifacts, skipped))
+}
+
+/// Writes the delta of a re-export and logs what it holds, exiting the process on failure.
+///
+/// # Arguments
+///
+/// * `config` - The configuration the artifacts were written with.
+/// * `artifacts` - The written artifacts.
+/// * `previous` - The destination's manifest from before this run, if it had one.
+/// * `only_changed` - Whether to leave unchanged artifacts out of the delta.
+fn write_delta(config: &Config, artifacts: &[Artifact], previous: Option<&Manifest>, only_changed: bool) {
+    if only_changed && previous.is_none() {
+        info!("{} has no previous manifest, so every artifact is part of the delta", config.dest_dir.display());
+    }
+    match delta::write(&config.dest_dir, artifacts, previous, only_changed, config) {
+        Ok(summary) => {
+            info!(
+                "Wrote {} artifacts to {} ({} unchanged left out)",
+                summary.written,
+                config.dest_dir.join(delta::DELTA_DIR).display(),
+                summary.unchanged
+            );
+            if !summary.removed.is_empty() {
+                info!("{} files are no longer collected: {}", summary.removed.len(), summary.removed.join(", "));
+            }
+        }
+        Err(e) => {
+            error!("Error while writing the delta: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Writes the JSON summary of a run, exiting the process on failure.
+///
+/// # Arguments
+///
+/// * `config` - The configuration the artifacts were written with.
+/// * `path` - The file to write the summary to.
+/// * `artifacts` - The written artifacts.
+/// * `skipped` - The number of planned files that were not collected.
+fn write_summary(config: &Config, path: &Path, artifacts: &[Artifact], skipped: usize) {
+    let summary = runs::RunSummary {
+        run_id: config.run_id.clone(),
+        destination: open::output_path(config),
+        written: artifacts.len(),
+        skipped,
+        tokens: tokens::total(artifacts),
+    };
+    match summary.write(path) {
+        Ok(()) => debug!("Wrote the run summary to {}", path.display()),
+        Err(e) => {
+            error!("Failed to write the run summary to {}: {}", path.display(), e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Logs how the token total changed since the last run into the same destination.
+///
+/// This run's totals are remembered in the state file for the next run to compare against.
+///
+/// # Arguments
+///
+/// * `config` - The configuration the run wrote with.
+/// * `record` - The token totals of the run.
+fn report_token_delta(config: &Config, record: TokenRecord) {
+    let dest = config.single_file.as_ref().or(config.output_archive.as_ref()).unwrap_or(&config.dest_dir);
+    let result = State::load().and_then(|mut state| {
+        let previous = state.remember_tokens(dest, record.clone());
+        state.save()?;
+        Ok(previous)
+    });
+    match result {
+        Ok(Some(previous)) => info!("{}", record.delta_since(&previous)),
+        Ok(None) => debug!("No earlier run into {} to compare token totals with", dest.display()),
+        Err(e) => warn!("Failed to remember the token totals of this run: {:#}", e),
+    }
+}
+
+/// Returns the socket given with `--socket`, or the default socket for the source directory.
+///
+/// # Arguments
+///
+/// * `socket` - The socket given on the command line, if any.
+/// * `source_dir` - The source directory the daemon serves.
+///
+/// # Returns
+///
+/// The socket path. Exits the process if there is no default socket path.
+fn daemon_socket(socket: Option<&Path>, source_dir: &Path) -> PathBuf {
+    match socket.map(Path::to_path_buf).or_else(|| daemon::socket_path(source_dir)) {
+        Some(socket) => socket,
+        None => {
+            error!("Could not determine the daemon socket for {}; pass --socket", source_dir.display());
+            process::exit(1);
+        }
+    }
+}
+
+/// Resolves a `--preset` specification, replacing `auto` with the detected preset.
+///
+/// # Arguments
+///
+/// * `spec` - The preset names, separated by `,` or `+`.
+/// * `source_dir` - The source directory to detect the project type of.
+///
+/// # Returns
+///
+/// The preset names joined with `+`, or `None` if nothing is left to apply.
+fn resolve_presets(spec: &str, source_dir: &Path) -> Option<String> {
+    let mut names = presets::split_presets(spec);
+    if let Some(auto) = names.iter().position(|&name| name == detect::AUTO_PRESET) {
+        match detect::detect_preset(source_dir) {
+            Some(detection) => {
+                info!("Detected a {} project from {}", detection.preset, detection.marker);
+                if !detection.also_matched.is_empty() {
+                    info!("Also matched: {}; pass --preset to choose one of them instead", detection.also_matched.join(", "));
+                }
+                names[auto] = detection.preset;
+            }
+            None => {
+                warn!("Could not detect the project type of {}; continuing without a detected preset", source_dir.display());
+                names.remove(auto);
+            }
+        }
+    }
+    (!names.is_empty()).then(|| names.join("+"))
+}
+
+/// Collects each preset of a `--matrix` run into its own destination.
+///
+/// The presets are collected in parallel over one shared walk of the source, and a
+/// failing preset does not stop the others.
+///
+/// # Arguments
+///
+/// * `fs` - The filesystem to collect from.
+/// * `config` - The configuration options the presets are applied to.
+/// * `presets` - The names of the presets to collect.
+///
+/// # Returns
+///
+/// `true` if any preset failed.
+fn collect_matrix(fs: &dyn FileSystem, config: &Config, presets: &[String]) -> bool {
+    let entries: Vec<(String, Config)> = match presets
+        .iter()
+        .map(|preset| config.matrix_entry(preset).map(|entry| (preset.clone(), entry)))
+        .collect()
+    {
+        Ok(entries) => entries,
+        Err(e) => {
+            error!("Failed to apply preset: {}", e);
+            return true;
+        }
+    };
+    for (i, (preset, entry)) in entries.iter().enumerate() {
+        if let Some((other, _)) = entries[..i].iter().find(|(_, other)| other.dest_dir == entry.dest_dir) {
+            error!("Presets {} and {} would both write to {}", other, preset, entry.dest_dir.display());
+            return true;
+        }
+    }
+
+    info!("Collecting {} presets from {}", entries.len(), config.source_dir.display());
+    let shared = SharedWalk::new(fs);
+    let results: Vec<Result<TokenRecord, String>> = entries
+        .par_iter()
+        .map(|(preset, entry)| {
+            info!("Collecting preset {} into {}", preset, entry.dest_dir.display());
+            collect_and_process_artifacts(&shared, entry)
+                .map(|(artifacts, _)| TokenRecord::from_artifacts(&artifacts))
+                .map_err(|e| e.to_string())
+        })
+        .collect();
+
+    let mut failed = false;
+    for ((preset, entry), result) in entries.iter().zip(results) {
+        match result {
+            Ok(record) => {
+                info!("Preset {} written to {}", preset, entry.dest_dir.display());
+                report_token_delta(entry, record);
+            }
+            Err(e) => {
+                error!("Error during file preparation for preset {}: {}", preset, e);
+                failed = true;
+            }
+        }
+    }
+    failed
+}
+
+/// Prints the files a collection would produce, with their new names and sizes.
+///
+/// # Arguments
+///
+/// * `plan` - The collection plan to print.
+fn print_plan(plan: &CollectPlan) {
+    let mut total = 0;
+    for planned in &plan.files {
+        println!("{:>10}  {} -> {}", preflight::format_bytes(planned.size), planned.relative_path.display(), planned.new_filename);
+        total += planned.size;
+    }
+    for tree in &plan.locale_trees {
+        let files: usize = tree.locales.values().map(Vec::len).sum();
+        println!("{:>10}  {} -> key listing of {} translation files", "-", tree.root.display(), files);
+    }
+    for stub in &plan.submodule_stubs {
+        let size = stub.content.len() as u64;
+        println!("{:>10}  {} -> {}", preflight::format_bytes(size), stub.relative_path.display(), stub.new_filename);
+        total += size;
+    }
+    println!(
+        "{} files, {} (dry run, nothing written)",
+        plan.files.len() + plan.submodule_stubs.len() + plan.locale_trees.len(),
+        preflight::format_bytes(total)
+    );
+}
+
+/// Verifies the checksum headers of all artifacts in the destination directory.
+///
+/// # Arguments
+///
+/// * `config` - The configuration options.
+///
+/// # Returns
+///
+/// Returns `Result<bool, Box<dyn std::error::Error>>` containing `true` if every artifact
+/// verified successfully, or `false` if any artifact is missing its header or was modified.
+fn verify_artifacts(config: &Config) -> Result<bool, Box<dyn std::error::Error>> {
+    info!("Verifying artifacts in {}", config.dest_dir.display());
+    let failures = checksum::verify_dir(&config.dest_dir)?;
+    for (path, e) in &failures {
+        error!("{}: {}", path.display(), e);
+    }
+    Ok(failures.is_empty())
 }
\ No newline at end of file
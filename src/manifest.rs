@@ -0,0 +1,260 @@
+use std::io;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::artifact::{Artifact, Sink};
+use crate::chunk::Part;
+use crate::config::{BinaryMode, OutputFormat};
+use crate::project::LanguageShare;
+use crate::redact::Redaction;
+use crate::vfs::FileSystem;
+
+/// Name of the manifest file written into every destination directory.
+pub const MANIFEST_FILE: &str = "MANIFEST.json";
+
+/// Name of the Markdown rendering of the manifest written with `--manifest-markdown`.
+pub const MANIFEST_MARKDOWN_FILE: &str = "MANIFEST.md";
+
+/// How the Markdown manifest starts, which identifies it as written by rustifacts.
+const MARKDOWN_PREAMBLE: &str = "# Manifest\n\nWritten by ";
+
+/// Describes the artifacts written by a rustifacts run.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Manifest {
+    pub tool: String,
+    pub version: String,
+    pub run_id: String,
+    /// The format the artifacts were rendered in, which `restore` undoes.
+    #[serde(default)]
+    pub format: OutputFormat,
+    pub artifacts: Vec<ManifestEntry>,
+    /// Files written by earlier runs that are still in the directory but no longer collected.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stale: Vec<String>,
+    /// The language breakdown of the artifacts, recorded with `--lang-stats`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub languages: Vec<LanguageShare>,
+}
+
+/// Maps a written artifact back to the file it was created from.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ManifestEntry {
+    pub original_path: String,
+    pub new_filename: String,
+    /// The size of the artifact's content in bytes.
+    #[serde(default)]
+    pub size: u64,
+    /// The number of lines in the artifact's content.
+    #[serde(default)]
+    pub lines: usize,
+    /// The short SHA-256 hash of the artifact's content.
+    #[serde(default)]
+    pub checksum: String,
+    /// Other original paths with the same content, which were not written separately.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub duplicates: Vec<String>,
+    /// Credentials replaced with `[REDACTED]` in the written artifact.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redactions: Vec<Redaction>,
+    /// How the artifact stands in for a binary source file, if it was one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub binary: Option<BinaryMode>,
+    /// The encoding the source file was transcoded to UTF-8 from, if it was not UTF-8.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    /// Which part of the source file the artifact holds, if the file was split.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub part: Option<Part>,
+}
+
+impl Manifest {
+    /// Builds a manifest describing the given artifacts.
+    ///
+    /// # Arguments
+    ///
+    /// * `artifacts` - The artifacts being written.
+    /// * `run_id` - The ID of the current run.
+    /// * `format` - The format the artifacts are rendered in.
+    ///
+    /// # Returns
+    ///
+    /// A new `Manifest` with one entry per artifact.
+    pub fn from_artifacts(artifacts: &[Artifact], run_id: &str, format: OutputFormat) -> Self {
+        Self {
+            tool: env!("CARGO_PKG_NAME").to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            run_id: run_id.to_string(),
+            format,
+            artifacts: artifacts
+                .iter()
+                .map(|artifact| ManifestEntry {
+                    original_path: artifact.relative_path.to_string_lossy().into_owned(),
+                    new_filename: artifact.new_filename.clone(),
+                    size: artifact.content.len() as u64,
+                    lines: artifact.content.lines().count(),
+                    checksum: artifact.checksum.clone(),
+                    duplicates: artifact
+                        .duplicates
+                        .iter()
+                        .map(|path| path.to_string_lossy().into_owned())
+                        .collect(),
+                    redactions: artifact.redactions.clone(),
+                    binary: artifact.binary,
+                    encoding: artifact.encoding.clone(),
+                    part: artifact.part,
+                })
+                .collect(),
+            stale: Vec::new(),
+            languages: Vec::new(),
+        }
+    }
+
+    /// Reads the manifest in a directory, if there is a valid one.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The directory to read the manifest from.
+    ///
+    /// # Returns
+    ///
+    /// `Some(Manifest)` if the directory holds a readable manifest, `None` otherwise.
+    pub fn read_from(dir: &Path) -> Option<Self> {
+        let json = std::fs::read_to_string(dir.join(MANIFEST_FILE)).ok()?;
+        serde_json::from_str(&json).ok()
+    }
+
+    /// Returns the names of every file the manifest accounts for: its artifacts and any
+    /// stale files left by earlier runs.
+    pub fn written_files(&self) -> impl Iterator<Item = &str> {
+        self.artifacts
+            .iter()
+            .map(|entry| entry.new_filename.as_str())
+            .chain(self.stale.iter().map(String::as_str))
+    }
+
+    /// Writes the manifest into a sink as `MANIFEST.json`.
+    ///
+    /// # Arguments
+    ///
+    /// * `sink` - The sink the artifacts are written into.
+    ///
+    /// # Returns
+    ///
+    /// Returns `io::Result<()>` indicating success or failure of the write operation.
+    pub fn write(&self, sink: &mut dyn Sink) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        sink.put(MANIFEST_FILE, json.as_bytes())
+    }
+
+    /// Renders the manifest as a Markdown table, one row per artifact.
+    ///
+    /// # Returns
+    ///
+    /// A `String` containing the Markdown document.
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = format!(
+            "{}{} {}, run `{}`.\n\n",
+            MARKDOWN_PREAMBLE, self.tool, self.version, self.run_id
+        );
+        markdown.push_str("| Original path | Artifact | Bytes | Lines | Checksum |\n");
+        markdown.push_str("|---|---|---:|---:|---|\n");
+        let (mut size, mut lines) = (0, 0);
+        for entry in &self.artifacts {
+            let part = entry
+                .part
+                .map(|part| format!(" (part {} of {}, lines {}-{})", part.index, part.count, part.start_line, part.end_line))
+                .unwrap_or_default();
+            markdown.push_str(&format!(
+                "| `{}` | `{}`{} | {} | {} | `{}` |\n",
+                table_cell(&entry.original_path), table_cell(&entry.new_filename), part, entry.size, entry.lines, entry.checksum
+            ));
+            for duplicate in &entry.duplicates {
+                markdown.push_str(&format!("| `{}` | `{}` (duplicate) | | | |\n", table_cell(duplicate), table_cell(&entry.new_filename)));
+            }
+            size += entry.size;
+            lines += entry.lines;
+        }
+        markdown.push_str(&format!("\n{} artifacts, {} bytes, {} lines.\n", self.artifacts.len(), size, lines));
+        if !self.languages.is_empty() {
+            let tokens: usize = self.languages.iter().map(|share| share.tokens).sum();
+            markdown.push_str("\n| Language | Files | Bytes | Tokens |\n");
+            markdown.push_str("|---|---:|---:|---:|\n");
+            for share in &self.languages {
+                markdown.push_str(&format!(
+                    "| {} | {} | {} ({}%) | {} ({}%) |\n",
+                    share.language,
+                    share.files,
+                    share.bytes,
+                    (share.bytes * 100).checked_div(size).unwrap_or(0),
+                    share.tokens,
+                    (share.tokens * 100).checked_div(tokens).unwrap_or(0),
+                ));
+            }
+        }
+        markdown
+    }
+
+    /// Writes the Markdown rendering of the manifest into a sink as `MANIFEST.md`.
+    ///
+    /// # Arguments
+    ///
+    /// * `sink` - The sink the artifacts are written into.
+    ///
+    /// # Returns
+    ///
+    /// Returns `io::Result<()>` indicating success or failure of the write operation.
+    pub fn write_markdown(&self, sink: &mut dyn Sink) -> io::Result<()> {
+        sink.put(MANIFEST_MARKDOWN_FILE, self.to_markdown().as_bytes())
+    }
+
+    /// Checks whether the file at a path is a Markdown manifest written by rustifacts.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file to check.
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if the file starts the way `to_markdown` renders it.
+    pub fn is_markdown_manifest(path: &Path) -> bool {
+        let preamble = format!("{}{} ", MARKDOWN_PREAMBLE, env!("CARGO_PKG_NAME"));
+        std::fs::read_to_string(path).is_ok_and(|text| text.starts_with(&preamble))
+    }
+
+    /// Checks whether a file is a manifest written by rustifacts.
+    ///
+    /// # Arguments
+    ///
+    /// * `fs` - The filesystem to read from.
+    /// * `path` - The path of the file to check.
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if the file is named like a manifest and was written by rustifacts.
+    pub fn is_manifest(fs: &dyn FileSystem, path: &Path) -> bool {
+        if !path.ends_with(MANIFEST_FILE) {
+            return false;
+        }
+        fs.read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str::<Self>(&json).ok())
+            .is_some_and(|manifest| manifest.tool == env!("CARGO_PKG_NAME"))
+    }
+
+    /// Checks whether a directory contains a rustifacts manifest.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The directory to check.
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if a manifest file is present, `false` otherwise.
+    pub fn exists_in(dir: &Path) -> bool {
+        dir.join(MANIFEST_FILE).is_file()
+    }
+}
+
+/// Escapes the pipes in a name so that it stays within one Markdown table cell.
+fn table_cell(name: &str) -> String {
+    name.replace('|', "\\|")
+}
@@ -0,0 +1,252 @@
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+use log::{debug, warn};
+use regex::{Captures, Regex};
+use crate::config::FrontMatterMode;
+use crate::vfs::{FileSystem, Metadata};
+
+/// Front-matter keys kept by `--front-matter summarize`.
+const SUMMARY_KEYS: [&str; 3] = ["title", "description", "summary"];
+
+/// How deeply included files may themselves include other files.
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// Checks whether a file is Markdown or MDX, judging by its extension.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file.
+///
+/// # Returns
+///
+/// Returns `true` for `.md`, `.markdown`, and `.mdx` files.
+pub fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ["md", "markdown", "mdx"].contains(&ext.to_lowercase().as_str()))
+}
+
+/// Strips or summarizes the YAML (`---`) or TOML (`+++`) front-matter block at the start of a document.
+///
+/// A summary keeps only the `title`, `description`, and `summary` keys (with any
+/// indented continuation lines) inside the original delimiters. A block without any of
+/// them is stripped.
+///
+/// # Arguments
+///
+/// * `content` - The document.
+/// * `mode` - What to do with the front matter.
+///
+/// # Returns
+///
+/// A `String` containing the document with its front matter processed. Documents
+/// without front matter are returned unchanged.
+///
+/// # Example
+///
+/// ```
+/// use rustifacts::config::FrontMatterMode;
+/// use rustifacts::markdown::process_front_matter;
+///
+/// let doc = "---\ntitle: Setup\nlayout: post\n---\n\n# Setup\n";
+/// assert_eq!(process_front_matter(doc, FrontMatterMode::Strip), "# Setup\n");
+/// assert_eq!(process_front_matter(doc, FrontMatterMode::Summarize), "---\ntitle: Setup\n---\n\n# Setup\n");
+/// ```
+pub fn process_front_matter(content: &str, mode: FrontMatterMode) -> String {
+    let Some((delimiter, block, body)) = split_front_matter(content).filter(|_| mode != FrontMatterMode::Keep) else {
+        return content.to_string();
+    };
+    let mut summary = String::new();
+    if mode == FrontMatterMode::Summarize {
+        let mut keeping = false;
+        for line in block.split_inclusive('\n') {
+            if !line.starts_with([' ', '\t']) {
+                keeping = SUMMARY_KEYS.iter().any(|key| {
+                    line.strip_prefix(key).is_some_and(|rest| rest.trim_start().starts_with([':', '=']))
+                });
+            }
+            if keeping {
+                summary.push_str(line);
+            }
+        }
+    }
+    if summary.is_empty() {
+        let body = body.strip_prefix("\r\n").or_else(|| body.strip_prefix('\n')).unwrap_or(body);
+        return body.to_string();
+    }
+    format!("{delimiter}\n{summary}{delimiter}\n{body}")
+}
+
+/// Splits a document into its front-matter delimiter, the block between the delimiters,
+/// and the body after the closing delimiter line.
+fn split_front_matter(content: &str) -> Option<(&'static str, &str, &str)> {
+    let delimiter = ["---", "+++"].into_iter().find(|delimiter| {
+        content.strip_prefix(delimiter).is_some_and(|rest| rest.starts_with('\n') || rest.starts_with("\r\n"))
+    })?;
+    let block_start = content.find('\n')? + 1;
+    let mut offset = block_start;
+    for line in content[block_start..].split_inclusive('\n') {
+        if line.trim_end() == delimiter {
+            return Some((delimiter, &content[block_start..offset], &content[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// Returns the include directive pattern, compiling it on first use.
+///
+/// It matches mdBook's `{{#include path}}` (with an optional `:start:end` line range)
+/// and Jekyll's `{% include_relative path %}`.
+fn include_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"\{\{#include\s+(?P<mdbook>[^}\s]+)\s*\}\}|\{%-?\s*include_relative\s+(?P<jekyll>[^%\s]+)\s*-?%\}")
+            .expect("include pattern is valid")
+    })
+}
+
+/// Replaces local include directives with the content of the files they name.
+///
+/// Included paths are relative to the including file and must stay inside the source
+/// directory. Included files are expanded in turn, up to a fixed depth. Directives that
+/// cannot be expanded (missing files, cycles, paths outside the source, mdBook anchors)
+/// are left in place with a warning.
+///
+/// Every file an include resolved to is listed alongside the result, whether or not it
+/// could be read, with its metadata from just before it was read, so that callers can
+/// tell when the expanded document would change.
+///
+/// # Arguments
+///
+/// * `fs` - The filesystem to read included files from.
+/// * `path` - The path of the document.
+/// * `content` - The document.
+/// * `source_dir` - The source directory that included files must lie in.
+///
+/// # Returns
+///
+/// A tuple of the document with its includes expanded and the files the includes
+/// resolved to, with their metadata if it could be read.
+pub fn expand_includes(
+    fs: &dyn FileSystem,
+    path: &Path,
+    content: &str,
+    source_dir: &Path,
+) -> (String, Vec<(PathBuf, Option<Metadata>)>) {
+    let Some(root) = normalize(source_dir) else {
+        return (content.to_string(), Vec::new());
+    };
+    let stack: Vec<PathBuf> = normalize(path).into_iter().collect();
+    let mut included = Vec::new();
+    let expanded = expand(fs, path, content, &root, &stack, &mut included);
+    (expanded, included)
+}
+
+fn expand(
+    fs: &dyn FileSystem,
+    path: &Path,
+    content: &str,
+    root: &Path,
+    stack: &[PathBuf],
+    included: &mut Vec<(PathBuf, Option<Metadata>)>,
+) -> String {
+    include_pattern()
+        .replace_all(content, |captures: &Captures| {
+            let target = captures.name("mdbook").or_else(|| captures.name("jekyll")).map_or("", |m| m.as_str());
+            let ranged = captures.name("mdbook").is_some();
+            match include(fs, path, target, ranged, root, stack, included) {
+                Ok(expanded) => {
+                    debug!("Expanded include of {} in {}", target, path.display());
+                    expanded
+                }
+                Err(reason) => {
+                    warn!("Not expanding include of {} in {}: {}", target, path.display(), reason);
+                    captures[0].to_string()
+                }
+            }
+        })
+        .into_owned()
+}
+
+/// Reads and expands one included file, listing it in `included` before it is read.
+fn include(
+    fs: &dyn FileSystem,
+    path: &Path,
+    target: &str,
+    ranged: bool,
+    root: &Path,
+    stack: &[PathBuf],
+    included: &mut Vec<(PathBuf, Option<Metadata>)>,
+) -> Result<String, String> {
+    let (file, range) = match target.split_once(':').filter(|_| ranged) {
+        Some((file, range)) => (file, Some(parse_range(range)?)),
+        None => (target, None),
+    };
+    if Path::new(file).is_absolute() {
+        return Err("absolute paths are not expanded".to_string());
+    }
+    let included_path = path
+        .parent()
+        .map(|parent| parent.join(file))
+        .and_then(|joined| normalize(&joined))
+        .filter(|normalized| normalized.starts_with(root))
+        .ok_or("the file is outside the source directory")?;
+    if stack.contains(&included_path) {
+        return Err("the file includes itself".to_string());
+    }
+    if stack.len() > MAX_INCLUDE_DEPTH {
+        return Err(format!("includes are nested more than {} levels deep", MAX_INCLUDE_DEPTH));
+    }
+
+    included.push((included_path.clone(), fs.metadata(&included_path).ok()));
+    let text = fs.read_to_string(&included_path).map_err(|e| e.to_string())?;
+    let text = match range {
+        Some((start, end)) => text
+            .lines()
+            .skip(start.saturating_sub(1))
+            .take(end.map_or(usize::MAX, |end| end.saturating_sub(start.saturating_sub(1))))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        None => text.strip_suffix('\n').unwrap_or(&text).to_string(),
+    };
+    let mut nested = stack.to_vec();
+    nested.push(included_path.clone());
+    Ok(expand(fs, &included_path, &text, root, &nested, included))
+}
+
+/// Parses an mdBook line range: `start:end`, `start:`, `:end`, or a single line `start`.
+fn parse_range(range: &str) -> Result<(usize, Option<usize>), String> {
+    let parse = |number: &str| -> Result<Option<usize>, String> {
+        if number.is_empty() {
+            return Ok(None);
+        }
+        number.parse().map(Some).map_err(|_| format!("anchor {:?} is not supported, only line ranges", range))
+    };
+    match range.split_once(':') {
+        Some((start, end)) => Ok((parse(start)?.unwrap_or(1), parse(end)?)),
+        None => {
+            let line = parse(range)?.unwrap_or(1);
+            Ok((line, Some(line)))
+        }
+    }
+}
+
+/// Resolves `.` and `..` components without touching the filesystem.
+///
+/// Returns `None` if a `..` would climb above the start of the path.
+fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            }
+            other => normalized.push(other),
+        }
+    }
+    Some(normalized)
+}
@@ -0,0 +1,106 @@
+use std::path::Path;
+use serde::Deserialize;
+
+/// A Jupyter notebook, reduced to the parts that are converted.
+#[derive(Deserialize)]
+struct Notebook {
+    cells: Vec<Cell>,
+}
+
+#[derive(Deserialize)]
+struct Cell {
+    cell_type: String,
+    #[serde(default)]
+    source: Source,
+}
+
+/// Cell source, which notebooks store either as one string or as a list of lines.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Source {
+    Text(String),
+    Lines(Vec<String>),
+}
+
+impl Default for Source {
+    fn default() -> Self {
+        Source::Text(String::new())
+    }
+}
+
+impl Source {
+    fn text(&self) -> String {
+        match self {
+            Source::Text(text) => text.clone(),
+            Source::Lines(lines) => lines.concat(),
+        }
+    }
+}
+
+/// Checks whether a file is a Jupyter notebook, judging by its extension.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file.
+///
+/// # Returns
+///
+/// Returns `true` for `.ipynb` files.
+pub fn is_notebook(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("ipynb"))
+}
+
+/// Converts a Jupyter notebook into a script in the percent format used by Jupytext and VS Code.
+///
+/// Each cell starts with a `# %%` marker. Code cells are written as they are, while
+/// Markdown and raw cells are commented out with `# `. Outputs, execution counts, and
+/// metadata are dropped.
+///
+/// # Arguments
+///
+/// * `content` - The notebook's JSON.
+///
+/// # Returns
+///
+/// Returns `Result<String, serde_json::Error>` containing the script, or an error if the
+/// content is not a notebook in nbformat 4.
+///
+/// # Example
+///
+/// ```
+/// use rustifacts::notebook::to_script;
+///
+/// let notebook = r##"{"cells": [
+///     {"cell_type": "markdown", "metadata": {}, "source": ["# Load\n", "Read the data."]},
+///     {"cell_type": "code", "metadata": {}, "execution_count": 1, "outputs": [], "source": "import pandas as pd"}
+/// ], "metadata": {}, "nbformat": 4, "nbformat_minor": 5}"##;
+/// assert_eq!(
+///     to_script(notebook).unwrap(),
+///     "# %% [markdown]\n# # Load\n# Read the data.\n\n# %%\nimport pandas as pd\n"
+/// );
+/// ```
+pub fn to_script(content: &str) -> Result<String, serde_json::Error> {
+    let notebook: Notebook = serde_json::from_str(content)?;
+    let mut script = String::new();
+    for cell in &notebook.cells {
+        if !script.is_empty() {
+            script.push('\n');
+        }
+        let source = cell.source.text();
+        if cell.cell_type == "code" {
+            script.push_str("# %%\n");
+            for line in source.lines() {
+                script.push_str(line);
+                script.push('\n');
+            }
+        } else {
+            script.push_str(&format!("# %% [{}]\n", cell.cell_type));
+            for line in source.lines() {
+                script.push_str(if line.is_empty() { "#" } else { "# " });
+                script.push_str(line);
+                script.push('\n');
+            }
+        }
+    }
+    Ok(script)
+}
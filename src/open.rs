@@ -0,0 +1,111 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use log::{debug, info};
+use thiserror::Error;
+use crate::config::Config;
+use crate::writer::DirectoryWriter;
+
+/// Custom error type for opening the output of a run.
+#[derive(Error, Debug)]
+pub enum OpenError {
+    #[error("Could not run `{command}`: {source}")]
+    Spawn { command: String, source: io::Error },
+    #[error("`{command}` failed with {status}")]
+    Failed { command: String, status: std::process::ExitStatus },
+}
+
+/// Returns what a run wrote its artifacts to: the `--single-file` bundle, the
+/// `--output-archive` archive, or the destination directory.
+///
+/// # Arguments
+///
+/// * `config` - The configuration options.
+///
+/// # Returns
+///
+/// A `PathBuf` containing the path of the output.
+pub fn output_path(config: &Config) -> PathBuf {
+    match (&config.single_file, &config.output_archive) {
+        (Some(single_file), _) => single_file.clone(),
+        (None, Some(output_archive)) => output_archive.clone(),
+        (None, None) => DirectoryWriter::from_config(config).output_dir(),
+    }
+}
+
+/// Opens a path in a viewer.
+///
+/// Without a command, the path is opened with the default application for it, or the
+/// file manager for a directory: `open` on macOS, `start` on Windows, and `xdg-open`
+/// elsewhere. A command is run through the shell with the quoted path appended, and is
+/// waited for, so terminal viewers such as `less` work too.
+///
+/// # Arguments
+///
+/// * `path` - The file or directory to open.
+/// * `with` - The command to open it with, such as `code`, if not the default.
+///
+/// # Returns
+///
+/// Returns `Result<(), OpenError>` indicating whether the viewer was started and exited
+/// successfully, or an `OpenError` if it could not be run or failed.
+pub fn open(path: &Path, with: Option<&str>) -> Result<(), OpenError> {
+    let mut command = match with {
+        Some(with) => shell(&format!("{} {}", with, quote(path))),
+        None if cfg!(target_os = "macos") => {
+            let mut command = Command::new("open");
+            command.arg(path);
+            command
+        }
+        None if cfg!(windows) => {
+            let mut command = Command::new("cmd");
+            // The empty argument is the window title `start` would otherwise take the path for.
+            command.args(["/C", "start", ""]).arg(path);
+            command
+        }
+        None => {
+            let mut command = Command::new("xdg-open");
+            command.arg(path);
+            command
+        }
+    };
+    let description = match with {
+        Some(with) => with,
+        None if cfg!(target_os = "macos") => "open",
+        None if cfg!(windows) => "start",
+        None => "xdg-open",
+    }
+    .to_string();
+    debug!("Opening {} with {}", path.display(), description);
+    let status = command.status().map_err(|source| OpenError::Spawn { command: description.clone(), source })?;
+    if !status.success() {
+        return Err(OpenError::Failed { command: description, status });
+    }
+    info!("Opened {}", path.display());
+    Ok(())
+}
+
+/// Builds the shell invocation of a command line.
+fn shell(command_line: &str) -> Command {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell.arg(command_line);
+    shell
+}
+
+/// Quotes a path for the shell `open` runs commands with.
+fn quote(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    if cfg!(windows) {
+        format!("\"{}\"", path)
+    } else {
+        format!("'{}'", path.replace('\'', "'\\''"))
+    }
+}
@@ -0,0 +1,61 @@
+use std::fs;
+use thiserror::Error;
+
+/// The user database that user names are looked up in.
+const PASSWD_FILE: &str = "/etc/passwd";
+
+/// Custom error type for resolving file owners.
+#[derive(Error, Debug)]
+pub enum OwnerError {
+    #[error("Unknown user {0}: not a numeric user ID and not listed in /etc/passwd")]
+    UnknownUser(String),
+    #[error("Filtering files by owner is only supported on Unix")]
+    Unsupported,
+}
+
+/// Resolves a comma-separated list of user names and numeric user IDs to user IDs.
+///
+/// Numeric entries are used as they are, and names are looked up in `/etc/passwd`.
+///
+/// # Arguments
+///
+/// * `users` - The users, separated by commas.
+///
+/// # Returns
+///
+/// Returns `Result<Vec<u32>, OwnerError>` containing the user IDs, or an error if a name
+/// is not known or the platform has no file owners.
+///
+/// # Example
+///
+/// ```
+/// use rustifacts::owner::resolve_users;
+///
+/// assert_eq!(resolve_users("1000, 0").unwrap(), vec![1000, 0]);
+/// assert!(resolve_users("").unwrap().is_empty());
+/// ```
+pub fn resolve_users(users: &str) -> Result<Vec<u32>, OwnerError> {
+    let users: Vec<&str> = users.split(',').map(str::trim).filter(|user| !user.is_empty()).collect();
+    if users.is_empty() {
+        return Ok(Vec::new());
+    }
+    if !cfg!(unix) {
+        return Err(OwnerError::Unsupported);
+    }
+    let passwd = fs::read_to_string(PASSWD_FILE).unwrap_or_default();
+    users
+        .into_iter()
+        .map(|user| user.parse().ok().or_else(|| lookup(&passwd, user)).ok_or_else(|| OwnerError::UnknownUser(user.to_string())))
+        .collect()
+}
+
+/// Finds the user ID of a user name in the contents of `/etc/passwd`.
+fn lookup(passwd: &str, name: &str) -> Option<u32> {
+    passwd.lines().find_map(|line| {
+        let mut fields = line.split(':');
+        if fields.next()? != name {
+            return None;
+        }
+        fields.nth(1)?.parse().ok()
+    })
+}
@@ -0,0 +1,181 @@
+use std::env;
+use std::path::{Component, Path, PathBuf, Prefix};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ExpandError {
+    #[error("Environment variable {0} is not set")]
+    UnsetVariable(String),
+    #[error("Cannot expand ~ because HOME is not set")]
+    NoHome,
+    #[error("Unclosed ${{ in {0}")]
+    Unclosed(String),
+}
+
+/// Normalizes a relative directory path from the command line or a configuration file.
+///
+/// Backslashes become forward slashes, which every platform accepts as a separator, so
+/// that `src\api` written on Windows and `src/api` written on macOS name the same
+/// directory everywhere. A leading `./` and trailing slashes are removed.
+///
+/// # Arguments
+///
+/// * `dir` - The directory path as written.
+///
+/// # Returns
+///
+/// A `String` containing the normalized path.
+///
+/// # Example
+///
+/// ```
+/// use rustifacts::paths::normalize_separators;
+///
+/// assert_eq!(normalize_separators(r".\app\build\"), "app/build");
+/// assert_eq!(normalize_separators("app/build"), "app/build");
+/// ```
+pub fn normalize_separators(dir: &str) -> String {
+    let dir = dir.trim().replace('\\', "/");
+    let dir = dir.trim_start_matches("./").trim_end_matches('/');
+    if dir.is_empty() { ".".to_string() } else { dir.to_string() }
+}
+
+/// Normalizes the separators in a glob pattern.
+///
+/// Every backslash is treated as a Windows separator and becomes `/`, since `src\*.rs`
+/// is far more common in configuration files than an escaped metacharacter. A literal
+/// metacharacter can still be matched with a character class such as `[*]`.
+///
+/// # Arguments
+///
+/// * `pattern` - The glob pattern as written.
+///
+/// # Returns
+///
+/// A `String` containing the normalized pattern.
+///
+/// # Example
+///
+/// ```
+/// use rustifacts::paths::normalize_glob;
+///
+/// assert_eq!(normalize_glob(r"src\**\*_test.go"), "src/**/*_test.go");
+/// assert_eq!(normalize_glob("src/**"), "src/**");
+/// ```
+pub fn normalize_glob(pattern: &str) -> String {
+    pattern.replace('\\', "/")
+}
+
+/// Gives Windows drive-letter paths one spelling, so that they compare equal.
+///
+/// The drive letter is upper-cased and the verbatim `\\?\` prefix that canonicalization
+/// adds is dropped, so `c:\work`, `C:\work`, and `\\?\C:\work` all become `C:\work`.
+/// Paths without a drive letter, including every path on other platforms, are returned
+/// unchanged.
+///
+/// # Arguments
+///
+/// * `path` - The path to normalize.
+///
+/// # Returns
+///
+/// A `PathBuf` containing the normalized path.
+pub fn normalize_drive(path: &Path) -> PathBuf {
+    let mut components = path.components();
+    let drive = match components.next() {
+        Some(Component::Prefix(prefix)) => match prefix.kind() {
+            Prefix::Disk(letter) | Prefix::VerbatimDisk(letter) => letter.to_ascii_uppercase() as char,
+            _ => return path.to_path_buf(),
+        },
+        _ => return path.to_path_buf(),
+    };
+    let mut normalized = PathBuf::from(format!("{}:", drive));
+    normalized.extend(components);
+    normalized
+}
+
+/// Expands a leading `~` and environment variables in a path from a configuration file.
+///
+/// `~` at the start of the path, alone or followed by a separator, becomes the home
+/// directory. Variables are written `$NAME` or `${NAME}`, and `$$` stands for a literal
+/// `$`. A `$` that is not followed by a name is kept as it is.
+///
+/// # Arguments
+///
+/// * `path` - The path as written.
+///
+/// # Returns
+///
+/// Returns `Result<String, ExpandError>` containing the expanded path, or an error if a
+/// variable or the home directory is not set.
+///
+/// # Example
+///
+/// ```
+/// use rustifacts::paths::expand;
+///
+/// std::env::set_var("PROJECT", "shop");
+/// assert_eq!(expand("projects/$PROJECT/src").unwrap(), "projects/shop/src");
+/// assert_eq!(expand("${PROJECT}_v2").unwrap(), "shop_v2");
+/// assert_eq!(expand("cost$$").unwrap(), "cost$");
+/// assert!(expand("$RUSTIFACTS_SURELY_UNSET").is_err());
+/// ```
+pub fn expand(path: &str) -> Result<String, ExpandError> {
+    let mut expanded = String::with_capacity(path.len());
+    let mut rest = path;
+    if let Some(after) = rest.strip_prefix('~') {
+        if after.is_empty() || after.starts_with(['/', '\\']) {
+            let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")).ok_or(ExpandError::NoHome)?;
+            expanded.push_str(&home.to_string_lossy());
+            rest = after;
+        }
+    }
+    while let Some(index) = rest.find('$') {
+        expanded.push_str(&rest[..index]);
+        let after = &rest[index + 1..];
+        let (name, remainder) = if let Some(braced) = after.strip_prefix('{') {
+            let end = braced.find('}').ok_or_else(|| ExpandError::Unclosed(path.to_string()))?;
+            (&braced[..end], &braced[end + 1..])
+        } else if let Some(remainder) = after.strip_prefix('$') {
+            expanded.push('$');
+            rest = remainder;
+            continue;
+        } else {
+            let end = after.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(after.len());
+            (&after[..end], &after[end..])
+        };
+        if name.is_empty() {
+            expanded.push('$');
+            rest = after;
+            continue;
+        }
+        let value = env::var_os(name).ok_or_else(|| ExpandError::UnsetVariable(name.to_string()))?;
+        expanded.push_str(&value.to_string_lossy());
+        rest = remainder;
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Expands a path from a configuration file and resolves it against the file's directory.
+///
+/// Absolute paths, including those that start with `~` or a variable holding an absolute
+/// path, are used as they are.
+///
+/// # Arguments
+///
+/// * `path` - The path as written in the file.
+/// * `dir` - The directory containing the configuration file.
+///
+/// # Returns
+///
+/// Returns `Result<String, ExpandError>` containing the resolved path.
+pub fn resolve_config_path(path: &str, dir: &Path) -> Result<String, ExpandError> {
+    let expanded = expand(path)?;
+    let expanded_path = Path::new(&expanded);
+    if expanded_path.is_absolute() || dir.as_os_str().is_empty() {
+        return Ok(expanded);
+    }
+    let resolved: PathBuf = dir.join(expanded_path).components().collect();
+    Ok(resolved.to_string_lossy().into_owned())
+}
@@ -0,0 +1,317 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use crate::transform::{TransformError, Transformer};
+use crate::vfs::Metadata;
+
+/// The name of the export deciding whether a file is collected.
+pub const FILTER_EXPORT: &str = "should_include";
+/// The name of the export rewriting a file's content.
+pub const TRANSFORM_EXPORT: &str = "transform";
+/// The instructions, as counted by wasmtime's fuel, a single call may execute.
+pub const FUEL_PER_CALL: u64 = 2_000_000_000;
+/// The bytes of linear memory a single call may use.
+pub const MEMORY_LIMIT: usize = 256 * 1024 * 1024;
+
+/// Custom error type for WebAssembly plugins.
+#[derive(Error, Debug)]
+pub enum PluginError {
+    #[error("Cannot load plugin {0}: rustifacts was built without WASM plugin support (rebuild with --features wasm-plugins)")]
+    Unsupported(PathBuf),
+    #[error("Failed to read plugin {path}: {source}")]
+    Read { path: PathBuf, source: io::Error },
+    #[error("Invalid plugin {path}: {message}")]
+    Invalid { path: PathBuf, message: String },
+    #[error("Plugin {path} failed in {function} for {file}: {message}")]
+    Call { path: PathBuf, function: &'static str, file: String, message: String },
+    #[error("Plugin {path} returned content for {file} that is not valid UTF-8")]
+    NotUtf8 { path: PathBuf, file: String },
+}
+
+/// A compiled WebAssembly plugin, given with `--plugin` as a binary `.wasm` or `.wat` text file.
+///
+/// A plugin may export either or both of:
+///
+/// * `should_include(path_ptr: i32, path_len: i32, meta_ptr: i32, meta_len: i32) -> i32`,
+///   called for every file that passed the other filters. Returning `0` leaves the file out.
+///   The metadata is a JSON object such as `{"size":1024,"modified":1700000000}`, where
+///   `modified` is in seconds since the Unix epoch, or `null` if it is unknown.
+/// * `transform(path_ptr: i32, path_len: i32, content_ptr: i32, content_len: i32) -> i64`,
+///   called with the content of every collected file. It returns the new content's pointer
+///   in the upper 32 bits and its length in the lower 32 bits; the content must be UTF-8.
+///
+/// Paths are relative to the source directory, with `/` separators. Strings are copied as
+/// UTF-8 into the module's exported `memory`, at pointers from its exported
+/// `alloc(len: i32) -> i32`.
+///
+/// Plugins cannot import anything from the host, so they have no access to the filesystem,
+/// the network, or the clock, and each call is limited in instructions and memory. Every
+/// call runs in a fresh instance, so no state carries over from one file to the next.
+///
+/// Compiling a module is expensive, so plugins are loaded through [`load`], which keeps
+/// each one until its file changes. Plugins can only be loaded when Rustifacts is built
+/// with the `wasm-plugins` feature.
+pub struct Plugin {
+    path: PathBuf,
+    has_filter: bool,
+    has_transform: bool,
+    #[cfg(feature = "wasm-plugins")]
+    engine: wasmtime::Engine,
+    #[cfg(feature = "wasm-plugins")]
+    module: wasmtime::Module,
+}
+
+/// A compiled plugin, along with the modification time of its file when it was compiled.
+type Loaded = (Option<SystemTime>, Arc<Plugin>);
+
+/// Loads a plugin, compiling it unless it was already compiled since the file last changed.
+///
+/// # Arguments
+///
+/// * `path` - The path of the `.wasm` or `.wat` file.
+///
+/// # Returns
+///
+/// Returns `Result<Arc<Plugin>, PluginError>` containing the plugin, or a `PluginError` if
+/// the file cannot be read, is not a valid plugin, or plugin support was not built in.
+pub fn load(path: &Path) -> Result<Arc<Plugin>, PluginError> {
+    static PLUGINS: OnceLock<Mutex<HashMap<PathBuf, Loaded>>> = OnceLock::new();
+    let modified = fs::metadata(path)
+        .map_err(|source| PluginError::Read { path: path.to_path_buf(), source })?
+        .modified()
+        .ok();
+    let mut plugins = PLUGINS.get_or_init(Default::default).lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some((loaded, plugin)) = plugins.get(path) {
+        if *loaded == modified {
+            return Ok(Arc::clone(plugin));
+        }
+    }
+    let plugin = Arc::new(Plugin::compile(path)?);
+    plugins.insert(path.to_path_buf(), (modified, Arc::clone(&plugin)));
+    Ok(plugin)
+}
+
+/// Describes the contents of plugin files, so that cached artifacts are rebuilt when a
+/// plugin changes.
+///
+/// # Arguments
+///
+/// * `paths` - The paths of the plugins.
+///
+/// # Returns
+///
+/// A `String` holding a digest of each file, or `missing` for files that cannot be read.
+pub fn fingerprint(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| match fs::read(path) {
+            Ok(bytes) => format!("{:x}", Sha256::digest(bytes)),
+            Err(_) => "missing".to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+impl Plugin {
+    /// Returns the path the plugin was loaded from.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns whether the plugin exports `should_include`.
+    pub fn has_filter(&self) -> bool {
+        self.has_filter
+    }
+
+    /// Returns whether the plugin exports `transform`.
+    pub fn has_transform(&self) -> bool {
+        self.has_transform
+    }
+
+    #[cfg(not(feature = "wasm-plugins"))]
+    fn compile(path: &Path) -> Result<Self, PluginError> {
+        Err(PluginError::Unsupported(path.to_path_buf()))
+    }
+
+    /// Asks the plugin whether to collect a file, including it if the plugin has no filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `relative_path` - The path of the file, relative to the source directory.
+    /// * `metadata` - The metadata of the file.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<bool, PluginError>` containing whether to collect the file, or a
+    /// `PluginError` if the call trapped, ran out of fuel, or exceeded the memory limit.
+    pub fn should_include(&self, relative_path: &Path, metadata: &Metadata) -> Result<bool, PluginError> {
+        if !self.has_filter {
+            return Ok(true);
+        }
+        let modified = metadata.modified.and_then(|time| time.duration_since(UNIX_EPOCH).ok()).map(|age| age.as_secs());
+        let metadata = serde_json::json!({ "size": metadata.len, "modified": modified }).to_string();
+        self.call_filter(&portable(relative_path), metadata.as_bytes())
+    }
+
+    /// Runs a file's content through the plugin, returning it unchanged if the plugin has
+    /// no transform.
+    ///
+    /// # Arguments
+    ///
+    /// * `relative_path` - The path of the file, relative to the source directory.
+    /// * `content` - The content of the file.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<String, PluginError>` containing the new content, or a `PluginError`
+    /// if the call failed or returned content that is not UTF-8.
+    pub fn transform(&self, relative_path: &Path, content: &str) -> Result<String, PluginError> {
+        if !self.has_transform {
+            return Ok(content.to_string());
+        }
+        let file = portable(relative_path);
+        let bytes = self.call_transform(&file, content.as_bytes())?;
+        String::from_utf8(bytes).map_err(|_| PluginError::NotUtf8 { path: self.path.clone(), file })
+    }
+
+    #[cfg(not(feature = "wasm-plugins"))]
+    fn call_filter(&self, _file: &str, _metadata: &[u8]) -> Result<bool, PluginError> {
+        Err(PluginError::Unsupported(self.path.clone()))
+    }
+
+    #[cfg(not(feature = "wasm-plugins"))]
+    fn call_transform(&self, _file: &str, _content: &[u8]) -> Result<Vec<u8>, PluginError> {
+        Err(PluginError::Unsupported(self.path.clone()))
+    }
+}
+
+#[cfg(feature = "wasm-plugins")]
+impl Plugin {
+    /// Compiles a module, checking that it imports nothing and exports what plugins need.
+    fn compile(path: &Path) -> Result<Self, PluginError> {
+        use wasmtime::{Config, Engine, ExternType, Module};
+
+        let invalid = |message: String| PluginError::Invalid { path: path.to_path_buf(), message };
+        let engine = Engine::new(Config::new().consume_fuel(true)).map_err(|e| invalid(format!("{:#}", e)))?;
+        let bytes = fs::read(path).map_err(|source| PluginError::Read { path: path.to_path_buf(), source })?;
+        let module = Module::new(&engine, bytes).map_err(|e| invalid(format!("{:#}", e)))?;
+
+        if let Some(import) = module.imports().next() {
+            return Err(invalid(format!(
+                "it imports {}::{}, but plugins cannot import anything from the host",
+                import.module(),
+                import.name()
+            )));
+        }
+        let is_func = |name: &str| matches!(module.get_export(name), Some(ExternType::Func(_)));
+        if !matches!(module.get_export("memory"), Some(ExternType::Memory(_))) || !is_func("alloc") {
+            return Err(invalid("it must export `memory` and `alloc`".to_string()));
+        }
+        let (has_filter, has_transform) = (is_func(FILTER_EXPORT), is_func(TRANSFORM_EXPORT));
+        if !has_filter && !has_transform {
+            return Err(invalid(format!("it exports neither `{}` nor `{}`", FILTER_EXPORT, TRANSFORM_EXPORT)));
+        }
+        log::debug!("Loaded plugin {} (filter: {}, transform: {})", path.display(), has_filter, has_transform);
+        Ok(Self { path: path.to_path_buf(), has_filter, has_transform, engine, module })
+    }
+
+    fn call_filter(&self, file: &str, metadata: &[u8]) -> Result<bool, PluginError> {
+        self.call(FILTER_EXPORT, file, metadata, |store, instance, args| {
+            let function = instance.get_typed_func::<(i32, i32, i32, i32), i32>(&mut *store, FILTER_EXPORT)?;
+            Ok(function.call(&mut *store, args)? != 0)
+        })
+    }
+
+    fn call_transform(&self, file: &str, content: &[u8]) -> Result<Vec<u8>, PluginError> {
+        self.call(TRANSFORM_EXPORT, file, content, |store, instance, args| {
+            let function = instance.get_typed_func::<(i32, i32, i32, i32), i64>(&mut *store, TRANSFORM_EXPORT)?;
+            let packed = function.call(&mut *store, args)? as u64;
+            let (ptr, len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+            let memory = instance.get_memory(&mut *store, "memory").ok_or_else(|| wasmtime::Error::msg("no memory export"))?;
+            let output = memory
+                .data(&*store)
+                .get(ptr..ptr + len)
+                .ok_or_else(|| wasmtime::Error::msg(format!("returned {} bytes at {}, outside its memory", len, ptr)))?;
+            Ok(output.to_vec())
+        })
+    }
+
+    /// Instantiates the module in a fresh, limited store, copies the file's path and `data`
+    /// into its memory, and runs `body` with their pointers and lengths.
+    fn call<T>(
+        &self,
+        function: &'static str,
+        file: &str,
+        data: &[u8],
+        body: impl FnOnce(&mut wasmtime::Store<wasmtime::StoreLimits>, &wasmtime::Instance, (i32, i32, i32, i32)) -> wasmtime::Result<T>,
+    ) -> Result<T, PluginError> {
+        use wasmtime::{Instance, Store, StoreLimitsBuilder};
+
+        let run = || -> wasmtime::Result<T> {
+            let mut store = Store::new(&self.engine, StoreLimitsBuilder::new().memory_size(MEMORY_LIMIT).build());
+            store.limiter(|limits| limits);
+            store.set_fuel(FUEL_PER_CALL)?;
+            let instance = Instance::new(&mut store, &self.module, &[])?;
+            let (path_ptr, path_len) = write(&mut store, &instance, file.as_bytes())?;
+            let (data_ptr, data_len) = write(&mut store, &instance, data)?;
+            body(&mut store, &instance, (path_ptr, path_len, data_ptr, data_len))
+        };
+        run().map_err(|e| PluginError::Call {
+            path: self.path.clone(),
+            function,
+            file: file.to_string(),
+            message: match e.downcast_ref::<wasmtime::Trap>() {
+                Some(trap) => trap.to_string(),
+                None => format!("{:#}", e),
+            },
+        })
+    }
+}
+
+/// Copies bytes into a plugin's memory at a pointer from its `alloc`.
+#[cfg(feature = "wasm-plugins")]
+fn write(
+    store: &mut wasmtime::Store<wasmtime::StoreLimits>,
+    instance: &wasmtime::Instance,
+    bytes: &[u8],
+) -> wasmtime::Result<(i32, i32)> {
+    let len = i32::try_from(bytes.len())?;
+    let alloc = instance.get_typed_func::<i32, i32>(&mut *store, "alloc")?;
+    let ptr = alloc.call(&mut *store, len)?;
+    let memory = instance.get_memory(&mut *store, "memory").ok_or_else(|| wasmtime::Error::msg("no memory export"))?;
+    memory.write(&mut *store, ptr as u32 as usize, bytes)?;
+    Ok((ptr, len))
+}
+
+/// Renders a relative path with `/` separators on every platform.
+fn portable(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+/// Runs file contents through a plugin's `transform`, for the plugins given with `--plugin`.
+#[derive(Debug, Clone)]
+pub struct PluginTransformer {
+    path: PathBuf,
+}
+
+impl PluginTransformer {
+    /// Creates a transformer for the plugin at a path, which is loaded when first used.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the plugin.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl Transformer for PluginTransformer {
+    fn transform(&self, path: &Path, content: &str) -> Result<String, TransformError> {
+        Ok(load(&self.path)?.transform(path, content)?)
+    }
+}
@@ -0,0 +1,210 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use log::debug;
+use thiserror::Error;
+use crate::artifact::CollectPlan;
+use crate::config::Config;
+use crate::manifest::{Manifest, MANIFEST_FILE};
+use crate::runs;
+use crate::writer::DirectoryWriter;
+
+/// Filesystem block size assumed when estimating how much space artifacts occupy on disk.
+const BLOCK_SIZE: u64 = 4096;
+
+/// Custom error type for pre-flight checks.
+#[derive(Error, Debug)]
+pub enum PreflightError {
+    #[error("destination {path} is not writable: {source}")]
+    NotWritable { path: PathBuf, source: io::Error },
+    #[error("not enough free space in {path}: need about {required}, only {available} available")]
+    InsufficientSpace { path: PathBuf, required: String, available: String },
+    #[error("destination {0} is not empty and has no {MANIFEST_FILE}; refusing to write into it without --force")]
+    ForeignDestination(PathBuf),
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// Runs every pre-flight check before a planned collection is written.
+///
+/// The checks apply to the directory the artifacts are written into: the destination
+/// (or, with `--run-dirs`, the run's directory beneath it), or the directory holding the
+/// `--single-file` bundle or `--output-archive` archive. It must be writable and have room
+/// for the planned artifacts, and unless a single file is written, neither it nor a
+/// `--run-dirs` destination may be a directory rustifacts did not create.
+///
+/// # Arguments
+///
+/// * `config` - The configuration options.
+/// * `plan` - The collection plan that is about to be executed.
+///
+/// # Returns
+///
+/// Returns `Result<(), PreflightError>` indicating whether the collection may be written.
+pub fn run(config: &Config, plan: &CollectPlan) -> Result<(), PreflightError> {
+    let output_file = config.single_file.as_ref().or(config.output_archive.as_ref());
+    let output_dir = match output_file {
+        Some(file) => file.parent().map(PathBuf::from).unwrap_or_else(|| PathBuf::from(".")),
+        None => DirectoryWriter::from_config(config).output_dir(),
+    };
+
+    debug!("Checking destination permissions");
+    check_writable(&output_dir)?;
+    if output_file.is_none() {
+        check_overwrite(&output_dir, config.force)?;
+        // Runs are written into a new directory beneath the destination, so guard the destination too
+        if config.run_dirs {
+            check_overwrite(&config.dest_dir, config.force)?;
+        }
+    }
+    debug!("Checking destination free space");
+    check_space(plan, &output_dir)
+}
+
+/// Verifies that the destination directory can be written to.
+///
+/// The destination does not need to exist yet; the check is performed against its
+/// nearest existing ancestor so that nothing is created on disk.
+///
+/// # Arguments
+///
+/// * `dest_dir` - The destination directory path.
+///
+/// # Returns
+///
+/// Returns `Result<(), PreflightError>` indicating whether the destination is writable.
+pub fn check_writable(dest_dir: &Path) -> Result<(), PreflightError> {
+    let existing = nearest_existing_ancestor(dest_dir);
+    debug!("Checking write permission in {}", existing.display());
+    tempfile::tempfile_in(&existing)
+        .map(|_| ())
+        .map_err(|source| PreflightError::NotWritable { path: existing, source })
+}
+
+/// Refuses to write into an existing, non-empty directory that rustifacts did not create.
+///
+/// A destination is considered safe if it does not exist, is empty, contains a
+/// rustifacts manifest from a previous run, or holds only the `runs/` directory and
+/// `latest` pointer written with `--run-dirs`. Passing `force` skips the check.
+///
+/// # Arguments
+///
+/// * `dest_dir` - The destination directory path.
+/// * `force` - Whether to allow writing into any destination.
+///
+/// # Returns
+///
+/// Returns `Result<(), PreflightError>` indicating whether the destination is safe to write into.
+pub fn check_overwrite(dest_dir: &Path, force: bool) -> Result<(), PreflightError> {
+    if force || !dest_dir.is_dir() || Manifest::exists_in(dest_dir) {
+        return Ok(());
+    }
+    if fs::read_dir(dest_dir)?.next().is_some() && !runs::is_run_layout(dest_dir)? {
+        return Err(PreflightError::ForeignDestination(dest_dir.to_path_buf()));
+    }
+    Ok(())
+}
+
+/// Verifies that the destination has enough free space for a planned collection.
+///
+/// The space is estimated from the sizes of the planned source files, before any of them
+/// is read, so that a full disk is caught before the expensive part of a run.
+///
+/// # Arguments
+///
+/// * `plan` - The collection plan that is about to be executed.
+/// * `dest_dir` - The destination directory path.
+///
+/// # Returns
+///
+/// Returns `Result<(), PreflightError>` indicating whether there is enough free space.
+pub fn check_space(plan: &CollectPlan, dest_dir: &Path) -> Result<(), PreflightError> {
+    let required = estimate_required_space(plan);
+    let existing = nearest_existing_ancestor(dest_dir);
+    let available = fs2::available_space(&existing)?;
+    debug!("Estimated space required: {} bytes, available: {} bytes", required, available);
+
+    if required > available {
+        return Err(PreflightError::InsufficientSpace {
+            path: existing,
+            required: format_bytes(required),
+            available: format_bytes(available),
+        });
+    }
+    Ok(())
+}
+
+/// Estimates the on-disk size of a plan's artifacts from the sizes of their source files,
+/// rounding each file up to a whole block.
+fn estimate_required_space(plan: &CollectPlan) -> u64 {
+    let sizes = plan.files.iter().map(|planned| planned.size);
+    let stubs = plan.submodule_stubs.iter().map(|stub| stub.content.len() as u64);
+    sizes
+        .chain(stubs)
+        .map(|size| {
+            // Allow for the optional checksum header line.
+            let bytes = size + 64;
+            bytes.div_ceil(BLOCK_SIZE) * BLOCK_SIZE
+        })
+        .sum()
+}
+
+/// Returns the closest ancestor of `path` (including itself) that exists on disk.
+fn nearest_existing_ancestor(path: &Path) -> PathBuf {
+    path.ancestors()
+        .find(|ancestor| !ancestor.as_os_str().is_empty() && ancestor.exists())
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// Parses a human-readable size such as `500KB`, `1.5 MB`, or `2048`.
+///
+/// Units are case-insensitive, `K`/`KB`/`KiB` (and likewise for M, G, and T) are
+/// powers of 1024 to match `format_bytes`, and a bare number is a count of bytes.
+///
+/// # Arguments
+///
+/// * `text` - The size to parse.
+///
+/// # Returns
+///
+/// Returns `Result<u64, String>` containing the number of bytes, or a message
+/// describing why the size is invalid.
+pub fn parse_bytes(text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    let split = text.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let value: f64 = number.parse().map_err(|_| format!("invalid size {:?}: expected a number such as 500KB", text))?;
+    let exponent = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 0,
+        "K" | "KB" | "KIB" => 1,
+        "M" | "MB" | "MIB" => 2,
+        "G" | "GB" | "GIB" => 3,
+        "T" | "TB" | "TIB" => 4,
+        _ => return Err(format!("invalid size {:?}: unknown unit {:?}", text, unit.trim())),
+    };
+    Ok((value * 1024f64.powi(exponent)).round() as u64)
+}
+
+/// Formats a byte count as a human-readable string.
+///
+/// # Arguments
+///
+/// * `bytes` - The number of bytes.
+///
+/// # Returns
+///
+/// A `String` such as `"512 B"`, `"1.5 KB"`, or `"3.2 GB"`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
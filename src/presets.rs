@@ -1,20 +1,135 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use serde::{Deserialize, Serialize};
 use crate::config::Config;
+use crate::global_config::GlobalConfig;
 
-#[derive(Clone, Debug)]
+/// A preset configuration.
+///
+/// User-defined presets are read from `[presets.<name>]` tables in the global
+/// configuration file. Its keys are those of the configuration file (with
+/// `ignored_dirs` accepted for `additional_ignored_dirs`), so `presets show` output can
+/// be used in either place.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PresetConfig {
+    #[serde(rename = "additional_ignored_dirs", alias = "ignored_dirs")]
     pub ignored_dirs: Vec<String>,
     pub included_extensions: Vec<String>,
     pub excluded_extensions: Vec<String>,
     pub target_dirs: Vec<String>,
+    /// Destination directory template used when no destination was given explicitly.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dest_dir: Option<String>,
+    /// Whether to convert Jupyter notebooks to scripts, as with `--convert-notebooks`.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub convert_notebooks: bool,
+    /// Whether to drop the data from large SQL dumps, as with `--summarize-sql-dumps`.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub summarize_sql_dumps: bool,
+}
+
+impl PresetConfig {
+    /// Merges a later preset into this one.
+    ///
+    /// Directory and extension lists are combined. Where the presets disagree, the later
+    /// one wins: an extension it includes is no longer excluded and vice versa, and its
+    /// destination replaces this one's. An empty extension list includes every extension,
+    /// so merging with it keeps the result unrestricted.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The preset to merge in.
+    pub fn merge(&mut self, other: &PresetConfig) {
+        union(&mut self.ignored_dirs, &other.ignored_dirs);
+        union(&mut self.target_dirs, &other.target_dirs);
+        if self.included_extensions.is_empty() || other.included_extensions.is_empty() {
+            self.included_extensions.clear();
+        } else {
+            union(&mut self.included_extensions, &other.included_extensions);
+        }
+        self.excluded_extensions.retain(|ext| !other.included_extensions.contains(ext));
+        self.included_extensions.retain(|ext| !other.excluded_extensions.contains(ext));
+        union(&mut self.excluded_extensions, &other.excluded_extensions);
+        if other.dest_dir.is_some() {
+            self.dest_dir.clone_from(&other.dest_dir);
+        }
+        self.convert_notebooks |= other.convert_notebooks;
+        self.summarize_sql_dumps |= other.summarize_sql_dumps;
+    }
+
+    /// Renders the preset as configuration-file TOML.
+    ///
+    /// The destination is left out, since a preset's own destination is only used with
+    /// `--dest-in-project` and would otherwise move output into the project.
+    ///
+    /// # Arguments
+    ///
+    /// * `spec` - The name the preset was resolved from.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<String, toml::ser::Error>` containing the TOML document.
+    pub fn to_toml(&self, spec: &str) -> Result<String, toml::ser::Error> {
+        let preset = PresetConfig { dest_dir: None, ..self.clone() };
+        Ok(format!("# Preset {}\n{}", spec, toml::to_string(&preset)?))
+    }
+}
+
+/// Appends the items of `other` that `list` does not contain yet.
+fn union(list: &mut Vec<String>, other: &[String]) {
+    for item in other {
+        if !list.contains(item) {
+            list.push(item.clone());
+        }
+    }
+}
+
+/// Splits a preset specification into the names of the presets it combines.
+///
+/// # Arguments
+///
+/// * `spec` - One preset name, or several separated by `,` or `+` (e.g., `rust,docs`).
+///
+/// # Returns
+///
+/// A `Vec<&str>` containing the preset names, in the order given.
+///
+/// # Example
+///
+/// ```
+/// use rustifacts::presets::split_presets;
+///
+/// assert_eq!(split_presets("rust, docs"), vec!["rust", "docs"]);
+/// assert_eq!(split_presets("rust+docs"), vec!["rust", "docs"]);
+/// ```
+pub fn split_presets(spec: &str) -> Vec<&str> {
+    spec.split([',', '+']).map(str::trim).filter(|name| !name.is_empty()).collect()
 }
 
 /// Returns a HashMap of preset configurations.
 ///
+/// User-defined presets are included, and replace built-in presets of the same name.
+///
 /// # Returns
 ///
-/// A `HashMap<String, PresetConfig>` containing predefined preset configurations.
+/// A `HashMap<String, PresetConfig>` containing every available preset configuration.
 pub fn get_preset_configs() -> HashMap<String, PresetConfig> {
+    let mut presets = builtin_presets();
+    presets.extend(user_presets());
+    presets
+}
+
+/// Returns the presets defined in the global configuration file.
+///
+/// # Returns
+///
+/// A `BTreeMap<String, PresetConfig>` containing the user-defined presets, which is empty
+/// if the global configuration file is missing or cannot be read.
+pub fn user_presets() -> BTreeMap<String, PresetConfig> {
+    GlobalConfig::load().map(|global| global.presets).unwrap_or_default()
+}
+
+fn builtin_presets() -> HashMap<String, PresetConfig> {
     let mut presets = HashMap::new();
 
     presets.insert(
@@ -38,6 +153,9 @@ pub fn get_preset_configs() -> HashMap<String, PresetConfig> {
                 "styles".to_string(),
                 "public".to_string(),
             ],
+            dest_dir: Some("./{preset}_context".to_string()),
+            convert_notebooks: false,
+            summarize_sql_dumps: false,
         }
     );
 
@@ -66,30 +184,442 @@ pub fn get_preset_configs() -> HashMap<String, PresetConfig> {
                 "examples".to_string(),
                 "benches".to_string(),
             ],
+            dest_dir: Some("./{preset}_context".to_string()),
+            convert_notebooks: false,
+            summarize_sql_dumps: false,
+        }
+    );
+
+    presets.insert(
+        "database".to_string(),
+        PresetConfig {
+            ignored_dirs: vec![
+                "node_modules".to_string(),
+                "target".to_string(),
+                "vendor".to_string(),
+                ".venv".to_string(),
+                ".git".to_string(),
+            ],
+            included_extensions: vec![
+                "sql".to_string(),
+                "prisma".to_string(),
+                "dbml".to_string(),
+            ],
+            excluded_extensions: vec![],
+            target_dirs: vec![
+                ".".to_string(),
+                "migrations".to_string(),
+                "db".to_string(),
+                "prisma".to_string(),
+                "schema".to_string(),
+                "sql".to_string(),
+            ],
+            dest_dir: Some("./{preset}_context".to_string()),
+            convert_notebooks: false,
+            summarize_sql_dumps: true,
+        }
+    );
+
+    presets.insert(
+        "datascience".to_string(),
+        PresetConfig {
+            ignored_dirs: vec![
+                "data".to_string(),
+                "models".to_string(),
+                "checkpoints".to_string(),
+                ".ipynb_checkpoints".to_string(),
+                "notebooks/.ipynb_checkpoints".to_string(),
+                "mlruns".to_string(),
+                "wandb".to_string(),
+                "__pycache__".to_string(),
+                ".venv".to_string(),
+                "venv".to_string(),
+                ".git".to_string(),
+            ],
+            included_extensions: vec![
+                "ipynb".to_string(), "py".to_string(), "r".to_string(), "qmd".to_string(),
+                "rmd".to_string(), "yaml".to_string(), "yml".to_string(), "toml".to_string(),
+                "txt".to_string(), "md".to_string(),
+            ],
+            excluded_extensions: vec![],
+            target_dirs: vec![
+                ".".to_string(),
+                "notebooks".to_string(),
+                "src".to_string(),
+                "scripts".to_string(),
+                "analysis".to_string(),
+            ],
+            dest_dir: Some("./{preset}_context".to_string()),
+            convert_notebooks: true,
+            summarize_sql_dumps: false,
+        }
+    );
+
+    presets.insert(
+        "python".to_string(),
+        PresetConfig {
+            ignored_dirs: vec![
+                "venv".to_string(),
+                ".venv".to_string(),
+                "__pycache__".to_string(),
+                ".mypy_cache".to_string(),
+                ".pytest_cache".to_string(),
+                ".ruff_cache".to_string(),
+                ".tox".to_string(),
+                "build".to_string(),
+                "dist".to_string(),
+                ".git".to_string(),
+            ],
+            included_extensions: vec![
+                "py".to_string(), "pyi".to_string(), "toml".to_string(), "cfg".to_string(),
+                "ini".to_string(), "txt".to_string(), "md".to_string(), "jinja".to_string(),
+                "j2".to_string(),
+            ],
+            excluded_extensions: vec![],
+            target_dirs: vec![
+                ".".to_string(),
+                "src".to_string(),
+                "tests".to_string(),
+                "scripts".to_string(),
+                "templates".to_string(),
+            ],
+            dest_dir: Some("./{preset}_context".to_string()),
+            convert_notebooks: false,
+            summarize_sql_dumps: false,
+        }
+    );
+
+    presets.insert(
+        "django".to_string(),
+        PresetConfig {
+            ignored_dirs: vec![
+                "venv".to_string(),
+                ".venv".to_string(),
+                "__pycache__".to_string(),
+                ".mypy_cache".to_string(),
+                ".pytest_cache".to_string(),
+                ".ruff_cache".to_string(),
+                ".tox".to_string(),
+                "staticfiles".to_string(),
+                "media".to_string(),
+                "node_modules".to_string(),
+                ".git".to_string(),
+            ],
+            included_extensions: vec![
+                "py".to_string(), "pyi".to_string(), "toml".to_string(), "cfg".to_string(),
+                "ini".to_string(), "txt".to_string(), "md".to_string(), "html".to_string(),
+                "css".to_string(), "js".to_string(),
+            ],
+            excluded_extensions: vec![],
+            target_dirs: vec![
+                ".".to_string(),
+                "templates".to_string(),
+                "static".to_string(),
+                "tests".to_string(),
+            ],
+            dest_dir: Some("./{preset}_context".to_string()),
+            convert_notebooks: false,
+            summarize_sql_dumps: false,
+        }
+    );
+
+    presets.insert(
+        "android".to_string(),
+        PresetConfig {
+            ignored_dirs: vec![
+                "build".to_string(),
+                "app/build".to_string(),
+                ".gradle".to_string(),
+                ".cxx".to_string(),
+                "app/.cxx".to_string(),
+                ".kotlin".to_string(),
+                "captures".to_string(),
+                "local.properties".to_string(),
+                ".idea".to_string(),
+                ".git".to_string(),
+            ],
+            included_extensions: vec![
+                "kt".to_string(), "kts".to_string(), "java".to_string(), "xml".to_string(),
+                "gradle".to_string(), "properties".to_string(), "pro".to_string(), "toml".to_string(),
+                "md".to_string(),
+            ],
+            excluded_extensions: vec![],
+            target_dirs: vec![
+                ".".to_string(),
+                "app/src/main".to_string(),
+                "app/src/test".to_string(),
+                "app/src/androidTest".to_string(),
+                "gradle".to_string(),
+            ],
+            dest_dir: Some("./{preset}_context".to_string()),
+            convert_notebooks: false,
+            summarize_sql_dumps: false,
+        }
+    );
+
+    presets.insert(
+        "ios".to_string(),
+        PresetConfig {
+            ignored_dirs: vec![
+                "DerivedData".to_string(),
+                "Pods".to_string(),
+                "Carthage/Build".to_string(),
+                ".build".to_string(),
+                ".swiftpm".to_string(),
+                "build".to_string(),
+                "fastlane/report.xml".to_string(),
+                ".git".to_string(),
+            ],
+            included_extensions: vec![
+                "swift".to_string(), "m".to_string(), "mm".to_string(), "h".to_string(),
+                "plist".to_string(), "xcconfig".to_string(), "entitlements".to_string(), "md".to_string(),
+            ],
+            excluded_extensions: vec![],
+            target_dirs: vec![
+                ".".to_string(),
+                "Sources".to_string(),
+                "Tests".to_string(),
+            ],
+            dest_dir: Some("./{preset}_context".to_string()),
+            convert_notebooks: false,
+            summarize_sql_dumps: false,
+        }
+    );
+
+    presets.insert(
+        "cpp".to_string(),
+        PresetConfig {
+            ignored_dirs: vec![
+                "build".to_string(),
+                "cmake-build-debug".to_string(),
+                "cmake-build-release".to_string(),
+                "out".to_string(),
+                "_deps".to_string(),
+                ".cache".to_string(),
+                ".git".to_string(),
+            ],
+            included_extensions: vec![
+                "c".to_string(), "cc".to_string(), "cpp".to_string(), "cxx".to_string(),
+                "h".to_string(), "hh".to_string(), "hpp".to_string(), "hxx".to_string(),
+                "inl".to_string(), "cmake".to_string(), "txt".to_string(), "md".to_string(),
+            ],
+            excluded_extensions: vec![
+                "o".to_string(), "obj".to_string(), "a".to_string(), "lib".to_string(),
+                "so".to_string(), "dylib".to_string(), "dll".to_string(), "exe".to_string(),
+            ],
+            target_dirs: vec![
+                ".".to_string(),
+                "src".to_string(),
+                "include".to_string(),
+                "tests".to_string(),
+                "cmake".to_string(),
+            ],
+            dest_dir: Some("./{preset}_context".to_string()),
+            convert_notebooks: false,
+            summarize_sql_dumps: false,
+        }
+    );
+
+    presets.insert(
+        "docs".to_string(),
+        PresetConfig {
+            ignored_dirs: vec![
+                "node_modules".to_string(),
+                "site".to_string(),
+                "_site".to_string(),
+                "book".to_string(),
+                ".git".to_string(),
+            ],
+            included_extensions: vec![
+                "md".to_string(), "mdx".to_string(), "markdown".to_string(), "rst".to_string(),
+                "adoc".to_string(), "txt".to_string(),
+            ],
+            excluded_extensions: vec![],
+            target_dirs: vec![
+                ".".to_string(),
+                "docs".to_string(),
+                "doc".to_string(),
+            ],
+            dest_dir: Some("./{preset}_context".to_string()),
+            convert_notebooks: false,
+            summarize_sql_dumps: false,
+        }
+    );
+
+    presets.insert(
+        "laravel".to_string(),
+        PresetConfig {
+            ignored_dirs: vec![
+                "vendor".to_string(),
+                "storage".to_string(),
+                "bootstrap/cache".to_string(),
+                "public/build".to_string(),
+                "node_modules".to_string(),
+                ".git".to_string(),
+            ],
+            included_extensions: vec![
+                "php".to_string(), "json".to_string(), "example".to_string(), "xml".to_string(),
+                "md".to_string(),
+            ],
+            excluded_extensions: vec![],
+            target_dirs: vec![
+                ".".to_string(),
+                "app".to_string(),
+                "config".to_string(),
+                "database".to_string(),
+                "routes".to_string(),
+                "resources/views".to_string(),
+                "tests".to_string(),
+            ],
+            dest_dir: Some("./{preset}_context".to_string()),
+            convert_notebooks: false,
+            summarize_sql_dumps: false,
+        }
+    );
+
+    presets.insert(
+        "rails".to_string(),
+        PresetConfig {
+            ignored_dirs: vec![
+                "vendor".to_string(),
+                "tmp".to_string(),
+                "log".to_string(),
+                "storage".to_string(),
+                "coverage".to_string(),
+                "public/assets".to_string(),
+                "public/packs".to_string(),
+                ".bundle".to_string(),
+                "node_modules".to_string(),
+                ".git".to_string(),
+            ],
+            included_extensions: vec![
+                "rb".to_string(), "erb".to_string(), "rake".to_string(), "ru".to_string(),
+                "yml".to_string(), "md".to_string(),
+            ],
+            excluded_extensions: vec![],
+            target_dirs: vec![
+                ".".to_string(),
+                "app".to_string(),
+                "config".to_string(),
+                "db".to_string(),
+                "lib".to_string(),
+                "spec".to_string(),
+                "test".to_string(),
+            ],
+            dest_dir: Some("./{preset}_context".to_string()),
+            convert_notebooks: false,
+            summarize_sql_dumps: false,
+        }
+    );
+
+    presets.insert(
+        "gamedev".to_string(),
+        PresetConfig {
+            ignored_dirs: vec![
+                "Content".to_string(),
+                "Library".to_string(),
+                "Temp".to_string(),
+                "Obj".to_string(),
+                "Logs".to_string(),
+                "Build".to_string(),
+                "Builds".to_string(),
+                "Binaries".to_string(),
+                "Intermediate".to_string(),
+                "Saved".to_string(),
+                "DerivedDataCache".to_string(),
+                "UserSettings".to_string(),
+                ".vs".to_string(),
+                ".git".to_string(),
+            ],
+            included_extensions: vec![
+                "cs".to_string(), "cpp".to_string(), "h".to_string(), "hpp".to_string(),
+                "ini".to_string(), "uproject".to_string(), "uplugin".to_string(), "asmdef".to_string(),
+                "asmref".to_string(), "shader".to_string(), "hlsl".to_string(), "cginc".to_string(),
+                "usf".to_string(), "ush".to_string(), "json".to_string(), "md".to_string(),
+            ],
+            excluded_extensions: vec![
+                "uasset".to_string(), "umap".to_string(), "fbx".to_string(), "obj".to_string(),
+                "blend".to_string(), "png".to_string(), "tga".to_string(), "psd".to_string(),
+                "exr".to_string(), "wav".to_string(), "ogg".to_string(), "mp3".to_string(),
+                "unity".to_string(), "prefab".to_string(), "mat".to_string(), "asset".to_string(),
+                "meta".to_string(),
+            ],
+            target_dirs: vec![
+                ".".to_string(),
+                "Source".to_string(),
+                "Config".to_string(),
+                "Plugins".to_string(),
+                "Assets".to_string(),
+                "Packages".to_string(),
+            ],
+            dest_dir: Some("./{preset}_context".to_string()),
+            convert_notebooks: false,
+            summarize_sql_dumps: false,
         }
     );
 
     presets
 }
 
+/// Returns the names of all available presets, sorted alphabetically.
+///
+/// # Returns
+///
+/// A `Vec<String>` containing the preset names.
+pub fn preset_names() -> Vec<String> {
+    let mut names: Vec<String> = get_preset_configs().into_keys().collect();
+    names.sort();
+    names
+}
+
 /// Applies a preset configuration to the given Config instance.
 ///
+/// Several presets separated by `,` or `+` are merged in order before being applied
+/// (see [`PresetConfig::merge`]).
+///
 /// # Arguments
 ///
 /// * `config` - The Config instance to update.
-/// * `preset_name` - The name of the preset to apply.
+/// * `preset_name` - The name of the preset to apply, or several names to combine.
 ///
 /// # Returns
 ///
 /// Returns `Result<(), String>` indicating success or failure of applying the preset.
 pub fn apply_preset(config: &mut Config, preset_name: &str) -> Result<(), String> {
-    if let Some(preset) = get_preset_configs().get(preset_name) {
-        config.additional_ignored_dirs = preset.ignored_dirs.join(",");
-        config.included_extensions = preset.included_extensions.join(",");
-        config.excluded_extensions = preset.excluded_extensions.join(",");
-        config.target_dirs = Some(preset.target_dirs.join(","));
-        Ok(())
-    } else {
-        Err(format!("Preset '{}' not found", preset_name))
+    let preset = resolve_preset(preset_name)?;
+    config.additional_ignored_dirs = preset.ignored_dirs.join(",");
+    config.included_extensions = preset.included_extensions.join(",");
+    config.excluded_extensions = preset.excluded_extensions.join(",");
+    config.target_dirs = Some(preset.target_dirs.join(","));
+    config.convert_notebooks |= preset.convert_notebooks;
+    config.summarize_sql_dumps |= preset.summarize_sql_dumps;
+    if let Some(ref dest_dir) = preset.dest_dir {
+        if config.dest_in_project && config.is_default_dest() {
+            config.dest_dir = dest_dir.into();
+        }
+    }
+    Ok(())
+}
+
+/// Looks up a preset, merging several presets in order if more than one is named.
+///
+/// # Arguments
+///
+/// * `preset_name` - The name of the preset, or several names separated by `,` or `+`.
+///
+/// # Returns
+///
+/// Returns `Result<PresetConfig, String>` containing the preset, or an error naming the
+/// first preset that does not exist.
+pub fn resolve_preset(preset_name: &str) -> Result<PresetConfig, String> {
+    let configs = get_preset_configs();
+    let mut merged: Option<PresetConfig> = None;
+    for name in split_presets(preset_name) {
+        let preset = configs.get(name).ok_or_else(|| format!("Preset '{}' not found", name))?;
+        match merged {
+            Some(ref mut merged) => merged.merge(preset),
+            None => merged = Some(preset.clone()),
+        }
     }
+    merged.ok_or_else(|| format!("Preset '{}' not found", preset_name))
 }
\ No newline at end of file
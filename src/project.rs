@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+use serde::{Deserialize, Serialize};
+use crate::artifact::Artifact;
+use crate::{format, git, runs};
+
+/// Languages listed individually in a header; smaller ones are grouped as `other`.
+const MAX_LANGUAGES: usize = 8;
+
+/// Metadata about a collected project, rendered as a header at the top of bundles.
+#[derive(Debug, Clone)]
+pub struct ProjectInfo {
+    /// The package name, or the source directory's name if there is no package manifest.
+    pub name: String,
+    pub description: Option<String>,
+    /// How the collected content splits across languages, largest first.
+    pub languages: Vec<LanguageShare>,
+    /// Whether the header also gives each language's share of the tokens, as with `--lang-stats`.
+    pub token_shares: bool,
+    /// The commit checked out in the source directory, if it is in a git repository.
+    pub commit: Option<String>,
+    /// When the bundle was generated, as an RFC 3339 UTC timestamp.
+    pub generated_at: String,
+    pub tool_version: String,
+}
+
+/// The collected files, bytes, and tokens of one language.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LanguageShare {
+    pub language: String,
+    pub files: usize,
+    pub bytes: u64,
+    #[serde(default)]
+    pub tokens: usize,
+}
+
+impl ProjectInfo {
+    /// Gathers metadata about the project in a source directory.
+    ///
+    /// The name and description are read from `Cargo.toml`, `package.json`, or
+    /// `pyproject.toml`, in that order, and the language breakdown from the artifacts.
+    ///
+    /// # Arguments
+    ///
+    /// * `source_dir` - The source directory the artifacts were collected from.
+    /// * `artifacts` - The collected artifacts.
+    ///
+    /// # Returns
+    ///
+    /// A `ProjectInfo` describing the project.
+    pub fn detect(source_dir: &Path, artifacts: &[Artifact]) -> Self {
+        let (name, description) = package_metadata(source_dir).unwrap_or_else(|| {
+            let name = source_dir
+                .canonicalize()
+                .ok()
+                .and_then(|dir| dir.file_name().map(|name| name.to_string_lossy().into_owned()))
+                .unwrap_or_else(|| source_dir.display().to_string());
+            (name, None)
+        });
+        Self {
+            name,
+            description,
+            languages: language_breakdown(artifacts),
+            token_shares: false,
+            commit: git::run(source_dir, &["rev-parse", "HEAD"]).ok(),
+            generated_at: runs::utc_timestamp(SystemTime::now()),
+            tool_version: format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+        }
+    }
+
+    /// Describes the language breakdown as percentages of the collected bytes.
+    ///
+    /// # Returns
+    ///
+    /// A `String` such as `rust 82% (14 files), toml 18% (2 files)`.
+    pub fn languages_summary(&self) -> String {
+        let total: u64 = self.languages.iter().map(|share| share.bytes).sum();
+        self.languages
+            .iter()
+            .map(|share| {
+                let percent = (share.bytes * 100).checked_div(total).unwrap_or(0);
+                let plural = if share.files == 1 { "" } else { "s" };
+                format!("{} {}% ({} file{})", share.language, percent, share.files, plural)
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Describes the language breakdown as percentages of the collected tokens.
+    ///
+    /// # Returns
+    ///
+    /// A `String` such as `rust 85%, toml 15%`, largest first.
+    pub fn tokens_summary(&self) -> String {
+        let total: usize = self.languages.iter().map(|share| share.tokens).sum();
+        let mut shares: Vec<&LanguageShare> = self.languages.iter().collect();
+        shares.sort_by(|a, b| b.tokens.cmp(&a.tokens).then_with(|| a.language.cmp(&b.language)));
+        shares
+            .iter()
+            .map(|share| format!("{} {}%", share.language, (share.tokens * 100).checked_div(total).unwrap_or(0)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Reads the package name and description from the first package manifest found.
+fn package_metadata(source_dir: &Path) -> Option<(String, Option<String>)> {
+    if let Some(manifest) = read_toml(&source_dir.join("Cargo.toml")) {
+        if let Some(metadata) = toml_metadata(manifest.get("package")) {
+            return Some(metadata);
+        }
+    }
+    if let Some(manifest) = fs::read_to_string(source_dir.join("package.json"))
+        .ok()
+        .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok())
+    {
+        if let Some(name) = manifest.get("name").and_then(|name| name.as_str()) {
+            let description = manifest.get("description").and_then(|description| description.as_str());
+            return Some((name.to_string(), description.map(String::from)));
+        }
+    }
+    read_toml(&source_dir.join("pyproject.toml")).and_then(|manifest| toml_metadata(manifest.get("project")))
+}
+
+/// Parses a TOML file, returning `None` if it is missing or invalid.
+fn read_toml(path: &Path) -> Option<toml::Table> {
+    fs::read_to_string(path).ok()?.parse().ok()
+}
+
+/// Reads `name` and `description` from a TOML table such as `[package]`.
+fn toml_metadata(table: Option<&toml::Value>) -> Option<(String, Option<String>)> {
+    let table = table?;
+    let name = table.get("name")?.as_str()?.to_string();
+    let description = table.get("description").and_then(|description| description.as_str()).map(String::from);
+    Some((name, description))
+}
+
+/// Totals the artifacts per language, the way `--lang-stats` reports them.
+///
+/// Languages are recognized by file extension or name, and files of unknown languages
+/// are grouped as `other`.
+///
+/// # Arguments
+///
+/// * `artifacts` - The collected artifacts.
+///
+/// # Returns
+///
+/// A `Vec<LanguageShare>` with one entry per language, largest by bytes first.
+pub fn language_stats(artifacts: &[Artifact]) -> Vec<LanguageShare> {
+    let mut totals: HashMap<&str, LanguageShare> = HashMap::new();
+    for artifact in artifacts {
+        let language = match format::language(&artifact.relative_path) {
+            "" => "other",
+            language => language,
+        };
+        let total = totals.entry(language).or_insert_with(|| LanguageShare {
+            language: language.to_string(),
+            files: 0,
+            bytes: 0,
+            tokens: 0,
+        });
+        // The parts of a split file count as one file.
+        if artifact.part.is_none_or(|part| part.index == 1) {
+            total.files += 1;
+        }
+        total.bytes += artifact.content.len() as u64;
+        total.tokens += artifact.tokens;
+    }
+    let mut shares: Vec<LanguageShare> = totals.into_values().collect();
+    shares.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.language.cmp(&b.language)));
+    shares
+}
+
+/// Totals the artifacts per language, largest first, grouping the smallest as `other`.
+fn language_breakdown(artifacts: &[Artifact]) -> Vec<LanguageShare> {
+    let mut shares = language_stats(artifacts);
+    if shares.len() > MAX_LANGUAGES {
+        let rest = shares.split_off(MAX_LANGUAGES - 1);
+        let mut other = LanguageShare { language: "other".to_string(), files: 0, bytes: 0, tokens: 0 };
+        for share in rest {
+            other.files += share.files;
+            other.bytes += share.bytes;
+            other.tokens += share.tokens;
+        }
+        match shares.iter_mut().find(|share| share.language == "other") {
+            Some(existing) => {
+                existing.files += other.files;
+                existing.bytes += other.bytes;
+                existing.tokens += other.tokens;
+            }
+            None => shares.push(other),
+        }
+    }
+    shares
+}
@@ -0,0 +1,314 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+use log::{debug, info, warn};
+use thiserror::Error;
+use crate::binary;
+use crate::checksum::{self, ChecksumError};
+use crate::config::BinaryMode;
+use crate::format;
+use crate::manifest::{Manifest, MANIFEST_FILE};
+
+/// Custom error type for restoring artifacts.
+#[derive(Error, Debug)]
+pub enum RestoreError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Invalid manifest: {0}")]
+    Manifest(#[from] serde_json::Error),
+    #[error("No {MANIFEST_FILE} in {0}; restoring needs the manifest to recover original paths")]
+    MissingManifest(PathBuf),
+    #[error("Refusing to restore to {0}: path escapes the destination directory")]
+    UnsafePath(PathBuf),
+    #[error("Invalid base64 in {name}: {source}")]
+    Base64 { name: String, source: base64::DecodeError },
+}
+
+/// Counts of what a restore did.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RestoreSummary {
+    /// Files written back into the tree.
+    pub restored: usize,
+    /// Artifacts whose checksum shows they were edited or corrupted after being written.
+    /// They are only restored with `allow_modified`.
+    pub modified: usize,
+    /// Manifest entries whose artifact no longer exists.
+    pub missing: usize,
+    /// Binary files that were written as placeholders and cannot be restored.
+    pub placeholders: usize,
+}
+
+/// Reverses the flattening of a previous run, writing artifacts back to their original paths.
+///
+/// `from` is either a destination directory written by rustifacts, whose `MANIFEST.json`
+/// maps each flattened name back to its original path, or a file written with
+/// `--single-file`, whose delimiters name each original path. Artifacts written with
+/// `--format markdown` or `claude-xml` are unwrapped from their markup, as recorded in the
+/// manifest. Checksum headers are verified and stripped from the restored files, and
+/// artifacts without one are verified against the checksum in the manifest. An artifact that fails verification is not
+/// restored unless `allow_modified` is set, and neither is any other part of the same
+/// split file. Files merged as duplicates of an artifact are restored from it, and binary
+/// files written as base64 are decoded. Binary files written as placeholders are skipped.
+///
+/// # Arguments
+///
+/// * `from` - The artifact directory or single file to restore from.
+/// * `to` - The directory to rebuild the original tree in.
+/// * `allow_modified` - Whether to restore artifacts that fail checksum verification.
+///
+/// # Returns
+///
+/// Returns `Result<RestoreSummary, RestoreError>` describing the restored files,
+/// or a `RestoreError` if the artifacts could not be read or written.
+pub fn restore(from: &Path, to: &Path, allow_modified: bool) -> Result<RestoreSummary, RestoreError> {
+    info!("Restoring {} into {}", from.display(), to.display());
+    if from.is_file() {
+        return restore_single_file(from, to);
+    }
+    if !Manifest::exists_in(from) {
+        return Err(RestoreError::MissingManifest(from.to_path_buf()));
+    }
+
+    let manifest: Manifest = serde_json::from_str(&fs::read_to_string(from.join(MANIFEST_FILE))?)?;
+    let mut summary = RestoreSummary::default();
+    // Every artifact is verified before anything is written, so that a modified part of a
+    // split file keeps the other parts from being restored as well.
+    let mut verified = Vec::new();
+    let mut modified = HashSet::new();
+    for entry in &manifest.artifacts {
+        if entry.binary == Some(BinaryMode::Placeholder) {
+            debug!("Skipping {}: binary file was written as a placeholder", entry.original_path);
+            summary.placeholders += 1;
+            continue;
+        }
+        let artifact_path = from.join(&entry.new_filename);
+        let text = match fs::read_to_string(&artifact_path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                warn!("Skipping {}: artifact {} is missing", entry.original_path, entry.new_filename);
+                summary.missing += 1;
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let (content, result) = checksum::verify_artifact(&text, manifest.format, entry);
+        match result {
+            Ok(()) => {}
+            Err(ChecksumError::MissingHeader) => {
+                debug!("Not verifying {}: the manifest records no checksum for it", entry.new_filename);
+            }
+            Err(e) => {
+                summary.modified += 1;
+                modified.insert(entry.original_path.as_str());
+                if allow_modified {
+                    warn!("{} was modified after it was written ({}); restoring it anyway", entry.new_filename, e);
+                } else {
+                    warn!("Skipping {}: {} was modified after it was written ({}); pass --allow-modified to restore it anyway",
+                          entry.original_path, entry.new_filename, e);
+                }
+            }
+        }
+        let content = match entry.binary {
+            Some(BinaryMode::Base64) => binary::decode(&content)
+                .map_err(|source| RestoreError::Base64 { name: entry.new_filename.clone(), source })?,
+            _ => content.into_bytes(),
+        };
+        verified.push((entry, content));
+    }
+
+    for (entry, content) in verified {
+        if !allow_modified && modified.contains(entry.original_path.as_str()) {
+            continue;
+        }
+        // The parts of a split file follow each other, and every part after the first is appended.
+        let append = entry.part.is_some_and(|part| part.index > 1);
+        for original_path in std::iter::once(&entry.original_path).chain(&entry.duplicates) {
+            write_restored(to, Path::new(original_path), &content, append)?;
+            if !append {
+                summary.restored += 1;
+            }
+        }
+    }
+    Ok(summary)
+}
+
+/// Restores the files delimited by `--- BEGIN <path> ---` / `--- END <path> ---` markers.
+///
+/// An END line carrying [`format::NO_NEWLINE_NOTE`] marks content that had no trailing
+/// newline, so the separator the writer added before it is dropped again.
+fn restore_single_file(from: &Path, to: &Path) -> Result<RestoreSummary, RestoreError> {
+    let text = fs::read_to_string(from)?;
+    let mut summary = RestoreSummary::default();
+    let mut restored = HashSet::new();
+    let mut lines = text.split_inclusive('\n');
+    while let Some(line) = lines.next() {
+        let Some(path) = line.trim_end().strip_prefix("--- BEGIN ").and_then(|rest| rest.strip_suffix(" ---")) else {
+            continue;
+        };
+        let end = format!("--- END {} ---", path);
+        let end_without_newline = format!("--- END {}{} ---", path, format::NO_NEWLINE_NOTE);
+        let mut content = String::new();
+        for line in lines.by_ref() {
+            if line.trim_end() == end {
+                break;
+            }
+            if line.trim_end() == end_without_newline {
+                content.pop();
+                break;
+            }
+            content.push_str(line);
+        }
+        // A path that appears again is the next part of a split file.
+        let append = !restored.insert(path.to_string());
+        write_restored(to, Path::new(path), content.as_bytes(), append)?;
+        if !append {
+            summary.restored += 1;
+        }
+    }
+    Ok(summary)
+}
+
+/// Writes one restored file beneath the destination, or appends to it, refusing paths that
+/// would escape it.
+fn write_restored(to: &Path, relative_path: &Path, content: &[u8], append: bool) -> Result<(), RestoreError> {
+    if !relative_path.components().all(|component| matches!(component, Component::Normal(_) | Component::CurDir)) {
+        return Err(RestoreError::UnsafePath(relative_path.to_path_buf()));
+    }
+    let dest_path = to.join(relative_path);
+    if let Some(parent) = dest_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    debug!("Restoring {}", dest_path.display());
+    if append {
+        fs::OpenOptions::new().append(true).create(true).open(dest_path)?.write_all(content)?;
+    } else {
+        fs::write(dest_path, content)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::artifact::Artifact;
+    use crate::config::{Config, OutputFormat};
+    use crate::writer::{DirectoryWriter, SingleFileWriter, Writer};
+
+    /// Writes artifacts for the given files into `dir` the way a run does, with or without
+    /// `--checksum-header`.
+    fn write_artifacts(dir: &Path, files: &[(&str, &str)], checksum_header: bool) {
+        write_formatted(dir, files, checksum_header, OutputFormat::Plain);
+    }
+
+    fn write_formatted(dir: &Path, files: &[(&str, &str)], checksum_header: bool, format: OutputFormat) {
+        let config = Config { format, ..Config::default() };
+        let artifacts: Vec<Artifact> = files
+            .iter()
+            .map(|(path, content)| Artifact::generated(PathBuf::from(path), PathBuf::from(path), content.to_string(), &config))
+            .collect();
+        DirectoryWriter::new(dir).checksum_header(checksum_header).format(format).write(&artifacts).unwrap();
+    }
+
+    #[test]
+    fn restores_verified_artifacts() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = (dir.path().join("from"), dir.path().join("to"));
+        write_artifacts(&from, &[("src/main.rs", "fn main() {}\n"), ("package.json", "{\"name\": \"demo\"}\n")], true);
+
+        let summary = restore(&from, &to, false).unwrap();
+        assert_eq!((summary.restored, summary.modified), (2, 0));
+        assert_eq!(fs::read_to_string(to.join("src/main.rs")).unwrap(), "fn main() {}\n");
+        assert_eq!(fs::read_to_string(to.join("package.json")).unwrap(), "{\"name\": \"demo\"}\n");
+    }
+
+    #[test]
+    fn skips_modified_artifacts_unless_allowed() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = (dir.path().join("from"), dir.path().join("to"));
+        write_artifacts(&from, &[("src/main.rs", "fn main() {}\n"), ("package.json", "{\"name\": \"demo\"}\n")], true);
+        let header = fs::read_to_string(from.join("src_main.rs")).unwrap().lines().next().unwrap().to_string();
+        fs::write(from.join("src_main.rs"), format!("{}\nfn main() {{ edited() }}\n", header)).unwrap();
+        fs::write(from.join("package.json"), "{\"name\": \"edited\"}\n").unwrap();
+
+        let summary = restore(&from, &to, false).unwrap();
+        assert_eq!((summary.restored, summary.modified), (0, 2));
+        assert!(!to.join("src/main.rs").exists());
+        assert!(!to.join("package.json").exists());
+
+        let summary = restore(&from, &to, true).unwrap();
+        assert_eq!((summary.restored, summary.modified), (2, 2));
+        assert_eq!(fs::read_to_string(to.join("src/main.rs")).unwrap(), "fn main() { edited() }\n");
+    }
+
+    #[test]
+    fn skips_modified_artifacts_written_without_headers() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = (dir.path().join("from"), dir.path().join("to"));
+        write_artifacts(&from, &[("src/main.rs", "fn main() {}\n"), ("src/lib.rs", "pub fn f() {}\n")], false);
+        fs::write(from.join("src_main.rs"), "fn main() { edited() }\n").unwrap();
+
+        let summary = restore(&from, &to, false).unwrap();
+        assert_eq!((summary.restored, summary.modified), (1, 1));
+        assert!(!to.join("src/main.rs").exists());
+        assert_eq!(fs::read_to_string(to.join("src/lib.rs")).unwrap(), "pub fn f() {}\n");
+    }
+
+    #[test]
+    fn unwraps_markdown_artifacts() {
+        let files = [("src/main.rs", "fn main() {}\n"), ("notes.txt", "no trailing newline"), ("README.md", "```rust\nlet x = 1;\n```\n")];
+        for checksum_header in [false, true] {
+            let dir = tempfile::tempdir().unwrap();
+            let (from, to) = (dir.path().join("from"), dir.path().join("to"));
+            write_formatted(&from, &files, checksum_header, OutputFormat::Markdown);
+
+            let summary = restore(&from, &to, false).unwrap();
+            assert_eq!((summary.restored, summary.modified), (3, 0));
+            for (path, content) in files {
+                assert_eq!(fs::read_to_string(to.join(path)).unwrap(), content);
+            }
+        }
+    }
+
+    #[test]
+    fn unwraps_claude_xml_artifacts() {
+        let files = [("src/main.rs", "fn f<T>(a: &T) -> bool { a > \"<b>\" }\n"), ("notes.txt", "AT&T & <co>")];
+        for checksum_header in [false, true] {
+            let dir = tempfile::tempdir().unwrap();
+            let (from, to) = (dir.path().join("from"), dir.path().join("to"));
+            write_formatted(&from, &files, checksum_header, OutputFormat::ClaudeXml);
+
+            let summary = restore(&from, &to, false).unwrap();
+            assert_eq!((summary.restored, summary.modified), (2, 0));
+            for (path, content) in files {
+                assert_eq!(fs::read_to_string(to.join(path)).unwrap(), content);
+            }
+        }
+    }
+
+    #[test]
+    fn restores_single_file_bundles_byte_for_byte() {
+        let files = [("src/main.rs", "fn main() {}\n"), ("notes.txt", "no trailing newline"), ("blank.txt", "ends in a blank line\n\n")];
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = (dir.path().join("bundle.txt"), dir.path().join("to"));
+        let config = Config::default();
+        let artifacts: Vec<Artifact> = files
+            .iter()
+            .map(|(path, content)| Artifact::generated(PathBuf::from(path), PathBuf::from(path), content.to_string(), &config))
+            .collect();
+        SingleFileWriter::new(&from).write(&artifacts).unwrap();
+
+        let summary = restore(&from, &to, false).unwrap();
+        assert_eq!(summary.restored, 3);
+        for (path, content) in files {
+            assert_eq!(fs::read_to_string(to.join(path)).unwrap(), content);
+        }
+    }
+
+    #[test]
+    fn refuses_paths_outside_the_destination() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = write_restored(dir.path(), Path::new("../escape.rs"), b"", false);
+        assert!(matches!(result, Err(RestoreError::UnsafePath(_))));
+    }
+}
@@ -0,0 +1,135 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// The request was not valid JSON.
+pub const PARSE_ERROR: i64 = -32700;
+/// The JSON was not a valid JSON-RPC request.
+pub const INVALID_REQUEST: i64 = -32600;
+/// No method has the requested name.
+pub const METHOD_NOT_FOUND: i64 = -32601;
+/// The parameters did not match what the method expects.
+pub const INVALID_PARAMS: i64 = -32602;
+/// The method ran and failed, for instance because collection failed.
+pub const SERVER_ERROR: i64 = -32000;
+
+/// A JSON-RPC 2.0 request, or a notification if it has no `id`.
+#[derive(Deserialize, Debug)]
+pub struct Request {
+    pub jsonrpc: String,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+    pub id: Option<Value>,
+}
+
+/// The error member of a failed JSON-RPC response.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    /// Creates an error with the given code and message.
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self { code, message: message.into() }
+    }
+}
+
+/// Decodes the parameters of a method, treating missing parameters as an empty object.
+///
+/// # Arguments
+///
+/// * `params` - The `params` member of the request.
+///
+/// # Returns
+///
+/// Returns `Result<T, RpcError>` containing the decoded parameters, or an
+/// `INVALID_PARAMS` error if they do not match `T`.
+pub fn params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+/// Builds a request to send to a JSON-RPC server.
+///
+/// # Arguments
+///
+/// * `method` - The name of the method to call.
+/// * `params` - The parameters, or `Value::Null` to leave them out.
+/// * `id` - The identifier the response will carry.
+///
+/// # Returns
+///
+/// A `Value` containing the request object.
+pub fn request(method: &str, params: Value, id: u64) -> Value {
+    let mut request = json!({"jsonrpc": "2.0", "method": method, "id": id});
+    if !params.is_null() {
+        request["params"] = params;
+    }
+    request
+}
+
+/// Handles one line of input: a single request, or an array of requests sent as a batch.
+///
+/// Each request is passed to `call` with its method name and parameters. Notifications
+/// get no response, so a line made only of notifications produces no output.
+///
+/// # Arguments
+///
+/// * `line` - The JSON text of the request or batch.
+/// * `call` - Runs a method, returning its result or an error.
+///
+/// # Returns
+///
+/// Returns `Some(Value)` with the response or array of responses, or `None` if there is
+/// nothing to send back.
+///
+/// # Example
+///
+/// ```
+/// use rustifacts::rpc::{handle, RpcError, METHOD_NOT_FOUND};
+/// use serde_json::json;
+///
+/// let call = |method: &str, _params| match method {
+///     "ping" => Ok(json!("pong")),
+///     _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("Unknown method {}", method))),
+/// };
+/// let response = handle(r#"{"jsonrpc": "2.0", "method": "ping", "id": 1}"#, call).unwrap();
+/// assert_eq!(response, json!({"jsonrpc": "2.0", "result": "pong", "id": 1}));
+/// assert_eq!(handle(r#"{"jsonrpc": "2.0", "method": "ping"}"#, call), None);
+/// ```
+pub fn handle(line: &str, mut call: impl FnMut(&str, Value) -> Result<Value, RpcError>) -> Option<Value> {
+    let message: Value = match serde_json::from_str(line) {
+        Ok(message) => message,
+        Err(e) => return Some(response(Value::Null, Err(RpcError::new(PARSE_ERROR, e.to_string())))),
+    };
+    match message {
+        Value::Array(batch) if !batch.is_empty() => {
+            let responses: Vec<Value> = batch.into_iter().filter_map(|message| handle_one(message, &mut call)).collect();
+            (!responses.is_empty()).then_some(Value::Array(responses))
+        }
+        message => handle_one(message, &mut call),
+    }
+}
+
+/// Handles a single request object.
+fn handle_one(message: Value, call: &mut impl FnMut(&str, Value) -> Result<Value, RpcError>) -> Option<Value> {
+    let id = message.get("id").cloned().unwrap_or(Value::Null);
+    let request = match serde_json::from_value::<Request>(message) {
+        Ok(request) if request.jsonrpc == "2.0" => request,
+        Ok(_) => return Some(response(id, Err(RpcError::new(INVALID_REQUEST, "jsonrpc must be \"2.0\"")))),
+        Err(e) => return Some(response(id, Err(RpcError::new(INVALID_REQUEST, e.to_string())))),
+    };
+    let result = call(&request.method, request.params);
+    request.id.map(|id| response(id, result))
+}
+
+/// Builds the response to a request.
+fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({"jsonrpc": "2.0", "result": result, "id": id}),
+        Err(error) => json!({"jsonrpc": "2.0", "error": error, "id": id}),
+    }
+}
@@ -0,0 +1,218 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::path::Path;
+use globset::{GlobBuilder, GlobMatcher};
+use serde::Deserialize;
+use crate::config::{Config, FrontMatterMode};
+use crate::config_file::ByteSize;
+use crate::paths;
+use crate::transform::TransformKind;
+
+/// A `[[rules]]` section of a configuration file, giving the files that match a pattern
+/// their own treatment.
+///
+/// Settings a rule leaves out keep the value the run has for every other file. When several
+/// rules match a file, they are applied in the order they are declared, so later rules win.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Rule {
+    /// The glob pattern, matched against paths relative to the source directory.
+    pub pattern: Pattern,
+    /// Whether to leave the matching files out.
+    #[serde(default)]
+    pub exclude: bool,
+    /// Keep only this many lines of each matching file.
+    pub truncate: Option<usize>,
+    pub max_lines: Option<usize>,
+    pub max_file_size: Option<ByteSize>,
+    pub front_matter: Option<FrontMatterMode>,
+    pub squeeze_blank_lines: Option<bool>,
+    pub minify_indent: Option<bool>,
+    /// Transformers to run on the matching files after those of the run.
+    #[serde(default)]
+    pub transforms: Vec<TransformKind>,
+    /// Shell commands to pipe the matching files through after those of the run.
+    #[serde(default)]
+    pub transform_cmds: Vec<String>,
+    /// Parts of the matching files to remove.
+    #[serde(default)]
+    pub strip: Vec<Strip>,
+}
+
+/// A part of a file that a rule can remove.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Strip {
+    /// The front-matter block at the top of Markdown and MDX files.
+    #[serde(alias = "frontmatter")]
+    FrontMatter,
+    /// Comments, removed with the `strip-comments` transformer.
+    Comments,
+}
+
+/// A glob pattern, compiled when the configuration file is read so that invalid patterns
+/// are reported there.
+#[derive(Clone)]
+pub struct Pattern {
+    glob: String,
+    matcher: GlobMatcher,
+}
+
+impl Pattern {
+    /// Compiles a glob pattern the way `--include-glob` and `--exclude-glob` are compiled.
+    ///
+    /// # Arguments
+    ///
+    /// * `glob` - The glob pattern.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<Self, globset::Error>` containing the compiled pattern, or an error
+    /// if the pattern is invalid.
+    pub fn new(glob: &str) -> Result<Self, globset::Error> {
+        let matcher = GlobBuilder::new(&paths::normalize_glob(glob)).literal_separator(true).build()?.compile_matcher();
+        Ok(Self { glob: glob.to_string(), matcher })
+    }
+
+    /// Checks whether a path, relative to the source directory, matches the pattern.
+    pub fn is_match(&self, relative_path: &Path) -> bool {
+        self.matcher.is_match(relative_path)
+    }
+}
+
+impl fmt::Debug for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.glob)
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let glob = String::deserialize(deserializer)?;
+        Self::new(&glob).map_err(serde::de::Error::custom)
+    }
+}
+
+/// How a single file is collected once the rules that match it are applied.
+#[derive(Debug)]
+pub struct Treatment<'a> {
+    /// The configuration for the file, with the settings of the matching rules applied.
+    pub config: Cow<'a, Config>,
+    /// Whether a matching rule leaves the file out.
+    pub exclude: bool,
+    /// How many lines of the file to keep, if a matching rule truncates it.
+    pub truncate: Option<usize>,
+}
+
+/// Applies the `[[rules]]` of a configuration that match a file.
+///
+/// The configuration is only copied if a rule matches, so files no rule applies to
+/// are collected with the configuration as it is.
+///
+/// # Arguments
+///
+/// * `config` - The configuration options, including its rules.
+/// * `relative_path` - The path of the file, relative to the source directory.
+///
+/// # Returns
+///
+/// A `Treatment` holding the configuration to collect the file with.
+///
+/// # Example
+///
+/// ```
+/// use std::path::Path;
+/// use rustifacts::config::Config;
+/// use rustifacts::rules::{self, Pattern, Rule, Strip};
+///
+/// let mut config = Config::default();
+/// config.rules.push(Rule {
+///     pattern: Pattern::new("docs/**").unwrap(),
+///     exclude: false,
+///     truncate: Some(200),
+///     max_lines: None,
+///     max_file_size: None,
+///     front_matter: None,
+///     squeeze_blank_lines: None,
+///     minify_indent: None,
+///     transforms: Vec::new(),
+///     transform_cmds: Vec::new(),
+///     strip: vec![Strip::FrontMatter],
+/// });
+///
+/// let docs = rules::treatment(&config, Path::new("docs/guide.md"));
+/// assert_eq!(docs.truncate, Some(200));
+/// assert_eq!(docs.config.front_matter, rustifacts::config::FrontMatterMode::Strip);
+/// assert_eq!(rules::treatment(&config, Path::new("src/main.rs")).truncate, None);
+/// ```
+pub fn treatment<'a>(config: &'a Config, relative_path: &Path) -> Treatment<'a> {
+    let mut treatment = Treatment { config: Cow::Borrowed(config), exclude: false, truncate: None };
+    for rule in config.rules.iter().filter(|rule| rule.pattern.is_match(relative_path)) {
+        treatment.exclude |= rule.exclude;
+        treatment.truncate = rule.truncate.or(treatment.truncate);
+        let file_config = treatment.config.to_mut();
+        if let Some(max_lines) = rule.max_lines {
+            file_config.max_lines = Some(max_lines);
+        }
+        if let Some(max_file_size) = rule.max_file_size {
+            file_config.max_file_size = Some(max_file_size.0);
+            file_config.max_file_size_per_extension.clear();
+        }
+        if let Some(front_matter) = rule.front_matter {
+            file_config.front_matter = front_matter;
+        }
+        if let Some(squeeze_blank_lines) = rule.squeeze_blank_lines {
+            file_config.squeeze_blank_lines = squeeze_blank_lines;
+        }
+        if let Some(minify_indent) = rule.minify_indent {
+            file_config.minify_indent = minify_indent;
+        }
+        let stripped = rule.strip.iter().filter_map(|strip| match strip {
+            Strip::FrontMatter => {
+                file_config.front_matter = FrontMatterMode::Strip;
+                None
+            }
+            Strip::Comments => Some(TransformKind::StripComments),
+        });
+        let added: Vec<TransformKind> = rule.transforms.iter().copied().chain(stripped).collect();
+        for kind in added {
+            if !file_config.transforms.contains(&kind) {
+                file_config.transforms.push(kind);
+            }
+        }
+        file_config.transform_cmds.extend(rule.transform_cmds.iter().cloned());
+    }
+    treatment
+}
+
+/// Keeps the first lines of a file's content, noting how many lines were cut.
+///
+/// # Arguments
+///
+/// * `content` - The content to truncate.
+/// * `lines` - The number of lines to keep.
+///
+/// # Returns
+///
+/// A `String` containing the content unchanged if it has no more than `lines` lines,
+/// otherwise its first `lines` lines followed by a note.
+///
+/// # Example
+///
+/// ```
+/// use rustifacts::rules::truncate;
+///
+/// assert_eq!(truncate("a\nb\nc\n", 1), "a\n[... 2 more lines truncated by rustifacts ...]\n");
+/// assert_eq!(truncate("a\nb\n", 2), "a\nb\n");
+/// ```
+pub fn truncate(content: &str, lines: usize) -> String {
+    let total = content.lines().count();
+    if total <= lines {
+        return content.to_string();
+    }
+    let mut truncated: String = content.split_inclusive('\n').take(lines).collect();
+    if !truncated.is_empty() && !truncated.ends_with('\n') {
+        truncated.push('\n');
+    }
+    truncated.push_str(&format!("[... {} more lines truncated by rustifacts ...]\n", total - lines));
+    truncated
+}
@@ -0,0 +1,245 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use serde::Serialize;
+use uuid::Uuid;
+
+/// Directory under dest_dir that holds one subdirectory per run.
+pub const RUNS_DIR: &str = "runs";
+
+/// Name of the pointer to the most recent run inside dest_dir.
+pub const LATEST: &str = "latest";
+
+/// Generates a new unique run ID.
+///
+/// # Returns
+///
+/// A `String` containing a random UUID.
+pub fn new_run_id() -> String {
+    Uuid::new_v4().to_string()
+}
+
+/// Returns the output directory for a run inside the destination directory.
+///
+/// # Arguments
+///
+/// * `dest_dir` - The destination directory path.
+/// * `run_id` - The ID of the run.
+///
+/// # Returns
+///
+/// A `PathBuf` pointing to `dest_dir/runs/<run_id>`.
+pub fn run_dir(dest_dir: &Path, run_id: &str) -> PathBuf {
+    dest_dir.join(RUNS_DIR).join(run_id)
+}
+
+/// Points `dest_dir/latest` at the given run.
+///
+/// On Unix this is a relative symlink to `runs/<run_id>`; elsewhere it is a plain
+/// file containing the run ID. An existing `latest` is only replaced if it is such a
+/// pointer, so that a file or directory rustifacts did not create is never removed.
+///
+/// # Arguments
+///
+/// * `dest_dir` - The destination directory path.
+/// * `run_id` - The ID of the run to point at.
+///
+/// # Returns
+///
+/// Returns `io::Result<()>` indicating success or failure of updating the pointer, or an
+/// `io::ErrorKind::AlreadyExists` error if `latest` exists and is not a run pointer.
+pub fn update_latest(dest_dir: &Path, run_id: &str) -> io::Result<()> {
+    let latest = dest_dir.join(LATEST);
+    if latest.symlink_metadata().is_ok() {
+        if !is_latest_pointer(&latest) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("refusing to replace {}: it was not written by rustifacts", latest.display()),
+            ));
+        }
+        fs::remove_file(&latest)?;
+    }
+
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(Path::new(RUNS_DIR).join(run_id), &latest)
+    }
+    #[cfg(not(unix))]
+    {
+        fs::write(&latest, run_id)
+    }
+}
+
+/// Checks whether a path is a `latest` pointer written by [`update_latest`].
+///
+/// # Arguments
+///
+/// * `latest` - The path of the pointer.
+///
+/// # Returns
+///
+/// Returns `true` if the path is a symlink to `runs/<run_id>` (on Unix) or a file
+/// holding a run ID (elsewhere).
+pub fn is_latest_pointer(latest: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        let Ok(target) = fs::read_link(latest) else {
+            return false;
+        };
+        let mut components = target.components();
+        components.next().is_some_and(|first| first.as_os_str() == RUNS_DIR)
+            && components.next().is_some_and(|id| Uuid::parse_str(&id.as_os_str().to_string_lossy()).is_ok())
+            && components.next().is_none()
+    }
+    #[cfg(not(unix))]
+    {
+        latest.is_file() && fs::read_to_string(latest).is_ok_and(|run_id| Uuid::parse_str(run_id.trim()).is_ok())
+    }
+}
+
+/// Checks whether a directory holds nothing but the run layout written with `--run-dirs`:
+/// a `runs` directory and, optionally, a `latest` pointer.
+///
+/// # Arguments
+///
+/// * `dest_dir` - The destination directory path.
+///
+/// # Returns
+///
+/// Returns `io::Result<bool>` containing `true` if the directory holds a run layout
+/// and nothing else.
+pub fn is_run_layout(dest_dir: &Path) -> io::Result<bool> {
+    let mut has_runs = false;
+    for entry in fs::read_dir(dest_dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        match entry.file_name().to_str() {
+            Some(RUNS_DIR) if path.is_dir() => has_runs = true,
+            Some(LATEST) if is_latest_pointer(&path) => {}
+            _ => return Ok(false),
+        }
+    }
+    Ok(has_runs)
+}
+
+/// The machine-readable outcome of a run, written with `--summary-json`.
+#[derive(Serialize, Debug, Clone)]
+pub struct RunSummary {
+    /// The ID of the run, as logged at startup and recorded in the manifest.
+    pub run_id: String,
+    /// The directory or file the artifacts were written to.
+    pub destination: PathBuf,
+    /// The number of artifacts written, including those left in place because they were unchanged.
+    pub written: usize,
+    /// The number of files that passed the filters but were not collected, such as empty,
+    /// binary, unreadable, or special files.
+    pub skipped: usize,
+    /// The total number of tokens in the written artifacts.
+    pub tokens: usize,
+}
+
+impl RunSummary {
+    /// Writes the summary as pretty-printed JSON.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The file to write the summary to.
+    ///
+    /// # Returns
+    ///
+    /// Returns `io::Result<()>` indicating success or failure of the write operation.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json + "\n")
+    }
+}
+
+/// Formats a point in time as an RFC 3339 UTC timestamp with second precision.
+///
+/// # Arguments
+///
+/// * `time` - The time to format.
+///
+/// # Returns
+///
+/// A `String` such as `2024-09-01T12:30:00Z`.
+pub fn utc_timestamp(time: SystemTime) -> String {
+    let seconds = time.duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or_default();
+    // Convert days since the epoch to a civil date (Howard Hinnant's algorithm).
+    let days = (seconds / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    let time_of_day = seconds % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time_of_day / 3_600,
+        time_of_day % 3_600 / 60,
+        time_of_day % 60
+    )
+}
+
+/// Parses a point in time given as a UTC date, a UTC timestamp, or an age.
+///
+/// Dates are written `YYYY-MM-DD`, timestamps `YYYY-MM-DDTHH:MM:SS` with an optional
+/// trailing `Z`, and ages as a number followed by `s`, `m`, `h`, `d`, or `w` (such as
+/// `7d`), counted back from now.
+///
+/// # Arguments
+///
+/// * `text` - The point in time to parse.
+///
+/// # Returns
+///
+/// Returns `Result<SystemTime, String>` containing the point in time, or a message
+/// describing why it is invalid.
+pub fn parse_since(text: &str) -> Result<SystemTime, String> {
+    let text = text.trim();
+    let invalid = || format!("invalid time {:?}: expected a date such as 2024-01-01 or an age such as 7d", text);
+    let split = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let (count, unit) = text.split_at(split);
+    let unit_seconds = match unit.to_ascii_lowercase().as_str() {
+        "s" => Some(1),
+        "m" => Some(60),
+        "h" => Some(3_600),
+        "d" => Some(86_400),
+        "w" => Some(604_800),
+        _ => None,
+    };
+    if let Some(unit_seconds) = unit_seconds {
+        let count: u64 = count.parse().map_err(|_| invalid())?;
+        return SystemTime::now().checked_sub(Duration::from_secs(count.saturating_mul(unit_seconds))).ok_or_else(invalid);
+    }
+
+    let (date, time) = text.split_once(['T', ' ']).unwrap_or((text, "00:00:00"));
+    let date: Vec<u32> = date.split('-').map(str::parse).collect::<Result<_, _>>().map_err(|_| invalid())?;
+    let time: Vec<u32> = time.trim_end_matches('Z').split(':').map(str::parse).collect::<Result<_, _>>().map_err(|_| invalid())?;
+    let (&[year, month, day], &[hour, minute, second]) = (date.as_slice(), time.as_slice()) else {
+        return Err(invalid());
+    };
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 59 || year < 1970 {
+        return Err(invalid());
+    }
+    // Convert the civil date to days since the epoch (Howard Hinnant's algorithm).
+    let year = i64::from(year) - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month_index = i64::from(if month > 2 { month - 3 } else { month + 9 });
+    let day_of_year = (153 * month_index + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+    let seconds = days as u64 * 86_400 + u64::from(hour) * 3_600 + u64::from(minute) * 60 + u64::from(second);
+    Ok(UNIX_EPOCH + Duration::from_secs(seconds))
+}
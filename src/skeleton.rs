@@ -0,0 +1,172 @@
+use std::ops::Range;
+use std::path::Path;
+use log::debug;
+use tree_sitter::{Language, Node, Parser};
+use crate::transform::{TransformError, Transformer};
+
+/// What replaces a function body in languages that delimit bodies with braces.
+pub const BRACE_BODY: &str = "{ … }";
+
+/// What replaces a Python function body, which keeps the stub valid Python.
+pub const PYTHON_BODY: &str = "...";
+
+/// The tree-sitter grammars skeletons can be extracted with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Grammar {
+    Rust,
+    Python,
+    JavaScript,
+    TypeScript,
+    Tsx,
+    Go,
+}
+
+impl Grammar {
+    /// Chooses the grammar for a file from its extension.
+    pub(crate) fn for_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        match extension.as_str() {
+            "rs" => Some(Self::Rust),
+            "py" | "pyi" => Some(Self::Python),
+            "js" | "jsx" | "mjs" | "cjs" => Some(Self::JavaScript),
+            "ts" | "mts" | "cts" => Some(Self::TypeScript),
+            "tsx" => Some(Self::Tsx),
+            "go" => Some(Self::Go),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn language(self) -> Language {
+        match self {
+            Self::Rust => tree_sitter_rust::LANGUAGE.into(),
+            Self::Python => tree_sitter_python::LANGUAGE.into(),
+            Self::JavaScript => tree_sitter_javascript::LANGUAGE.into(),
+            Self::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            Self::Tsx => tree_sitter_typescript::LANGUAGE_TSX.into(),
+            Self::Go => tree_sitter_go::LANGUAGE.into(),
+        }
+    }
+
+    /// The kinds of node whose `body` field holds a function body.
+    fn functions(self) -> &'static [&'static str] {
+        match self {
+            Self::Rust => &["function_item"],
+            Self::Python => &["function_definition"],
+            Self::JavaScript | Self::TypeScript | Self::Tsx => &[
+                "function_declaration",
+                "function_expression",
+                "function",
+                "generator_function_declaration",
+                "generator_function",
+                "method_definition",
+                "arrow_function",
+            ],
+            Self::Go => &["function_declaration", "method_declaration", "func_literal"],
+        }
+    }
+}
+
+/// Reduces a source file to its signatures and structure.
+///
+/// The bodies of functions, methods, and closures declared with a block are replaced
+/// with `{ … }`, or with `...` in Python, where a docstring is kept. Signatures, types,
+/// imports, constants, and doc comments are left as they are. Rust, Python, JavaScript,
+/// TypeScript, and Go are supported, recognized by file extension.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file, used to choose its language.
+/// * `content` - The content of the file.
+///
+/// # Returns
+///
+/// `Some(String)` containing the skeleton, or `None` if the language is not supported or
+/// the file could not be parsed without errors.
+///
+/// # Example
+///
+/// ```
+/// use std::path::Path;
+/// use rustifacts::skeleton::skeleton;
+///
+/// let source = "/// Adds one.\npub fn add_one(x: u32) -> u32 {\n    x + 1\n}\n";
+/// assert_eq!(
+///     skeleton(Path::new("lib.rs"), source).unwrap(),
+///     "/// Adds one.\npub fn add_one(x: u32) -> u32 { … }\n",
+/// );
+/// assert!(skeleton(Path::new("notes.txt"), source).is_none());
+/// ```
+pub fn skeleton(path: &Path, content: &str) -> Option<String> {
+    let grammar = Grammar::for_path(path)?;
+    let mut parser = Parser::new();
+    parser.set_language(&grammar.language()).ok()?;
+    let tree = parser.parse(content, None)?;
+    if tree.root_node().has_error() {
+        debug!("Keeping {} whole: it could not be parsed", path.display());
+        return None;
+    }
+
+    let mut replacements = Vec::new();
+    collect_bodies(tree.root_node(), grammar, &mut replacements);
+    let mut output = String::with_capacity(content.len());
+    let mut end = 0;
+    for (range, replacement) in replacements {
+        output.push_str(&content[end..range.start]);
+        output.push_str(&replacement);
+        end = range.end;
+    }
+    output.push_str(&content[end..]);
+    Some(output)
+}
+
+/// Finds the function bodies beneath a node, in source order, without descending into
+/// the bodies it replaces.
+fn collect_bodies(node: Node, grammar: Grammar, replacements: &mut Vec<(Range<usize>, String)>) {
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        match body_replacement(child, grammar) {
+            Some(replacement) => replacements.push(replacement),
+            None => collect_bodies(child, grammar, replacements),
+        }
+    }
+}
+
+/// Returns the byte range of a function's body and what replaces it, if the node is a
+/// function with a block body.
+fn body_replacement(node: Node, grammar: Grammar) -> Option<(Range<usize>, String)> {
+    if !grammar.functions().contains(&node.kind()) {
+        return None;
+    }
+    let body = node.child_by_field_name("body")?;
+    if grammar != Grammar::Python {
+        // Arrow functions with an expression body have no block to replace.
+        return matches!(body.kind(), "block" | "statement_block").then(|| (body.byte_range(), BRACE_BODY.to_string()));
+    }
+
+    let docstring = body
+        .named_child(0)
+        .filter(|statement| statement.kind() == "expression_statement")
+        .filter(|statement| statement.named_child(0).is_some_and(|expression| expression.kind() == "string"));
+    match docstring {
+        // A body that is only a docstring is already as short as a stub.
+        Some(_) if body.named_child_count() == 1 => None,
+        Some(docstring) => {
+            let indent = " ".repeat(body.start_position().column);
+            Some((docstring.end_byte()..body.end_byte(), format!("\n{}{}", indent, PYTHON_BODY)))
+        }
+        None => Some((body.byte_range(), PYTHON_BODY.to_string())),
+    }
+}
+
+/// Replaces function bodies with `{ … }` (or `...` in Python), selected with `--skeleton`
+/// or the `skeleton` transformer.
+///
+/// Files in unsupported languages, and files that cannot be parsed, are kept whole.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Skeleton;
+
+impl Transformer for Skeleton {
+    fn transform(&self, path: &Path, content: &str) -> Result<String, TransformError> {
+        Ok(skeleton(path, content).unwrap_or_else(|| content.to_string()))
+    }
+}
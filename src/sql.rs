@@ -0,0 +1,192 @@
+use std::path::Path;
+
+/// SQL files at least this large are treated as dumps by `--summarize-sql-dumps`.
+pub const DUMP_THRESHOLD: usize = 64 * 1024;
+
+/// Checks whether a file is SQL, judging by its extension.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file.
+///
+/// # Returns
+///
+/// Returns `true` for `.sql` files.
+pub fn is_sql(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("sql"))
+}
+
+/// Drops the data from a SQL dump, keeping its schema.
+///
+/// `INSERT` and `REPLACE` statements and PostgreSQL `COPY ... FROM stdin` blocks are
+/// removed, along with their leading comments. Each run of consecutive data statements
+/// is replaced by a single comment naming the tables it filled. Everything else (DDL,
+/// functions, settings) is kept as written.
+///
+/// # Arguments
+///
+/// * `content` - The SQL dump.
+///
+/// # Returns
+///
+/// A `String` containing the dump without its data.
+///
+/// # Example
+///
+/// ```
+/// use rustifacts::sql::summarize_dump;
+///
+/// let dump = "CREATE TABLE users (id int, name text);\nINSERT INTO users VALUES (1, 'a;b');\nINSERT INTO users VALUES (2, 'c');\n";
+/// assert_eq!(
+///     summarize_dump(dump),
+///     "CREATE TABLE users (id int, name text);\n-- 2 data statements omitted: users\n"
+/// );
+/// ```
+pub fn summarize_dump(content: &str) -> String {
+    let mut summarized = String::with_capacity(content.len() / 4);
+    let mut omitted = Omitted::default();
+    let mut start = 0;
+    while start < content.len() {
+        let end = statement_end(content, start);
+        let statement = &content[start..end];
+        match data_table(skip_comments(statement)) {
+            Some((table, is_copy)) => {
+                omitted.add(table);
+                start = if is_copy { copy_data_end(content, end) } else { end };
+            }
+            None => {
+                let statement = if omitted.flush(&mut summarized) {
+                    statement.strip_prefix("\r\n").or_else(|| statement.strip_prefix('\n')).unwrap_or(statement)
+                } else {
+                    statement
+                };
+                summarized.push_str(statement);
+                start = end;
+            }
+        }
+    }
+    omitted.flush(&mut summarized);
+    summarized
+}
+
+/// Data statements dropped since the last statement that was kept.
+#[derive(Default)]
+struct Omitted {
+    count: usize,
+    tables: Vec<String>,
+}
+
+impl Omitted {
+    fn add(&mut self, table: String) {
+        self.count += 1;
+        if !self.tables.contains(&table) {
+            self.tables.push(table);
+        }
+    }
+
+    /// Writes the summary comment for the dropped statements, if there were any.
+    fn flush(&mut self, summarized: &mut String) -> bool {
+        if self.count == 0 {
+            return false;
+        }
+        if !summarized.is_empty() && !summarized.ends_with('\n') {
+            summarized.push('\n');
+        }
+        let noun = if self.count == 1 { "statement" } else { "statements" };
+        summarized.push_str(&format!("-- {} data {} omitted: {}\n", self.count, noun, self.tables.join(", ")));
+        *self = Self::default();
+        true
+    }
+}
+
+/// Returns the index just past the `;` ending the statement that starts at `start`.
+///
+/// Semicolons inside string literals, quoted identifiers, comments, and PostgreSQL
+/// dollar-quoted bodies do not end a statement. A final statement without a `;`
+/// runs to the end of the content.
+fn statement_end(content: &str, start: usize) -> usize {
+    let bytes = content.as_bytes();
+    let mut i = start;
+    while i < bytes.len() {
+        match bytes[i] {
+            b';' => return i + 1,
+            quote @ (b'\'' | b'"' | b'`') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    // MySQL dumps escape quotes inside strings with a backslash.
+                    if bytes[i] == b'\\' && quote == b'\'' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                i = content[i..].find('\n').map_or(bytes.len(), |offset| i + offset);
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = content[i + 2..].find("*/").map_or(bytes.len(), |offset| i + 2 + offset + 1);
+            }
+            b'$' => {
+                if let Some(tag) = dollar_tag(&content[i..]) {
+                    let body = i + tag.len();
+                    i = content[body..].find(tag).map_or(bytes.len(), |offset| body + offset + tag.len() - 1);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+/// Returns the opening `$tag$` of a dollar-quoted string, if `text` starts with one.
+fn dollar_tag(text: &str) -> Option<&str> {
+    let rest = text.strip_prefix('$')?;
+    let length = rest.find('$')?;
+    let tag = &rest[..length];
+    let is_tag = !tag.starts_with(|c: char| c.is_ascii_digit())
+        && tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    is_tag.then(|| &text[..length + 2])
+}
+
+/// Skips the comments and whitespace at the start of a statement.
+fn skip_comments(mut text: &str) -> &str {
+    loop {
+        text = text.trim_start();
+        if text.starts_with("--") {
+            text = text.find('\n').map_or("", |offset| &text[offset..]);
+        } else if text.starts_with("/*") && !text.starts_with("/*!") {
+            // MySQL's `/*!...*/` conditional comments are executable, so they count as statements.
+            text = text.find("*/").map_or("", |offset| &text[offset + 2..]);
+        } else {
+            return text;
+        }
+    }
+}
+
+/// Returns the table a data statement fills, and whether it is a `COPY ... FROM stdin`
+/// whose rows follow it, or `None` if the statement is not a data statement.
+fn data_table(body: &str) -> Option<(String, bool)> {
+    let mut words = body.split_whitespace();
+    let keyword = words.next()?.to_ascii_uppercase();
+    let table = match keyword.as_str() {
+        "INSERT" | "REPLACE" => words.find(|word| word.eq_ignore_ascii_case("INTO")).and_then(|_| words.next())?,
+        "COPY" if body.to_ascii_uppercase().contains("FROM STDIN") => words.next()?,
+        _ => return None,
+    };
+    let table = table.split('(').next().unwrap_or(table).replace(['`', '"', '[', ']'], "");
+    Some((table, keyword == "COPY"))
+}
+
+/// Returns the index just past the `\.` line ending the rows of a `COPY ... FROM stdin`.
+fn copy_data_end(content: &str, start: usize) -> usize {
+    let mut offset = start;
+    for line in content[start..].split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        if line_start > start && line.trim_end() == "\\." {
+            return line_start + 2;
+        }
+    }
+    content.len()
+}
@@ -0,0 +1,102 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use crate::global_config::GlobalConfig;
+use crate::stats::TokenRecord;
+
+/// Name of the state file inside the user cache directory.
+pub const STATE_FILE_NAME: &str = "state.json";
+
+/// What Rustifacts remembers between runs, such as the last collection run in each directory
+/// and the token totals last written to each destination.
+///
+/// It lives at `state.json` in the user cache directory, so deleting the cache forgets it.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct State {
+    /// The arguments of the last collection run, keyed by the directory it was run from.
+    #[serde(default)]
+    pub last_runs: BTreeMap<String, Vec<String>>,
+    /// The token totals of the last run, keyed by the destination it was written to.
+    #[serde(default)]
+    pub last_tokens: BTreeMap<String, TokenRecord>,
+}
+
+impl State {
+    /// Returns the path of the state file, if a home directory is known.
+    pub fn path() -> Option<PathBuf> {
+        GlobalConfig::cache_dir().map(|dir| dir.join(STATE_FILE_NAME))
+    }
+
+    /// Loads the state, returning an empty one if the file does not exist.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<Self, anyhow::Error>` containing the state, or an error if the file
+    /// exists but cannot be read or parsed.
+    pub fn load() -> Result<Self> {
+        let Some(path) = Self::path().filter(|path| path.is_file()) else {
+            return Ok(Self::default());
+        };
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read state file: {}", path.display()))?;
+        serde_json::from_str(&contents).with_context(|| format!("Failed to parse state file: {}", path.display()))
+    }
+
+    /// Writes the state back to its file, creating the cache directory if needed.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<(), anyhow::Error>` indicating success, or an error if no home
+    /// directory is known or the file cannot be written.
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path().context("No user cache directory is known")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        }
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write state file: {}", temp_path.display()))?;
+        fs::rename(&temp_path, &path).with_context(|| format!("Failed to write state file: {}", path.display()))
+    }
+
+    /// Returns the arguments of the last collection run in a directory, if one is remembered.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The directory the run was started from.
+    pub fn last_run(&self, dir: &Path) -> Option<&[String]> {
+        self.last_runs.get(&key(dir)).map(Vec::as_slice)
+    }
+
+    /// Remembers the arguments of a collection run in a directory, replacing the previous run.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The directory the run was started from.
+    /// * `args` - The command-line arguments, without the program name.
+    pub fn remember_run(&mut self, dir: &Path, args: Vec<String>) {
+        self.last_runs.insert(key(dir), args);
+    }
+
+    /// Remembers the token totals written to a destination, returning those of the run before.
+    ///
+    /// # Arguments
+    ///
+    /// * `dest` - The destination directory or file the run wrote to.
+    /// * `record` - The token totals of the run.
+    ///
+    /// # Returns
+    ///
+    /// An `Option<TokenRecord>` containing the previous run's totals, if one was remembered.
+    pub fn remember_tokens(&mut self, dest: &Path, record: TokenRecord) -> Option<TokenRecord> {
+        self.last_tokens.insert(key(dest), record)
+    }
+}
+
+/// Returns the key a path is remembered under, which is its canonical path.
+fn key(path: &Path) -> String {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf()).to_string_lossy().into_owned()
+}
@@ -0,0 +1,265 @@
+use std::collections::{BTreeMap, BTreeSet};
+use serde::{Deserialize, Serialize};
+use crate::artifact::Artifact;
+use crate::preflight::format_bytes;
+use crate::{project, tokens};
+
+/// Aggregate counts for a group of artifacts.
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct FileStats {
+    pub files: usize,
+    pub bytes: u64,
+    pub tokens: usize,
+}
+
+impl FileStats {
+    fn add(&mut self, artifact: &Artifact) {
+        // The parts of a split file count as one file.
+        if artifact.part.is_none_or(|part| part.index == 1) {
+            self.files += 1;
+        }
+        self.bytes += artifact.content.len() as u64;
+        self.tokens += artifact.tokens;
+    }
+}
+
+/// Groups artifacts by file extension and totals their counts.
+///
+/// Files without an extension are grouped under `(none)`.
+///
+/// # Arguments
+///
+/// * `artifacts` - The artifacts to summarize.
+///
+/// # Returns
+///
+/// A `BTreeMap<String, FileStats>` keyed by lowercase extension.
+pub fn by_extension(artifacts: &[Artifact]) -> BTreeMap<String, FileStats> {
+    let mut stats: BTreeMap<String, FileStats> = BTreeMap::new();
+    for artifact in artifacts {
+        let extension = artifact
+            .relative_path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_else(|| "(none)".to_string());
+        stats.entry(extension).or_default().add(artifact);
+    }
+    stats
+}
+
+/// Totals the counts for all artifacts.
+///
+/// # Arguments
+///
+/// * `artifacts` - The artifacts to summarize.
+///
+/// # Returns
+///
+/// A `FileStats` containing the totals.
+pub fn total(artifacts: &[Artifact]) -> FileStats {
+    let mut stats = FileStats::default();
+    for artifact in artifacts {
+        stats.add(artifact);
+    }
+    stats
+}
+
+/// The token totals of one run, kept to compare the next run against.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TokenRecord {
+    pub total: usize,
+    /// Tokens per directory, counting only the files directly inside it (`""` for the source root).
+    pub directories: BTreeMap<String, usize>,
+}
+
+impl TokenRecord {
+    /// Totals the tokens of a set of artifacts, overall and per directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `artifacts` - The artifacts of the run.
+    ///
+    /// # Returns
+    ///
+    /// A `TokenRecord` containing the totals.
+    pub fn from_artifacts(artifacts: &[Artifact]) -> Self {
+        let mut directories: BTreeMap<String, usize> = BTreeMap::new();
+        for artifact in artifacts {
+            let dir = artifact.relative_path.parent().map(|dir| dir.to_string_lossy().replace('\\', "/")).unwrap_or_default();
+            *directories.entry(dir).or_default() += artifact.tokens;
+        }
+        Self { total: tokens::total(artifacts), directories }
+    }
+
+    /// Describes how the token total changed since an earlier run.
+    ///
+    /// The description names the deepest directory that accounts for at least half of
+    /// the change, if there is one below the source root.
+    ///
+    /// # Arguments
+    ///
+    /// * `previous` - The totals of the earlier run.
+    ///
+    /// # Returns
+    ///
+    /// A `String` such as `+4.2k tokens since the last run (12.3k -> 16.5k), mostly from src/api/`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use rustifacts::stats::TokenRecord;
+    ///
+    /// let record = |dirs: &[(&str, usize)]| TokenRecord {
+    ///     total: dirs.iter().map(|(_, tokens)| tokens).sum(),
+    ///     directories: dirs.iter().map(|(dir, tokens)| (dir.to_string(), *tokens)).collect::<BTreeMap<_, _>>(),
+    /// };
+    /// let before = record(&[("", 300), ("src", 2_000), ("src/api", 10_000)]);
+    /// let after = record(&[("", 300), ("src", 2_100), ("src/api", 14_100)]);
+    /// assert_eq!(
+    ///     after.delta_since(&before),
+    ///     "+4.2k tokens since the last run (12.3k -> 16.5k), mostly from src/api/"
+    /// );
+    /// ```
+    pub fn delta_since(&self, previous: &TokenRecord) -> String {
+        let delta = self.total as i64 - previous.total as i64;
+        if delta == 0 {
+            return format!("Token total unchanged since the last run ({})", format_tokens(self.total as i64));
+        }
+        let dirs: BTreeSet<&String> = self.directories.keys().chain(previous.directories.keys()).collect();
+        let mut deltas: BTreeMap<&str, i64> = BTreeMap::new();
+        for dir in dirs {
+            let change = *self.directories.get(dir).unwrap_or(&0) as i64 - *previous.directories.get(dir).unwrap_or(&0) as i64;
+            if change == 0 {
+                continue;
+            }
+            // Credit the change to the directory and every directory above it
+            let mut ancestor = dir.as_str();
+            while !ancestor.is_empty() {
+                *deltas.entry(ancestor).or_default() += change;
+                ancestor = ancestor.rsplit_once('/').map_or("", |(parent, _)| parent);
+            }
+        }
+        let mostly = deltas
+            .into_iter()
+            .filter(|(_, change)| change.signum() == delta.signum() && change.abs() * 2 >= delta.abs())
+            .max_by_key(|(dir, change)| (dir.matches('/').count(), change.abs()))
+            .map(|(dir, _)| format!(", mostly from {}/", dir))
+            .unwrap_or_default();
+        format!(
+            "{}{} tokens since the last run ({} -> {}){}",
+            if delta > 0 { "+" } else { "-" },
+            format_tokens(delta.abs()),
+            format_tokens(previous.total as i64),
+            format_tokens(self.total as i64),
+            mostly
+        )
+    }
+}
+
+/// Formats a token count compactly, such as `850`, `4.2k`, or `1.3M`.
+fn format_tokens(tokens: i64) -> String {
+    match tokens {
+        tokens if tokens >= 1_000_000 => format!("{:.1}M", tokens as f64 / 1_000_000.0),
+        tokens if tokens >= 1_000 => format!("{:.1}k", tokens as f64 / 1_000.0),
+        tokens => tokens.to_string(),
+    }
+}
+
+/// Prints a statistics report for the artifacts to standard output.
+///
+/// # Arguments
+///
+/// * `artifacts` - The artifacts to summarize.
+/// * `by_ext` - Whether to include a per-extension breakdown, largest first.
+pub fn print_report(artifacts: &[Artifact], by_ext: bool) {
+    println!("{:<12} {:>8} {:>12} {:>10}", "EXTENSION", "FILES", "BYTES", "TOKENS");
+    if by_ext {
+        let mut rows: Vec<(String, FileStats)> = by_extension(artifacts).into_iter().collect();
+        rows.sort_by(|a, b| b.1.bytes.cmp(&a.1.bytes).then_with(|| a.0.cmp(&b.0)));
+        for (extension, stats) in rows {
+            print_row(&extension, &stats);
+        }
+    }
+    print_row("total", &total(artifacts));
+}
+
+/// Prints the largest artifacts by token count, each with `--exclude-glob` suggestions.
+///
+/// # Arguments
+///
+/// * `artifacts` - The artifacts to rank.
+/// * `count` - The number of artifacts to list.
+pub fn print_largest(artifacts: &[Artifact], count: usize) {
+    let mut ranked: Vec<&Artifact> = artifacts.iter().collect();
+    ranked.sort_by(|a, b| b.tokens.cmp(&a.tokens).then_with(|| a.relative_path.cmp(&b.relative_path)));
+
+    println!("Largest {} artifacts by tokens:", count.min(ranked.len()));
+    for artifact in ranked.into_iter().take(count) {
+        let path = artifact.relative_path.to_string_lossy().replace('\\', "/");
+        println!("{:>10} tokens  {}", artifact.tokens, path);
+        println!("    --exclude-glob '{}'", path);
+        if let Some(suggestion) = directory_glob(&path) {
+            println!("    --exclude-glob '{}'", suggestion);
+        }
+    }
+}
+
+/// Prints the token count of every artifact, in path order, followed by the total.
+///
+/// # Arguments
+///
+/// * `artifacts` - The artifacts to report on.
+/// * `budget` - The token budget to compare the total against, if any.
+pub fn print_token_report(artifacts: &[Artifact], budget: Option<usize>) {
+    let mut sorted: Vec<&Artifact> = artifacts.iter().collect();
+    sorted.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+    for artifact in sorted {
+        println!("{:>10}  {}", artifact.tokens, artifact.relative_path.display());
+    }
+    let total = tokens::total(artifacts);
+    match budget {
+        Some(budget) => println!("{:>10}  total ({:.1}% of the {} token budget)", total, total as f64 * 100.0 / budget as f64, budget),
+        None => println!("{:>10}  total", total),
+    }
+}
+
+/// Prints each language's share of the artifacts by bytes and tokens, largest first.
+///
+/// # Arguments
+///
+/// * `artifacts` - The artifacts to summarize.
+pub fn print_language_report(artifacts: &[Artifact]) {
+    let total = total(artifacts);
+    let percent = |part: f64, whole: f64| if whole > 0.0 { part * 100.0 / whole } else { 0.0 };
+    println!("{:<12} {:>8} {:>12} {:>7} {:>10}", "LANGUAGE", "FILES", "BYTES", "", "TOKENS");
+    for share in project::language_stats(artifacts) {
+        println!(
+            "{:<12} {:>8} {:>12} {:>6.1}% {:>10} {:>6.1}%",
+            share.language,
+            share.files,
+            format_bytes(share.bytes),
+            percent(share.bytes as f64, total.bytes as f64),
+            share.tokens,
+            percent(share.tokens as f64, total.tokens as f64),
+        );
+    }
+    println!("{:<12} {:>8} {:>12} {:>7} {:>10}", "total", total.files, format_bytes(total.bytes), "", total.tokens);
+}
+
+/// Builds a glob covering every file with the same extension in the same directory tree.
+fn directory_glob(path: &str) -> Option<String> {
+    let (dir, file_name) = path.rsplit_once('/')?;
+    let (_, extension) = file_name.rsplit_once('.')?;
+    Some(format!("{}/**/*.{}", dir, extension))
+}
+
+fn print_row(label: &str, stats: &FileStats) {
+    println!(
+        "{:<12} {:>8} {:>12} {:>10}",
+        label,
+        stats.files,
+        format_bytes(stats.bytes),
+        stats.tokens
+    );
+}
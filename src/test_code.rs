@@ -0,0 +1,127 @@
+use std::ops::Range;
+use std::path::Path;
+use log::debug;
+use tree_sitter::{Node, Parser};
+use crate::transform::{TransformError, Transformer};
+
+/// Removes the items compiled only for tests from a Rust file.
+///
+/// Items marked `#[cfg(test)]`, usually a `mod tests` block or declaration, are removed
+/// together with their other attributes and doc comments. Other files are not changed,
+/// since their tests live in files of their own.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file, used to recognize Rust files.
+/// * `content` - The content of the file.
+///
+/// # Returns
+///
+/// `Some(String)` containing the content without its test items, or `None` if the file is
+/// not a Rust file or could not be parsed without errors.
+///
+/// # Example
+///
+/// ```
+/// use std::path::Path;
+/// use rustifacts::test_code::strip_test_code;
+///
+/// let source = "pub fn one() -> u32 {\n    1\n}\n\n#[cfg(test)]\nmod tests {\n    #[test]\n    fn is_one() {}\n}\n";
+/// assert_eq!(strip_test_code(Path::new("lib.rs"), source).unwrap(), "pub fn one() -> u32 {\n    1\n}\n\n");
+/// assert!(strip_test_code(Path::new("app.ts"), source).is_none());
+/// ```
+pub fn strip_test_code(path: &Path, content: &str) -> Option<String> {
+    if !path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("rs")) {
+        return None;
+    }
+    let mut parser = Parser::new();
+    parser.set_language(&tree_sitter_rust::LANGUAGE.into()).ok()?;
+    let tree = parser.parse(content, None)?;
+    if tree.root_node().has_error() {
+        debug!("Keeping the tests of {}: it could not be parsed", path.display());
+        return None;
+    }
+
+    let mut removals = Vec::new();
+    collect_test_items(tree.root_node(), content, &mut removals);
+    let mut output = String::with_capacity(content.len());
+    let mut end = 0;
+    for range in removals {
+        output.push_str(&content[end..range.start]);
+        end = range.end;
+    }
+    output.push_str(&content[end..]);
+    Some(output)
+}
+
+/// Finds the byte ranges of the `#[cfg(test)]` items beneath a node, in source order.
+fn collect_test_items(node: Node, content: &str, removals: &mut Vec<Range<usize>>) {
+    let mut cursor = node.walk();
+    let children: Vec<Node> = node.children(&mut cursor).collect();
+    let mut index = 0;
+    while index < children.len() {
+        let child = children[index];
+        if !is_cfg_test(child, content) {
+            collect_test_items(child, content, removals);
+            index += 1;
+            continue;
+        }
+        // Doc comments and other attributes directly above the attribute belong to the item too.
+        let mut first = index;
+        while first > 0
+            && is_attached(children[first - 1])
+            && children[first - 1].end_position().row + 1 >= children[first].start_position().row
+        {
+            first -= 1;
+        }
+        let mut last = index;
+        while last + 1 < children.len() && is_attached(children[last]) {
+            last += 1;
+        }
+        removals.push(line_range(content, children[first].start_byte()..children[last].end_byte()));
+        index = last + 1;
+    }
+}
+
+/// Checks whether a node is the attribute `#[cfg(test)]`.
+fn is_cfg_test(node: Node, content: &str) -> bool {
+    if node.kind() != "attribute_item" {
+        return false;
+    }
+    let text: String = content[node.byte_range()].chars().filter(|c| !c.is_whitespace()).collect();
+    text == "#[cfg(test)]"
+}
+
+/// Checks whether a node is an attribute or comment that precedes the item it annotates.
+fn is_attached(node: Node) -> bool {
+    matches!(node.kind(), "attribute_item" | "line_comment" | "block_comment")
+}
+
+/// Widens a range to whole lines when nothing else shares them, and to the blank line
+/// after it when one also precedes it.
+fn line_range(content: &str, range: Range<usize>) -> Range<usize> {
+    let line_start = content[..range.start].rfind('\n').map_or(0, |newline| newline + 1);
+    let start = if content[line_start..range.start].trim().is_empty() { line_start } else { range.start };
+    let rest = &content[range.end..];
+    let end = match rest.find('\n') {
+        Some(newline) if rest[..newline].trim().is_empty() => range.end + newline + 1,
+        None if rest.trim().is_empty() => content.len(),
+        _ => range.end,
+    };
+    let blank_before = start == line_start && content[..start].ends_with("\n\n");
+    let blank_after = content[end..].starts_with('\n');
+    if blank_before && blank_after { start..end + 1 } else { start..end }
+}
+
+/// Removes `#[cfg(test)]` items from Rust files, selected with `--no-tests` or the
+/// `strip-tests` transformer.
+///
+/// Files that cannot be parsed are kept whole.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StripTests;
+
+impl Transformer for StripTests {
+    fn transform(&self, path: &Path, content: &str) -> Result<String, TransformError> {
+        Ok(strip_test_code(path, content).unwrap_or_else(|| content.to_string()))
+    }
+}
@@ -0,0 +1,108 @@
+use std::sync::OnceLock;
+use log::{info, warn};
+use thiserror::Error;
+use tiktoken_rs::CoreBPE;
+use crate::artifact::Artifact;
+use crate::config::BudgetPolicy;
+
+/// Average number of characters per token, used if the tokenizer cannot be loaded.
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Custom error type for token budget enforcement.
+#[derive(Error, Debug)]
+pub enum TokenError {
+    #[error("Artifacts total {total} tokens, exceeding the budget of {budget} tokens")]
+    BudgetExceeded { total: usize, budget: usize },
+}
+
+/// Returns the shared tokenizer, loading it on first use.
+fn tokenizer() -> Option<&'static CoreBPE> {
+    static TOKENIZER: OnceLock<Option<CoreBPE>> = OnceLock::new();
+    TOKENIZER
+        .get_or_init(|| match tiktoken_rs::cl100k_base() {
+            Ok(bpe) => Some(bpe),
+            Err(e) => {
+                warn!("Failed to load tokenizer, falling back to an estimate: {}", e);
+                None
+            }
+        })
+        .as_ref()
+}
+
+/// Counts the number of tokens a piece of content occupies in a model's context.
+///
+/// Claude's tokenizer is not public, so this uses the `cl100k_base` BPE vocabulary,
+/// which tracks it closely for source code and prose.
+///
+/// # Arguments
+///
+/// * `content` - The content to count.
+///
+/// # Returns
+///
+/// A `usize` containing the token count.
+pub fn count(content: &str) -> usize {
+    match tokenizer() {
+        Some(bpe) => bpe.encode_ordinary(content).len(),
+        None => content.chars().count().div_ceil(CHARS_PER_TOKEN),
+    }
+}
+
+/// Sums the token counts of a set of artifacts.
+///
+/// # Arguments
+///
+/// * `artifacts` - The artifacts to total.
+///
+/// # Returns
+///
+/// A `usize` containing the total token count.
+pub fn total(artifacts: &[Artifact]) -> usize {
+    artifacts.iter().map(|artifact| artifact.tokens).sum()
+}
+
+/// Checks the artifacts against a token budget, applying the policy if they exceed it.
+///
+/// With [`BudgetPolicy::Trim`], the largest artifacts are dropped one at a time until
+/// the remainder fits.
+///
+/// # Arguments
+///
+/// * `artifacts` - The artifacts to check, trimmed in place.
+/// * `budget` - The maximum total number of tokens.
+/// * `policy` - What to do if the budget is exceeded.
+///
+/// # Returns
+///
+/// Returns `Result<(), TokenError>` indicating whether the artifacts fit the budget
+/// after the policy was applied.
+pub fn enforce_budget(artifacts: &mut Vec<Artifact>, budget: usize, policy: BudgetPolicy) -> Result<(), TokenError> {
+    let mut total = total(artifacts);
+    if total <= budget {
+        info!("Artifacts total {} tokens, within the budget of {}", total, budget);
+        return Ok(());
+    }
+    if policy == BudgetPolicy::Fail {
+        return Err(TokenError::BudgetExceeded { total, budget });
+    }
+
+    let mut by_size: Vec<usize> = (0..artifacts.len()).collect();
+    by_size.sort_by(|&a, &b| artifacts[b].tokens.cmp(&artifacts[a].tokens));
+    let mut dropped = vec![false; artifacts.len()];
+    for index in by_size {
+        if total <= budget {
+            break;
+        }
+        let artifact = &artifacts[index];
+        warn!("Dropping {} ({} tokens) to fit the token budget", artifact.relative_path.display(), artifact.tokens);
+        total -= artifact.tokens;
+        dropped[index] = true;
+    }
+    let mut index = 0;
+    artifacts.retain(|_| {
+        index += 1;
+        !dropped[index - 1]
+    });
+    info!("Trimmed artifacts to {} tokens, within the budget of {}", total, budget);
+    Ok(())
+}
@@ -0,0 +1,411 @@
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use clap::ValueEnum;
+use log::debug;
+use serde::Deserialize;
+use thiserror::Error;
+use crate::config::Config;
+use crate::plugin::{PluginError, PluginTransformer};
+use crate::skeleton::Skeleton;
+use crate::test_code::StripTests;
+
+/// Name of the environment variable holding the path of the file a `--transform-cmd` command
+/// is given, relative to the source directory.
+pub const PATH_ENV_VAR: &str = "RUSTIFACTS_PATH";
+
+/// Custom error type for content transformers.
+#[derive(Error, Debug)]
+pub enum TransformError {
+    #[error("Failed to run `{command}`: {source}")]
+    Spawn { command: String, source: io::Error },
+    #[error("`{command}` failed for {path} ({status}){}", if stderr.is_empty() { String::new() } else { format!(": {}", stderr) })]
+    Failed { command: String, path: String, status: String, stderr: String },
+    #[error("`{command}` wrote output for {path} that is not valid UTF-8")]
+    NotUtf8 { command: String, path: String },
+    #[error("{0}")]
+    Plugin(#[from] PluginError),
+}
+
+/// A stage of the content pipeline that rewrites the text of an artifact.
+///
+/// Transformers run after the built-in reshaping (front matter, notebooks, SQL dumps) and
+/// before blank lines are squeezed and indentation is minified, so their output is tidied
+/// up like any other content. The checksum and token count describe the transformed text.
+pub trait Transformer: Send + Sync {
+    /// Rewrites the content of a file, returning it unchanged if the transformer does not
+    /// apply to the file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file, relative to the source directory.
+    /// * `content` - The content of the file.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<String, TransformError>` containing the transformed content, or an
+    /// error if the transformer failed.
+    fn transform(&self, path: &Path, content: &str) -> Result<String, TransformError>;
+}
+
+/// The transformers that can be selected with `--transform`, in the config file, or in a rule.
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TransformKind {
+    /// Remove comments from Rust, JavaScript, TypeScript, and Python files.
+    StripComments,
+    /// Replace function bodies in Rust, Python, JavaScript, TypeScript, and Go files with `{ … }`.
+    Skeleton,
+    /// Remove `#[cfg(test)]` items from Rust files.
+    StripTests,
+}
+
+impl TransformKind {
+    /// Returns the transformer that implements this kind.
+    pub fn transformer(self) -> Box<dyn Transformer> {
+        match self {
+            Self::StripComments => Box::new(StripComments),
+            Self::Skeleton => Box::new(Skeleton),
+            Self::StripTests => Box::new(StripTests),
+        }
+    }
+}
+
+/// Builds the transformers a configuration selects: the test removal of `--no-tests`, the
+/// skeleton extraction of `--skeleton`, those named with `--transform`, the `--transform-cmd` commands, and then
+/// the `--plugin` plugins, each in the order given.
+///
+/// # Arguments
+///
+/// * `config` - The configuration options, with any `[[rules]]` for the file applied.
+///
+/// # Returns
+///
+/// A `Vec<Box<dyn Transformer>>` containing the transformers to run, in order.
+pub fn for_config(config: &Config) -> Vec<Box<dyn Transformer>> {
+    let commands = config.transform_cmds.iter().map(|command| {
+        Box::new(CommandTransformer::new(command, &config.source_dir)) as Box<dyn Transformer>
+    });
+    let plugins = config.plugins.iter().map(|path| Box::new(PluginTransformer::new(path)) as Box<dyn Transformer>);
+    let strip_tests = (config.no_tests && !config.transforms.contains(&TransformKind::StripTests)).then_some(TransformKind::StripTests);
+    let skeleton = (config.skeleton && !config.transforms.contains(&TransformKind::Skeleton)).then_some(TransformKind::Skeleton);
+    strip_tests
+        .iter()
+        .chain(&skeleton)
+        .chain(&config.transforms)
+        .map(|kind| kind.transformer())
+        .chain(commands)
+        .chain(plugins)
+        .collect()
+}
+
+/// Runs a file's content through a list of transformers, in order.
+///
+/// # Arguments
+///
+/// * `transformers` - The transformers to run.
+/// * `path` - The path of the file, relative to the source directory.
+/// * `content` - The content of the file.
+///
+/// # Returns
+///
+/// Returns `Result<String, TransformError>` containing the transformed content, or the
+/// error of the first transformer that failed.
+///
+/// # Example
+///
+/// ```
+/// use std::path::Path;
+/// use rustifacts::transform::{apply, TransformKind};
+///
+/// let content = "let url = \"http://example.com\"; // the endpoint\n";
+/// assert_eq!(
+///     apply(&[TransformKind::StripComments.transformer()], Path::new("main.js"), content).unwrap(),
+///     "let url = \"http://example.com\";\n",
+/// );
+/// ```
+pub fn apply(transformers: &[Box<dyn Transformer>], path: &Path, content: &str) -> Result<String, TransformError> {
+    transformers
+        .iter()
+        .try_fold(content.to_string(), |content, transformer| transformer.transform(path, &content))
+}
+
+/// Pipes content through an external command given with `--transform-cmd`.
+///
+/// The command is run by the shell (`sh -c`, or `cmd /C` on Windows) from the source
+/// directory, with the content on its standard input and the file's relative path in
+/// the `RUSTIFACTS_PATH` environment variable. What it writes to standard output replaces
+/// the content; a command that exits unsuccessfully fails the file.
+#[derive(Debug, Clone)]
+pub struct CommandTransformer {
+    command: String,
+    dir: PathBuf,
+}
+
+impl CommandTransformer {
+    /// Creates a transformer running a shell command from a directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - The shell command.
+    /// * `dir` - The directory to run it from, usually the source directory. A directory
+    ///   that does not exist on disk, such as that of an archive, is not used.
+    pub fn new(command: &str, dir: &Path) -> Self {
+        Self { command: command.to_string(), dir: dir.to_path_buf() }
+    }
+
+    /// Builds the shell invocation of the command.
+    fn shell(&self) -> Command {
+        let mut shell = if cfg!(windows) {
+            let mut shell = Command::new("cmd");
+            shell.arg("/C");
+            shell
+        } else {
+            let mut shell = Command::new("sh");
+            shell.arg("-c");
+            shell
+        };
+        shell.arg(&self.command);
+        if self.dir.is_dir() {
+            shell.current_dir(&self.dir);
+        }
+        shell
+    }
+}
+
+impl Transformer for CommandTransformer {
+    fn transform(&self, path: &Path, content: &str) -> Result<String, TransformError> {
+        debug!("Running `{}` on {}", self.command, path.display());
+        let spawn_error = |source| TransformError::Spawn { command: self.command.clone(), source };
+        let mut child = self
+            .shell()
+            .env(PATH_ENV_VAR, path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(spawn_error)?;
+        // Feed the content from another thread, so that a command writing output before it
+        // has read all of its input cannot deadlock against us.
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let output = thread::scope(|scope| {
+            // A command that exits without reading its input closes the pipe; its exit
+            // status, not the broken pipe, tells whether it failed.
+            scope.spawn(move || stdin.write_all(content.as_bytes()));
+            child.wait_with_output()
+        })
+        .map_err(spawn_error)?;
+        if !output.status.success() {
+            return Err(TransformError::Failed {
+                command: self.command.clone(),
+                path: path.display().to_string(),
+                status: output.status.to_string(),
+                stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+        String::from_utf8(output.stdout).map_err(|_| TransformError::NotUtf8 {
+            command: self.command.clone(),
+            path: path.display().to_string(),
+        })
+    }
+}
+
+/// Removes comments while leaving string literals, and the comment markers inside them, intact.
+///
+/// Lines that held only a comment are dropped, and trailing whitespace is trimmed from lines
+/// that lose one; blank lines that were already in the file are kept. Rust doc comments
+/// are removed as well, while Python docstrings are kept because they are string literals
+/// that a function body may consist of. A shebang on the first line is kept.
+#[derive(Debug, Clone, Copy)]
+pub struct StripComments;
+
+impl Transformer for StripComments {
+    fn transform(&self, path: &Path, content: &str) -> Result<String, TransformError> {
+        let Some(language) = Language::for_path(path) else {
+            return Ok(content.to_string());
+        };
+        let (stripped, commented_lines) = strip_comments(content, language);
+        let mut result = String::with_capacity(stripped.len());
+        for (index, line) in stripped.split_inclusive('\n').enumerate() {
+            if !commented_lines.contains(&index) {
+                result.push_str(line);
+            } else if !line.trim().is_empty() {
+                result.push_str(line.trim_end());
+                if line.ends_with('\n') {
+                    result.push('\n');
+                }
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// The languages whose comments `StripComments` understands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Language {
+    Rust,
+    JavaScript,
+    Python,
+}
+
+impl Language {
+    /// Picks the language of a file from its extension.
+    fn for_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        match extension.as_str() {
+            "rs" => Some(Self::Rust),
+            "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "mts" | "cts" => Some(Self::JavaScript),
+            "py" | "pyi" => Some(Self::Python),
+            _ => None,
+        }
+    }
+}
+
+/// Removes the comments of a language from content, keeping every newline so that the
+/// result has the same lines as the input.
+///
+/// Returns the stripped content along with the indexes of the lines that held a comment.
+fn strip_comments(content: &str, language: Language) -> (String, HashSet<usize>) {
+    let mut stripped = String::with_capacity(content.len());
+    let mut commented_lines = HashSet::new();
+    let mut line = 0;
+    let mut i = 0;
+    while let Some(c) = content[i..].chars().next() {
+        let rest = &content[i..];
+        let previous = content[..i].chars().next_back();
+        let skip = match language {
+            Language::Python if c == '#' && !(i == 0 && rest.starts_with("#!")) => Skip::Comment(line_end(rest)),
+            Language::Python if rest.starts_with("\"\"\"") || rest.starts_with("'''") => Skip::Literal(quoted(rest, &rest[..3], true)),
+            Language::Python if c == '"' || c == '\'' => Skip::Literal(quoted(rest, &rest[..1], false)),
+            Language::Rust | Language::JavaScript if rest.starts_with("//") => Skip::Comment(line_end(rest)),
+            Language::Rust if rest.starts_with("/*") => Skip::Comment(nested_block_end(rest)),
+            Language::JavaScript if rest.starts_with("/*") => Skip::Comment(block_end(rest)),
+            Language::Rust if c == '"' => Skip::Literal(quoted(rest, "\"", true)),
+            Language::JavaScript if c == '"' || c == '\'' => Skip::Literal(quoted(rest, &rest[..1], false)),
+            Language::JavaScript if c == '`' => Skip::Literal(quoted(rest, "`", true)),
+            Language::Rust if c == '\'' => Skip::Literal(char_literal(rest).unwrap_or(1)),
+            Language::Rust if c == 'r' && !previous.is_some_and(is_ident_char) => {
+                Skip::Literal(raw_string(&rest[1..]).map_or(1, |len| len + 1))
+            }
+            Language::Rust if rest.starts_with("br") && !previous.is_some_and(is_ident_char) => {
+                Skip::Literal(raw_string(&rest[2..]).map_or(1, |len| len + 2))
+            }
+            _ => Skip::Literal(c.len_utf8()),
+        };
+        match skip {
+            Skip::Literal(len) => {
+                stripped.push_str(&rest[..len]);
+                line += rest[..len].matches('\n').count();
+            }
+            Skip::Comment(len) => {
+                commented_lines.insert(line);
+                for _ in rest[..len].matches('\n') {
+                    stripped.push('\n');
+                    line += 1;
+                    commented_lines.insert(line);
+                }
+            }
+        }
+        i += skip.len();
+    }
+    (stripped, commented_lines)
+}
+
+/// How much of the remaining content to consume, and whether to keep it.
+#[derive(Debug, Clone, Copy)]
+enum Skip {
+    /// Code or a literal, which is kept.
+    Literal(usize),
+    /// A comment, of which only the newlines are kept.
+    Comment(usize),
+}
+
+impl Skip {
+    fn len(self) -> usize {
+        match self {
+            Self::Literal(len) | Self::Comment(len) => len,
+        }
+    }
+}
+
+/// Returns the length of a line comment, up to but not including its newline.
+fn line_end(rest: &str) -> usize {
+    rest.find('\n').unwrap_or(rest.len())
+}
+
+/// Returns the length of a `/* */` block comment, or of the rest if it is unterminated.
+fn block_end(rest: &str) -> usize {
+    rest[2..].find("*/").map_or(rest.len(), |end| end + 4)
+}
+
+/// Returns the length of a Rust block comment, which may contain nested block comments.
+fn nested_block_end(rest: &str) -> usize {
+    let mut depth = 0;
+    let mut i = 0;
+    while i < rest.len() {
+        if rest[i..].starts_with("/*") {
+            depth += 1;
+            i += 2;
+        } else if rest[i..].starts_with("*/") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return i;
+            }
+        } else {
+            i += rest[i..].chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    rest.len()
+}
+
+/// Returns the length of a string literal that opens and closes with `quote` and
+/// escapes with a backslash.
+///
+/// An unterminated literal runs to the end of the content if it may span lines, and
+/// otherwise to the end of its line.
+fn quoted(rest: &str, quote: &str, multiline: bool) -> usize {
+    let mut chars = rest.char_indices().skip(quote.chars().count());
+    while let Some((i, c)) = chars.next() {
+        if c == '\\' {
+            chars.next();
+        } else if rest[i..].starts_with(quote) {
+            return i + quote.len();
+        } else if c == '\n' && !multiline {
+            return i;
+        }
+    }
+    rest.len()
+}
+
+/// Returns the length of a Rust character literal, or `None` if the quote starts a lifetime.
+fn char_literal(rest: &str) -> Option<usize> {
+    let mut chars = rest.char_indices().skip(1);
+    match chars.next()? {
+        (_, '\\') => {
+            chars.next()?;
+            chars.find(|&(_, c)| c == '\'' || c == '\n').filter(|&(_, c)| c == '\'').map(|(i, _)| i + 1)
+        }
+        (_, '\'' | '\n') => None,
+        _ => chars.next().filter(|&(_, c)| c == '\'').map(|(i, _)| i + 1),
+    }
+}
+
+/// Returns the length of the part of a Rust raw string after its `r`, such as `#"..."#`,
+/// or `None` if the `r` does not start a raw string.
+fn raw_string(rest: &str) -> Option<usize> {
+    let hashes = rest.len() - rest.trim_start_matches('#').len();
+    if !rest[hashes..].starts_with('"') {
+        return None;
+    }
+    let closing = format!("\"{}", "#".repeat(hashes));
+    let body = &rest[hashes + 1..];
+    Some(body.find(&closing).map_or(rest.len(), |end| hashes + 1 + end + closing.len()))
+}
+
+/// Checks whether a character can be part of an identifier.
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
@@ -0,0 +1,57 @@
+use std::fs;
+use serde::Deserialize;
+use super::multipart::Multipart;
+use super::{content_type, env_var, flat_name, request_error, UploadError, UploadFile, UploadTarget};
+
+/// Version of the Anthropic API the requests are written against.
+const API_VERSION: &str = "2023-06-01";
+
+/// Beta flag enabling the Files API.
+const FILES_BETA: &str = "files-api-2025-04-14";
+
+/// Settings for uploading to the Anthropic Files API.
+#[derive(Deserialize, Debug, Clone)]
+pub struct AnthropicFilesConfig {
+    /// The environment variable holding the API key.
+    #[serde(default = "default_api_key_env")]
+    pub api_key_env: String,
+    #[serde(default = "default_base_url")]
+    pub base_url: String,
+}
+
+fn default_api_key_env() -> String {
+    "ANTHROPIC_API_KEY".to_string()
+}
+
+fn default_base_url() -> String {
+    "https://api.anthropic.com".to_string()
+}
+
+/// Uploads each file to the Anthropic Files API.
+pub(super) struct AnthropicFiles {
+    config: AnthropicFilesConfig,
+}
+
+impl AnthropicFiles {
+    pub(super) fn new(config: AnthropicFilesConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl UploadTarget for AnthropicFiles {
+    fn upload(&self, file: &UploadFile) -> Result<(), UploadError> {
+        let api_key = env_var(&self.config.api_key_env)?;
+        let content = fs::read(&file.path)?;
+        let (form_type, body) = Multipart::new()
+            .file("file", &flat_name(&file.name), content_type(&file.name), &content)
+            .finish();
+        ureq::post(&format!("{}/v1/files", self.config.base_url.trim_end_matches('/')))
+            .set("x-api-key", &api_key)
+            .set("anthropic-version", API_VERSION)
+            .set("anthropic-beta", FILES_BETA)
+            .set("Content-Type", &form_type)
+            .send_bytes(&body)
+            .map_err(|e| request_error(&file.name, e))?;
+        Ok(())
+    }
+}
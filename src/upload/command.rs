@@ -0,0 +1,65 @@
+use std::process;
+use log::debug;
+use serde::Deserialize;
+use super::{UploadError, UploadFile, UploadTarget};
+
+/// Settings for uploading with a user-supplied command.
+#[derive(Deserialize, Debug, Clone)]
+pub struct CommandConfig {
+    /// The program and its arguments, run once per file without a shell. `{path}` is
+    /// replaced with the path of the file and `{name}` with its name in the output.
+    pub command: Vec<String>,
+}
+
+/// Runs a command once per file.
+pub(super) struct Command {
+    config: CommandConfig,
+}
+
+impl Command {
+    pub(super) fn new(config: CommandConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl UploadTarget for Command {
+    fn upload(&self, file: &UploadFile) -> Result<(), UploadError> {
+        let path = file.path.to_string_lossy();
+        let args: Vec<String> = self
+            .config
+            .command
+            .iter()
+            .map(|arg| arg.replace("{path}", &path).replace("{name}", &file.name))
+            .collect();
+        let Some((program, args)) = args.split_first() else {
+            return Err(UploadError::CommandFailed {
+                command: String::new(),
+                stderr: "the command is empty".to_string(),
+            });
+        };
+        run(program, args)
+    }
+}
+
+/// Runs a program to completion, failing if it exits unsuccessfully.
+///
+/// # Arguments
+///
+/// * `program` - The program to run.
+/// * `args` - The arguments to pass to it.
+///
+/// # Returns
+///
+/// Returns `Result<(), UploadError>` indicating whether the program succeeded.
+pub(super) fn run(program: &str, args: &[String]) -> Result<(), UploadError> {
+    let command = std::iter::once(program).chain(args.iter().map(String::as_str)).collect::<Vec<_>>().join(" ");
+    debug!("Running {}", command);
+    let output = process::Command::new(program).args(args).output()?;
+    if !output.status.success() {
+        return Err(UploadError::CommandFailed {
+            command,
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(())
+}
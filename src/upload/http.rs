@@ -0,0 +1,116 @@
+use std::collections::BTreeMap;
+use std::fs;
+use serde::Deserialize;
+use crate::artifact::{Sink, ZipSink};
+use super::{content_type, env_var, request_error, UploadError, UploadFile, UploadTarget};
+
+/// Settings for uploading to a generic HTTP endpoint.
+#[derive(Deserialize, Debug, Clone)]
+pub struct HttpConfig {
+    /// The URL to send each body to; `{name}` is replaced with the file's name in the
+    /// output, or with `archive_name` when uploading an archive.
+    pub url: String,
+    #[serde(default)]
+    pub method: HttpMethod,
+    /// Headers sent with every request, with literal values.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Headers sent with every request, mapped to the environment variables holding their values.
+    #[serde(default)]
+    pub env_headers: BTreeMap<String, String>,
+    /// The environment variable holding a token sent as `Authorization: Bearer <token>`.
+    pub bearer_token_env: Option<String>,
+    #[serde(default)]
+    pub body: HttpBody,
+    /// The name of the zip archive sent with `body = "archive"`.
+    #[serde(default = "default_archive_name")]
+    pub archive_name: String,
+}
+
+/// The HTTP method used to upload.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum HttpMethod {
+    #[default]
+    Put,
+    Post,
+}
+
+/// What each request carries.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HttpBody {
+    /// One request per file, with the file's content as the body.
+    #[default]
+    File,
+    /// A single request whose body is a zip archive of every file.
+    Archive,
+}
+
+fn default_archive_name() -> String {
+    "artifacts.zip".to_string()
+}
+
+/// Sends files to a URL, one request per file or one archive for all of them.
+pub(super) struct Http {
+    config: HttpConfig,
+}
+
+impl Http {
+    pub(super) fn new(config: HttpConfig) -> Self {
+        Self { config }
+    }
+
+    /// Sends one body under the given name.
+    fn send(&self, name: &str, content: &[u8]) -> Result<(), UploadError> {
+        let url = self.config.url.replace("{name}", &encode_path(name));
+        let method = match self.config.method {
+            HttpMethod::Put => "PUT",
+            HttpMethod::Post => "POST",
+        };
+        let mut request = ureq::request(method, &url).set("Content-Type", content_type(name));
+        for (header, value) in &self.config.headers {
+            request = request.set(header, value);
+        }
+        for (header, variable) in &self.config.env_headers {
+            request = request.set(header, &env_var(variable)?);
+        }
+        if let Some(ref token_env) = self.config.bearer_token_env {
+            request = request.set("Authorization", &format!("Bearer {}", env_var(token_env)?));
+        }
+        request.send_bytes(content).map_err(|e| request_error(name, e))?;
+        Ok(())
+    }
+}
+
+impl UploadTarget for Http {
+    fn upload(&self, file: &UploadFile) -> Result<(), UploadError> {
+        self.send(&file.name, &fs::read(&file.path)?)
+    }
+
+    fn upload_all(&self, files: &[UploadFile]) -> Result<usize, UploadError> {
+        if self.config.body == HttpBody::File {
+            return super::upload_each(self, files);
+        }
+        let archive = tempfile::Builder::new().suffix(".zip").tempfile()?;
+        let mut sink = ZipSink::create(archive.path())?;
+        for file in files {
+            sink.put(&file.name, &fs::read(&file.path)?)?;
+        }
+        sink.finish()?;
+        self.send(&self.config.archive_name, &fs::read(archive.path())?)?;
+        Ok(files.len())
+    }
+}
+
+/// Percent-encodes a name for use in a URL path, keeping `/` separators.
+fn encode_path(name: &str) -> String {
+    let mut encoded = String::with_capacity(name.len());
+    for byte in name.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
@@ -0,0 +1,206 @@
+mod anthropic;
+mod command;
+mod http;
+mod multipart;
+mod openai;
+mod s3;
+
+use std::collections::HashSet;
+use std::env;
+use std::io;
+use std::path::{Path, PathBuf};
+use log::{debug, info};
+use serde::Deserialize;
+use thiserror::Error;
+use crate::config::Config;
+use crate::manifest::Manifest;
+use crate::writer::DirectoryWriter;
+
+pub use anthropic::AnthropicFilesConfig;
+pub use command::CommandConfig;
+pub use http::{HttpBody, HttpConfig, HttpMethod};
+pub use openai::OpenAiConfig;
+pub use s3::S3Config;
+
+/// Custom error type for uploads.
+#[derive(Error, Debug)]
+pub enum UploadError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("No upload target named {0:?}; declare it under [targets.{0}] in the config file")]
+    UnknownTarget(String),
+    #[error("Environment variable {0} is not set")]
+    MissingEnv(String),
+    #[error("Upload of {name} failed: {message}")]
+    Request { name: String, message: String },
+    #[error("`{command}` failed: {stderr}")]
+    CommandFailed { command: String, stderr: String },
+    #[error("Nothing to upload in {0}; collect artifacts first")]
+    NothingToUpload(PathBuf),
+}
+
+/// A written output file to upload.
+#[derive(Debug, Clone)]
+pub struct UploadFile {
+    /// The file's name relative to the output, with `/` separators.
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// A destination that written artifacts can be uploaded to.
+pub trait UploadTarget {
+    /// Uploads one file.
+    ///
+    /// # Arguments
+    ///
+    /// * `file` - The file to upload.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<(), UploadError>` indicating success or failure of the upload.
+    fn upload(&self, file: &UploadFile) -> Result<(), UploadError>;
+
+    /// Uploads every file, one at a time by default, stopping at the first failure.
+    ///
+    /// Targets that send all files in one request override this.
+    ///
+    /// # Arguments
+    ///
+    /// * `files` - The files to upload.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<usize, UploadError>` containing the number of uploaded files,
+    /// or an `UploadError` for the first file that failed.
+    fn upload_all(&self, files: &[UploadFile]) -> Result<usize, UploadError> {
+        upload_each(self, files)
+    }
+}
+
+/// The settings of a target declared in the config file, keyed by its `type`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum TargetConfig {
+    /// The Anthropic Files API.
+    AnthropicFiles(AnthropicFilesConfig),
+    /// The OpenAI Files API.
+    Openai(OpenAiConfig),
+    /// An S3 bucket, through the `aws` command-line tool.
+    S3(S3Config),
+    /// A generic HTTP endpoint, sent one file or one zip archive per request.
+    #[serde(alias = "http-put")]
+    Http(HttpConfig),
+    /// A user-supplied command run once per file.
+    Command(CommandConfig),
+}
+
+impl TargetConfig {
+    /// Creates the target these settings describe.
+    ///
+    /// # Returns
+    ///
+    /// A `Box<dyn UploadTarget>` ready to upload files.
+    pub fn build(&self) -> Box<dyn UploadTarget> {
+        match self {
+            Self::AnthropicFiles(config) => Box::new(anthropic::AnthropicFiles::new(config.clone())),
+            Self::Openai(config) => Box::new(openai::OpenAi::new(config.clone())),
+            Self::S3(config) => Box::new(s3::S3::new(config.clone())),
+            Self::Http(config) => Box::new(http::Http::new(config.clone())),
+            Self::Command(config) => Box::new(command::Command::new(config.clone())),
+        }
+    }
+}
+
+/// Looks up a target declared in the configuration.
+///
+/// # Arguments
+///
+/// * `config` - The configuration options.
+/// * `name` - The name of the target.
+///
+/// # Returns
+///
+/// Returns `Result<&TargetConfig, UploadError>` containing the target's settings,
+/// or `UploadError::UnknownTarget` if no target has that name.
+pub fn find_target<'a>(config: &'a Config, name: &str) -> Result<&'a TargetConfig, UploadError> {
+    config.targets.get(name).ok_or_else(|| UploadError::UnknownTarget(name.to_string()))
+}
+
+/// Lists the files a collection wrote, which are what gets uploaded.
+///
+/// This is the `--single-file` or `--output-archive` file if one is configured, and otherwise
+/// every file named in the output directory's manifest, without the manifest itself.
+///
+/// # Arguments
+///
+/// * `config` - The configuration options.
+///
+/// # Returns
+///
+/// Returns `Result<Vec<UploadFile>, UploadError>` containing the files to upload,
+/// or `UploadError::NothingToUpload` if the output has not been written.
+pub fn output_files(config: &Config) -> Result<Vec<UploadFile>, UploadError> {
+    if let Some(file) = config.single_file.as_ref().or(config.output_archive.as_ref()) {
+        if !file.is_file() {
+            return Err(UploadError::NothingToUpload(file.clone()));
+        }
+        let name = file.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        return Ok(vec![UploadFile { name, path: file.clone() }]);
+    }
+
+    let dir = DirectoryWriter::from_config(config).output_dir();
+    let manifest = Manifest::read_from(&dir).ok_or_else(|| UploadError::NothingToUpload(dir.clone()))?;
+    // With --markdown-per-target several entries share one document.
+    let mut seen = HashSet::new();
+    Ok(manifest
+        .artifacts
+        .iter()
+        .filter(|entry| seen.insert(entry.new_filename.as_str()))
+        .map(|entry| UploadFile { name: entry.new_filename.clone(), path: dir.join(&entry.new_filename) })
+        .filter(|file| file.path.is_file())
+        .collect())
+}
+
+/// Uploads files to a target one at a time, stopping at the first failure.
+fn upload_each<T: UploadTarget + ?Sized>(target: &T, files: &[UploadFile]) -> Result<usize, UploadError> {
+    for file in files {
+        debug!("Uploading {}", file.path.display());
+        target.upload(file)?;
+        info!("Uploaded {}", file.name);
+    }
+    Ok(files.len())
+}
+
+/// Reads a secret such as an API key from an environment variable.
+fn env_var(name: &str) -> Result<String, UploadError> {
+    env::var(name).map_err(|_| UploadError::MissingEnv(name.to_string()))
+}
+
+/// Returns a file name without directories, for APIs that take a flat name.
+///
+/// Directories kept with `--keep-dirs` are joined with underscores so names stay distinct.
+fn flat_name(name: &str) -> String {
+    name.replace('/', "_")
+}
+
+/// Returns the MIME type to send a file as.
+fn content_type(name: &str) -> &'static str {
+    match Path::new(name).extension().and_then(|ext| ext.to_str()) {
+        Some("zip") => "application/zip",
+        Some("md") => "text/markdown; charset=utf-8",
+        Some("xml") => "application/xml",
+        Some("json") => "application/json",
+        _ => "text/plain; charset=utf-8",
+    }
+}
+
+/// Converts a failed HTTP request into an `UploadError`, keeping the response body for context.
+fn request_error(name: &str, error: ureq::Error) -> UploadError {
+    let message = match error {
+        ureq::Error::Status(code, response) => {
+            format!("HTTP {}: {}", code, response.into_string().unwrap_or_default().trim())
+        }
+        e => e.to_string(),
+    };
+    UploadError::Request { name: name.to_string(), message }
+}
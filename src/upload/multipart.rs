@@ -0,0 +1,52 @@
+use uuid::Uuid;
+
+/// A `multipart/form-data` request body.
+pub(super) struct Multipart {
+    boundary: String,
+    body: Vec<u8>,
+}
+
+impl Multipart {
+    /// Starts an empty body with a random boundary.
+    pub(super) fn new() -> Self {
+        Self {
+            boundary: format!("rustifacts-{}", Uuid::new_v4().simple()),
+            body: Vec::new(),
+        }
+    }
+
+    /// Adds a text field.
+    pub(super) fn text(mut self, name: &str, value: &str) -> Self {
+        self.body.extend_from_slice(
+            format!("--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n", self.boundary, name, value).as_bytes(),
+        );
+        self
+    }
+
+    /// Adds a file field.
+    pub(super) fn file(mut self, name: &str, file_name: &str, content_type: &str, content: &[u8]) -> Self {
+        self.body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+                self.boundary,
+                name,
+                file_name.replace('"', "%22"),
+                content_type
+            )
+            .as_bytes(),
+        );
+        self.body.extend_from_slice(content);
+        self.body.extend_from_slice(b"\r\n");
+        self
+    }
+
+    /// Closes the body.
+    ///
+    /// # Returns
+    ///
+    /// A tuple of the `Content-Type` header value and the encoded body.
+    pub(super) fn finish(mut self) -> (String, Vec<u8>) {
+        self.body.extend_from_slice(format!("--{}--\r\n", self.boundary).as_bytes());
+        (format!("multipart/form-data; boundary={}", self.boundary), self.body)
+    }
+}
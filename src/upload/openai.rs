@@ -0,0 +1,57 @@
+use std::fs;
+use serde::Deserialize;
+use super::multipart::Multipart;
+use super::{content_type, env_var, flat_name, request_error, UploadError, UploadFile, UploadTarget};
+
+/// Settings for uploading to the OpenAI Files API.
+#[derive(Deserialize, Debug, Clone)]
+pub struct OpenAiConfig {
+    /// The environment variable holding the API key.
+    #[serde(default = "default_api_key_env")]
+    pub api_key_env: String,
+    #[serde(default = "default_base_url")]
+    pub base_url: String,
+    /// What the files are for, as the API's `purpose` field.
+    #[serde(default = "default_purpose")]
+    pub purpose: String,
+}
+
+fn default_api_key_env() -> String {
+    "OPENAI_API_KEY".to_string()
+}
+
+fn default_base_url() -> String {
+    "https://api.openai.com/v1".to_string()
+}
+
+fn default_purpose() -> String {
+    "assistants".to_string()
+}
+
+/// Uploads each file to the OpenAI Files API.
+pub(super) struct OpenAi {
+    config: OpenAiConfig,
+}
+
+impl OpenAi {
+    pub(super) fn new(config: OpenAiConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl UploadTarget for OpenAi {
+    fn upload(&self, file: &UploadFile) -> Result<(), UploadError> {
+        let api_key = env_var(&self.config.api_key_env)?;
+        let content = fs::read(&file.path)?;
+        let (form_type, body) = Multipart::new()
+            .text("purpose", &self.config.purpose)
+            .file("file", &flat_name(&file.name), content_type(&file.name), &content)
+            .finish();
+        ureq::post(&format!("{}/files", self.config.base_url.trim_end_matches('/')))
+            .set("Authorization", &format!("Bearer {}", api_key))
+            .set("Content-Type", &form_type)
+            .send_bytes(&body)
+            .map_err(|e| request_error(&file.name, e))?;
+        Ok(())
+    }
+}
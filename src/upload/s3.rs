@@ -0,0 +1,49 @@
+use serde::Deserialize;
+use super::command::run;
+use super::{UploadError, UploadFile, UploadTarget};
+
+/// Settings for uploading to an S3 bucket.
+///
+/// Files are copied with the `aws` command-line tool, so credentials come from its
+/// usual configuration (environment, profiles, or instance roles).
+#[derive(Deserialize, Debug, Clone)]
+pub struct S3Config {
+    pub bucket: String,
+    /// The key prefix to upload under, such as `kb/my-project`.
+    #[serde(default)]
+    pub prefix: String,
+    pub profile: Option<String>,
+    pub region: Option<String>,
+}
+
+/// Copies each file into an S3 bucket with `aws s3 cp`.
+pub(super) struct S3 {
+    config: S3Config,
+}
+
+impl S3 {
+    pub(super) fn new(config: S3Config) -> Self {
+        Self { config }
+    }
+}
+
+impl UploadTarget for S3 {
+    fn upload(&self, file: &UploadFile) -> Result<(), UploadError> {
+        let prefix = self.config.prefix.trim_matches('/');
+        let key = if prefix.is_empty() { file.name.clone() } else { format!("{}/{}", prefix, file.name) };
+        let mut args = vec![
+            "s3".to_string(),
+            "cp".to_string(),
+            "--only-show-errors".to_string(),
+            file.path.to_string_lossy().into_owned(),
+            format!("s3://{}/{}", self.config.bucket, key),
+        ];
+        if let Some(ref profile) = self.config.profile {
+            args.extend(["--profile".to_string(), profile.clone()]);
+        }
+        if let Some(ref region) = self.config.region {
+            args.extend(["--region".to_string(), region.clone()]);
+        }
+        run("aws", &args)
+    }
+}
@@ -0,0 +1,311 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use walkdir::WalkDir;
+
+/// The kind of filesystem entry a path refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    File,
+    Dir,
+    Special(SpecialKind),
+}
+
+/// An entry that is neither a regular file nor a directory.
+///
+/// Special files are never read, since reading a FIFO blocks until another process
+/// writes to it and reading a device may never end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecialKind {
+    Fifo,
+    Socket,
+    BlockDevice,
+    CharDevice,
+    Other,
+}
+
+impl SpecialKind {
+    /// Classifies a file type that is neither a regular file nor a directory.
+    pub fn of(file_type: &fs::FileType) -> Self {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::FileTypeExt;
+            if file_type.is_fifo() {
+                return Self::Fifo;
+            } else if file_type.is_socket() {
+                return Self::Socket;
+            } else if file_type.is_block_device() {
+                return Self::BlockDevice;
+            } else if file_type.is_char_device() {
+                return Self::CharDevice;
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = file_type;
+        Self::Other
+    }
+
+    /// Returns a short description of the kind, such as "FIFO".
+    pub fn describe(self) -> &'static str {
+        match self {
+            Self::Fifo => "FIFO",
+            Self::Socket => "socket",
+            Self::BlockDevice => "block device",
+            Self::CharDevice => "character device",
+            Self::Other => "special file",
+        }
+    }
+}
+
+/// The subset of file metadata the collection pipeline relies on.
+#[derive(Debug, Clone)]
+pub struct Metadata {
+    pub kind: FileKind,
+    /// The size of the entry in bytes.
+    pub len: u64,
+    /// When the entry was last modified, if the filesystem records it.
+    pub modified: Option<SystemTime>,
+    /// The user ID of the entry's owner, if the filesystem records one.
+    pub owner: Option<u32>,
+}
+
+/// Abstracts the file access performed while collecting artifacts.
+///
+/// Implementing this trait lets the pipeline run against sources other than the
+/// local disk, such as generated content or extracted archives held in memory.
+/// Files are read from several threads at once, so implementations must be `Sync`.
+pub trait FileSystem: Sync {
+    /// Lists the paths of the entries directly inside a directory.
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>>;
+
+    /// Reads the full contents of a file.
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Returns the metadata of a path, following symlinks.
+    fn metadata(&self, path: &Path) -> io::Result<Metadata>;
+
+    /// Recursively lists every file beneath `root`.
+    ///
+    /// Entries that cannot be read are skipped. Special files such as FIFOs are listed
+    /// along with regular files, so that callers can report them; check their metadata
+    /// before reading them.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The directory to walk.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec<PathBuf>` containing the paths of all files beneath `root`.
+    fn walk(&self, root: &Path) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        let mut pending = vec![root.to_path_buf()];
+        while let Some(path) = pending.pop() {
+            match self.metadata(&path).map(|metadata| metadata.kind) {
+                Ok(FileKind::File | FileKind::Special(_)) => files.push(path),
+                Ok(FileKind::Dir) => {
+                    if let Ok(mut children) = self.read_dir(&path) {
+                        children.reverse();
+                        pending.extend(children);
+                    }
+                }
+                Err(_) => {}
+            }
+        }
+        files
+    }
+
+    /// Reads a file as UTF-8 text.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file to read.
+    ///
+    /// # Returns
+    ///
+    /// Returns `io::Result<String>` containing the file contents, or an `InvalidData`
+    /// error if the file is not valid UTF-8.
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// The local disk.
+pub struct RealFs;
+
+impl FileSystem for RealFs {
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect()
+    }
+
+    /// Reads a file, refusing special files, which could block the read forever.
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let file_type = fs::metadata(path)?.file_type();
+        if !file_type.is_file() && !file_type.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is a {}, not a regular file", path.display(), SpecialKind::of(&file_type).describe()),
+            ));
+        }
+        fs::read(path)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        let metadata = fs::metadata(path)?;
+        let kind = if metadata.is_file() {
+            FileKind::File
+        } else if metadata.is_dir() {
+            FileKind::Dir
+        } else {
+            FileKind::Special(SpecialKind::of(&metadata.file_type()))
+        };
+        #[cfg(unix)]
+        let owner = Some(std::os::unix::fs::MetadataExt::uid(&metadata));
+        #[cfg(not(unix))]
+        let owner = None;
+        Ok(Metadata { kind, len: metadata.len(), modified: metadata.modified().ok(), owner })
+    }
+
+    /// Walks the directory with `walkdir`, following symlinks while guarding against loops.
+    fn walk(&self, root: &Path) -> Vec<PathBuf> {
+        WalkDir::new(root)
+            .follow_links(true)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| !entry.file_type().is_dir())
+            .map(|entry| entry.into_path())
+            .collect()
+    }
+}
+
+/// An in-memory filesystem holding a fixed set of files.
+///
+/// Directories are implied by the paths of the files added to it.
+#[derive(Debug, Default)]
+pub struct MemoryFs {
+    files: BTreeMap<PathBuf, Vec<u8>>,
+}
+
+impl MemoryFs {
+    /// Creates an empty `MemoryFs`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file, replacing any existing file at the same path.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file.
+    /// * `content` - The contents of the file.
+    pub fn add_file(&mut self, path: impl AsRef<Path>, content: impl Into<Vec<u8>>) {
+        self.files.insert(normalize(path.as_ref()), content.into());
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.as_os_str().is_empty()
+            || self.files.keys().any(|file| file != path && file.starts_with(path))
+    }
+}
+
+impl FileSystem for MemoryFs {
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let dir = normalize(path);
+        if !self.is_dir(&dir) {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("{} is not a directory", path.display())));
+        }
+        let mut children: Vec<PathBuf> = self
+            .files
+            .keys()
+            .filter_map(|file| file.strip_prefix(&dir).ok())
+            .filter_map(|rest| rest.components().next())
+            .map(|first| path.join(first))
+            .collect();
+        children.dedup();
+        Ok(children)
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.files
+            .get(&normalize(path))
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} not found", path.display())))
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        let path = normalize(path);
+        if let Some(content) = self.files.get(&path) {
+            Ok(Metadata { kind: FileKind::File, len: content.len() as u64, modified: None, owner: None })
+        } else if self.is_dir(&path) {
+            Ok(Metadata { kind: FileKind::Dir, len: 0, modified: None, owner: None })
+        } else {
+            Err(io::Error::new(io::ErrorKind::NotFound, format!("{} not found", path.display())))
+        }
+    }
+}
+
+/// Wraps a filesystem so that the source tree is walked only once.
+///
+/// A walk of a directory that was already walked, or of a directory beneath it, is
+/// answered from the earlier result. This lets several collections over one source
+/// (such as the presets of a `--matrix` run) share a single traversal. File contents
+/// and metadata are always read from the wrapped filesystem.
+pub struct SharedWalk<'a> {
+    fs: &'a dyn FileSystem,
+    walks: Mutex<Vec<(PathBuf, Vec<PathBuf>)>>,
+}
+
+impl<'a> SharedWalk<'a> {
+    /// Creates a `SharedWalk` over the given filesystem.
+    ///
+    /// # Arguments
+    ///
+    /// * `fs` - The filesystem to walk and read from.
+    pub fn new(fs: &'a dyn FileSystem) -> Self {
+        Self { fs, walks: Mutex::new(Vec::new()) }
+    }
+}
+
+impl FileSystem for SharedWalk<'_> {
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        self.fs.read_dir(path)
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.fs.read(path)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        self.fs.metadata(path)
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        self.fs.read_to_string(path)
+    }
+
+    /// Serves the walk from an earlier walk of `root` or one of its ancestors if there
+    /// is one. The lock is held while walking, so concurrent callers wait for the first
+    /// walk instead of repeating it.
+    fn walk(&self, root: &Path) -> Vec<PathBuf> {
+        let mut walks = self.walks.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some((_, files)) = walks.iter().find(|(walked, _)| root.starts_with(walked)) {
+            return files.iter().filter(|file| file.starts_with(root)).cloned().collect();
+        }
+        let files = self.fs.walk(root);
+        walks.push((root.to_path_buf(), files.clone()));
+        files
+    }
+}
+
+/// Drops `.` components and leading roots so that `./src/main.rs` and `src/main.rs` match.
+fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| matches!(component, Component::Normal(_) | Component::ParentDir))
+        .collect()
+}
@@ -0,0 +1,216 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use crate::checksum;
+use crate::global_config::GlobalConfig;
+use crate::vfs::{FileSystem, Metadata, RealFs};
+
+/// Directory under the user cache directory that holds one walk cache per source root.
+const WALKS_DIR: &str = "walks";
+
+/// How recently before the cache was saved a directory may have changed and still be trusted.
+///
+/// A change made within the same timestamp tick as the listing would leave the
+/// directory's modification time untouched, so such directories are listed again.
+const RACY_WINDOW: Duration = Duration::from_secs(2);
+
+/// The entries of one directory, as last listed.
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Listing {
+    /// The directory's modification time in nanoseconds since the Unix epoch.
+    modified: u128,
+    files: Vec<String>,
+    dirs: Vec<String>,
+    /// The subset of `dirs` reached through a symlink.
+    links: Vec<String>,
+}
+
+/// Every directory listing beneath a source root, keyed by path relative to the root.
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Snapshot {
+    /// When the snapshot was saved, in nanoseconds since the Unix epoch.
+    saved_at: u128,
+    listings: BTreeMap<PathBuf, Listing>,
+}
+
+/// Walks the local disk, reusing the directory listings saved by earlier runs.
+///
+/// The listings of a source root are saved under the user cache directory after each
+/// walk. The next walk of the same root lists again only the directories whose
+/// modification time changed, so repeated runs with different filters skip most of the
+/// traversal. Only the shape of the tree is cached; file contents and metadata are
+/// always read from disk.
+pub struct WalkCache {
+    root: PathBuf,
+    path: Option<PathBuf>,
+    files: Mutex<Option<Vec<PathBuf>>>,
+}
+
+impl WalkCache {
+    /// Creates a walk cache for a source root.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The source directory, as it appears in walked paths.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        let path = fs::canonicalize(&root).ok().and_then(|canonical| {
+            let name = format!("{}.json", checksum::short_hash(&canonical.to_string_lossy()));
+            GlobalConfig::cache_dir().map(|dir| dir.join(WALKS_DIR).join(name))
+        });
+        Self { root, path, files: Mutex::new(None) }
+    }
+
+    /// Forgets the files found by the last walk, so that the next walk checks the tree again.
+    ///
+    /// Long-running processes call this when the source may have changed. The next walk
+    /// still lists again only the directories whose modification time changed.
+    pub fn invalidate(&self) {
+        *self.files.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+    }
+
+    /// Walks the source root, listing only the directories that changed since the saved snapshot.
+    fn refresh(&self) -> io::Result<Vec<PathBuf>> {
+        let mut previous = self.path.as_ref().and_then(|path| load(path)).unwrap_or_default();
+        let mut snapshot = Snapshot::default();
+        let mut files = Vec::new();
+        let mut reused = 0;
+        let mut pending = vec![(PathBuf::new(), fs::canonicalize(&self.root)?)];
+        while let Some((relative, canonical)) = pending.pop() {
+            let dir = self.root.join(&relative);
+            let Ok(modified) = fs::metadata(&dir).and_then(|metadata| metadata.modified()) else {
+                continue;
+            };
+            let modified = nanos(modified);
+            let listing = match previous.listings.remove(&relative) {
+                Some(listing) if listing.modified == modified && modified + RACY_WINDOW.as_nanos() <= previous.saved_at => {
+                    reused += 1;
+                    listing
+                }
+                _ => match list(&dir, modified) {
+                    Ok(listing) => listing,
+                    Err(e) if e.kind() == io::ErrorKind::InvalidData => return Err(e),
+                    Err(e) => {
+                        debug!("Failed to list {}: {}", dir.display(), e);
+                        continue;
+                    }
+                },
+            };
+
+            files.extend(listing.files.iter().map(|name| dir.join(name)));
+            for name in listing.dirs.iter().rev() {
+                let child = if listing.links.contains(name) {
+                    // Follow symlinks like `RealFs::walk`, but never into one of their own ancestors.
+                    match fs::canonicalize(dir.join(name)) {
+                        Ok(target) if !canonical.starts_with(&target) => target,
+                        _ => continue,
+                    }
+                } else {
+                    canonical.join(name)
+                };
+                pending.push((relative.join(name), child));
+            }
+            snapshot.listings.insert(relative, listing);
+        }
+        debug!("Reused {} of {} directory listings from the walk cache", reused, snapshot.listings.len());
+
+        if let Some(ref path) = self.path {
+            snapshot.saved_at = nanos(SystemTime::now());
+            if let Err(e) = save(path, &snapshot) {
+                debug!("Failed to save the walk cache {}: {}", path.display(), e);
+            }
+        }
+        Ok(files)
+    }
+}
+
+impl FileSystem for WalkCache {
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        RealFs.read_dir(path)
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        RealFs.read(path)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        RealFs.metadata(path)
+    }
+
+    /// Serves walks of the source root and the directories beneath it from the cached
+    /// listings, refreshing them on the first walk. Other roots are walked directly.
+    fn walk(&self, root: &Path) -> Vec<PathBuf> {
+        if !root.starts_with(&self.root) || !self.root.is_dir() {
+            return RealFs.walk(root);
+        }
+        let mut files = self.files.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if files.is_none() {
+            match self.refresh() {
+                Ok(walked) => *files = Some(walked),
+                Err(e) => {
+                    debug!("Failed to walk {} through the walk cache: {}", self.root.display(), e);
+                    return RealFs.walk(root);
+                }
+            }
+        }
+        files.iter().flatten().filter(|file| file.starts_with(root)).cloned().collect()
+    }
+}
+
+/// Lists a directory, sorting its entries into files and directories.
+///
+/// Symlinks are resolved, and entries whose target is missing are left out. Special files
+/// such as FIFOs are listed as files, like [`crate::vfs::FileSystem::walk`] lists them.
+fn list(dir: &Path, modified: u128) -> io::Result<Listing> {
+    let mut listing = Listing { modified, ..Listing::default() };
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        // Names are stored as JSON strings, so a tree with non-UTF-8 names is walked directly.
+        let name = entry
+            .file_name()
+            .into_string()
+            .map_err(|name| io::Error::new(io::ErrorKind::InvalidData, format!("{:?} is not valid UTF-8", name)))?;
+        let file_type = entry.file_type()?;
+        let is_link = file_type.is_symlink();
+        let is_dir = if is_link {
+            match fs::metadata(entry.path()) {
+                Ok(metadata) => metadata.is_dir(),
+                Err(_) => continue,
+            }
+        } else {
+            file_type.is_dir()
+        };
+        if is_dir {
+            if is_link {
+                listing.links.push(name.clone());
+            }
+            listing.dirs.push(name);
+        } else {
+            listing.files.push(name);
+        }
+    }
+    listing.files.sort();
+    listing.dirs.sort();
+    Ok(listing)
+}
+
+fn load(path: &Path) -> Option<Snapshot> {
+    let json = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+fn save(path: &Path, snapshot: &Snapshot) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string(snapshot)?)
+}
+
+fn nanos(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_nanos()).unwrap_or_default()
+}
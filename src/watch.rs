@@ -0,0 +1,322 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::Duration;
+use log::{debug, info, warn};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use thiserror::Error;
+use crate::artifact::{Artifact, ArtifactError, CollectFilter, DirSink};
+use crate::config::Config;
+use crate::manifest::Manifest;
+use crate::preflight::{self, PreflightError};
+use crate::upload::{self, UploadFile, UploadTarget};
+use crate::vfs::{FileSystem, RealFs};
+use crate::writer::{self, DirectoryWriter};
+
+/// Custom error type for watch mode.
+#[derive(Error, Debug)]
+pub enum WatchError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Artifact error: {0}")]
+    Artifact(#[from] ArtifactError),
+    #[error("Preflight error: {0}")]
+    Preflight(#[from] PreflightError),
+    #[error("Watch error: {0}")]
+    Notify(#[from] notify::Error),
+}
+
+/// Counts of what changed during one sync cycle.
+#[derive(Debug, Default)]
+struct SyncSummary {
+    updated: usize,
+    removed: usize,
+    /// Names of the files written to the destination directory.
+    written: Vec<String>,
+}
+
+/// Keeps the destination in sync with the source directory until the process is stopped.
+///
+/// An initial full collection is written first. After that, filesystem events are
+/// debounced and only the paths they touch are re-collected, written, or removed.
+///
+/// With an upload target, the initial output is uploaded, and so is every file written
+/// by a later sync cycle (or the whole `--single-file` bundle whenever it changes).
+/// Files removed from the destination are left on the target. A failed upload does not
+/// stop watching; its files are retried after the next sync cycle.
+///
+/// # Arguments
+///
+/// * `config` - The configuration options.
+/// * `debounce` - How long the source must stay quiet before a sync cycle runs.
+/// * `target` - The name of the target to upload changes to and the target itself, if any.
+///
+/// # Returns
+///
+/// Returns `Result<(), WatchError>` if watching stops because of an error.
+pub fn run(mut config: Config, debounce: Duration, target: Option<(&str, &dyn UploadTarget)>) -> Result<(), WatchError> {
+    config.source_dir = config.source_dir.canonicalize()?;
+    // Files are synced one at a time, so duplicates cannot be tracked back to a merged artifact.
+    config.keep_duplicates = true;
+    let fs = RealFs;
+    let directory_writer = DirectoryWriter::from_config(&config);
+    let output = writer::from_config(&config);
+
+    let output_dir = match config.single_file {
+        Some(ref single_file) => single_file.parent().map(PathBuf::from).unwrap_or_else(|| PathBuf::from(".")),
+        None => directory_writer.output_dir(),
+    };
+    let plan = Artifact::plan(&fs, &config)?;
+    preflight::run(&config, &plan)?;
+
+    let mut artifacts: BTreeMap<PathBuf, Artifact> = Artifact::execute(&fs, plan, &config)?
+        .into_iter()
+        .map(|artifact| (artifact.relative_path.clone(), artifact))
+        .collect();
+    output.write(&snapshot(&artifacts))?;
+    info!("Initial sync complete: {} artifacts", artifacts.len());
+
+    // Files whose upload is still outstanding, by name in the output.
+    let mut pending: BTreeMap<String, PathBuf> = BTreeMap::new();
+    if let Some((name, target)) = target {
+        match upload::output_files(&config) {
+            Ok(files) => pending.extend(files.into_iter().map(|file| (file.name, file.path))),
+            Err(e) => warn!("Nothing uploaded to {}: {}", name, e),
+        }
+        upload_pending(name, target, &mut pending);
+    }
+
+    // Changes to the written output must not trigger another sync.
+    let written_path = match config.single_file {
+        Some(ref single_file) => single_file.canonicalize()?,
+        None => output_dir.canonicalize()?,
+    };
+    let filter = CollectFilter::from_config(&config)?;
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = RecommendedWatcher::new(tx, notify::Config::default())?;
+    watcher.watch(&config.source_dir, RecursiveMode::Recursive)?;
+    info!("Watching {} for changes", config.source_dir.display());
+
+    loop {
+        let changed = next_batch(&rx, debounce)?;
+        let mut summary = SyncSummary::default();
+
+        for path in changed {
+            if path.starts_with(&written_path) {
+                continue;
+            }
+            let Ok(relative_path) = path.strip_prefix(&config.source_dir).map(Path::to_path_buf) else {
+                continue;
+            };
+
+            if path.is_file() {
+                sync_file(&fs, &path, &relative_path, &config, &filter, &mut artifacts, &mut summary)?;
+            } else if path.is_dir() {
+                for file in fs.walk(&path) {
+                    let Ok(relative_file) = file.strip_prefix(&config.source_dir).map(Path::to_path_buf) else {
+                        continue;
+                    };
+                    sync_file(&fs, &file, &relative_file, &config, &filter, &mut artifacts, &mut summary)?;
+                }
+            } else {
+                let gone: Vec<PathBuf> = artifacts
+                    .keys()
+                    .filter(|key| key.starts_with(&relative_path))
+                    .cloned()
+                    .collect();
+                for key in gone {
+                    if let Some(artifact) = artifacts.remove(&key) {
+                        remove_written(&artifact, &config, &directory_writer);
+                        summary.removed += 1;
+                    }
+                }
+            }
+        }
+
+        if summary.updated == 0 && summary.removed == 0 {
+            debug!("No artifacts affected by the latest changes");
+            continue;
+        }
+
+        if let Some(ref single_file) = config.single_file {
+            output.write(&snapshot(&artifacts))?;
+            summary.written.push(upload_name(single_file));
+        } else {
+            let manifest = Manifest::from_artifacts(&snapshot(&artifacts), &config.run_id, config.format);
+            let mut sink = DirSink::new(&output_dir);
+            manifest.write(&mut sink)?;
+            if config.manifest_markdown {
+                manifest.write_markdown(&mut sink)?;
+            }
+        }
+        info!(
+            "Sync complete: {} updated, {} removed, {} artifacts total",
+            summary.updated,
+            summary.removed,
+            artifacts.len()
+        );
+
+        if let Some((name, target)) = target {
+            for file in summary.written {
+                let path = config.single_file.clone().unwrap_or_else(|| output_dir.join(&file));
+                pending.insert(file, path);
+            }
+            upload_pending(name, target, &mut pending);
+        }
+    }
+}
+
+/// Uploads the outstanding files, keeping them outstanding if the upload fails.
+fn upload_pending(name: &str, target: &dyn UploadTarget, pending: &mut BTreeMap<String, PathBuf>) {
+    // A file removed again before it was uploaded has nothing left to upload.
+    pending.retain(|_, path| path.is_file());
+    if pending.is_empty() {
+        return;
+    }
+    let files: Vec<UploadFile> =
+        pending.iter().map(|(file, path)| UploadFile { name: file.clone(), path: path.clone() }).collect();
+    match target.upload_all(&files) {
+        Ok(count) => {
+            info!("Uploaded {} files to {}", count, name);
+            pending.clear();
+        }
+        Err(e) => warn!("Upload to {} failed, retrying after the next change: {}", name, e),
+    }
+}
+
+/// Returns the name under which a `--single-file` bundle is uploaded.
+fn upload_name(single_file: &Path) -> String {
+    single_file.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+/// Re-collects one file, writing it if it changed and removing it if it no longer qualifies.
+fn sync_file(
+    fs: &dyn FileSystem,
+    path: &Path,
+    relative_path: &Path,
+    config: &Config,
+    filter: &CollectFilter,
+    artifacts: &mut BTreeMap<PathBuf, Artifact>,
+    summary: &mut SyncSummary,
+) -> Result<(), WatchError> {
+    let directory_writer = DirectoryWriter::from_config(config);
+    match Artifact::collect_file(fs, path, config, filter)? {
+        Some(mut artifact) => {
+            let existing = artifacts.get(relative_path);
+            if existing.is_some_and(|existing| existing.checksum == artifact.checksum) {
+                return Ok(());
+            }
+            // Keep any name given to disambiguate a collision in the initial collection, and
+            // never let a new file take the name of one already tracked.
+            if let Some(existing) = existing {
+                artifact.new_filename = existing.new_filename.clone();
+            } else {
+                let taken: HashMap<&str, &Path> = artifacts
+                    .values()
+                    .map(|tracked| (tracked.new_filename.as_str(), tracked.relative_path.as_path()))
+                    .collect();
+                if let Err(e) = artifact.disambiguate_against(&taken, config) {
+                    warn!("Skipping {}: {}", relative_path.display(), e);
+                    return Ok(());
+                }
+            }
+            if config.single_file.is_none() {
+                artifact.write(&mut DirSink::new(directory_writer.output_dir()), config.checksum_header, config.format)?;
+                summary.written.push(artifact.new_filename.clone());
+            }
+            artifacts.insert(relative_path.to_path_buf(), artifact);
+            summary.updated += 1;
+        }
+        None => {
+            if let Some(artifact) = artifacts.remove(relative_path) {
+                remove_written(&artifact, config, &directory_writer);
+                summary.removed += 1;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Deletes the written copy of an artifact from the destination directory.
+fn remove_written(artifact: &Artifact, config: &Config, directory_writer: &DirectoryWriter) {
+    if config.single_file.is_some() {
+        return;
+    }
+    let written = directory_writer.output_dir().join(&artifact.new_filename);
+    if let Err(e) = fs::remove_file(&written) {
+        warn!("Failed to remove {}: {}", written.display(), e);
+    }
+}
+
+/// Waits for filesystem events and gathers every path touched until the source stays quiet.
+fn next_batch(rx: &Receiver<notify::Result<Event>>, debounce: Duration) -> Result<HashSet<PathBuf>, WatchError> {
+    let mut changed = HashSet::new();
+    let first = rx.recv().map_err(|e| io::Error::new(io::ErrorKind::BrokenPipe, e))?;
+    changed.extend(first?.paths);
+    loop {
+        match rx.recv_timeout(debounce) {
+            Ok(event) => changed.extend(event?.paths),
+            Err(RecvTimeoutError::Timeout) => return Ok(changed),
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err(io::Error::new(io::ErrorKind::BrokenPipe, "watcher disconnected").into())
+            }
+        }
+    }
+}
+
+/// Copies the tracked artifacts, in path order, so they can be handed to a `Writer`.
+fn snapshot(artifacts: &BTreeMap<PathBuf, Artifact>) -> Vec<Artifact> {
+    artifacts.values().cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Collects `dir/project` into `dir/out`, then syncs the files at the given relative paths.
+    fn collect_then_sync(dir: &Path, config: &Config, added: &[&str]) -> BTreeMap<PathBuf, Artifact> {
+        let mut artifacts: BTreeMap<PathBuf, Artifact> = Artifact::collect(&RealFs, config)
+            .unwrap()
+            .into_iter()
+            .map(|artifact| (artifact.relative_path.clone(), artifact))
+            .collect();
+        let filter = CollectFilter::from_config(config).unwrap();
+        for relative_path in added {
+            let path = dir.join("project").join(relative_path);
+            fs::write(&path, format!("const PATH: &str = {:?};\n", relative_path)).unwrap();
+            sync_file(&RealFs, &path, Path::new(relative_path), config, &filter, &mut artifacts, &mut SyncSummary::default()).unwrap();
+        }
+        artifacts
+    }
+
+    fn config(dir: &Path) -> Config {
+        Config { source_dir: dir.join("project"), dest_dir: dir.join("out"), ..Config::default() }
+    }
+
+    #[test]
+    fn files_added_while_watching_do_not_take_names_in_use() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("project/a")).unwrap();
+        fs::write(dir.path().join("project/a/b.rs"), "const PATH: &str = \"a/b.rs\";\n").unwrap();
+
+        let artifacts = collect_then_sync(dir.path(), &config(dir.path()), &["a_b.rs"]);
+        assert_eq!(artifacts[Path::new("a/b.rs")].new_filename, "a_b.rs");
+        let added = &artifacts[Path::new("a_b.rs")].new_filename;
+        assert_ne!(added, "a_b.rs");
+        assert_eq!(fs::read_to_string(dir.path().join("out").join(added)).unwrap(), "const PATH: &str = \"a_b.rs\";\n");
+    }
+
+    #[test]
+    fn files_added_while_watching_are_skipped_on_collision_with_fail_on_collision() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("project/a")).unwrap();
+        fs::write(dir.path().join("project/a/b.rs"), "const PATH: &str = \"a/b.rs\";\n").unwrap();
+
+        let config = Config { fail_on_collision: true, ..config(dir.path()) };
+        let artifacts = collect_then_sync(dir.path(), &config, &["a_b.rs"]);
+        assert_eq!(artifacts.keys().collect::<Vec<_>>(), [Path::new("a/b.rs")]);
+    }
+}
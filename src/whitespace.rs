@@ -0,0 +1,75 @@
+/// Collapses every run of blank lines into a single empty line.
+///
+/// Lines containing only whitespace count as blank.
+///
+/// # Arguments
+///
+/// * `content` - The content to squeeze.
+///
+/// # Returns
+///
+/// A `String` containing the squeezed content.
+pub fn squeeze_blank_lines(content: &str) -> String {
+    let mut squeezed = String::with_capacity(content.len());
+    let mut previous_blank = false;
+    for line in content.split_inclusive('\n') {
+        let blank = line.trim().is_empty();
+        if blank && previous_blank {
+            continue;
+        }
+        if blank {
+            squeezed.push_str(if line.ends_with('\n') { "\n" } else { "" });
+        } else {
+            squeezed.push_str(line);
+        }
+        previous_blank = blank;
+    }
+    squeezed
+}
+
+/// Shrinks space indentation to the smallest width that keeps every line's nesting intact.
+///
+/// The indentation of each space-indented line is divided by the greatest common divisor
+/// of all such indentation widths, so four-space code becomes one-space code while relative
+/// nesting (including for indentation-sensitive languages) is preserved. Lines indented
+/// with tabs are left untouched.
+///
+/// # Arguments
+///
+/// * `content` - The content to minify.
+///
+/// # Returns
+///
+/// A `String` containing the minified content.
+pub fn minify_indent(content: &str) -> String {
+    let unit = content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| space_indent(line).filter(|&width| width > 0))
+        .fold(0, gcd);
+    if unit <= 1 {
+        return content.to_string();
+    }
+
+    let mut minified = String::with_capacity(content.len());
+    for line in content.split_inclusive('\n') {
+        match space_indent(line) {
+            Some(width) if !line.trim().is_empty() => {
+                minified.push_str(&" ".repeat(width / unit));
+                minified.push_str(&line[width..]);
+            }
+            _ => minified.push_str(line),
+        }
+    }
+    minified
+}
+
+/// Returns the number of leading spaces, or `None` if the indentation contains a tab.
+fn space_indent(line: &str) -> Option<usize> {
+    let indent = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+    (!indent.contains('\t')).then_some(indent.len())
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
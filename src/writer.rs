@@ -0,0 +1,560 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use log::info;
+use crate::artifact::{Artifact, DirSink, Sink, ZipSink};
+use crate::cache::{self, Cache};
+use crate::clean;
+use crate::config::{Config, OutputFormat, WalkOrder};
+use crate::format;
+use crate::manifest::Manifest;
+use crate::project::{self, ProjectInfo};
+use crate::runs;
+
+/// Writes collected artifacts to some destination.
+pub trait Writer {
+    /// Writes all artifacts to the writer's destination.
+    ///
+    /// # Arguments
+    ///
+    /// * `artifacts` - A slice of `Artifact` instances to write.
+    ///
+    /// # Returns
+    ///
+    /// Returns `io::Result<()>` indicating success or failure of the write operations.
+    fn write(&self, artifacts: &[Artifact]) -> io::Result<()>;
+}
+
+/// Writes each artifact as its own file in a destination directory, alongside a manifest.
+#[derive(Debug, Clone)]
+pub struct DirectoryWriter {
+    dest_dir: PathBuf,
+    run_id: String,
+    run_dirs: bool,
+    checksum_header: bool,
+    format: OutputFormat,
+    cache: bool,
+    settings: String,
+    prune: Prune,
+    force: bool,
+    manifest_markdown: bool,
+    lang_stats: bool,
+}
+
+/// Which files already in the destination are removed before a `DirectoryWriter` writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prune {
+    /// Leave existing files alone.
+    None,
+    /// Remove previously written artifacts that are not part of the current run.
+    Stale,
+    /// Remove every previously written artifact.
+    All,
+}
+
+impl Prune {
+    /// Selects the prune setting for `--clean` and `--prune`, with `--clean` taking precedence.
+    pub fn from_config(config: &Config) -> Self {
+        if config.clean {
+            Self::All
+        } else if config.prune {
+            Self::Stale
+        } else {
+            Self::None
+        }
+    }
+}
+
+impl DirectoryWriter {
+    /// Creates a writer for the given destination directory with a fresh run ID.
+    ///
+    /// # Arguments
+    ///
+    /// * `dest_dir` - The destination directory path.
+    pub fn new(dest_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dest_dir: dest_dir.into(),
+            run_id: runs::new_run_id(),
+            run_dirs: false,
+            checksum_header: false,
+            format: OutputFormat::Plain,
+            cache: false,
+            settings: String::new(),
+            manifest_markdown: false,
+            lang_stats: false,
+            prune: Prune::None,
+            force: false,
+        }
+    }
+
+    /// Creates a writer using the destination, run, and header settings of a `Config`.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The configuration options.
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            dest_dir: config.dest_dir.clone(),
+            run_id: config.run_id.clone(),
+            run_dirs: config.run_dirs,
+            checksum_header: config.checksum_header,
+            format: config.format,
+            cache: !config.force,
+            settings: cache::settings_fingerprint(config),
+            prune: Prune::from_config(config),
+            force: config.force,
+            manifest_markdown: config.manifest_markdown,
+            lang_stats: config.lang_stats,
+        }
+    }
+
+    /// Sets whether artifacts the previous run already wrote, unchanged, are skipped.
+    pub fn cache(mut self, enabled: bool) -> Self {
+        self.cache = enabled;
+        self
+    }
+
+    /// Sets whether each artifact starts with a checksum header line.
+    pub fn checksum_header(mut self, enabled: bool) -> Self {
+        self.checksum_header = enabled;
+        self
+    }
+
+    /// Sets the format artifacts are rendered in.
+    pub fn format(mut self, format: OutputFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets which existing files are removed before writing, and whether files rustifacts
+    /// did not write may be removed too.
+    pub fn prune(mut self, prune: Prune, force: bool) -> Self {
+        self.prune = prune;
+        self.force = force;
+        self
+    }
+
+    /// Sets whether a Markdown rendering of the manifest is written next to it.
+    pub fn manifest_markdown(mut self, enabled: bool) -> Self {
+        self.manifest_markdown = enabled;
+        self
+    }
+
+    /// Sets whether the manifest records the language breakdown of the artifacts.
+    pub fn lang_stats(mut self, enabled: bool) -> Self {
+        self.lang_stats = enabled;
+        self
+    }
+
+    /// Sets whether each run is written into its own `runs/<run-id>/` directory.
+    pub fn run_dirs(mut self, enabled: bool) -> Self {
+        self.run_dirs = enabled;
+        self
+    }
+
+    /// Returns the directory artifacts are written to.
+    pub fn output_dir(&self) -> PathBuf {
+        if self.run_dirs {
+            runs::run_dir(&self.dest_dir, &self.run_id)
+        } else {
+            self.dest_dir.clone()
+        }
+    }
+}
+
+impl Writer for DirectoryWriter {
+    fn write(&self, artifacts: &[Artifact]) -> io::Result<()> {
+        let output_dir = self.output_dir();
+        fs::create_dir_all(&output_dir)?;
+        let mut sink = DirSink::new(&output_dir);
+        let names: HashSet<String> = artifacts.iter().map(|artifact| artifact.new_filename.clone()).collect();
+        let mut stale = Vec::new();
+        if !self.run_dirs {
+            prune_dir(&output_dir, self.prune, names.clone(), self.force)?;
+            stale = stale_files(&output_dir, &names);
+        }
+        let previous = if self.cache && !self.run_dirs {
+            Cache::load(&output_dir, &self.settings)
+        } else {
+            Cache::new(&self.settings)
+        };
+        let mut next = Cache::new(&self.settings);
+        let mut unchanged = 0;
+        for artifact in artifacts {
+            if previous.is_written(artifact) && output_dir.join(&artifact.new_filename).is_file() {
+                unchanged += 1;
+            } else {
+                artifact.write(&mut sink, self.checksum_header, self.format)?;
+            }
+            next.record(artifact);
+        }
+        if unchanged > 0 {
+            info!("Skipped writing {} unchanged artifacts", unchanged);
+        }
+        let mut manifest = Manifest::from_artifacts(artifacts, &self.run_id, self.format);
+        manifest.stale = stale;
+        if self.lang_stats {
+            manifest.languages = project::language_stats(artifacts);
+        }
+        manifest.write(&mut sink)?;
+        if self.manifest_markdown {
+            manifest.write_markdown(&mut sink)?;
+        }
+        if !self.run_dirs {
+            next.save(&output_dir)?;
+        }
+        if self.run_dirs {
+            runs::update_latest(&self.dest_dir, &self.run_id)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes all artifacts into one file, each wrapped in delimiters (or, for Markdown,
+/// a heading) naming its original path.
+///
+/// Artifacts are written in the walk order of their relative paths, by name unless set
+/// with [`SingleFileWriter::order`], so that the output is identical across runs
+/// regardless of directory traversal order.
+#[derive(Debug, Clone)]
+pub struct SingleFileWriter {
+    path: PathBuf,
+    format: OutputFormat,
+    order: WalkOrder,
+    /// The source directory to describe in a project header, if one is written.
+    header_source: Option<PathBuf>,
+    lang_stats: bool,
+}
+
+impl SingleFileWriter {
+    /// Creates a writer for the given output file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the combined output file.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            format: OutputFormat::Plain,
+            order: WalkOrder::Name,
+            header_source: None,
+            lang_stats: false,
+        }
+    }
+
+    /// Sets the order artifacts are written in.
+    pub fn order(mut self, order: WalkOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Sets the format artifacts are rendered in.
+    pub fn format(mut self, format: OutputFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Starts the bundle with a header describing the project in the given source directory.
+    pub fn project_header(mut self, source_dir: impl Into<PathBuf>) -> Self {
+        self.header_source = Some(source_dir.into());
+        self
+    }
+
+    /// Sets whether the project header also gives each language's share of the tokens.
+    pub fn lang_stats(mut self, enabled: bool) -> Self {
+        self.lang_stats = enabled;
+        self
+    }
+
+    /// Returns the path of the combined output file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Writer for SingleFileWriter {
+    fn write(&self, artifacts: &[Artifact]) -> io::Result<()> {
+        let mut ordered: Vec<&Artifact> = artifacts.iter().collect();
+        ordered.sort_by(|a, b| self.order.compare(&a.relative_path, &b.relative_path));
+
+        let mut output = String::new();
+        if let Some(ref source_dir) = self.header_source {
+            let mut project = ProjectInfo::detect(source_dir, artifacts);
+            project.token_shares = self.lang_stats;
+            output.push_str(&format::render_header(self.format, &project));
+        }
+        output.push_str(&format::render_bundle(self.format, &ordered));
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, output)
+    }
+}
+
+/// Writes each artifact as an entry of a zip archive, alongside a manifest.
+///
+/// The archive holds the same files a `DirectoryWriter` would write into its directory.
+#[derive(Debug, Clone)]
+pub struct ArchiveWriter {
+    path: PathBuf,
+    run_id: String,
+    checksum_header: bool,
+    format: OutputFormat,
+    manifest_markdown: bool,
+    lang_stats: bool,
+}
+
+impl ArchiveWriter {
+    /// Creates a writer for the given zip archive with a fresh run ID.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the zip archive.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            run_id: runs::new_run_id(),
+            checksum_header: false,
+            format: OutputFormat::Plain,
+            manifest_markdown: false,
+            lang_stats: false,
+        }
+    }
+
+    /// Creates a writer for the given zip archive using the run, header, and format settings of a `Config`.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the zip archive.
+    /// * `config` - The configuration options.
+    pub fn from_config(path: impl Into<PathBuf>, config: &Config) -> Self {
+        Self {
+            path: path.into(),
+            run_id: config.run_id.clone(),
+            checksum_header: config.checksum_header,
+            format: config.format,
+            manifest_markdown: config.manifest_markdown,
+            lang_stats: config.lang_stats,
+        }
+    }
+
+    /// Sets whether each artifact starts with a checksum header line.
+    pub fn checksum_header(mut self, enabled: bool) -> Self {
+        self.checksum_header = enabled;
+        self
+    }
+
+    /// Sets the format artifacts are rendered in.
+    pub fn format(mut self, format: OutputFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets whether a Markdown rendering of the manifest is written next to it.
+    pub fn manifest_markdown(mut self, enabled: bool) -> Self {
+        self.manifest_markdown = enabled;
+        self
+    }
+
+    /// Sets whether the manifest records the language breakdown of the artifacts.
+    pub fn lang_stats(mut self, enabled: bool) -> Self {
+        self.lang_stats = enabled;
+        self
+    }
+
+    /// Returns the path of the zip archive.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Writer for ArchiveWriter {
+    fn write(&self, artifacts: &[Artifact]) -> io::Result<()> {
+        let mut sink = ZipSink::create(&self.path)?;
+        for artifact in artifacts {
+            artifact.write(&mut sink, self.checksum_header, self.format)?;
+        }
+        let mut manifest = Manifest::from_artifacts(artifacts, &self.run_id, self.format);
+        if self.lang_stats {
+            manifest.languages = project::language_stats(artifacts);
+        }
+        manifest.write(&mut sink)?;
+        if self.manifest_markdown {
+            manifest.write_markdown(&mut sink)?;
+        }
+        sink.finish()
+    }
+}
+
+/// Writes one Markdown document per target directory, each with a table of contents.
+///
+/// Artifacts are grouped under the most specific target directory containing them
+/// (`src` becomes `src.md`, `src/api` becomes `src_api.md` with the default separator);
+/// artifacts outside every target go into `root.md`. A manifest maps each original
+/// path to the document holding it.
+#[derive(Debug, Clone)]
+pub struct MarkdownPerTargetWriter {
+    dest_dir: PathBuf,
+    run_id: String,
+    target_dirs: Vec<PathBuf>,
+    separator: String,
+    prune: Prune,
+    force: bool,
+    manifest_markdown: bool,
+    lang_stats: bool,
+    order: WalkOrder,
+}
+
+impl MarkdownPerTargetWriter {
+    /// Creates a writer using the destination, run, target directory, separator, and walk order settings of a `Config`.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The configuration options.
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            dest_dir: config.dest_dir.clone(),
+            run_id: config.run_id.clone(),
+            target_dirs: config.get_target_dirs(),
+            separator: config.flatten_separator.clone(),
+            prune: Prune::from_config(config),
+            force: config.force,
+            manifest_markdown: config.manifest_markdown,
+            lang_stats: config.lang_stats,
+            order: config.walk_order,
+        }
+    }
+
+    /// Returns the name of the document an artifact belongs in.
+    fn document_name(&self, artifact: &Artifact) -> String {
+        let target = self
+            .target_dirs
+            .iter()
+            .map(|dir| dir.components().filter(|component| matches!(component, Component::Normal(_))).collect::<PathBuf>())
+            .filter(|dir| !dir.as_os_str().is_empty() && artifact.relative_path.starts_with(dir))
+            .max_by_key(|dir| dir.components().count());
+        match target {
+            Some(dir) => {
+                let parts: Vec<String> = dir.components().map(|c| c.as_os_str().to_string_lossy().into_owned()).collect();
+                format!("{}.md", parts.join(&self.separator))
+            }
+            None => "root.md".to_string(),
+        }
+    }
+}
+
+impl Writer for MarkdownPerTargetWriter {
+    fn write(&self, artifacts: &[Artifact]) -> io::Result<()> {
+        let mut documents: BTreeMap<String, Vec<&Artifact>> = BTreeMap::new();
+        for artifact in artifacts {
+            documents.entry(self.document_name(artifact)).or_default().push(artifact);
+        }
+
+        fs::create_dir_all(&self.dest_dir)?;
+        let names: HashSet<String> = documents.keys().cloned().collect();
+        prune_dir(&self.dest_dir, self.prune, names.clone(), self.force)?;
+        let stale = stale_files(&self.dest_dir, &names);
+        let mut sink = DirSink::new(&self.dest_dir);
+        for (name, mut members) in documents {
+            members.sort_by(|a, b| self.order.compare(&a.relative_path, &b.relative_path));
+            let title = name.trim_end_matches(".md");
+            sink.put(&name, format::markdown_document(title, &members).as_bytes())?;
+        }
+
+        let mut manifest = Manifest::from_artifacts(artifacts, &self.run_id, OutputFormat::Markdown);
+        for (entry, artifact) in manifest.artifacts.iter_mut().zip(artifacts) {
+            entry.new_filename = self.document_name(artifact);
+        }
+        manifest.stale = stale;
+        if self.lang_stats {
+            manifest.languages = project::language_stats(artifacts);
+        }
+        manifest.write(&mut sink)?;
+        if self.manifest_markdown {
+            manifest.write_markdown(&mut sink)?;
+        }
+        Ok(())
+    }
+}
+
+/// Removes existing files from an output directory according to a `Prune` setting.
+///
+/// # Arguments
+///
+/// * `dir` - The output directory.
+/// * `prune` - Which existing files to remove.
+/// * `names` - The names of the files the current run writes.
+/// * `force` - Whether files rustifacts did not write may be removed too.
+fn prune_dir(dir: &Path, prune: Prune, names: HashSet<String>, force: bool) -> io::Result<()> {
+    let keep = match prune {
+        Prune::None => return Ok(()),
+        Prune::Stale => names,
+        Prune::All => HashSet::new(),
+    };
+    let removed = clean::prune(dir, &keep, force).map_err(io::Error::other)?;
+    if removed > 0 {
+        info!("Removed {} stale files from {}", removed, dir.display());
+    }
+    Ok(())
+}
+
+/// Lists the files earlier runs wrote into a directory that the current run does not write.
+///
+/// They are carried forward in the new manifest so that a later `--prune` or `clean`
+/// can still find them.
+///
+/// # Arguments
+///
+/// * `dir` - The output directory.
+/// * `names` - The names of the files the current run writes.
+///
+/// # Returns
+///
+/// A `Vec<String>` containing the names of the stale files that still exist.
+fn stale_files(dir: &Path, names: &HashSet<String>) -> Vec<String> {
+    let Some(previous) = Manifest::read_from(dir) else {
+        return Vec::new();
+    };
+    let mut stale: Vec<String> = previous
+        .written_files()
+        .filter(|name| !names.contains(*name) && dir.join(name).is_file())
+        .map(String::from)
+        .collect();
+    stale.sort();
+    stale.dedup();
+    if !stale.is_empty() {
+        info!("{} files from earlier runs are no longer collected; pass --prune to remove them", stale.len());
+    }
+    stale
+}
+
+/// Creates the writer selected by a `Config`.
+///
+/// # Arguments
+///
+/// * `config` - The configuration options.
+///
+/// # Returns
+///
+/// A `Box<dyn Writer>` writing to the single output file or zip archive if one is
+/// configured, one Markdown document per target directory with `--markdown-per-target`,
+/// or loose files in the destination directory otherwise.
+pub fn from_config(config: &Config) -> Box<dyn Writer> {
+    match (&config.single_file, &config.output_archive) {
+        (Some(path), _) if config.bundle_header => {
+            Box::new(
+                SingleFileWriter::new(path)
+                    .format(config.format)
+                    .order(config.walk_order)
+                    .project_header(&config.source_dir)
+                    .lang_stats(config.lang_stats),
+            )
+        }
+        (Some(path), _) => Box::new(SingleFileWriter::new(path).format(config.format).order(config.walk_order)),
+        (None, Some(path)) => Box::new(ArchiveWriter::from_config(path, config)),
+        (None, None) if config.markdown_per_target => Box::new(MarkdownPerTargetWriter::from_config(config)),
+        (None, None) => Box::new(DirectoryWriter::from_config(config)),
+    }
+}